@@ -0,0 +1,67 @@
+//! Off-chain helpers for building Data Store transactions.
+
+use solana_program::rent::Rent;
+
+use crate::{
+    instruction::DataStoreInstruction,
+    state::{DataStoreTypeOption, UpdateDataStoreArgs, MAX_DATA_SPACE, METADATA_SIZE},
+};
+
+/// Maximum size of a serialized transaction, as enforced by the Solana network.
+pub const PACKET_DATA_SIZE: usize = 1232;
+
+/// Bytes of a legacy transaction around the instruction data of a single
+/// UpdateDataStore instruction signed only by the authority, who also pays the fees:
+/// - 1 + 64: signature count and the authority signature
+/// - 3: message header
+/// - 1 + 6 * 32: account keys (authority, datastore, pda, system program, config pda,
+///   data program)
+/// - 32: recent blockhash
+/// - 1 + 1 + 1 + 5 + 2: instruction count, program index, account indices, data length
+pub const UPDATE_TRANSACTION_OVERHEAD: usize = 65 + 3 + 193 + 32 + 10;
+
+/// Returns the largest `data` length that fits one UpdateDataStore transaction.
+///
+/// Assumes the transaction carries that single instruction, with the authority as the
+/// only signer and fee payer, and `expected_revision` but not `expected_hash` set.
+/// Additional instructions such as compute budget ones, or an `expected_hash`, reduce
+/// the room accordingly. Writes to dynamic data stores are also bounded by the growth
+/// left from current_len up to `MAX_DATA_SPACE`, while static ones stay within their
+/// allocated space, which is checked on-chain.
+/// Hashing a chunk of this size stays well within the default compute unit limit.
+pub fn max_chunk_size(is_dynamic: bool, current_len: usize) -> usize {
+    let instruction_overhead = borsh::to_vec(&DataStoreInstruction::UpdateDataStore(
+        UpdateDataStoreArgs {
+            debug: false,
+            data_hash: [0; 32],
+            data: Vec::new(),
+            offset: 0,
+            realloc_down: false,
+            data_type: DataStoreTypeOption::File,
+            expected_revision: Some(0),
+            allow_gaps: false,
+            expected_hash: None,
+        },
+    ))
+    .map(|data| data.len())
+    .unwrap_or_default();
+
+    let chunk_size =
+        PACKET_DATA_SIZE.saturating_sub(UPDATE_TRANSACTION_OVERHEAD + instruction_overhead);
+    if is_dynamic {
+        chunk_size.min(MAX_DATA_SPACE.saturating_sub(current_len))
+    } else {
+        chunk_size
+    }
+}
+
+/// Returns the lamports a feepayer needs to initialize a data store of space bytes:
+/// the rent-exempt balances of the data account and of its METADATA_SIZE metadata PDA.
+///
+/// Uses the default rent parameters, those of every public cluster. Transaction fees,
+/// and the top-ups of dynamic data stores growing past space, come on top of it.
+pub fn estimate_rent(space: usize) -> u64 {
+    let rent = Rent::default();
+    rent.minimum_balance(space)
+        .saturating_add(rent.minimum_balance(METADATA_SIZE))
+}
@@ -10,10 +10,7 @@ fn process_instruction(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
     // instruction_data contains the serialized instruction data for the program
-instruction_data: &[u8]) -> ProgramResult {
-    Processor::process_instruction(
-        program_id,
-        accounts,
-        instruction_data,
-    )
-}
\ No newline at end of file
+    instruction_data: &[u8],
+) -> ProgramResult {
+    Processor::process_instruction(program_id, accounts, instruction_data)
+}
@@ -36,6 +36,64 @@ pub enum DataStoreError {
     DataVerificationFailed,
     #[error("Invalid instruction data")]
     InvalidInstructionData,
+    #[error("Expected revision does not match the current revision")]
+    RevisionMismatch,
+    #[error("Requested space exceeds the maximum data account size")]
+    SpaceTooLarge,
+    #[error("Data account should be finalized")]
+    NotFinalized,
+    #[error("Account should be owned by the data program")]
+    InvalidAccountOwner,
+    #[error("Metadata account was written by an unsupported version")]
+    UnsupportedVersion,
+    #[error("Cannot overwrite previously written data of an append-only data store account")]
+    AppendOnlyViolation,
+    #[error("Directory space should be a multiple of the directory entry size")]
+    InvalidDirectorySize,
+    #[error("Static data store account should have non-zero space")]
+    ZeroSpace,
+    #[error("Directory should contain the entry")]
+    EntryNotFound,
+    #[error("Directory already contains the entry")]
+    EntryAlreadyExists,
+    #[error("Account should hold enough lamports to be rent-exempt")]
+    NotRentExempt,
+    #[error("Flag cannot be changed after initialization")]
+    ImmutableFlag,
+    #[error("Directory has reached its maximum number of entries")]
+    DirectoryFull,
+    #[error("Upload should reach the expected total before finalizing")]
+    UploadIncomplete,
+    #[error("New authority should differ from the current authority")]
+    AuthorityUnchanged,
+    #[error("Update is within the minimum interval since the last update")]
+    UpdateTooSoon,
+    #[error("Data store account should be dynamic")]
+    NotDynamic,
+    #[error("Adopted account size should match the requested space")]
+    SizeMismatch,
+    #[error("Metadata account data could not be deserialized")]
+    CorruptMetadata,
+    #[error("Write should start at or before the end of the written data")]
+    NonContiguousWrite,
+    #[error("Program is paused by its admin")]
+    ProgramPaused,
+    #[error("Operation exceeds the compute budget of an instruction")]
+    OperationTooLarge,
+    #[error("Directory already contains an entry with the name")]
+    NameConflict,
+    #[error("The same account was passed for two different account roles")]
+    DuplicateAccount,
+    #[error("Headers already hold the maximum number of entries")]
+    HeadersFull,
+    #[error("Data store is frozen")]
+    Frozen,
+    #[error("Content pda registering the data store is missing")]
+    ContentRegistered,
+    #[error("Parent directory listing the data store is missing")]
+    ParentRequired,
+    #[error("Metadata account predates metadata_crc and must be migrated")]
+    MigrationRequired,
 }
 
 impl FromPrimitive for DataStoreError {
@@ -69,6 +127,35 @@ impl FromPrimitive for DataStoreError {
             13 => Some(Self::InvalidDataType),
             14 => Some(Self::DataVerificationFailed),
             15 => Some(Self::InvalidInstructionData),
+            16 => Some(Self::RevisionMismatch),
+            17 => Some(Self::SpaceTooLarge),
+            18 => Some(Self::NotFinalized),
+            19 => Some(Self::InvalidAccountOwner),
+            20 => Some(Self::UnsupportedVersion),
+            21 => Some(Self::AppendOnlyViolation),
+            22 => Some(Self::InvalidDirectorySize),
+            23 => Some(Self::ZeroSpace),
+            24 => Some(Self::EntryNotFound),
+            25 => Some(Self::EntryAlreadyExists),
+            26 => Some(Self::NotRentExempt),
+            27 => Some(Self::ImmutableFlag),
+            28 => Some(Self::DirectoryFull),
+            29 => Some(Self::UploadIncomplete),
+            30 => Some(Self::AuthorityUnchanged),
+            31 => Some(Self::UpdateTooSoon),
+            32 => Some(Self::NotDynamic),
+            33 => Some(Self::SizeMismatch),
+            34 => Some(Self::CorruptMetadata),
+            35 => Some(Self::NonContiguousWrite),
+            36 => Some(Self::ProgramPaused),
+            37 => Some(Self::OperationTooLarge),
+            38 => Some(Self::NameConflict),
+            39 => Some(Self::DuplicateAccount),
+            40 => Some(Self::HeadersFull),
+            41 => Some(Self::Frozen),
+            42 => Some(Self::ContentRegistered),
+            43 => Some(Self::ParentRequired),
+            44 => Some(Self::MigrationRequired),
             _ => None,
         }
     }
@@ -78,4 +165,14 @@ impl From<DataStoreError> for ProgramError {
     fn from(e: DataStoreError) -> Self {
         ProgramError::Custom(e as u32)
     }
-}
\ No newline at end of file
+}
+
+/// Returns the error behind the code of a `ProgramError::Custom` raised by the program
+pub fn error_from_code(n: u32) -> Option<DataStoreError> {
+    DataStoreError::from_u32(n)
+}
+
+/// Returns the code the program raises error with as `ProgramError::Custom`
+pub fn error_code(error: &DataStoreError) -> u32 {
+    error.clone() as u32
+}
@@ -0,0 +1,42 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::{log::sol_log_data, pubkey::Pubkey};
+
+use crate::state::DataStoreTypeOption;
+
+/// Events logged for indexers, regardless of the debug flag.
+/// Each event is borsh-encoded and emitted as a single `Program data:` log entry.
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize, PartialEq)]
+pub enum DataStoreEvent {
+    Initialized {
+        data_account: Pubkey,
+        authority: Pubkey,
+        data_type: DataStoreTypeOption,
+    },
+    Updated {
+        data_account: Pubkey,
+        revision: u64,
+        data_len: u64,
+        data_hash: [u8; 32],
+    },
+    AuthorityChanged {
+        data_account: Pubkey,
+        old_authority: Pubkey,
+        new_authority: Pubkey,
+    },
+    Finalized {
+        data_account: Pubkey,
+        data_hash: [u8; 32],
+    },
+    Closed {
+        data_account: Pubkey,
+        recipient: Pubkey,
+    },
+}
+
+impl DataStoreEvent {
+    pub fn emit(&self) {
+        if let Ok(data) = borsh::to_vec(self) {
+            sol_log_data(&[&data]);
+        }
+    }
+}
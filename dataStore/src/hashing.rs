@@ -0,0 +1,250 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+
+/// Hash algorithms supported by the Data Store.
+#[derive(Clone, Copy, Debug, BorshSerialize, BorshDeserialize, PartialEq)]
+#[borsh(use_discriminant = true)]
+pub enum HashAlgorithm {
+    Sha256 = 0,
+    Blake3 = 1,
+}
+
+/// Hashes data with the given algorithm.
+/// The program always goes through the solana_program syscalls, even when a dependent
+/// crate enables the `client` feature; off-chain builds with it use the host backend.
+pub fn digest(algo: HashAlgorithm, data: &[u8]) -> [u8; 32] {
+    digestv(algo, &[data])
+}
+
+/// Hashes the concatenation of vals with the given algorithm.
+pub fn digestv(algo: HashAlgorithm, vals: &[&[u8]]) -> [u8; 32] {
+    #[cfg(all(feature = "client", not(target_os = "solana")))]
+    return host::digestv(algo, vals);
+
+    #[cfg(any(not(feature = "client"), target_os = "solana"))]
+    return syscall::digestv(algo, vals);
+}
+
+/// CRC-32 (IEEE 802.3) lookup table, built at compile time
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// CRC-32 (IEEE 802.3) checksum of data, as computed by zlib and browsers.
+/// A cheap check against accidental corruption, not a substitute for digest.
+pub fn crc32(data: &[u8]) -> u32 {
+    crc32_append(0, data)
+}
+
+/// CRC-32 of the bytes checksummed into crc followed by data
+pub fn crc32_append(crc: u32, data: &[u8]) -> u32 {
+    !data.iter().fold(!crc, |crc, byte| {
+        CRC32_TABLE[((crc ^ *byte as u32) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+#[rustfmt::skip]
+const SHA256_INITIAL_STATE: [u32; 8] = [
+    0x6a09_e667, 0xbb67_ae85, 0x3c6e_f372, 0xa54f_f53a,
+    0x510e_527f, 0x9b05_688c, 0x1f83_d9ab, 0x5be0_cd19,
+];
+
+#[rustfmt::skip]
+const SHA256_ROUND_CONSTANTS: [u32; 64] = [
+    0x428a_2f98, 0x7137_4491, 0xb5c0_fbcf, 0xe9b5_dba5, 0x3956_c25b, 0x59f1_11f1, 0x923f_82a4,
+    0xab1c_5ed5, 0xd807_aa98, 0x1283_5b01, 0x2431_85be, 0x550c_7dc3, 0x72be_5d74, 0x80de_b1fe,
+    0x9bdc_06a7, 0xc19b_f174, 0xe49b_69c1, 0xefbe_4786, 0x0fc1_9dc6, 0x240c_a1cc, 0x2de9_2c6f,
+    0x4a74_84aa, 0x5cb0_a9dc, 0x76f9_88da, 0x983e_5152, 0xa831_c66d, 0xb003_27c8, 0xbf59_7fc7,
+    0xc6e0_0bf3, 0xd5a7_9147, 0x06ca_6351, 0x1429_2967, 0x27b7_0a85, 0x2e1b_2138, 0x4d2c_6dfc,
+    0x5338_0d13, 0x650a_7354, 0x766a_0abb, 0x81c2_c92e, 0x9272_2c85, 0xa2bf_e8a1, 0xa81a_664b,
+    0xc24b_8b70, 0xc76c_51a3, 0xd192_e819, 0xd699_0624, 0xf40e_3585, 0x106a_a070, 0x19a4_c116,
+    0x1e37_6c08, 0x2748_774c, 0x34b0_bcb5, 0x391c_0cb3, 0x4ed8_aa4a, 0x5b9c_ca4f, 0x682e_6ff3,
+    0x748f_82ee, 0x78a5_636f, 0x84c8_7814, 0x8cc7_0208, 0x90be_fffa, 0xa450_6ceb, 0xbef9_a3f7,
+    0xc671_78f2,
+];
+
+/// SHA-256 computed incrementally, whose state can be stored between instructions.
+/// Unlike digest it runs in software, the syscalls only hashing whole inputs, so it
+/// costs more per byte but lets each update hash only the bytes it appends.
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize, PartialEq)]
+pub struct Sha256Stream {
+    state: [u32; 8],
+    /// Bytes hashed so far
+    len: u64,
+    /// Trailing bytes not filling a whole block yet
+    buffer: Vec<u8>,
+}
+
+impl Default for Sha256Stream {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Sha256Stream {
+    pub fn new() -> Self {
+        Self {
+            state: SHA256_INITIAL_STATE,
+            len: 0,
+            buffer: Vec::new(),
+        }
+    }
+
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Whether the stream is one update could have produced, its buffer holding exactly
+    /// the bytes past the last whole block. Streams deserialized from accounts must be
+    /// checked before use, as update relies on the buffer holding less than a block.
+    pub fn is_valid(&self) -> bool {
+        self.buffer.len() as u64 == self.len % 64
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.len += data.len() as u64;
+
+        let mut data = data;
+        if !self.buffer.is_empty() {
+            let take = (64 - self.buffer.len()).min(data.len());
+            self.buffer.extend_from_slice(&data[..take]);
+            data = &data[take..];
+            if self.buffer.len() < 64 {
+                return;
+            }
+            let block = std::mem::take(&mut self.buffer);
+            sha256_compress(&mut self.state, &block);
+        }
+
+        let mut blocks = data.chunks_exact(64);
+        for block in &mut blocks {
+            sha256_compress(&mut self.state, block);
+        }
+        self.buffer.extend_from_slice(blocks.remainder());
+    }
+
+    /// Digest of the bytes hashed so far, the same as digest with HashAlgorithm::Sha256.
+    /// The stream itself is left as is, so more bytes can still be appended.
+    pub fn finalize(&self) -> [u8; 32] {
+        let mut state = self.state;
+        let mut tail = self.buffer.clone();
+        tail.push(0x80);
+        let padded_len = if tail.len() > 56 { 128 } else { 64 };
+        tail.resize(padded_len - 8, 0);
+        tail.extend_from_slice(&(self.len * 8).to_be_bytes());
+        for block in tail.chunks_exact(64) {
+            sha256_compress(&mut state, block);
+        }
+
+        let mut hash = [0u8; 32];
+        for (bytes, word) in hash.chunks_exact_mut(4).zip(state) {
+            bytes.copy_from_slice(&word.to_be_bytes());
+        }
+        hash
+    }
+}
+
+/// Folds a 64-byte block into state, the SHA-256 compression function
+fn sha256_compress(state: &mut [u32; 8], block: &[u8]) {
+    let mut schedule = [0u32; 64];
+    for (word, bytes) in schedule.iter_mut().zip(block.chunks_exact(4)) {
+        *word = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    }
+    for i in 16..64 {
+        let s0 = schedule[i - 15].rotate_right(7)
+            ^ schedule[i - 15].rotate_right(18)
+            ^ (schedule[i - 15] >> 3);
+        let s1 = schedule[i - 2].rotate_right(17)
+            ^ schedule[i - 2].rotate_right(19)
+            ^ (schedule[i - 2] >> 10);
+        schedule[i] = schedule[i - 16]
+            .wrapping_add(s0)
+            .wrapping_add(schedule[i - 7])
+            .wrapping_add(s1);
+    }
+
+    let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *state;
+    for (constant, word) in SHA256_ROUND_CONSTANTS.iter().zip(schedule) {
+        let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+        let choice = (e & f) ^ (!e & g);
+        let t1 = h
+            .wrapping_add(s1)
+            .wrapping_add(choice)
+            .wrapping_add(*constant)
+            .wrapping_add(word);
+        let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+        let majority = (a & b) ^ (a & c) ^ (b & c);
+        let t2 = s0.wrapping_add(majority);
+
+        h = g;
+        g = f;
+        f = e;
+        e = d.wrapping_add(t1);
+        d = c;
+        c = b;
+        b = a;
+        a = t1.wrapping_add(t2);
+    }
+
+    for (word, value) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+        *word = word.wrapping_add(value);
+    }
+}
+
+/// Backend used by the program, via the solana_program hashing syscalls.
+pub mod syscall {
+    use super::HashAlgorithm;
+    use solana_program::{blake3, hash};
+
+    pub fn digestv(algo: HashAlgorithm, vals: &[&[u8]]) -> [u8; 32] {
+        match algo {
+            HashAlgorithm::Sha256 => hash::hashv(vals).to_bytes(),
+            HashAlgorithm::Blake3 => blake3::hashv(vals).to_bytes(),
+        }
+    }
+}
+
+/// Backend used by off-chain verifiers, via the sha2 and blake3 crates.
+#[cfg(all(feature = "client", not(target_os = "solana")))]
+pub mod host {
+    use super::HashAlgorithm;
+    use sha2::{Digest, Sha256};
+
+    pub fn digestv(algo: HashAlgorithm, vals: &[&[u8]]) -> [u8; 32] {
+        match algo {
+            HashAlgorithm::Sha256 => {
+                let mut hasher = Sha256::new();
+                for val in vals {
+                    hasher.update(val);
+                }
+                hasher.finalize().into()
+            }
+            HashAlgorithm::Blake3 => {
+                let mut hasher = blake3::Hasher::new();
+                for val in vals {
+                    hasher.update(val);
+                }
+                hasher.finalize().into()
+            }
+        }
+    }
+}
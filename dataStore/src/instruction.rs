@@ -1,49 +1,483 @@
 use borsh::{BorshDeserialize, BorshSerialize};
-use shank::{ShankInstruction};
-
+use shank::ShankInstruction;
 
 use crate::state::{
-    CloseDataStoreArgs, FinalizeDataStoreArgs, InitializeDataStoreArgs, UpdateDataStoreArgs,
-    UpdateDataStoreAuthorityArgs,
+    AddDirectoryEntryArgs, CloseDataStoreArgs, CloseDataStoreBatchArgs, ConcatDataArgs,
+    ConvertDataTypeArgs, CopyInDataArgs, CreateSymlinkArgs, FinalizeDataStoreArgs,
+    GetAccountStateArgs, GetDataHashArgs, GetDirectoryEntriesArgs, GetETagArgs, GetHeadersArgs,
+    GetMetadataArgs, GetOrCreateDirectoryArgs, GetSpaceInfoArgs, GetUploadProgressArgs,
+    InitializeDataStoreArgs, InitializeDataStoreBatchArgs, InitializeDirectoryArgs,
+    MigrateMetadataArgs, MoveEntryArgs, RawMetadataWriteArgs, ReadDataArgs, ReclaimRentArgs,
+    RecomputeHashArgs, RegionHashArgs, ReinitializeArgs, SetAllowlistArgs, SetCacheTtlArgs,
+    SetContentTypeArgs, SetHeaderArgs, SetMetadataFlagsArgs, SetNameArgs, SetPauseArgs,
+    SetReaderArgs, SplitDataArgs, SupersedeArgs, SwapDataArgs, UpdateDataStoreArgs,
+    UpdateDataStoreAuthorityArgs, UpdateSymlinkArgs, ValidateArgs,
 };
 
 /// Instructions supported by the Data Store.
+/// Every instruction that modifies accounts, except SetPause and RawMetadataWrite, takes
+/// the config PDA as an extra last account, checked for the program being paused.
+/// This breaks the wire format of 0.1.x, whose clients must append the config PDA to
+/// those instructions since 0.2.0.
+// Keep each shank account attribute on a single line
+#[rustfmt::skip]
 #[derive(Clone, Debug, BorshSerialize, BorshDeserialize, ShankInstruction)]
 pub enum DataStoreInstruction {
     /// Initializes a new data store account. that is accessible by the authority.
     /// sets the owner of the data store account to be the data program.
     /// If a data account was already initialized for given user, it returns Error.
+    /// Programs built with the `permissioned-init` feature also require the allowlist PDA,
+    /// which must list the feepayer.
+    /// With a file_name, the data store account is the file PDA of the authority and that
+    /// name, see derive_file_pda, created by the program.
     #[account(0, signer, writable, name = "feepayer", desc = "Account responsible for paying the transaction fees for initializing the data store")]
     #[account(1, writable, name = "datastore", desc = "Data store account")] 
     #[account(2, writable, name = "data_store_pda", desc = "Data Store pda's account")]
     #[account(3, name = "system_program", desc = "System program account")]
+    #[account(4, optional, name = "allowlist", desc = "Allowlist pda's account, required with the permissioned-init feature")]
+    #[account(5, name = "config", desc = "Config pda's account, passed last after any optional accounts")]
     InitializeDataStore(InitializeDataStoreArgs),
 
     /// Updates the data store account.
+    /// Updates estimated above UPDATE_COMPUTE_BUDGET fail early with OperationTooLarge.
+    /// Audited data stores also require their audit PDA, which gets an AuditRecord appended.
     #[account(0, signer, writable, name = "authority", desc = "Authority account")]
     #[account(1, writable, name = "datastore", desc = "Data store account")]
     #[account(2, writable, name = "data_store_pda", desc = "Data Store pda's account")]
     #[account(3, name = "system_program", desc = "System program account")]
+    #[account(4, optional, writable, name = "audit_pda", desc = "Audit pda's account, required for audited data stores")]
+    #[account(5, name = "config", desc = "Config pda's account, passed last after any optional accounts")]
     UpdateDataStore(UpdateDataStoreArgs),
 
 
     /// Updates the authority of the data store account.
+    /// Only the old and new authorities sign, the data store account is only used
+    /// to check the PDA. A new authority that is a PDA does not sign, instead its
+    /// seeds and program are supplied and verified.
     #[account(0, signer, name = "old_authority", desc = "Old Authority account")]
-    #[account(1, signer, name = "datastore", desc = "Data account")]
+    #[account(1, name = "datastore", desc = "Data store account")]
     #[account(2, writable, name = "data_store_pda", desc = "Data Store pda's account")]
-    #[account(3, name = "new_authority", desc = "New Authority account")]
+    #[account(3, signer, name = "new_authority", desc = "New Authority account, signer unless a PDA")]
+    #[account(4, name = "config", desc = "Config pda's account, passed last after any optional accounts")]
     UpdateDataStoreAuthority(UpdateDataStoreAuthorityArgs),
 
 
     /// Finalizes the data store account.
-    #[account(0, signer, name = "authority", desc = "Authority account")]
-    #[account(1, name = "datastore", desc = "Data store account")]
+    /// With lock_authority, the authority is also renounced, making the data store permanent.
+    /// If the content PDA of the data is provided, see derive_content_pda, it is created to
+    /// map the content to the data store, paid for by the authority. An existing content
+    /// PDA keeps the data store registered first.
+    #[account(0, signer, name = "authority", desc = "Authority account, writable when creating the content PDA")]
+    #[account(1, name = "datastore", desc = "Data store account, read-only as its contents are only verified")]
     #[account(2, writable, name = "data_store_pda", desc = "Data Store pda's account")]
+    #[account(3, optional, writable, name = "content_pda", desc = "Content pda's account of the data")]
+    #[account(4, optional, name = "system_program", desc = "System program account, required with the content pda")]
+    #[account(5, name = "config", desc = "Config pda's account, passed last after any optional accounts")]
     FinalizeDataStore(FinalizeDataStoreArgs),
 
     /// Closes the data store account.
-    #[account(0, signer, name = "authority", desc = "Authority account")]
+    /// A data store that is not finalized is only closed when forced.
+    /// Lamports are refunded to the recipient if provided, otherwise to the authority.
+    /// Past its expiry_slot, any signer may close the data store if the recipient is its creator.
+    /// A data store registered by its content PDA closes that PDA too, refunding the recipient.
+    #[account(0, signer, writable, name = "authority", desc = "Authority account, or any signer once expired")]
     #[account(1, writable, name = "datastore", desc = "Data store account")]
     #[account(2, writable, name = "data_store_pda", desc = "Data Store pda's account")]
+    #[account(3, optional, writable, name = "recipient", desc = "Account receiving the refunded lamports")]
+    #[account(4, optional, writable, name = "content_pda", desc = "Content pda's account, required when it registers the data store")]
+    #[account(5, name = "config", desc = "Config pda's account, passed last after any optional accounts")]
     CloseDataStore(CloseDataStoreArgs),
-}
\ No newline at end of file
+
+    /// Initializes a new data store account holding a symlink to another account.
+    /// The data region stores the 32-byte target pubkey.
+    #[account(0, signer, writable, name = "feepayer", desc = "Account responsible for paying the transaction fees for initializing the data store")]
+    #[account(1, writable, name = "datastore", desc = "Data store account")]
+    #[account(2, writable, name = "data_store_pda", desc = "Data Store pda's account")]
+    #[account(3, name = "system_program", desc = "System program account")]
+    #[account(4, name = "config", desc = "Config pda's account, passed last after any optional accounts")]
+    CreateSymlink(CreateSymlinkArgs),
+
+    /// Updates the target of a symlink data store account.
+    #[account(0, signer, name = "authority", desc = "Authority account")]
+    #[account(1, writable, name = "datastore", desc = "Data store account")]
+    #[account(2, writable, name = "data_store_pda", desc = "Data Store pda's account")]
+    #[account(3, name = "config", desc = "Config pda's account, passed last after any optional accounts")]
+    UpdateSymlink(UpdateSymlinkArgs),
+
+    /// Returns the borsh-encoded metadata of the data store account via return data.
+    /// Private data stores require the signature of the authority or its reader delegate.
+    #[account(0, name = "datastore", desc = "Data store account")]
+    #[account(1, name = "data_store_pda", desc = "Data Store pda's account")]
+    #[account(2, optional, signer, name = "authority", desc = "Authority or reader delegate account, required for private data stores")]
+    GetMetadata(GetMetadataArgs),
+
+    /// Appends a child entry to a directory data store account.
+    /// Dynamic directories grow to fit the entry, static ones must have room for it.
+    /// Entries of NamedEntries directories carry a name, which must be unique.
+    #[account(0, signer, writable, name = "authority", desc = "Authority account")]
+    #[account(1, writable, name = "datastore", desc = "Directory data store account")]
+    #[account(2, writable, name = "data_store_pda", desc = "Data Store pda's account")]
+    #[account(3, name = "system_program", desc = "System program account")]
+    #[account(4, name = "config", desc = "Config pda's account, passed last after any optional accounts")]
+    AddDirectoryEntry(AddDirectoryEntryArgs),
+
+    /// Returns up to count packed child pubkeys of a directory, starting at entry offset,
+    /// via return data. NamedEntries directories return their length-prefixed records,
+    /// as many as fit the return data.
+    #[account(0, name = "datastore", desc = "Directory data store account")]
+    #[account(1, name = "data_store_pda", desc = "Data Store pda's account")]
+    GetDirectoryEntries(GetDirectoryEntriesArgs),
+
+    /// Migrates the metadata of a data store account written by an older version
+    /// of the program to the current layout.
+    #[account(0, signer, name = "authority", desc = "Authority account")]
+    #[account(1, name = "datastore", desc = "Data store account")]
+    #[account(2, writable, name = "data_store_pda", desc = "Data Store pda's account")]
+    #[account(3, name = "config", desc = "Config pda's account, passed last after any optional accounts")]
+    MigrateMetadata(MigrateMetadataArgs),
+
+    /// Returns the sha256 of len bytes of the data store account starting at offset
+    /// via return data.
+    #[account(0, name = "datastore", desc = "Data store account")]
+    #[account(1, name = "data_store_pda", desc = "Data Store pda's account")]
+    RegionHash(RegionHashArgs),
+
+    /// Initializes a new directory data store account.
+    /// Directories are dynamic. Packed directories must have a space that is a multiple of
+    /// the entry size, JSON manifest ones are written with UpdateDataStore instead.
+    #[account(0, signer, writable, name = "feepayer", desc = "Account responsible for paying the transaction fees for initializing the data store")]
+    #[account(1, writable, name = "datastore", desc = "Directory data store account")]
+    #[account(2, writable, name = "data_store_pda", desc = "Data Store pda's account")]
+    #[account(3, name = "system_program", desc = "System program account")]
+    #[account(4, name = "config", desc = "Config pda's account, passed last after any optional accounts")]
+    InitializeDirectory(InitializeDirectoryArgs),
+
+    /// Runs every check of UpdateDataStore for a write of data_len bytes without
+    /// applying it, returning the error the update would fail with.
+    #[account(0, signer, writable, name = "authority", desc = "Authority account")]
+    #[account(1, writable, name = "datastore", desc = "Data store account")]
+    #[account(2, writable, name = "data_store_pda", desc = "Data Store pda's account")]
+    Validate(ValidateArgs),
+
+    /// Updates the data store account and finalizes it in the same instruction.
+    #[account(0, signer, writable, name = "authority", desc = "Authority account")]
+    #[account(1, writable, name = "datastore", desc = "Data store account")]
+    #[account(2, writable, name = "data_store_pda", desc = "Data Store pda's account")]
+    #[account(3, name = "system_program", desc = "System program account")]
+    #[account(4, optional, writable, name = "audit_pda", desc = "Audit pda's account, required for audited data stores")]
+    #[account(5, name = "config", desc = "Config pda's account, passed last after any optional accounts")]
+    WriteAndFinalize(UpdateDataStoreArgs),
+
+    /// Moves a child entry from the source directory to the destination directory
+    /// atomically. Both directory authorities must sign, a shared authority may be
+    /// passed for both. Dynamic destinations grow to fit the entry, paid by its authority.
+    /// Append-only sources never give up an entry.
+    #[account(0, signer, name = "source_authority", desc = "Authority of the source directory")]
+    #[account(1, writable, name = "source_datastore", desc = "Source directory data store account")]
+    #[account(2, writable, name = "source_data_store_pda", desc = "Source directory pda's account")]
+    #[account(3, signer, writable, name = "destination_authority", desc = "Authority of the destination directory")]
+    #[account(4, writable, name = "destination_datastore", desc = "Destination directory data store account")]
+    #[account(5, writable, name = "destination_data_store_pda", desc = "Destination directory pda's account")]
+    #[account(6, name = "system_program", desc = "System program account")]
+    #[account(7, name = "config", desc = "Config pda's account, passed last after any optional accounts")]
+    MoveEntry(MoveEntryArgs),
+
+    /// Returns the 32-byte data_hash of the data store account via return data.
+    #[account(0, name = "datastore", desc = "Data store account")]
+    #[account(1, name = "data_store_pda", desc = "Data Store pda's account")]
+    GetDataHash(GetDataHashArgs),
+
+    /// Returns len bytes of the data store account starting at offset via return data,
+    /// preceded by their little-endian u32 count. A range past the used bytes fails, or
+    /// with clamp returns the bytes available. With verify, the used bytes are checked against
+    /// data_hash first.
+    /// Private data stores require the signature of the authority or its reader delegate,
+    /// see SetReader. This only gates what the program returns to callers, the account data itself remains public on-chain.
+    #[account(0, name = "datastore", desc = "Data store account")]
+    #[account(1, name = "data_store_pda", desc = "Data Store pda's account")]
+    #[account(2, optional, signer, name = "authority", desc = "Authority or reader delegate account, required for private data stores")]
+    ReadData(ReadDataArgs),
+
+    /// Closes up to MAX_CLOSE_BATCH_SIZE data store accounts sharing one authority,
+    /// passed as (datastore, data_store_pda) pairs after the recipient.
+    /// Every pair is checked before any is closed, so the batch closes all or nothing.
+    /// Data stores registered by their content PDA are closed with CloseDataStore instead.
+    #[account(0, signer, writable, name = "authority", desc = "Authority account")]
+    #[account(1, writable, name = "recipient", desc = "Account receiving the refunded lamports")]
+    #[account(2, name = "config", desc = "Config pda's account, passed last after the pairs")]
+    CloseDataStoreBatch(CloseDataStoreBatchArgs),
+
+    /// Sets the content_type of the data store account.
+    /// Strict data stores reject a content_type not matching the magic number of the data.
+    #[account(0, signer, name = "authority", desc = "Authority account")]
+    #[account(1, name = "datastore", desc = "Data store account")]
+    #[account(2, writable, name = "data_store_pda", desc = "Data Store pda's account")]
+    #[account(3, name = "config", desc = "Config pda's account, passed last after any optional accounts")]
+    SetContentType(SetContentTypeArgs),
+
+    /// Links a freshly initialized data store account to the finalized data store it
+    /// supersedes, forming a version chain. The source is left untouched and the
+    /// destination keeps accepting updates.
+    #[account(0, signer, name = "authority", desc = "Authority of both data store accounts")]
+    #[account(1, name = "source_datastore", desc = "Finalized data store account being superseded")]
+    #[account(2, name = "source_data_store_pda", desc = "Source data store pda's account")]
+    #[account(3, name = "datastore", desc = "Fresh data store account superseding the source")]
+    #[account(4, writable, name = "data_store_pda", desc = "Data Store pda's account")]
+    #[account(5, name = "config", desc = "Config pda's account, passed last after any optional accounts")]
+    Supersede(SupersedeArgs),
+
+    /// Sets several metadata flags at once, the ones in mask to their bit in values.
+    /// Flags fixed at initialization, such as FLAG_DYNAMIC, cannot be changed.
+    /// Setting FLAG_FROZEN rejects every write to the data store until it is cleared.
+    #[account(0, signer, name = "authority", desc = "Authority account")]
+    #[account(1, name = "datastore", desc = "Data store account")]
+    #[account(2, writable, name = "data_store_pda", desc = "Data Store pda's account")]
+    #[account(3, name = "config", desc = "Config pda's account, passed last after any optional accounts")]
+    SetMetadataFlags(SetMetadataFlagsArgs),
+
+    /// Returns the 32-byte ETag of the data store account via return data.
+    #[account(0, name = "datastore", desc = "Data store account")]
+    #[account(1, name = "data_store_pda", desc = "Data Store pda's account")]
+    GetETag(GetETagArgs),
+
+    /// Returns the borsh-encoded (upload_cursor, expected_total) of the data store account
+    /// via return data, letting a client resume a multi-transaction upload.
+    #[account(0, name = "datastore", desc = "Data store account")]
+    #[account(1, name = "data_store_pda", desc = "Data Store pda's account")]
+    GetUploadProgress(GetUploadProgressArgs),
+
+    /// Creates the allowlist PDA, owned by the signing authority, or replaces its members.
+    /// The allowlist gates InitializeDataStore in programs built with `permissioned-init`,
+    /// other programs reject this instruction. Only the upgrade authority of the program
+    /// may create the allowlist, and only its authority may change it afterwards.
+    #[account(0, signer, writable, name = "authority", desc = "Allowlist authority, paying for its creation")]
+    #[account(1, writable, name = "allowlist", desc = "Allowlist pda's account")]
+    #[account(2, name = "system_program", desc = "System program account")]
+    #[account(3, name = "program_data", desc = "ProgramData account of the program, holding its upgrade authority")]
+    #[account(4, name = "config", desc = "Config pda's account, passed last after any optional accounts")]
+    SetAllowlist(SetAllowlistArgs),
+
+    /// Swaps the contents of two data store accounts of the same type sharing an authority.
+    /// Dynamic accounts are resized to the contents they receive, static ones must have the
+    /// space for them, and strict ones contents matching their content type. Hashes are
+    /// recomputed for each account, while revisions and upload progress are swapped along
+    /// with the contents.
+    #[account(0, signer, writable, name = "authority", desc = "Authority of both data store accounts")]
+    #[account(1, writable, name = "datastore", desc = "First data store account")]
+    #[account(2, writable, name = "data_store_pda", desc = "First data store pda's account")]
+    #[account(3, writable, name = "other_datastore", desc = "Second data store account")]
+    #[account(4, writable, name = "other_data_store_pda", desc = "Second data store pda's account")]
+    #[account(5, name = "system_program", desc = "System program account")]
+    #[account(6, name = "config", desc = "Config pda's account, passed last after any optional accounts")]
+    SwapData(SwapDataArgs),
+
+    /// Returns the status of the data store account packed in a single byte via return
+    /// data, see DataStoreAccountMetadata::account_state for the bit layout.
+    #[account(0, name = "datastore", desc = "Data store account")]
+    #[account(1, name = "data_store_pda", desc = "Data Store pda's account")]
+    GetAccountState(GetAccountStateArgs),
+
+    /// Shrinks a dynamic data store account to its used length, refunding the freed rent
+    /// to the authority without writing any data.
+    #[account(0, signer, writable, name = "authority", desc = "Authority account")]
+    #[account(1, writable, name = "datastore", desc = "Data store account")]
+    #[account(2, writable, name = "data_store_pda", desc = "Data Store pda's account")]
+    #[account(3, name = "system_program", desc = "System program account")]
+    #[account(4, name = "config", desc = "Config pda's account, passed last after any optional accounts")]
+    ReclaimRent(ReclaimRentArgs),
+
+    /// Creates the config PDA, with the signer as admin, or sets whether the program is
+    /// paused. Only the upgrade authority of the program may create the config, and only
+    /// its admin may change it afterwards.
+    #[account(0, signer, writable, name = "admin", desc = "Program admin, paying for the config creation")]
+    #[account(1, writable, name = "config", desc = "Config pda's account")]
+    #[account(2, name = "system_program", desc = "System program account")]
+    #[account(3, name = "program_data", desc = "ProgramData account of the program, holding its upgrade authority")]
+    SetPause(SetPauseArgs),
+
+    /// Converts the data store account to another data type.
+    /// The used data must be empty or already valid for the new type: whole entries for a packed
+    /// directory, a UTF-8 `{...}` object for a JSON manifest, a 32-byte pubkey for a symlink.
+    #[account(0, signer, name = "authority", desc = "Authority account")]
+    #[account(1, name = "datastore", desc = "Data store account")]
+    #[account(2, writable, name = "data_store_pda", desc = "Data Store pda's account")]
+    #[account(3, name = "config", desc = "Config pda's account, passed last after any optional accounts")]
+    ConvertDataType(ConvertDataTypeArgs),
+
+    /// Initializes up to MAX_INIT_BATCH_SIZE empty data store accounts sharing one
+    /// feepayer and authority, passed as (datastore, data_store_pda) pairs after the
+    /// system program, and the allowlist with the `permissioned-init` feature.
+    /// Any invalid pair fails the instruction, reverting the data stores created before it.
+    #[account(0, signer, writable, name = "feepayer", desc = "Account responsible for paying the transaction fees for initializing the data stores")]
+    #[account(1, name = "system_program", desc = "System program account")]
+    #[account(2, optional, name = "allowlist", desc = "Allowlist pda's account, required with the permissioned-init feature")]
+    #[account(3, name = "config", desc = "Config pda's account, passed last after the pairs")]
+    InitializeDataStoreBatch(InitializeDataStoreBatchArgs),
+
+    /// Updates the data store account on behalf of an authority that did not sign the
+    /// transaction. The instruction right before it must be an ed25519 program instruction
+    /// verifying the authority's signature of relayed_update_message, with its signature,
+    /// public key and message in its own data. The relayer pays the fees and any realloc.
+    #[account(0, signer, writable, name = "relayer", desc = "Account relaying the update")]
+    #[account(1, writable, name = "datastore", desc = "Data store account")]
+    #[account(2, writable, name = "data_store_pda", desc = "Data Store pda's account")]
+    #[account(3, name = "system_program", desc = "System program account")]
+    #[account(4, name = "instructions", desc = "Instructions sysvar account")]
+    #[account(5, optional, writable, name = "audit_pda", desc = "Audit pda's account, required for audited data stores")]
+    #[account(6, name = "config", desc = "Config pda's account, passed last after any optional accounts")]
+    RelayedUpdate(UpdateDataStoreArgs),
+
+    /// Resets an initialized or finalized data store account to a fresh, empty, initialized
+    /// one with a new data type and authority, keeping the accounts and their rent.
+    /// The revision keeps counting, so ETags of the previous contents are not reused.
+    /// A data store registered by its content PDA closes that PDA, refunding the authority,
+    /// and the headers in its headers PDA are cleared.
+    /// A data store listed in a parent directory, see SetName, is removed from it. The
+    /// parent accounts then follow the content PDA, or take its place when there is none.
+    #[account(0, signer, name = "authority", desc = "Authority account, writable when closing the content pda")]
+    #[account(1, writable, name = "datastore", desc = "Data store account")]
+    #[account(2, writable, name = "data_store_pda", desc = "Data Store pda's account")]
+    #[account(3, name = "headers", desc = "Headers pda's account, writable once created")]
+    #[account(4, optional, writable, name = "content_pda", desc = "Content pda's account, required when it registers the data store")]
+    #[account(5, optional, writable, name = "parent", desc = "Parent directory data store account, required once recorded")]
+    #[account(6, optional, writable, name = "parent_pda", desc = "Parent directory data store pda's account, required once recorded")]
+    #[account(7, name = "config", desc = "Config pda's account, passed last after any optional accounts")]
+    Reinitialize(ReinitializeArgs),
+
+    /// Returns the borsh-encoded SpaceInfo of the data store account via return data.
+    #[account(0, name = "datastore", desc = "Data store account")]
+    #[account(1, name = "data_store_pda", desc = "Data Store pda's account")]
+    GetSpaceInfo(GetSpaceInfoArgs),
+
+    /// Sets the name of the data store account, leaving its data untouched.
+    /// If the NamedEntries parent directory listing it is provided, the name must not
+    /// be taken by another entry of that directory, and the entry is renamed along with
+    /// it. The parent is then recorded, and required by every later rename.
+    /// The parent must share the authority, which pays for growing a dynamic parent.
+    #[account(0, signer, name = "authority", desc = "Authority account, writable when growing the parent")]
+    #[account(1, name = "datastore", desc = "Data store account")]
+    #[account(2, writable, name = "data_store_pda", desc = "Data Store pda's account")]
+    #[account(3, optional, writable, name = "parent", desc = "Parent directory data store account, required once recorded")]
+    #[account(4, optional, writable, name = "parent_pda", desc = "Parent directory data store pda's account")]
+    #[account(5, optional, name = "system_program", desc = "System program account, required when growing the parent")]
+    #[account(6, name = "config", desc = "Config pda's account, passed last after any optional accounts")]
+    SetName(SetNameArgs),
+
+    /// Writes bytes at offset into the metadata of a data store account, bypassing every
+    /// state check. An escape hatch for migration tooling, such as backfilling a new field,
+    /// only callable by the upgrade authority of the program and blocked while paused.
+    /// The write may not touch the authority, data_status or bump_seed, the result must
+    /// still deserialize as metadata of the data store, and nothing else is validated.
+    #[account(0, signer, name = "admin", desc = "Upgrade authority of the program")]
+    #[account(1, name = "datastore", desc = "Data store account")]
+    #[account(2, writable, name = "data_store_pda", desc = "Data Store pda's account")]
+    #[account(3, name = "config", desc = "Config pda's account")]
+    #[account(4, name = "program_data", desc = "ProgramData account of the program, holding its upgrade authority")]
+    RawMetadataWrite(RawMetadataWriteArgs),
+
+    /// Writes len bytes of the data of a source account, owned by any program, starting
+    /// at source_offset, into the data store account at offset. Runs every check of an
+    /// update, without the client relaying the bytes.
+    #[account(0, signer, writable, name = "authority", desc = "Authority account")]
+    #[account(1, writable, name = "datastore", desc = "Data store account")]
+    #[account(2, writable, name = "data_store_pda", desc = "Data Store pda's account")]
+    #[account(3, name = "system_program", desc = "System program account")]
+    #[account(4, name = "source", desc = "Account whose data is copied")]
+    #[account(5, optional, writable, name = "audit_pda", desc = "Audit pda's account, required for audited data stores")]
+    #[account(6, name = "config", desc = "Config pda's account, passed last after any optional accounts")]
+    CopyInData(CopyInDataArgs),
+
+    /// Splits the data store account at offset: the bytes from offset on move to a new
+    /// data store of the same authority, namespace, data type and dynamism, and the source
+    /// keeps the bytes before offset, shrinking if dynamic. The source is truncated with
+    /// every check of an update, so append-only, finalized, frozen or rate-limited
+    /// sources cannot be split, nor can sources with their content registered. Programs
+    /// built with the `permissioned-init` feature also require the allowlist PDA, listing
+    /// the authority.
+    #[account(0, signer, writable, name = "authority", desc = "Authority account, paying for the destination")]
+    #[account(1, writable, name = "datastore", desc = "Source data store account")]
+    #[account(2, writable, name = "data_store_pda", desc = "Source data store pda's account")]
+    #[account(3, signer, writable, name = "destination", desc = "Destination data store account, not created yet")]
+    #[account(4, writable, name = "destination_pda", desc = "Destination data store pda's account")]
+    #[account(5, name = "system_program", desc = "System program account")]
+    #[account(6, optional, writable, name = "audit_pda", desc = "Audit pda's account of the source, required for audited sources")]
+    #[account(7, optional, name = "allowlist", desc = "Allowlist pda's account, required with the permissioned-init feature")]
+    #[account(8, name = "config", desc = "Config pda's account, passed last after any optional accounts")]
+    SplitData(SplitDataArgs),
+
+    /// Appends the written data of a source data store account onto a destination data
+    /// store account of the same authority, as an update of the destination, optionally
+    /// closing the source. Neither may be finalized. Closing the source also closes its
+    /// content PDA and removes its entry from its parent directory, when it has them.
+    #[account(0, signer, writable, name = "authority", desc = "Authority account")]
+    #[account(1, writable, name = "datastore", desc = "Destination data store account")]
+    #[account(2, writable, name = "data_store_pda", desc = "Destination data store pda's account")]
+    #[account(3, writable, name = "source", desc = "Source data store account")]
+    #[account(4, writable, name = "source_pda", desc = "Source data store pda's account")]
+    #[account(5, name = "system_program", desc = "System program account")]
+    #[account(6, optional, writable, name = "audit_pda", desc = "Destination audit pda's account, required for audited data stores")]
+    #[account(7, optional, writable, name = "content_pda", desc = "Content pda's account of a source being closed, required if registered")]
+    #[account(8, optional, writable, name = "parent", desc = "Parent directory of a source being closed, required if it has one")]
+    #[account(9, optional, writable, name = "parent_pda", desc = "Parent directory pda's account, required with parent")]
+    #[account(10, name = "config", desc = "Config pda's account, passed last after any optional accounts")]
+    ConcatData(ConcatDataArgs),
+
+    /// Sets the cache lifetime gateways should advertise for the data store account.
+    #[account(0, signer, name = "authority", desc = "Authority account")]
+    #[account(1, name = "datastore", desc = "Data store account")]
+    #[account(2, writable, name = "data_store_pda", desc = "Data Store pda's account")]
+    #[account(3, name = "config", desc = "Config pda's account, passed last after any optional accounts")]
+    SetCacheTtl(SetCacheTtlArgs),
+
+    /// Returns the directory named name of the authority, creating it first if it does not
+    /// exist yet. Its data store account is the file PDA of the authority and name, see
+    /// derive_file_pda, so concurrent callers always resolve to the same directory.
+    /// The directory's data store account is returned via return data either way.
+    #[account(0, signer, writable, name = "feepayer", desc = "Account responsible for paying the transaction fees for initializing the data store")]
+    #[account(1, writable, name = "datastore", desc = "Directory data store account")]
+    #[account(2, writable, name = "data_store_pda", desc = "Data Store pda's account")]
+    #[account(3, name = "system_program", desc = "System program account")]
+    #[account(4, name = "config", desc = "Config pda's account, passed last after any optional accounts")]
+    GetOrCreateDirectory(GetOrCreateDirectoryArgs),
+
+    /// Re-hashes the data store account after it was written outside the program, updating
+    /// the data_hash, crc32 and revision without resubmitting the data.
+    /// Append-only data stores cannot shrink their data_len this way, and an upload_cursor
+    /// past the new data_len is moved back to it. Counts as an update: rate limits,
+    /// revision limits and audit logs apply, and registered data stores are refused.
+    #[account(0, signer, name = "authority", desc = "Authority account, writable to fund the audit pda of audited data stores")]
+    #[account(1, name = "datastore", desc = "Data store account")]
+    #[account(2, writable, name = "data_store_pda", desc = "Data Store pda's account")]
+    #[account(3, optional, name = "system_program", desc = "System program account, required for audited data stores")]
+    #[account(4, optional, writable, name = "audit_pda", desc = "Audit pda's account, required for audited data stores")]
+    #[account(5, name = "config", desc = "Config pda's account, passed last after any optional accounts")]
+    RecomputeHash(RecomputeHashArgs),
+
+    /// Sets a response header of the data store account, replacing any header with the same
+    /// case-insensitive key, or removes it when value is empty. The headers PDA holding them
+    /// is created on first use, paid by the authority.
+    #[account(0, signer, writable, name = "authority", desc = "Authority account")]
+    #[account(1, name = "datastore", desc = "Data store account")]
+    #[account(2, name = "data_store_pda", desc = "Data Store pda's account")]
+    #[account(3, writable, name = "headers", desc = "Headers pda's account")]
+    #[account(4, name = "system_program", desc = "System program account")]
+    #[account(5, name = "config", desc = "Config pda's account, passed last after any optional accounts")]
+    SetHeader(SetHeaderArgs),
+
+    /// Returns the borsh-encoded Vec<HttpHeader> of the data store account via return data,
+    /// empty if no header was ever set.
+    /// Private data stores require the signature of the authority or its reader delegate.
+    #[account(0, name = "datastore", desc = "Data store account")]
+    #[account(1, name = "data_store_pda", desc = "Data Store pda's account")]
+    #[account(2, name = "headers", desc = "Headers pda's account")]
+    #[account(3, optional, signer, name = "authority", desc = "Authority or reader delegate account, required for private data stores")]
+    GetHeaders(GetHeadersArgs),
+
+    /// Sets or revokes the reader delegate of the data store account, who may read it
+    /// like the authority while it is private.
+    #[account(0, signer, name = "authority", desc = "Authority account")]
+    #[account(1, name = "datastore", desc = "Data store account")]
+    #[account(2, writable, name = "data_store_pda", desc = "Data Store pda's account")]
+    #[account(3, name = "config", desc = "Config pda's account, passed last after any optional accounts")]
+    SetReader(SetReaderArgs),
+}
@@ -1,5 +1,24 @@
+/// Logs with msg! when the debug flag is set.
+/// Compiles to nothing unless the `verbose-logs` feature is enabled, so production
+/// builds carry neither the log strings nor the branches.
+#[macro_export]
+macro_rules! debug_msg {
+    ($debug:expr, $($arg:tt)+) => {{
+        #[cfg(feature = "verbose-logs")]
+        if $debug {
+            solana_program::msg!($($arg)+);
+        }
+        #[cfg(not(feature = "verbose-logs"))]
+        let _ = $debug;
+    }};
+}
+
+#[cfg(feature = "client")]
+pub mod client;
 pub mod entrypoint;
 pub mod error;
+pub mod events;
+pub mod hashing;
 pub mod instruction;
 pub mod processor;
-pub mod state;
\ No newline at end of file
+pub mod state;
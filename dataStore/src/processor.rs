@@ -1,26 +1,69 @@
 use borsh::{BorshDeserialize, BorshSerialize};
 use solana_program::{
     account_info::{next_account_info, AccountInfo},
+    bpf_loader_upgradeable::{self, UpgradeableLoaderState},
+    ed25519_program,
     entrypoint::ProgramResult,
-    msg,
-    program::{invoke, invoke_signed},
+    program::{invoke, invoke_signed, set_return_data, MAX_RETURN_DATA},
+    program_error::ProgramError,
+    program_utils::limited_deserialize,
     pubkey::Pubkey,
     system_instruction,
-    sysvar::{rent::Rent, Sysvar},
+    sysvar::{
+        clock::Clock,
+        instructions::{load_current_index_checked, load_instruction_at_checked},
+        rent::Rent,
+        Sysvar,
+    },
 };
 
+#[cfg(feature = "permissioned-init")]
+use crate::state::{
+    Allowlist, SetAllowlistArgs, ALLOWLIST_SEED, ALLOWLIST_SIZE, MAX_ALLOWLIST_MEMBERS,
+};
 use crate::{
     error::DataStoreError,
+    events::DataStoreEvent,
+    hashing::{digest, HashAlgorithm},
     instruction::DataStoreInstruction,
     state::{
-        CloseDataStoreArgs, FinalizeDataStoreArgs, InitializeDataStoreArgs, UpdateDataStoreArgs,
-        UpdateDataStoreAuthorityArgs, DataStoreAccountMetadata, DataStoreTypeOption,
-        SerializationStatusOption, METADATA_SIZE, PDA_SEED,
+        compute_content_hash, derive_content_pda, derive_file_pda, derive_headers_pda,
+        encode_named_entry, estimate_update_compute_units, find_directory_entry, is_valid_header,
+        matches_content_type, metadata_pda_seeds, parse_named_entries, relayed_update_message,
+        verify_data, verify_data_integrity, verify_directory_data, AddDirectoryEntryArgs,
+        AuditRecord, CloseDataStoreArgs, CloseDataStoreBatchArgs, ConcatDataArgs,
+        ConvertDataTypeArgs, CopyInDataArgs, CreateSymlinkArgs, DataStoreAccountMetadata,
+        DataStoreAccountMetadataV0, DataStoreTypeOption, DirectoryFormat, FinalizeDataStoreArgs,
+        GetAccountStateArgs, GetDataHashArgs, GetDirectoryEntriesArgs, GetETagArgs, GetHeadersArgs,
+        GetMetadataArgs, GetOrCreateDirectoryArgs, GetSpaceInfoArgs, GetUploadProgressArgs,
+        HttpHeader, InitializeDataStoreArgs, InitializeDataStoreBatchArgs, InitializeDirectoryArgs,
+        MigrateMetadataArgs, MoveEntryArgs, ProgramConfig, RawMetadataWriteArgs, ReadDataArgs,
+        ReclaimRentArgs, RecomputeHashArgs, RegionHashArgs, ReinitializeArgs,
+        SerializationStatusOption, SetCacheTtlArgs, SetContentTypeArgs, SetHeaderArgs,
+        SetMetadataFlagsArgs, SetNameArgs, SetPauseArgs, SetReaderArgs, SpaceInfo, SplitDataArgs,
+        SupersedeArgs, SwapDataArgs, UpdateDataStoreArgs, UpdateDataStoreAuthorityArgs,
+        UpdateSymlinkArgs, ValidateArgs, AUDIT_RECORD_SIZE, AUDIT_SEED, CONFIG_SEED, CONFIG_SIZE,
+        CONTENT_INDEX_SIZE, CONTENT_SEED, DATA_STORE_VERSION, DEFAULT_MAX_DIRECTORY_ENTRIES,
+        DIRECTORY_ENTRY_SIZE, FILE_SEED, FLAG_AUTHORITY_RENOUNCED, FLAG_CONTENT_REGISTERED,
+        FLAG_STRICT_CONTENT_TYPE, HEADERS_SEED, HEADERS_SIZE, MAX_CLOSE_BATCH_SIZE,
+        MAX_CONTENT_TYPE_LEN, MAX_DATA_SPACE, MAX_DIRECTORY_ENTRIES_PER_READ, MAX_ENTRY_NAME_LEN,
+        MAX_FILE_NAME_LEN, MAX_HEADERS, MAX_INIT_BATCH_SIZE, METADATA_AUTHORITY_OFFSET,
+        METADATA_DATA_HASH_OFFSET, METADATA_SIZE, METADATA_VERSION_OFFSET, MUTABLE_FLAGS,
+        READ_DATA_HEADER_LEN, RENOUNCED_AUTHORITY, SYMLINK_TARGET_LEN, UPDATE_COMPUTE_BUDGET,
+        UPDATE_COMPUTE_UNITS_PER_STREAMED_BYTE,
     },
 };
 
 pub struct Processor {}
 
+/// Outcome of the checks shared by UpdateDataStore and Validate
+struct UpdatePlan {
+    account_metadata: DataStoreAccountMetadata,
+    offset: usize,
+    end_len: usize,
+    new_len: usize,
+}
+
 impl Processor {
     pub fn process_instruction(
         program_id: &Pubkey,
@@ -30,6 +73,18 @@ impl Processor {
         let instruction = DataStoreInstruction::try_from_slice(instruction_data)
             .map_err(|_| DataStoreError::InvalidInstructionData)?;
 
+        // Ensure the program is not paused before modifying accounts, the config PDA being
+        // passed last so handlers never see it
+        let accounts = if Self::is_mutating(&instruction) {
+            let (config_account, accounts) = accounts
+                .split_last()
+                .ok_or(ProgramError::NotEnoughAccountKeys)?;
+            Self::check_not_paused(program_id, config_account)?;
+            accounts
+        } else {
+            accounts
+        };
+
         match instruction {
             DataStoreInstruction::InitializeDataStore(args) => {
                 Self::initialize_data_store(program_id, accounts, args)
@@ -46,6 +101,104 @@ impl Processor {
             DataStoreInstruction::CloseDataStore(args) => {
                 Self::close_data_store(program_id, accounts, args)
             }
+            DataStoreInstruction::CreateSymlink(args) => {
+                Self::create_symlink(program_id, accounts, args)
+            }
+            DataStoreInstruction::UpdateSymlink(args) => {
+                Self::update_symlink(program_id, accounts, args)
+            }
+            DataStoreInstruction::GetMetadata(args) => {
+                Self::get_metadata(program_id, accounts, args)
+            }
+            DataStoreInstruction::AddDirectoryEntry(args) => {
+                Self::add_directory_entry(program_id, accounts, args)
+            }
+            DataStoreInstruction::GetDirectoryEntries(args) => {
+                Self::get_directory_entries(program_id, accounts, args)
+            }
+            DataStoreInstruction::MigrateMetadata(args) => {
+                Self::migrate_metadata(program_id, accounts, args)
+            }
+            DataStoreInstruction::RegionHash(args) => Self::region_hash(program_id, accounts, args),
+            DataStoreInstruction::InitializeDirectory(args) => {
+                Self::initialize_directory(program_id, accounts, args)
+            }
+            DataStoreInstruction::Validate(args) => Self::validate(program_id, accounts, args),
+            DataStoreInstruction::WriteAndFinalize(args) => {
+                Self::write_and_finalize(program_id, accounts, args)
+            }
+            DataStoreInstruction::MoveEntry(args) => Self::move_entry(program_id, accounts, args),
+            DataStoreInstruction::GetDataHash(args) => {
+                Self::get_data_hash(program_id, accounts, args)
+            }
+            DataStoreInstruction::ReadData(args) => Self::read_data(program_id, accounts, args),
+            DataStoreInstruction::CloseDataStoreBatch(args) => {
+                Self::close_data_store_batch(program_id, accounts, args)
+            }
+            DataStoreInstruction::SetContentType(args) => {
+                Self::set_content_type(program_id, accounts, args)
+            }
+            DataStoreInstruction::Supersede(args) => Self::supersede(program_id, accounts, args),
+            DataStoreInstruction::SetMetadataFlags(args) => {
+                Self::set_metadata_flags(program_id, accounts, args)
+            }
+            DataStoreInstruction::GetETag(args) => Self::get_etag(program_id, accounts, args),
+            DataStoreInstruction::GetUploadProgress(args) => {
+                Self::get_upload_progress(program_id, accounts, args)
+            }
+            #[cfg(feature = "permissioned-init")]
+            DataStoreInstruction::SetAllowlist(args) => {
+                Self::set_allowlist(program_id, accounts, args)
+            }
+            // The allowlist only gates programs built with `permissioned-init`
+            #[cfg(not(feature = "permissioned-init"))]
+            DataStoreInstruction::SetAllowlist(_) => {
+                Err(DataStoreError::InvalidInstructionData.into())
+            }
+            DataStoreInstruction::SwapData(args) => Self::swap_data(program_id, accounts, args),
+            DataStoreInstruction::GetAccountState(args) => {
+                Self::get_account_state(program_id, accounts, args)
+            }
+            DataStoreInstruction::ReclaimRent(args) => {
+                Self::reclaim_rent(program_id, accounts, args)
+            }
+            DataStoreInstruction::SetPause(args) => Self::set_pause(program_id, accounts, args),
+            DataStoreInstruction::ConvertDataType(args) => {
+                Self::convert_data_type(program_id, accounts, args)
+            }
+            DataStoreInstruction::InitializeDataStoreBatch(args) => {
+                Self::initialize_data_store_batch(program_id, accounts, args)
+            }
+            DataStoreInstruction::RelayedUpdate(args) => {
+                Self::relayed_update(program_id, accounts, args)
+            }
+            DataStoreInstruction::Reinitialize(args) => {
+                Self::reinitialize(program_id, accounts, args)
+            }
+            DataStoreInstruction::GetSpaceInfo(args) => {
+                Self::get_space_info(program_id, accounts, args)
+            }
+            DataStoreInstruction::SetName(args) => Self::set_name(program_id, accounts, args),
+            DataStoreInstruction::RawMetadataWrite(args) => {
+                Self::raw_metadata_write(program_id, accounts, args)
+            }
+            DataStoreInstruction::CopyInData(args) => {
+                Self::copy_in_data(program_id, accounts, args)
+            }
+            DataStoreInstruction::SplitData(args) => Self::split_data(program_id, accounts, args),
+            DataStoreInstruction::ConcatData(args) => Self::concat_data(program_id, accounts, args),
+            DataStoreInstruction::SetCacheTtl(args) => {
+                Self::set_cache_ttl(program_id, accounts, args)
+            }
+            DataStoreInstruction::GetOrCreateDirectory(args) => {
+                Self::get_or_create_directory(program_id, accounts, args)
+            }
+            DataStoreInstruction::RecomputeHash(args) => {
+                Self::recompute_hash(program_id, accounts, args)
+            }
+            DataStoreInstruction::SetHeader(args) => Self::set_header(program_id, accounts, args),
+            DataStoreInstruction::GetHeaders(args) => Self::get_headers(program_id, accounts, args),
+            DataStoreInstruction::SetReader(args) => Self::set_reader(program_id, accounts, args),
         }
     }
 
@@ -54,9 +207,7 @@ impl Processor {
         accounts: &[AccountInfo],
         args: InitializeDataStoreArgs,
     ) -> ProgramResult {
-        if args.debug {
-            msg!("InitializeDataStore");
-        }
+        debug_msg!(args.debug, "InitializeDataStore");
 
         let accounts_iter = &mut accounts.iter();
         let feepayer = next_account_info(accounts_iter)?;
@@ -64,82 +215,193 @@ impl Processor {
         let metadata_account = next_account_info(accounts_iter)?;
         let system_program = next_account_info(accounts_iter)?;
 
+        // Ensure the feepayer is allowed to initialize data stores
+        #[cfg(feature = "permissioned-init")]
+        Self::check_allowlist(program_id, feepayer, next_account_info(accounts_iter).ok())?;
+
+        // Ensure requested space is within the maximum data account size and fits a usize,
+        // so the casts below can not wrap on 32-bit targets
+        if args.space > usize::MAX as u64 || args.space > MAX_DATA_SPACE as u64 {
+            return Err(DataStoreError::SpaceTooLarge.into());
+        }
+
+        // Ensure a static data_account has capacity, only dynamic ones may start empty
+        if args.space == 0 && !args.is_dynamic {
+            return Err(DataStoreError::ZeroSpace.into());
+        }
+
+        // Ensure a revision limit allows at least one update
+        if args.max_revisions == Some(0) {
+            return Err(DataStoreError::InvalidInstructionData.into());
+        }
+
+        // Ensure the data store is not initialized yet, unless an identical one may be reused
+        if !metadata_account.data_is_empty() {
+            let account_metadata =
+                DataStoreAccountMetadata::load(&metadata_account.try_borrow_data()?)?;
+            let is_reusable = args.idempotent
+                && metadata_account.owner == program_id
+                && *account_metadata.data_status() == SerializationStatusOption::Initialized
+                && *account_metadata.authority() == args.authority
+                && *account_metadata.data_type() == args.data_type
+                && account_metadata.metadata_pda(program_id, data_account.key)?
+                    == *metadata_account.key;
+            if !is_reusable {
+                return Err(DataStoreError::AlreadyInitialized.into());
+            }
+
+            debug_msg!(args.debug, "data store already initialized");
+
+            return Ok(());
+        }
+
+        // Ensure the content_type fits the metadata and, if strict, matches the initial data
+        if args.content_type.len() > MAX_CONTENT_TYPE_LEN {
+            return Err(DataStoreError::InvalidInstructionData.into());
+        }
+        if args.strict_content_type && !matches_content_type(&args.content_type, &args.initial_data)
+        {
+            return Err(DataStoreError::DataVerificationFailed.into());
+        }
+
+        // Ensure a named data_account is the file PDA of the authority and file_name
+        let file_bump_seed = if args.file_name.is_empty() {
+            None
+        } else {
+            if args.file_name.len() > MAX_FILE_NAME_LEN || args.is_created {
+                return Err(DataStoreError::InvalidInstructionData.into());
+            }
+            let (pda, bump_seed) = derive_file_pda(program_id, &args.authority, &args.file_name);
+            if pda != *data_account.key {
+                return Err(DataStoreError::InvalidPDA.into());
+            }
+            Some(bump_seed)
+        };
+
         // Create a data_account of given space if not done so already
         if !args.is_created {
             let space = args.space as usize;
-            let rent_exemption_amount = Rent::get()?.minimum_balance(space);
-
-            let create_account_ix = system_instruction::create_account(
-                &feepayer.key,
-                &data_account.key,
-                rent_exemption_amount,
-                space as u64,
-                &program_id,
-            );
-            invoke(
-                &create_account_ix,
-                &[
-                    feepayer.clone(),
-                    data_account.clone(),
-                    system_program.clone(),
-                ],
-            )?;
 
-            if args.debug {
-                msg!("account of space: {} created", space);
+            // A file PDA is signed for by the program, a keypair data_account by the client
+            match file_bump_seed {
+                Some(bump_seed) => Self::create_program_account(
+                    program_id,
+                    feepayer,
+                    data_account,
+                    system_program,
+                    space,
+                    &[&[
+                        FILE_SEED,
+                        args.authority.as_ref(),
+                        args.file_name.as_bytes(),
+                        &[bump_seed],
+                    ]],
+                )?,
+                None => Self::create_program_account(
+                    program_id,
+                    feepayer,
+                    data_account,
+                    system_program,
+                    space,
+                    &[],
+                )?,
             }
+
+            debug_msg!(args.debug, "account of space: {} created", space);
         }
         // Else set data program as the owner of the data_account
         else {
             let assign_ix = system_instruction::assign(&data_account.key, &program_id);
             invoke(&assign_ix, &[data_account.clone(), system_program.clone()])?;
 
-            if args.debug {
-                msg!("account owner updated");
+            debug_msg!(args.debug, "account owner updated");
+
+            // Ensure the adopted data_account has the requested space, resizing dynamic ones
+            let space = args.space as usize;
+            if data_account.data_len() != space {
+                if !args.is_dynamic {
+                    return Err(DataStoreError::SizeMismatch.into());
+                }
+                Self::resize_data_account(feepayer, data_account, system_program, space)?;
+
+                debug_msg!(args.debug, "adopted account realloc-ed {}", space);
             }
         }
         data_account.data.borrow_mut().fill(0);
 
+        // Ensure the initial data fits the data_account and write it
+        if args.initial_data.len() > data_account.data_len() {
+            return Err(DataStoreError::InsufficientSpace.into());
+        }
+        data_account.data.borrow_mut()[..args.initial_data.len()]
+            .copy_from_slice(&args.initial_data);
+
         // Create data_account PDA to store metadata
-        let (pda, bump_seed) = Pubkey::find_program_address(
-            &[PDA_SEED, data_account.key.as_ref()],
-            program_id,
-        );
-        // Ensure the PDA is valid
-        if pda != *metadata_account.key {
+        let pda_seeds = metadata_pda_seeds(data_account.key, &args.namespace);
+        let (pda, bump_seed) = Pubkey::find_program_address(&pda_seeds, program_id);
+        // Ensure the PDA is valid and the supplied bump_seed is the canonical one.
+        // The bump is checked against find_program_address rather than used directly,
+        // so a data_account can never get a second metadata PDA from a non-canonical bump.
+        if pda != *metadata_account.key || bump_seed != args.bump_seed {
             return Err(DataStoreError::InvalidPDA.into());
         }
         // Create PDA account
-        let rent_exemption_amount = Rent::get()?.minimum_balance(METADATA_SIZE);
-        let create_pda_ix = system_instruction::create_account(
-            &feepayer.key,
-            &metadata_account.key,
-            rent_exemption_amount,
-            METADATA_SIZE as u64,
-            &program_id,
-        );
-        invoke_signed(
-            &create_pda_ix,
-            &[
-                feepayer.clone(),
-                data_account.clone(),
-                metadata_account.clone(),
-                system_program.clone(),
-            ],
-            &[&[PDA_SEED, data_account.key.as_ref(), &[bump_seed]]],
+        let bump = [bump_seed];
+        let mut signer_seeds = pda_seeds.clone();
+        signer_seeds.push(&bump);
+        Self::create_program_account(
+            program_id,
+            feepayer,
+            metadata_account,
+            system_program,
+            METADATA_SIZE,
+            &[&signer_seeds],
         )?;
 
-        if args.debug {
-            msg!("metadata pda created");
+        // Ensure the created metadata_account is rent-exempt for its actual size
+        if metadata_account.lamports() < Rent::get()?.minimum_balance(metadata_account.data_len()) {
+            return Err(DataStoreError::NotRentExempt.into());
         }
 
+        debug_msg!(args.debug, "metadata pda created");
+
         // Create initial state for data_account metadata and write to it
-        let account_metadata = DataStoreAccountMetadata::new(
+        let mut account_metadata = DataStoreAccountMetadata::new(
             args.authority,
             args.data_type,
             bump_seed,
+            args.is_dynamic,
         );
-        account_metadata.serialize(&mut &mut metadata_account.data.borrow_mut()[..])?;
+        account_metadata.set_space(data_account.data_len());
+        account_metadata.set_data_len(args.initial_data.len());
+        if args.stream_hash {
+            account_metadata.start_hash_stream(data_account.key, &data_account.data.borrow());
+        } else {
+            account_metadata.update_checksums(data_account.key, &data_account.data.borrow());
+        }
+        account_metadata.set_append_only(args.append_only);
+        account_metadata.set_namespace(args.namespace);
+        account_metadata.set_private(args.private);
+        account_metadata.set_content_type(args.content_type);
+        account_metadata.set_strict_content_type(args.strict_content_type);
+        account_metadata.set_expected_total(args.expected_total);
+        account_metadata.set_creator(feepayer.key);
+        account_metadata.set_min_update_interval_slots(args.min_update_interval_slots);
+        account_metadata.set_expiry_slot(args.expiry_slot);
+        account_metadata.set_max_revisions(args.max_revisions);
+        account_metadata.set_audit(args.audit);
+        account_metadata.set_name(args.file_name);
+        account_metadata.set_zero_on_shrink(args.zero_on_shrink);
+        account_metadata.set_cache_ttl_seconds(args.cache_ttl_seconds);
+        account_metadata.set_indexable(args.indexable);
+        account_metadata.store(&mut metadata_account.data.borrow_mut())?;
+
+        DataStoreEvent::Initialized {
+            data_account: *data_account.key,
+            authority: *account_metadata.authority(),
+            data_type: account_metadata.data_type().clone(),
+        }
+        .emit();
 
         Ok(())
     }
@@ -149,236 +411,316 @@ impl Processor {
         accounts: &[AccountInfo],
         args: UpdateDataStoreArgs,
     ) -> ProgramResult {
-        if args.debug {
-            msg!("UpdateDataStore");
-        }
+        debug_msg!(args.debug, "UpdateDataStore");
 
         let accounts_iter = &mut accounts.iter();
         let authority = next_account_info(accounts_iter)?;
         let data_account = next_account_info(accounts_iter)?;
         let metadata_account = next_account_info(accounts_iter)?;
         let system_program = next_account_info(accounts_iter)?;
+        let audit_account = next_account_info(accounts_iter).ok();
 
-        // Ensure authority is signer
-        if !authority.is_signer {
-            return Err(DataStoreError::NotSigner.into());
-        }
+        let plan = Self::validate_update(
+            program_id,
+            authority,
+            data_account,
+            metadata_account,
+            &ValidateArgs::from(&args),
+        )?;
 
-        // Ensure authority, data_account, and metadata_account are writable
-        if !authority.is_writable
-            || !data_account.is_writable
-            || !metadata_account.is_writable
-        {
-            return Err(DataStoreError::NotWriteable.into());
-        }
+        debug_msg!(args.debug, "account checks passed");
 
-        // Ensure length is not 0
-        if metadata_account.data_is_empty() {
-            return Err(DataStoreError::NoAccountLength.into());
-        }
+        Self::apply_update(
+            program_id,
+            authority,
+            data_account,
+            metadata_account,
+            system_program,
+            audit_account,
+            plan,
+            &args,
+        )
+    }
 
-        let mut account_metadata =
-            DataStoreAccountMetadata::try_from_slice(&metadata_account.try_borrow_data()?)?;
+    /// Writes args.data as planned by validate_update, payer funding any realloc and the
+    /// growth of the audit_account of audited data stores
+    #[allow(clippy::too_many_arguments)]
+    fn apply_update<'a>(
+        program_id: &Pubkey,
+        payer: &AccountInfo<'a>,
+        data_account: &AccountInfo<'a>,
+        metadata_account: &AccountInfo<'a>,
+        system_program: &AccountInfo<'a>,
+        audit_account: Option<&AccountInfo<'a>>,
+        plan: UpdatePlan,
+        args: &UpdateDataStoreArgs,
+    ) -> ProgramResult {
+        let UpdatePlan {
+            mut account_metadata,
+            offset,
+            end_len,
+            new_len,
+        } = plan;
 
-        // Ensure data_account is initialized and not finalized
-        match *account_metadata.data_status() {
-            SerializationStatusOption::Uninitialized => {
-                return Err(DataStoreError::NotInitialized.into());
-            }
-            SerializationStatusOption::Finalized => {
-                return Err(DataStoreError::AlreadyFinalized.into());
+        // Ensure data_account has enough space by reallocing if needed.
+        // A dynamic data_account initialized with zero space grows from empty on its first
+        // write, with the payer topping up its whole rent-exempt balance for new_len.
+        let old_len = data_account.data_len();
+        if old_len != new_len {
+            if new_len < old_len && account_metadata.zero_on_shrink() {
+                data_account.data.borrow_mut()[new_len..].fill(0);
             }
-            _ => (),
-        }
+            Self::resize_data_account(payer, data_account, system_program, new_len)?;
 
-        // Ensure data_account is being written to by valid authority
-        if account_metadata.authority() != authority.key {
-            return Err(DataStoreError::InvalidAuthority.into());
+            debug_msg!(args.debug, "realloc-ed {} from {}", new_len, old_len);
         }
 
-        // Ensure the metadata_account corresponds to the data_account
-        let pda = Pubkey::create_program_address(
-            &[
-                PDA_SEED,
-                data_account.key.as_ref(),
-                &[account_metadata.bump_seed()],
-            ],
-            program_id,
-        )?;
-        if pda != *metadata_account.key {
-            return Err(DataStoreError::InvalidPDA.into());
+        // Ensure the data_account holds the whole write after any realloc, failing
+        // cleanly rather than panicking on the copy below
+        if data_account.data_len() < end_len {
+            return Err(DataStoreError::InsufficientSpace.into());
         }
 
-        let old_len = data_account.data_len();
-        let end_len = args.offset as usize + args.data.len();
+        // Update the data_account
+        debug_msg!(
+            args.debug,
+            "replaced {:?} with {:?}",
+            &args.data,
+            &data_account.data.borrow()[offset..end_len]
+        );
 
-        // Ensure static data_account has sufficient space
-        if !account_metadata.is_dynamic() && old_len < end_len {
-            return Err(DataStoreError::InsufficientSpace.into());
+        // Zero-fill any gap between the written data and offset, so it never holds stale bytes
+        let written_len = account_metadata.data_len();
+        if offset > written_len {
+            data_account.data.borrow_mut()[written_len..offset].fill(0);
         }
 
-        if args.debug {
-            msg!("account checks passed");
-        }
+        data_account.data.borrow_mut()[offset..end_len].copy_from_slice(&args.data);
 
-        let new_len = if !account_metadata.is_dynamic() {
-            old_len
-        } else if args.realloc_down {
+        // Update the metadata_account
+        let data_len = if account_metadata.is_dynamic() && args.realloc_down {
             end_len
         } else {
-            old_len.max(end_len)
+            account_metadata.data_len().max(end_len)
         };
+        account_metadata.set_space(new_len);
+        account_metadata.set_data_len(data_len);
+        account_metadata.set_upload_cursor(end_len as u64);
+        // Fold appended bytes into the hash stream, if any, rather than rehashing all data
+        if offset == written_len {
+            account_metadata.append_checksums(
+                data_account.key,
+                &data_account.data.borrow(),
+                written_len,
+            );
+        } else {
+            account_metadata.update_checksums(data_account.key, &data_account.data.borrow());
+        }
+        Self::commit_update(
+            program_id,
+            payer,
+            data_account,
+            metadata_account,
+            audit_account.map(|audit_account| (system_program, audit_account)),
+            account_metadata,
+            args.debug,
+        )
+    }
 
-        // Update the metadata_account
-        account_metadata.set_data_type(args.data_type);
-        account_metadata.serialize(&mut &mut metadata_account.data.borrow_mut()[..])?;
+    /// Records an update already written to data_account with its checksums: stamps its
+    /// slot and bumps the revision, logs it to the audit_account of an audited
+    /// data_account, funded by payer through system_program, finalizes a data_account
+    /// whose last allowed update this was, then stores account_metadata
+    fn commit_update<'a>(
+        program_id: &Pubkey,
+        payer: &AccountInfo<'a>,
+        data_account: &AccountInfo<'a>,
+        metadata_account: &AccountInfo<'a>,
+        audit_accounts: Option<(&AccountInfo<'a>, &AccountInfo<'a>)>,
+        mut account_metadata: DataStoreAccountMetadata,
+        debug: bool,
+    ) -> ProgramResult {
+        account_metadata.set_updated_slot(Clock::get()?.slot);
+        account_metadata
+            .increment_revision()
+            .ok_or(DataStoreError::Overflow)?;
 
-        // Ensure data_account has enough space by reallocing if needed
-        if old_len != new_len {
-            let new_space = new_len;
-            let new_minimum_balance = Rent::get()?.minimum_balance(new_space);
-            let lamports_diff = if old_len < new_len {
-                new_minimum_balance.saturating_sub(data_account.lamports())
-            } else {
-                data_account.lamports().saturating_sub(new_minimum_balance)
+        // Log the update to the audit_account of an audited data_account
+        if account_metadata.is_audited() {
+            let (system_program, audit_account) =
+                audit_accounts.ok_or(ProgramError::NotEnoughAccountKeys)?;
+            let record = AuditRecord {
+                slot: account_metadata.updated_slot(),
+                authority: *account_metadata.authority(),
+                revision: account_metadata.revision(),
+                data_hash: *account_metadata.data_hash(),
             };
+            Self::append_audit_record(
+                program_id,
+                payer,
+                data_account,
+                audit_account,
+                system_program,
+                &record,
+            )?;
 
-            if old_len < new_len {
-                let transfer_ix = system_instruction::transfer(
-                    authority.key,
-                    data_account.key,
-                    lamports_diff,
-                );
-                invoke(
-                    &transfer_ix,
-                    &[
-                        authority.clone(),
-                        data_account.clone(),
-                        system_program.clone(),
-                    ],
-                )?;
-            } else {
-                let authority_lamports = authority.lamports();
-                **authority.lamports.borrow_mut() = authority_lamports
-                    .checked_add(lamports_diff)
-                    .ok_or(DataStoreError::Overflow)?;
-                **data_account.lamports.borrow_mut() = new_minimum_balance;
-            }
-
-            data_account.realloc(new_space, false)?;
+            debug_msg!(debug, "audit record appended");
+        }
 
-            if args.debug {
-                msg!("realloc-ed {}", new_space);
-            }
+        // Finalize a data_account whose last allowed update this was
+        let is_exhausted = account_metadata.is_revision_limit_reached();
+        if is_exhausted {
+            account_metadata.set_data_status(SerializationStatusOption::Finalized);
+            account_metadata.end_hash_stream();
         }
+        account_metadata.store(&mut metadata_account.data.borrow_mut())?;
 
-        // Update the data_account
-        if args.debug {
-            msg!(
-                "replaced {:?} with {:?}",
-                &args.data,
-                &data_account.data.borrow()[args.offset as usize..end_len]
-            );
+        DataStoreEvent::Updated {
+            data_account: *data_account.key,
+            revision: account_metadata.revision(),
+            data_len: account_metadata.data_len() as u64,
+            data_hash: *account_metadata.data_hash(),
         }
+        .emit();
+
+        if is_exhausted {
+            DataStoreEvent::Finalized {
+                data_account: *data_account.key,
+                data_hash: *account_metadata.data_hash(),
+            }
+            .emit();
 
-        data_account.data.borrow_mut()[args.offset as usize..end_len]
-            .copy_from_slice(&args.data);
+            debug_msg!(debug, "revision limit reached, finalized");
+        }
 
         Ok(())
     }
 
-    fn update_data_store_authority(
+    fn write_and_finalize(
         program_id: &Pubkey,
         accounts: &[AccountInfo],
-        args: UpdateDataStoreAuthorityArgs,
+        args: UpdateDataStoreArgs,
     ) -> ProgramResult {
-        if args.debug {
-            msg!("UpdateDataStoreAuthority");
-        }
+        debug_msg!(args.debug, "WriteAndFinalize");
+
+        let debug = args.debug;
+
+        // Write the data with every check of a regular update
+        Self::update_data_store(program_id, accounts, args)?;
 
         let accounts_iter = &mut accounts.iter();
-        let authority = next_account_info(accounts_iter)?;
+        let _authority = next_account_info(accounts_iter)?;
         let data_account = next_account_info(accounts_iter)?;
         let metadata_account = next_account_info(accounts_iter)?;
-        let new_authority = next_account_info(accounts_iter)?;
-
-        // Ensure authority and new_authority are signer
-        if !authority.is_signer || !new_authority.is_signer {
-            return Err(DataStoreError::NotSigner.into());
-        }
 
-        // Ensure metadata_account is writable
-        if !metadata_account.is_writable {
-            return Err(DataStoreError::NotWriteable.into());
-        }
-
-        // Ensure length is not 0
-        if metadata_account.data_is_empty() {
-            return Err(DataStoreError::NoAccountLength.into());
-        }
+        // Seal the data_account in the same instruction, unless the update already did as
+        // the last one its revision limit allows
+        let mut is_sealed = false;
+        let account_metadata = Self::with_metadata(metadata_account, |account_metadata| {
+            if *account_metadata.data_status() == SerializationStatusOption::Finalized {
+                is_sealed = true;
+                return Ok(());
+            }
 
-        let mut account_metadata =
-            DataStoreAccountMetadata::try_from_slice(&metadata_account.try_borrow_data()?)?;
+            // Ensure a tracked upload is complete
+            if !account_metadata.is_upload_complete() {
+                return Err(DataStoreError::UploadIncomplete.into());
+            }
 
-        // Ensure data_account is initialized
-        if *account_metadata.data_status() == SerializationStatusOption::Uninitialized {
-            return Err(DataStoreError::NotInitialized.into());
-        }
+            // Ensure a directory holds well-formed entries for its format
+            if *account_metadata.data_type() == DataStoreTypeOption::Directory
+                && !verify_directory_data(
+                    &data_account.data.borrow()[..account_metadata.data_len()],
+                    account_metadata.directory_format(),
+                )
+            {
+                return Err(DataStoreError::DataVerificationFailed.into());
+            }
 
-        // Ensure data_account is being written to by valid authority
-        if account_metadata.authority() != authority.key {
-            return Err(DataStoreError::InvalidAuthority.into());
-        }
+            account_metadata.set_data_status(SerializationStatusOption::Finalized);
+            account_metadata.end_hash_stream();
 
-        // Ensure the metadata_account corresponds to the data_account
-        let pda = Pubkey::create_program_address(
-            &[
-                PDA_SEED,
-                data_account.key.as_ref(),
-                &[account_metadata.bump_seed()],
-            ],
-            program_id,
-        )?;
-        if pda != *metadata_account.key {
-            return Err(DataStoreError::InvalidPDA.into());
+            Ok(())
+        })?;
+        if is_sealed {
+            return Ok(());
         }
 
-        if args.debug {
-            msg!("account checks passed")
+        DataStoreEvent::Finalized {
+            data_account: *data_account.key,
+            data_hash: *account_metadata.data_hash(),
         }
+        .emit();
 
-        // Update the authority
-        account_metadata.set_authority(new_authority.key);
-        account_metadata.serialize(&mut &mut metadata_account.data.borrow_mut()[..])?;
-
-        if args.debug {
-            msg!("updated authority");
-        }
+        debug_msg!(debug, "updated finalize flag");
 
         Ok(())
     }
 
-    fn finalize_data_store(
+    fn validate(
         program_id: &Pubkey,
         accounts: &[AccountInfo],
-        args: FinalizeDataStoreArgs,
+        args: ValidateArgs,
     ) -> ProgramResult {
-        if args.debug {
-            msg!("FinalizeDataStore");
-        }
+        debug_msg!(args.debug, "Validate");
 
         let accounts_iter = &mut accounts.iter();
         let authority = next_account_info(accounts_iter)?;
         let data_account = next_account_info(accounts_iter)?;
         let metadata_account = next_account_info(accounts_iter)?;
 
+        Self::validate_update(program_id, authority, data_account, metadata_account, &args)?;
+
+        debug_msg!(args.debug, "account checks passed");
+
+        Ok(())
+    }
+
+    /// Runs every check of an update without writing anything
+    fn validate_update(
+        program_id: &Pubkey,
+        authority: &AccountInfo,
+        data_account: &AccountInfo,
+        metadata_account: &AccountInfo,
+        args: &ValidateArgs,
+    ) -> Result<UpdatePlan, ProgramError> {
         // Ensure authority is signer
         if !authority.is_signer {
             return Err(DataStoreError::NotSigner.into());
         }
 
-        // Ensure metadata_account is writable
-        if !metadata_account.is_writable {
+        Self::validate_authorized_update(
+            program_id,
+            authority.key,
+            authority,
+            data_account,
+            metadata_account,
+            args,
+        )
+    }
+
+    /// Runs the checks of an update authorized by authority, either as a signer of the
+    /// transaction or through a relayed signature, with payer funding any realloc
+    fn validate_authorized_update(
+        program_id: &Pubkey,
+        authority: &Pubkey,
+        payer: &AccountInfo,
+        data_account: &AccountInfo,
+        metadata_account: &AccountInfo,
+        args: &ValidateArgs,
+    ) -> Result<UpdatePlan, ProgramError> {
+        // Ensure data_account and metadata_account are distinct accounts
+        if data_account.key == metadata_account.key {
+            return Err(DataStoreError::DuplicateAccount.into());
+        }
+
+        // Ensure data_account and metadata_account are owned by the data program
+        if data_account.owner != program_id || metadata_account.owner != program_id {
+            return Err(DataStoreError::InvalidAccountOwner.into());
+        }
+
+        // Ensure payer, data_account, and metadata_account are writable
+        if !payer.is_writable || !data_account.is_writable || !metadata_account.is_writable {
             return Err(DataStoreError::NotWriteable.into());
         }
 
@@ -387,8 +729,8 @@ impl Processor {
             return Err(DataStoreError::NoAccountLength.into());
         }
 
-        let mut account_metadata =
-            DataStoreAccountMetadata::try_from_slice(&metadata_account.try_borrow_data()?)?;
+        let account_metadata =
+            DataStoreAccountMetadata::load(&metadata_account.try_borrow_data()?)?;
 
         // Ensure data_account is initialized and not finalized
         match *account_metadata.data_status() {
@@ -401,63 +743,4020 @@ impl Processor {
             _ => (),
         }
 
-        // Ensure metadata_account is being written to by valid authority
-        if account_metadata.authority() != authority.key {
+        // Ensure data_account is not frozen
+        if account_metadata.is_frozen() {
+            return Err(DataStoreError::Frozen.into());
+        }
+
+        // Ensure the data_type of an initialized data_account is not changed
+        if args.data_type != *account_metadata.data_type() {
+            return Err(DataStoreError::InvalidDataType.into());
+        }
+
+        // Ensure data_account is being written to by valid authority
+        if account_metadata.authority() != authority {
             return Err(DataStoreError::InvalidAuthority.into());
         }
 
         // Ensure the metadata_account corresponds to the data_account
-        let pda = Pubkey::create_program_address(
-            &[
-                PDA_SEED,
-                data_account.key.as_ref(),
-                &[account_metadata.bump_seed()],
-            ],
-            program_id,
-        )?;
+        let pda = account_metadata.metadata_pda(program_id, data_account.key)?;
         if pda != *metadata_account.key {
             return Err(DataStoreError::InvalidPDA.into());
         }
 
-        if args.debug {
-            msg!("account checks passed");
+        // Ensure the writer has seen the latest revision, if requested
+        if let Some(expected_revision) = args.expected_revision {
+            if expected_revision != account_metadata.revision() {
+                return Err(DataStoreError::RevisionMismatch.into());
+            }
         }
 
-        // Update the data_account
-        account_metadata.set_data_status(SerializationStatusOption::Finalized);
-        account_metadata.serialize(&mut &mut metadata_account.data.borrow_mut()[..])?;
-
-        if args.debug {
-            msg!("updated finalize flag");
+        // Ensure the writer has seen the current contents, if requested
+        if let Some(expected_hash) = args.expected_hash {
+            if expected_hash != *account_metadata.data_hash() {
+                return Err(DataStoreError::RevisionMismatch.into());
+            }
         }
 
-        Ok(())
-    }
+        // Ensure a rate-limited data_account was not updated too recently
+        Self::check_update_interval(&account_metadata)?;
 
-    fn close_data_store(
-        program_id: &Pubkey,
-        accounts: &[AccountInfo],
-        args: CloseDataStoreArgs,
-    ) -> ProgramResult {
-        if args.debug {
-            msg!("CloseDataStore");
+        let old_len = data_account.data_len();
+        let offset = usize::try_from(args.offset).map_err(|_| DataStoreError::Overflow)?;
+        let end_len = usize::try_from(args.data_len)
+            .ok()
+            .and_then(|data_len| offset.checked_add(data_len))
+            .ok_or(DataStoreError::Overflow)?;
+
+        // Ensure the write is contiguous with the written data, unless gaps are allowed
+        if !args.allow_gaps && offset > account_metadata.data_len() {
+            return Err(DataStoreError::NonContiguousWrite.into());
         }
 
-        let accounts_iter = &mut accounts.iter();
-        let authority = next_account_info(accounts_iter)?;
-        let data_account = next_account_info(accounts_iter)?;
-        let metadata_account = next_account_info(accounts_iter)?;
+        // Ensure append-only data_account is only written past its written length
+        if account_metadata.is_append_only() && offset < account_metadata.data_len() {
+            return Err(DataStoreError::AppendOnlyViolation.into());
+        }
 
-        // Ensure authority is signer
-        if !authority.is_signer {
-            return Err(DataStoreError::NotSigner.into());
+        // Ensure the write ends within the maximum data account size
+        if end_len > MAX_DATA_SPACE {
+            return Err(DataStoreError::SpaceTooLarge.into());
+        }
+
+        // Ensure static data_account has sufficient space
+        if !account_metadata.is_dynamic() && old_len < end_len {
+            return Err(DataStoreError::InsufficientSpace.into());
+        }
+
+        let new_len = if !account_metadata.is_dynamic() {
+            old_len
+        } else if args.realloc_down {
+            end_len
+        } else {
+            old_len.max(end_len)
+        };
+
+        // Ensure the write, hashing and realloc fit the compute budget, failing early
+        // rather than running out of compute midway. Appends to a hash stream only hash
+        // the written bytes, in software.
+        let (hashed_len, streamed_len) = if account_metadata.is_streamed_append(offset) {
+            (0, end_len - offset)
+        } else if account_metadata.is_dynamic() && args.realloc_down {
+            (end_len, 0)
+        } else {
+            (account_metadata.data_len().max(end_len), 0)
+        };
+        let compute_units =
+            estimate_update_compute_units(end_len - offset, hashed_len, new_len != old_len)
+                .saturating_add(
+                    (streamed_len as u64).saturating_mul(UPDATE_COMPUTE_UNITS_PER_STREAMED_BYTE),
+                );
+        if compute_units > UPDATE_COMPUTE_BUDGET {
+            return Err(DataStoreError::OperationTooLarge.into());
+        }
+
+        Ok(UpdatePlan {
+            account_metadata,
+            offset,
+            end_len,
+            new_len,
+        })
+    }
+
+    /// Ensures the min_update_interval_slots of a rate-limited data store passed since its
+    /// last update
+    fn check_update_interval(account_metadata: &DataStoreAccountMetadata) -> ProgramResult {
+        let min_interval = account_metadata.min_update_interval_slots();
+        if min_interval != 0 && account_metadata.updated_slot() != 0 {
+            let next_slot = account_metadata.updated_slot().saturating_add(min_interval);
+            if Clock::get()?.slot < next_slot {
+                return Err(DataStoreError::UpdateTooSoon.into());
+            }
+        }
+
+        Ok(())
+    }
+
+    fn update_data_store_authority(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        args: UpdateDataStoreAuthorityArgs,
+    ) -> ProgramResult {
+        debug_msg!(args.debug, "UpdateDataStoreAuthority");
+
+        let accounts_iter = &mut accounts.iter();
+        let authority = next_account_info(accounts_iter)?;
+        let data_account = next_account_info(accounts_iter)?;
+        let metadata_account = next_account_info(accounts_iter)?;
+        let new_authority = next_account_info(accounts_iter)?;
+
+        // Ensure authority is signer
+        if !authority.is_signer {
+            return Err(DataStoreError::NotSigner.into());
+        }
+
+        // Ensure new_authority is signer, or a PDA derived from the supplied seeds
+        match &args.new_authority_pda {
+            Some(new_authority_pda) => {
+                let seeds: Vec<&[u8]> = new_authority_pda.seeds.iter().map(Vec::as_slice).collect();
+                let pda = Pubkey::create_program_address(&seeds, &new_authority_pda.program)?;
+                if pda != *new_authority.key {
+                    return Err(DataStoreError::InvalidPDA.into());
+                }
+            }
+            None => {
+                if !new_authority.is_signer {
+                    return Err(DataStoreError::NotSigner.into());
+                }
+            }
+        }
+
+        // Ensure metadata_account is writable
+        if !metadata_account.is_writable {
+            return Err(DataStoreError::NotWriteable.into());
+        }
+
+        // Ensure length is not 0
+        if metadata_account.data_is_empty() {
+            return Err(DataStoreError::NoAccountLength.into());
+        }
+
+        // Ensure metadata_account is owned by the data program
+        if metadata_account.owner != program_id {
+            return Err(DataStoreError::InvalidAccountOwner.into());
+        }
+
+        Self::with_metadata(metadata_account, |account_metadata| {
+            // Ensure data_account is initialized
+            if *account_metadata.data_status() == SerializationStatusOption::Uninitialized {
+                return Err(DataStoreError::NotInitialized.into());
+            }
+
+            // Ensure data_account is being written to by valid authority, which can only
+            // be while it was not renounced
+            if account_metadata.is_authority_renounced()
+                || account_metadata.authority() != authority.key
+            {
+                return Err(DataStoreError::InvalidAuthority.into());
+            }
+
+            // Ensure the metadata_account corresponds to the data_account
+            let pda = account_metadata.metadata_pda(program_id, data_account.key)?;
+            if pda != *metadata_account.key {
+                return Err(DataStoreError::InvalidPDA.into());
+            }
+
+            // Ensure the authority actually changes
+            if account_metadata.authority() == new_authority.key {
+                return Err(DataStoreError::AuthorityUnchanged.into());
+            }
+
+            debug_msg!(args.debug, "account checks passed");
+
+            // Update the authority
+            account_metadata.set_authority(new_authority.key);
+
+            Ok(())
+        })?;
+
+        DataStoreEvent::AuthorityChanged {
+            data_account: *data_account.key,
+            old_authority: *authority.key,
+            new_authority: *new_authority.key,
+        }
+        .emit();
+
+        debug_msg!(args.debug, "updated authority");
+
+        Ok(())
+    }
+
+    fn finalize_data_store(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        args: FinalizeDataStoreArgs,
+    ) -> ProgramResult {
+        debug_msg!(args.debug, "FinalizeDataStore");
+
+        let accounts_iter = &mut accounts.iter();
+        let authority = next_account_info(accounts_iter)?;
+        let data_account = next_account_info(accounts_iter)?;
+        let metadata_account = next_account_info(accounts_iter)?;
+
+        // Ensure data_account and metadata_account are distinct accounts
+        if data_account.key == metadata_account.key {
+            return Err(DataStoreError::DuplicateAccount.into());
+        }
+
+        // Ensure data_account and metadata_account are owned by the data program. Finalize
+        // only reads the data_account, to verify its contents, so unlike update and close
+        // it need not be writable.
+        if data_account.owner != program_id || metadata_account.owner != program_id {
+            return Err(DataStoreError::InvalidAccountOwner.into());
+        }
+
+        // Ensure authority is signer
+        if !authority.is_signer {
+            return Err(DataStoreError::NotSigner.into());
+        }
+
+        // Ensure metadata_account is writable
+        if !metadata_account.is_writable {
+            return Err(DataStoreError::NotWriteable.into());
+        }
+
+        // Ensure length is not 0
+        if metadata_account.data_is_empty() {
+            return Err(DataStoreError::NoAccountLength.into());
+        }
+
+        let account_metadata = Self::with_metadata(metadata_account, |account_metadata| {
+            // Ensure data_account is initialized and not finalized
+            match *account_metadata.data_status() {
+                SerializationStatusOption::Uninitialized => {
+                    return Err(DataStoreError::NotInitialized.into());
+                }
+                SerializationStatusOption::Finalized => {
+                    return Err(DataStoreError::AlreadyFinalized.into());
+                }
+                _ => (),
+            }
+
+            // Ensure metadata_account is being written to by valid authority
+            if account_metadata.authority() != authority.key {
+                return Err(DataStoreError::InvalidAuthority.into());
+            }
+
+            // Ensure the metadata_account corresponds to the data_account
+            let pda = account_metadata.metadata_pda(program_id, data_account.key)?;
+            if pda != *metadata_account.key {
+                return Err(DataStoreError::InvalidPDA.into());
+            }
+
+            // Ensure a tracked upload is complete
+            if !account_metadata.is_upload_complete() {
+                return Err(DataStoreError::UploadIncomplete.into());
+            }
+
+            // Ensure a directory holds well-formed entries for its format
+            if *account_metadata.data_type() == DataStoreTypeOption::Directory
+                && !verify_directory_data(
+                    &data_account.data.borrow()[..account_metadata.data_len()],
+                    account_metadata.directory_format(),
+                )
+            {
+                return Err(DataStoreError::DataVerificationFailed.into());
+            }
+
+            debug_msg!(args.debug, "account checks passed");
+
+            // Update the data_account, renouncing the authority if locking
+            account_metadata.set_data_status(SerializationStatusOption::Finalized);
+            account_metadata.end_hash_stream();
+            if args.lock_authority {
+                account_metadata.set_authority(&RENOUNCED_AUTHORITY);
+                account_metadata.set_flag(FLAG_AUTHORITY_RENOUNCED, true);
+            }
+
+            Ok(())
+        })?;
+
+        DataStoreEvent::Finalized {
+            data_account: *data_account.key,
+            data_hash: *account_metadata.data_hash(),
+        }
+        .emit();
+
+        if args.lock_authority {
+            DataStoreEvent::AuthorityChanged {
+                data_account: *data_account.key,
+                old_authority: *authority.key,
+                new_authority: RENOUNCED_AUTHORITY,
+            }
+            .emit();
+
+            debug_msg!(args.debug, "renounced authority");
+        }
+
+        // Register the content of the data_account if the content PDA is provided
+        if let Ok(content_account) = next_account_info(accounts_iter) {
+            let system_program = next_account_info(accounts_iter)?;
+            let data_len = account_metadata.data_len();
+            let registered = Self::register_content_hash(
+                program_id,
+                authority,
+                data_account,
+                &data_account.data.borrow()[..data_len],
+                content_account,
+                system_program,
+            )?;
+            if registered {
+                Self::with_metadata(metadata_account, |account_metadata| {
+                    account_metadata.set_flag(FLAG_CONTENT_REGISTERED, true);
+                    Ok(())
+                })?;
+
+                debug_msg!(args.debug, "content hash registered");
+            }
+        }
+
+        debug_msg!(args.debug, "updated finalize flag");
+
+        Ok(())
+    }
+
+    /// Maps the content hash of data to data_account in the content PDA, created and
+    /// paid for by payer. A content PDA that already exists keeps its first registrant.
+    /// Returns whether data_account got registered.
+    fn register_content_hash<'a>(
+        program_id: &Pubkey,
+        payer: &AccountInfo<'a>,
+        data_account: &AccountInfo<'a>,
+        data: &[u8],
+        content_account: &AccountInfo<'a>,
+        system_program: &AccountInfo<'a>,
+    ) -> Result<bool, ProgramError> {
+        // Ensure the content_account is the content PDA of data
+        let content_hash = compute_content_hash(data);
+        let (pda, bump_seed) = derive_content_pda(program_id, &content_hash);
+        if pda != *content_account.key {
+            return Err(DataStoreError::InvalidPDA.into());
+        }
+
+        // Keep the first data account registered for this content
+        if !content_account.data_is_empty() {
+            return Ok(false);
+        }
+
+        // Ensure payer and content_account are writable
+        if !payer.is_writable || !content_account.is_writable {
+            return Err(DataStoreError::NotWriteable.into());
+        }
+
+        Self::create_program_account(
+            program_id,
+            payer,
+            content_account,
+            system_program,
+            CONTENT_INDEX_SIZE,
+            &[&[CONTENT_SEED, &content_hash, &[bump_seed]]],
+        )?;
+
+        content_account
+            .data
+            .borrow_mut()
+            .copy_from_slice(data_account.key.as_ref());
+
+        Ok(true)
+    }
+
+    /// Closes the content PDA registering data_account, whose contents are data, refunding
+    /// its rent to recipient. Keeps the registry from pointing at contents that are gone.
+    fn close_content_pda(
+        program_id: &Pubkey,
+        recipient: &AccountInfo,
+        data_account: &AccountInfo,
+        data: &[u8],
+        content_account: &AccountInfo,
+    ) -> ProgramResult {
+        // Ensure the content_account is the content PDA of data
+        let (pda, _) = derive_content_pda(program_id, &compute_content_hash(data));
+        if pda != *content_account.key {
+            return Err(DataStoreError::InvalidPDA.into());
+        }
+
+        // Ensure the content_account was created by the data program
+        if content_account.owner != program_id {
+            return Err(DataStoreError::InvalidAccountOwner.into());
+        }
+
+        // Ensure content_account is writable
+        if !content_account.is_writable {
+            return Err(DataStoreError::NotWriteable.into());
+        }
+
+        // Ensure the content_account registers data_account
+        if content_account.try_borrow_data()?.as_ref() != data_account.key.as_ref() {
+            return Err(DataStoreError::InvalidInstructionData.into());
+        }
+
+        Self::drain_account(recipient, content_account)
+    }
+
+    fn close_data_store(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        args: CloseDataStoreArgs,
+    ) -> ProgramResult {
+        debug_msg!(args.debug, "CloseDataStore");
+
+        let accounts_iter = &mut accounts.iter();
+        let authority = next_account_info(accounts_iter)?;
+        let data_account = next_account_info(accounts_iter)?;
+        let metadata_account = next_account_info(accounts_iter)?;
+        let recipient = next_account_info(accounts_iter).unwrap_or(authority);
+        let content_account = next_account_info(accounts_iter).ok();
+
+        // Ensure recipient is writable
+        if !recipient.is_writable {
+            return Err(DataStoreError::NotWriteable.into());
+        }
+
+        Self::check_closable(
+            program_id,
+            authority,
+            recipient,
+            data_account,
+            metadata_account,
+            args.force,
+        )?;
+
+        // Close the content PDA registering the data_account along with it
+        let account_metadata =
+            DataStoreAccountMetadata::load(&metadata_account.try_borrow_data()?)?;
+        if account_metadata.has_flag(FLAG_CONTENT_REGISTERED) {
+            let content_account = content_account.ok_or(DataStoreError::ContentRegistered)?;
+            Self::close_content_pda(
+                program_id,
+                recipient,
+                data_account,
+                &data_account.data.borrow()[..account_metadata.data_len()],
+                content_account,
+            )?;
+
+            debug_msg!(args.debug, "content pda lamports transfered to recipient");
+        }
+
+        Self::drain_account(recipient, metadata_account)?;
+
+        debug_msg!(args.debug, "metadata pda lamports transfered to recipient");
+
+        Self::drain_account(recipient, data_account)?;
+
+        debug_msg!(args.debug, "data account lamports transfered to recipient");
+
+        DataStoreEvent::Closed {
+            data_account: *data_account.key,
+            recipient: *recipient.key,
+        }
+        .emit();
+
+        Ok(())
+    }
+
+    fn close_data_store_batch(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        args: CloseDataStoreBatchArgs,
+    ) -> ProgramResult {
+        debug_msg!(args.debug, "CloseDataStoreBatch");
+
+        let accounts_iter = &mut accounts.iter();
+        let authority = next_account_info(accounts_iter)?;
+        let recipient = next_account_info(accounts_iter)?;
+        let pairs = accounts_iter.as_slice();
+
+        // Ensure recipient is writable
+        if !recipient.is_writable {
+            return Err(DataStoreError::NotWriteable.into());
+        }
+
+        // Ensure the remaining accounts are (data_account, metadata_account) pairs within
+        // the batch bound
+        let batch_size = pairs.len() / 2;
+        if batch_size == 0 || batch_size * 2 != pairs.len() || batch_size > MAX_CLOSE_BATCH_SIZE {
+            return Err(DataStoreError::InvalidInstructionData.into());
+        }
+
+        // Ensure every data store is closable before closing any of them
+        for pair in pairs.chunks_exact(2) {
+            Self::check_closable(
+                program_id, authority, recipient, &pair[0], &pair[1], args.force,
+            )?;
+
+            // Ensure no data store has its content registered, which only CloseDataStore
+            // closes along with it
+            let account_metadata = DataStoreAccountMetadata::load(&pair[1].try_borrow_data()?)?;
+            if account_metadata.has_flag(FLAG_CONTENT_REGISTERED) {
+                return Err(DataStoreError::ContentRegistered.into());
+            }
+        }
+
+        debug_msg!(args.debug, "account checks passed");
+
+        for pair in pairs.chunks_exact(2) {
+            let (data_account, metadata_account) = (&pair[0], &pair[1]);
+            Self::drain_account(recipient, metadata_account)?;
+            Self::drain_account(recipient, data_account)?;
+
+            DataStoreEvent::Closed {
+                data_account: *data_account.key,
+                recipient: *recipient.key,
+            }
+            .emit();
+        }
+
+        debug_msg!(args.debug, "closed {} data stores", batch_size);
+
+        Ok(())
+    }
+
+    /// Runs the checks for authority closing data_account and its metadata_account.
+    /// Once the data store expired any signer may close it, as long as the lamports
+    /// go to its creator.
+    fn check_closable(
+        program_id: &Pubkey,
+        authority: &AccountInfo,
+        recipient: &AccountInfo,
+        data_account: &AccountInfo,
+        metadata_account: &AccountInfo,
+        force: bool,
+    ) -> ProgramResult {
+        // Ensure no account is aliased, which would drain or zero it twice
+        if data_account.key == metadata_account.key
+            || recipient.key == data_account.key
+            || recipient.key == metadata_account.key
+        {
+            return Err(DataStoreError::DuplicateAccount.into());
+        }
+
+        // Ensure data_account and metadata_account are owned by the data program
+        if data_account.owner != program_id || metadata_account.owner != program_id {
+            return Err(DataStoreError::InvalidAccountOwner.into());
+        }
+
+        // Ensure authority is signer
+        if !authority.is_signer {
+            return Err(DataStoreError::NotSigner.into());
         }
 
         // Ensure authority, data_account, and metadata_account are writable
-        if !authority.is_writable
-            || !data_account.is_writable
-            || !metadata_account.is_writable
+        if !authority.is_writable || !data_account.is_writable || !metadata_account.is_writable {
+            return Err(DataStoreError::NotWriteable.into());
+        }
+
+        // Ensure length is not 0
+        if metadata_account.data_is_empty() {
+            return Err(DataStoreError::NoAccountLength.into());
+        }
+
+        let account_metadata =
+            DataStoreAccountMetadata::load(&metadata_account.try_borrow_data()?)?;
+
+        let expired = account_metadata.is_expired(Clock::get()?.slot);
+
+        // Ensure data_account is initialized, and finalized unless closing is forced or
+        // it expired
+        match *account_metadata.data_status() {
+            SerializationStatusOption::Uninitialized => {
+                return Err(DataStoreError::NotInitialized.into());
+            }
+            SerializationStatusOption::Initialized if !force && !expired => {
+                return Err(DataStoreError::NotFinalized.into());
+            }
+            _ => (),
+        }
+
+        // Ensure the authority of data_account was not renounced, which keeps it forever
+        if account_metadata.is_authority_renounced() {
+            return Err(DataStoreError::InvalidAuthority.into());
+        }
+
+        // Ensure data_account is being closed by valid authority, or by anyone refunding
+        // the creator once it expired
+        if account_metadata.authority() != authority.key
+            && !(expired && account_metadata.creator() == recipient.key)
         {
+            return Err(DataStoreError::InvalidAuthority.into());
+        }
+
+        // Ensure the metadata_account corresponds to the data_account
+        let pda = account_metadata.metadata_pda(program_id, data_account.key)?;
+        if pda != *metadata_account.key {
+            return Err(DataStoreError::InvalidPDA.into());
+        }
+
+        Ok(())
+    }
+
+    /// Transfers all lamports of account to recipient and resets its data
+    fn drain_account(recipient: &AccountInfo, account: &AccountInfo) -> ProgramResult {
+        let curr_lamports = recipient.lamports();
+        **recipient.lamports.borrow_mut() = curr_lamports
+            .checked_add(account.lamports())
+            .ok_or(DataStoreError::Overflow)?;
+        **account.lamports.borrow_mut() = 0;
+        account.data.borrow_mut().fill(0);
+
+        Ok(())
+    }
+
+    fn create_symlink(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        args: CreateSymlinkArgs,
+    ) -> ProgramResult {
+        debug_msg!(args.debug, "CreateSymlink");
+
+        // Create the data_account and its metadata as a fixed-size symlink to the target
+        Self::initialize_data_store(
+            program_id,
+            accounts,
+            InitializeDataStoreArgs {
+                debug: args.debug,
+                data_type: DataStoreTypeOption::Symlink,
+                bump_seed: args.bump_seed,
+                is_created: false,
+                space: SYMLINK_TARGET_LEN as u64,
+                authority: args.authority,
+                is_dynamic: false,
+                initial_data: args.target.to_bytes().to_vec(),
+                append_only: false,
+                namespace: args.namespace,
+                idempotent: false,
+                private: false,
+                content_type: String::new(),
+                strict_content_type: false,
+                expected_total: 0,
+                min_update_interval_slots: 0,
+                expiry_slot: None,
+                max_revisions: None,
+                audit: false,
+                file_name: String::new(),
+                zero_on_shrink: true,
+                cache_ttl_seconds: 0,
+                indexable: true,
+                stream_hash: false,
+            },
+        )?;
+
+        debug_msg!(args.debug, "symlink to {} created", args.target);
+
+        Ok(())
+    }
+
+    fn update_symlink(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        args: UpdateSymlinkArgs,
+    ) -> ProgramResult {
+        debug_msg!(args.debug, "UpdateSymlink");
+
+        let accounts_iter = &mut accounts.iter();
+        let authority = next_account_info(accounts_iter)?;
+        let data_account = next_account_info(accounts_iter)?;
+        let metadata_account = next_account_info(accounts_iter)?;
+
+        // Ensure data_account and metadata_account are owned by the data program
+        if data_account.owner != program_id || metadata_account.owner != program_id {
+            return Err(DataStoreError::InvalidAccountOwner.into());
+        }
+
+        // Ensure authority is signer
+        if !authority.is_signer {
+            return Err(DataStoreError::NotSigner.into());
+        }
+
+        // Ensure data_account and metadata_account are writable
+        if !data_account.is_writable || !metadata_account.is_writable {
+            return Err(DataStoreError::NotWriteable.into());
+        }
+
+        // Ensure length is not 0
+        if metadata_account.data_is_empty() {
+            return Err(DataStoreError::NoAccountLength.into());
+        }
+
+        let mut account_metadata =
+            DataStoreAccountMetadata::load(&metadata_account.try_borrow_data()?)?;
+
+        // Ensure data_account is initialized and not finalized
+        match *account_metadata.data_status() {
+            SerializationStatusOption::Uninitialized => {
+                return Err(DataStoreError::NotInitialized.into());
+            }
+            SerializationStatusOption::Finalized => {
+                return Err(DataStoreError::AlreadyFinalized.into());
+            }
+            _ => (),
+        }
+
+        // Ensure data_account is not frozen
+        if account_metadata.is_frozen() {
+            return Err(DataStoreError::Frozen.into());
+        }
+
+        // Ensure data_account holds a symlink
+        if *account_metadata.data_type() != DataStoreTypeOption::Symlink {
+            return Err(DataStoreError::InvalidDataType.into());
+        }
+
+        // Ensure data_account is being written to by valid authority
+        if account_metadata.authority() != authority.key {
+            return Err(DataStoreError::InvalidAuthority.into());
+        }
+
+        // Ensure the metadata_account corresponds to the data_account
+        let pda = account_metadata.metadata_pda(program_id, data_account.key)?;
+        if pda != *metadata_account.key {
+            return Err(DataStoreError::InvalidPDA.into());
+        }
+
+        // Ensure data_account has room for exactly one target
+        if verify_data(&data_account.data.borrow(), DataStoreTypeOption::Symlink)
+            != SerializationStatusOption::Initialized
+        {
+            return Err(DataStoreError::DataVerificationFailed.into());
+        }
+
+        debug_msg!(args.debug, "account checks passed");
+
+        // Update the symlink target
+        data_account
+            .data
+            .borrow_mut()
+            .copy_from_slice(args.target.as_ref());
+
+        // Update the metadata_account
+        account_metadata.update_checksums(data_account.key, &data_account.data.borrow());
+        account_metadata
+            .increment_revision()
+            .ok_or(DataStoreError::Overflow)?;
+        account_metadata.store(&mut metadata_account.data.borrow_mut())?;
+
+        DataStoreEvent::Updated {
+            data_account: *data_account.key,
+            revision: account_metadata.revision(),
+            data_len: account_metadata.data_len() as u64,
+            data_hash: *account_metadata.data_hash(),
+        }
+        .emit();
+
+        debug_msg!(args.debug, "symlink target updated to {}", args.target);
+
+        Ok(())
+    }
+
+    fn get_metadata(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        args: GetMetadataArgs,
+    ) -> ProgramResult {
+        debug_msg!(args.debug, "GetMetadata");
+
+        let accounts_iter = &mut accounts.iter();
+        let data_account = next_account_info(accounts_iter)?;
+        let metadata_account = next_account_info(accounts_iter)?;
+        let reader = next_account_info(accounts_iter).ok();
+
+        // Ensure length is not 0
+        if metadata_account.data_is_empty() {
+            return Err(DataStoreError::NoAccountLength.into());
+        }
+
+        // Ensure metadata_account is owned by the data program
+        if metadata_account.owner != program_id {
+            return Err(DataStoreError::InvalidAccountOwner.into());
+        }
+
+        let account_metadata =
+            DataStoreAccountMetadata::load(&metadata_account.try_borrow_data()?)?;
+
+        // Ensure data_account is initialized
+        if *account_metadata.data_status() == SerializationStatusOption::Uninitialized {
+            return Err(DataStoreError::NotInitialized.into());
+        }
+
+        // Ensure the metadata_account corresponds to the data_account
+        let pda = account_metadata.metadata_pda(program_id, data_account.key)?;
+        if pda != *metadata_account.key {
+            return Err(DataStoreError::InvalidPDA.into());
+        }
+
+        Self::check_read_access(&account_metadata, reader)?;
+
+        // Return the canonical encoding of the metadata
+        set_return_data(&borsh::to_vec(&account_metadata)?);
+
+        debug_msg!(args.debug, "returned metadata");
+
+        Ok(())
+    }
+
+    fn add_directory_entry(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        args: AddDirectoryEntryArgs,
+    ) -> ProgramResult {
+        debug_msg!(args.debug, "AddDirectoryEntry");
+
+        let accounts_iter = &mut accounts.iter();
+        let authority = next_account_info(accounts_iter)?;
+        let data_account = next_account_info(accounts_iter)?;
+        let metadata_account = next_account_info(accounts_iter)?;
+        let system_program = next_account_info(accounts_iter)?;
+
+        // Ensure data_account and metadata_account are owned by the data program
+        if data_account.owner != program_id || metadata_account.owner != program_id {
+            return Err(DataStoreError::InvalidAccountOwner.into());
+        }
+
+        // Ensure authority is signer
+        if !authority.is_signer {
+            return Err(DataStoreError::NotSigner.into());
+        }
+
+        // Ensure authority, data_account, and metadata_account are writable
+        if !authority.is_writable || !data_account.is_writable || !metadata_account.is_writable {
+            return Err(DataStoreError::NotWriteable.into());
+        }
+
+        // Ensure length is not 0
+        if metadata_account.data_is_empty() {
+            return Err(DataStoreError::NoAccountLength.into());
+        }
+
+        let account_metadata = Self::with_metadata(metadata_account, |account_metadata| {
+            // Ensure data_account is initialized and not finalized
+            match *account_metadata.data_status() {
+                SerializationStatusOption::Uninitialized => {
+                    return Err(DataStoreError::NotInitialized.into());
+                }
+                SerializationStatusOption::Finalized => {
+                    return Err(DataStoreError::AlreadyFinalized.into());
+                }
+                _ => (),
+            }
+
+            // Ensure data_account is not frozen
+            if account_metadata.is_frozen() {
+                return Err(DataStoreError::Frozen.into());
+            }
+
+            // Ensure data_account is a directory of packed or named entries
+            if *account_metadata.data_type() != DataStoreTypeOption::Directory
+                || *account_metadata.directory_format() == DirectoryFormat::JsonManifest
+            {
+                return Err(DataStoreError::InvalidDataType.into());
+            }
+
+            // Ensure data_account is being written to by valid authority
+            if account_metadata.authority() != authority.key {
+                return Err(DataStoreError::InvalidAuthority.into());
+            }
+
+            // Ensure the metadata_account corresponds to the data_account
+            let pda = account_metadata.metadata_pda(program_id, data_account.key)?;
+            if pda != *metadata_account.key {
+                return Err(DataStoreError::InvalidPDA.into());
+            }
+
+            // Build the entry, ensuring a named one has a valid name that is not taken yet
+            let (entry, entry_count) = match account_metadata.directory_format() {
+                DirectoryFormat::NamedEntries => {
+                    if args.name.is_empty() || args.name.len() > MAX_ENTRY_NAME_LEN {
+                        return Err(DataStoreError::InvalidInstructionData.into());
+                    }
+                    let data = data_account.data.borrow();
+                    let entries = parse_named_entries(&data[..account_metadata.data_len()])
+                        .ok_or(DataStoreError::DataVerificationFailed)?;
+                    if entries
+                        .iter()
+                        .any(|(name, _)| *name == args.name.as_bytes())
+                    {
+                        return Err(DataStoreError::EntryAlreadyExists.into());
+                    }
+                    (
+                        encode_named_entry(args.name.as_bytes(), &args.child),
+                        entries.len(),
+                    )
+                }
+                _ => {
+                    if !args.name.is_empty() {
+                        return Err(DataStoreError::InvalidInstructionData.into());
+                    }
+                    let entry_count = account_metadata.data_len() / DIRECTORY_ENTRY_SIZE;
+                    (args.child.to_bytes().to_vec(), entry_count)
+                }
+            };
+
+            // Ensure the directory is below its entry cap
+            if entry_count >= account_metadata.max_entries() {
+                return Err(DataStoreError::DirectoryFull.into());
+            }
+
+            let start = account_metadata.data_len();
+            let end = start + entry.len();
+
+            // Ensure the directory has room for the entry, growing dynamic directories
+            if data_account.data_len() < end {
+                if !account_metadata.is_dynamic() {
+                    return Err(DataStoreError::InsufficientSpace.into());
+                }
+                if end > MAX_DATA_SPACE {
+                    return Err(DataStoreError::SpaceTooLarge.into());
+                }
+
+                Self::resize_data_account(authority, data_account, system_program, end)?;
+
+                debug_msg!(args.debug, "realloc-ed {}", end);
+            }
+
+            debug_msg!(args.debug, "account checks passed");
+
+            // Append the entry to the directory entries
+            data_account.data.borrow_mut()[start..end].copy_from_slice(&entry);
+
+            // Update the metadata_account
+            account_metadata.set_space(data_account.data_len());
+            account_metadata.set_data_len(end);
+            account_metadata.update_checksums(data_account.key, &data_account.data.borrow());
+            account_metadata
+                .increment_revision()
+                .ok_or(DataStoreError::Overflow)?;
+
+            Ok(())
+        })?;
+
+        DataStoreEvent::Updated {
+            data_account: *data_account.key,
+            revision: account_metadata.revision(),
+            data_len: account_metadata.data_len() as u64,
+            data_hash: *account_metadata.data_hash(),
+        }
+        .emit();
+
+        debug_msg!(args.debug, "added {} to directory", args.child);
+
+        Ok(())
+    }
+
+    fn get_directory_entries(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        args: GetDirectoryEntriesArgs,
+    ) -> ProgramResult {
+        debug_msg!(args.debug, "GetDirectoryEntries");
+
+        let accounts_iter = &mut accounts.iter();
+        let data_account = next_account_info(accounts_iter)?;
+        let metadata_account = next_account_info(accounts_iter)?;
+
+        // Ensure length is not 0
+        if metadata_account.data_is_empty() {
+            return Err(DataStoreError::NoAccountLength.into());
+        }
+
+        // Ensure metadata_account is owned by the data program
+        if metadata_account.owner != program_id {
+            return Err(DataStoreError::InvalidAccountOwner.into());
+        }
+
+        let account_metadata =
+            DataStoreAccountMetadata::load(&metadata_account.try_borrow_data()?)?;
+
+        // Ensure data_account is initialized
+        if *account_metadata.data_status() == SerializationStatusOption::Uninitialized {
+            return Err(DataStoreError::NotInitialized.into());
+        }
+
+        // Ensure data_account is a directory of packed or named entries
+        if *account_metadata.data_type() != DataStoreTypeOption::Directory
+            || *account_metadata.directory_format() == DirectoryFormat::JsonManifest
+        {
+            return Err(DataStoreError::InvalidDataType.into());
+        }
+
+        // Ensure the metadata_account corresponds to the data_account
+        let pda = account_metadata.metadata_pda(program_id, data_account.key)?;
+        if pda != *metadata_account.key {
+            return Err(DataStoreError::InvalidPDA.into());
+        }
+
+        let data = data_account.data.borrow();
+        let data = &data[..account_metadata.data_len()];
+
+        // Return the requested page of entries, bounded by the return data size
+        match account_metadata.directory_format() {
+            DirectoryFormat::NamedEntries => {
+                let entries =
+                    parse_named_entries(data).ok_or(DataStoreError::DataVerificationFailed)?;
+                let first = (args.offset as usize).min(entries.len());
+                let mut page = Vec::new();
+                for (name, child) in entries.iter().skip(first).take(args.count as usize) {
+                    let record = encode_named_entry(name, child);
+                    if page.len() + record.len() > MAX_RETURN_DATA {
+                        break;
+                    }
+                    page.extend_from_slice(&record);
+                }
+                set_return_data(&page);
+
+                debug_msg!(
+                    args.debug,
+                    "returned {} bytes of entries from {} of {}",
+                    page.len(),
+                    first,
+                    entries.len()
+                );
+            }
+            _ => {
+                let total = data.len() / DIRECTORY_ENTRY_SIZE;
+                let first = (args.offset as usize).min(total);
+                let last = first
+                    .saturating_add((args.count as usize).min(MAX_DIRECTORY_ENTRIES_PER_READ))
+                    .min(total);
+                set_return_data(&data[first * DIRECTORY_ENTRY_SIZE..last * DIRECTORY_ENTRY_SIZE]);
+
+                debug_msg!(
+                    args.debug,
+                    "returned entries {}..{} of {}",
+                    first,
+                    last,
+                    total
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn migrate_metadata(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        args: MigrateMetadataArgs,
+    ) -> ProgramResult {
+        debug_msg!(args.debug, "MigrateMetadata");
+
+        let accounts_iter = &mut accounts.iter();
+        let authority = next_account_info(accounts_iter)?;
+        let data_account = next_account_info(accounts_iter)?;
+        let metadata_account = next_account_info(accounts_iter)?;
+
+        // Ensure authority is signer
+        if !authority.is_signer {
+            return Err(DataStoreError::NotSigner.into());
+        }
+
+        // Ensure metadata_account is writable
+        if !metadata_account.is_writable {
+            return Err(DataStoreError::NotWriteable.into());
+        }
+
+        // Ensure length is not 0
+        if metadata_account.data_is_empty() {
+            return Err(DataStoreError::NoAccountLength.into());
+        }
+
+        // Ensure metadata_account is owned by the data program
+        if metadata_account.owner != program_id {
+            return Err(DataStoreError::InvalidAccountOwner.into());
+        }
+
+        // Map the metadata from the layout matching its version to the current layout,
+        // reading the version in place as older layouts deserialize differently
+        let old_version = *metadata_account
+            .try_borrow_data()?
+            .get(METADATA_VERSION_OFFSET)
+            .ok_or(DataStoreError::CorruptMetadata)?;
+        let mut account_metadata = match old_version {
+            0 => {
+                let metadata_v0 = DataStoreAccountMetadataV0::deserialize(
+                    &mut &metadata_account.try_borrow_data()?[..],
+                )?;
+                let mut account_metadata = DataStoreAccountMetadata::from(metadata_v0);
+                // Older versions neither tracked the sizes nor maintained the hash
+                account_metadata.set_space(data_account.data_len());
+                account_metadata.set_data_len(data_account.data_len());
+                account_metadata.update_checksums(data_account.key, &data_account.data.borrow());
+                account_metadata
+            }
+            // load_unsealed maps the later layouts, verifying the metadata_crc of those
+            // sealed with one and building the flags of accounts older than FLAGS_VERSION
+            // from their toggle fields
+            _ if old_version < DATA_STORE_VERSION => {
+                DataStoreAccountMetadata::load_unsealed(&metadata_account.try_borrow_data()?)?
+            }
+            _ if old_version > DATA_STORE_VERSION => {
+                return Err(DataStoreError::UnsupportedVersion.into());
+            }
+            _ => {
+                debug_msg!(args.debug, "metadata already at version {}", old_version);
+                return Ok(());
+            }
+        };
+
+        // Ensure data_account is initialized
+        if *account_metadata.data_status() == SerializationStatusOption::Uninitialized {
+            return Err(DataStoreError::NotInitialized.into());
+        }
+
+        // Ensure metadata_account is being migrated by valid authority
+        if account_metadata.authority() != authority.key {
+            return Err(DataStoreError::InvalidAuthority.into());
+        }
+
+        // Ensure the metadata_account corresponds to the data_account
+        let pda = account_metadata.metadata_pda(program_id, data_account.key)?;
+        if pda != *metadata_account.key {
+            return Err(DataStoreError::InvalidPDA.into());
+        }
+
+        debug_msg!(args.debug, "account checks passed");
+
+        account_metadata.store(&mut metadata_account.data.borrow_mut())?;
+
+        debug_msg!(
+            args.debug,
+            "migrated metadata from version {} to {}",
+            old_version,
+            account_metadata.version()
+        );
+
+        Ok(())
+    }
+
+    fn region_hash(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        args: RegionHashArgs,
+    ) -> ProgramResult {
+        debug_msg!(args.debug, "RegionHash");
+
+        let accounts_iter = &mut accounts.iter();
+        let data_account = next_account_info(accounts_iter)?;
+        let metadata_account = next_account_info(accounts_iter)?;
+
+        // Ensure length is not 0
+        if metadata_account.data_is_empty() {
+            return Err(DataStoreError::NoAccountLength.into());
+        }
+
+        // Ensure metadata_account is owned by the data program
+        if metadata_account.owner != program_id {
+            return Err(DataStoreError::InvalidAccountOwner.into());
+        }
+
+        let account_metadata =
+            DataStoreAccountMetadata::load(&metadata_account.try_borrow_data()?)?;
+
+        // Ensure data_account is initialized
+        if *account_metadata.data_status() == SerializationStatusOption::Uninitialized {
+            return Err(DataStoreError::NotInitialized.into());
+        }
+
+        // Ensure the metadata_account corresponds to the data_account
+        let pda = account_metadata.metadata_pda(program_id, data_account.key)?;
+        if pda != *metadata_account.key {
+            return Err(DataStoreError::InvalidPDA.into());
+        }
+
+        // Ensure the region lies within the data_account
+        let start = usize::try_from(args.offset).map_err(|_| DataStoreError::Overflow)?;
+        let end = usize::try_from(args.len)
+            .ok()
+            .and_then(|len| start.checked_add(len))
+            .ok_or(DataStoreError::Overflow)?;
+        if end > data_account.data_len() {
+            return Err(DataStoreError::InsufficientSpace.into());
+        }
+
+        // Return the plain sha256 of the region so it matches a client-computed chunk hash
+        let region_hash = digest(
+            HashAlgorithm::Sha256,
+            &data_account.data.borrow()[start..end],
+        );
+        set_return_data(&region_hash);
+
+        debug_msg!(args.debug, "returned hash of region {}..{}", start, end);
+
+        Ok(())
+    }
+
+    fn initialize_directory(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        args: InitializeDirectoryArgs,
+    ) -> ProgramResult {
+        debug_msg!(args.debug, "InitializeDirectory");
+
+        Self::create_directory(program_id, accounts, args, String::new())
+    }
+
+    /// Creates a directory data store, named by file_name unless it is empty
+    fn create_directory(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        args: InitializeDirectoryArgs,
+        file_name: String,
+    ) -> ProgramResult {
+        // Ensure space holds a whole number of entries for packed directories
+        let entry_size = DIRECTORY_ENTRY_SIZE as u64;
+        if args.directory_format == DirectoryFormat::PackedPubkeys
+            && args.space / entry_size * entry_size != args.space
+        {
+            return Err(DataStoreError::InvalidDirectorySize.into());
+        }
+
+        // Ensure the entry cap fits a data account and covers the initial space
+        let max_entries = match args.max_entries {
+            0 => DEFAULT_MAX_DIRECTORY_ENTRIES,
+            max_entries => max_entries,
+        };
+        let max_space = max_entries as u64 * entry_size;
+        if max_space > MAX_DATA_SPACE as u64 {
+            return Err(DataStoreError::SpaceTooLarge.into());
+        }
+        if args.space > max_space {
+            return Err(DataStoreError::InvalidDirectorySize.into());
+        }
+
+        // Create the data_account and its metadata as an empty dynamic directory
+        Self::initialize_data_store(
+            program_id,
+            accounts,
+            InitializeDataStoreArgs {
+                debug: args.debug,
+                data_type: DataStoreTypeOption::Directory,
+                bump_seed: args.bump_seed,
+                is_created: args.is_created,
+                space: args.space,
+                authority: args.authority,
+                is_dynamic: true,
+                initial_data: Vec::new(),
+                append_only: false,
+                namespace: args.namespace,
+                idempotent: false,
+                private: false,
+                content_type: String::new(),
+                strict_content_type: false,
+                expected_total: 0,
+                min_update_interval_slots: 0,
+                expiry_slot: None,
+                max_revisions: None,
+                audit: false,
+                file_name,
+                zero_on_shrink: true,
+                cache_ttl_seconds: 0,
+                indexable: true,
+                stream_hash: false,
+            },
+        )?;
+
+        // Record the entry cap and format of the directory
+        let metadata_account = accounts.get(2).ok_or(ProgramError::NotEnoughAccountKeys)?;
+        Self::with_metadata(metadata_account, |account_metadata| {
+            account_metadata.set_max_entries(max_entries);
+            account_metadata.set_directory_format(args.directory_format);
+            Ok(())
+        })?;
+
+        debug_msg!(args.debug, "directory capped at {} entries", max_entries);
+
+        Ok(())
+    }
+
+    fn move_entry(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        args: MoveEntryArgs,
+    ) -> ProgramResult {
+        debug_msg!(args.debug, "MoveEntry");
+
+        let accounts_iter = &mut accounts.iter();
+        let source_authority = next_account_info(accounts_iter)?;
+        let source_account = next_account_info(accounts_iter)?;
+        let source_metadata_account = next_account_info(accounts_iter)?;
+        let destination_authority = next_account_info(accounts_iter)?;
+        let destination_account = next_account_info(accounts_iter)?;
+        let destination_metadata_account = next_account_info(accounts_iter)?;
+        let system_program = next_account_info(accounts_iter)?;
+
+        // Ensure the entry moves between two distinct directories
+        if source_account.key == destination_account.key {
+            return Err(DataStoreError::InvalidInstructionData.into());
+        }
+
+        // Ensure destination_authority can pay for growing the destination
+        if !destination_authority.is_writable {
+            return Err(DataStoreError::NotWriteable.into());
+        }
+
+        let mut source_metadata = Self::load_directory(
+            program_id,
+            source_authority,
+            source_account,
+            source_metadata_account,
+        )?;
+        let mut destination_metadata = Self::load_directory(
+            program_id,
+            destination_authority,
+            destination_account,
+            destination_metadata_account,
+        )?;
+
+        // Ensure the source may have an entry removed, the destination only being appended to
+        if source_metadata.is_append_only() {
+            return Err(DataStoreError::AppendOnlyViolation.into());
+        }
+
+        // Ensure the child is linked in the source and not yet in the destination
+        let source_len = source_metadata.data_len();
+        let index = find_directory_entry(&source_account.data.borrow()[..source_len], &args.child)
+            .ok_or(DataStoreError::EntryNotFound)?;
+        let start = destination_metadata.data_len();
+        if find_directory_entry(&destination_account.data.borrow()[..start], &args.child).is_some()
+        {
+            return Err(DataStoreError::EntryAlreadyExists.into());
+        }
+        let end = start + DIRECTORY_ENTRY_SIZE;
+
+        // Ensure the destination is below its entry cap
+        if start / DIRECTORY_ENTRY_SIZE >= destination_metadata.max_entries() {
+            return Err(DataStoreError::DirectoryFull.into());
+        }
+
+        // Ensure the destination has room for the entry, growing dynamic directories
+        if destination_account.data_len() < end {
+            if !destination_metadata.is_dynamic() {
+                return Err(DataStoreError::InsufficientSpace.into());
+            }
+            if end > MAX_DATA_SPACE {
+                return Err(DataStoreError::SpaceTooLarge.into());
+            }
+
+            Self::resize_data_account(
+                destination_authority,
+                destination_account,
+                system_program,
+                end,
+            )?;
+
+            debug_msg!(args.debug, "realloc-ed {}", end);
+        }
+
+        debug_msg!(args.debug, "account checks passed");
+
+        // Swap-remove the child from the source entries
+        {
+            let mut source_data = source_account.data.borrow_mut();
+            let removed = index * DIRECTORY_ENTRY_SIZE;
+            let last = source_len - DIRECTORY_ENTRY_SIZE;
+            source_data.copy_within(last..source_len, removed);
+            source_data[last..source_len].fill(0);
+        }
+        source_metadata.set_data_len(source_len - DIRECTORY_ENTRY_SIZE);
+
+        // Append the child to the destination entries
+        destination_account.data.borrow_mut()[start..end].copy_from_slice(args.child.as_ref());
+        destination_metadata.set_space(destination_account.data_len());
+        destination_metadata.set_data_len(end);
+
+        // Update both metadata_accounts
+        for (data_account, metadata_account, account_metadata) in [
+            (
+                source_account,
+                source_metadata_account,
+                &mut source_metadata,
+            ),
+            (
+                destination_account,
+                destination_metadata_account,
+                &mut destination_metadata,
+            ),
+        ] {
+            account_metadata.update_checksums(data_account.key, &data_account.data.borrow());
+            account_metadata
+                .increment_revision()
+                .ok_or(DataStoreError::Overflow)?;
+            account_metadata.store(&mut metadata_account.data.borrow_mut())?;
+
+            DataStoreEvent::Updated {
+                data_account: *data_account.key,
+                revision: account_metadata.revision(),
+                data_len: account_metadata.data_len() as u64,
+                data_hash: *account_metadata.data_hash(),
+            }
+            .emit();
+        }
+
+        debug_msg!(
+            args.debug,
+            "moved {} to {}",
+            args.child,
+            destination_account.key
+        );
+
+        Ok(())
+    }
+
+    /// Loads the metadata of a data store being modified by authority, after checking
+    /// the accounts the same way AddDirectoryEntry does, except for the data type.
+    fn load_writable(
+        program_id: &Pubkey,
+        authority: &AccountInfo,
+        data_account: &AccountInfo,
+        metadata_account: &AccountInfo,
+    ) -> Result<DataStoreAccountMetadata, ProgramError> {
+        // Ensure data_account and metadata_account are owned by the data program
+        if data_account.owner != program_id || metadata_account.owner != program_id {
+            return Err(DataStoreError::InvalidAccountOwner.into());
+        }
+
+        // Ensure authority is signer
+        if !authority.is_signer {
+            return Err(DataStoreError::NotSigner.into());
+        }
+
+        // Ensure data_account and metadata_account are writable
+        if !data_account.is_writable || !metadata_account.is_writable {
+            return Err(DataStoreError::NotWriteable.into());
+        }
+
+        // Ensure length is not 0
+        if metadata_account.data_is_empty() {
+            return Err(DataStoreError::NoAccountLength.into());
+        }
+
+        let account_metadata =
+            DataStoreAccountMetadata::load(&metadata_account.try_borrow_data()?)?;
+
+        // Ensure data_account is initialized and not finalized
+        match *account_metadata.data_status() {
+            SerializationStatusOption::Uninitialized => {
+                return Err(DataStoreError::NotInitialized.into());
+            }
+            SerializationStatusOption::Finalized => {
+                return Err(DataStoreError::AlreadyFinalized.into());
+            }
+            _ => (),
+        }
+
+        // Ensure data_account is not frozen
+        if account_metadata.is_frozen() {
+            return Err(DataStoreError::Frozen.into());
+        }
+
+        // Ensure data_account is being written to by valid authority
+        if account_metadata.authority() != authority.key {
+            return Err(DataStoreError::InvalidAuthority.into());
+        }
+
+        // Ensure the metadata_account corresponds to the data_account
+        let pda = account_metadata.metadata_pda(program_id, data_account.key)?;
+        if pda != *metadata_account.key {
+            return Err(DataStoreError::InvalidPDA.into());
+        }
+
+        Ok(account_metadata)
+    }
+
+    /// Loads the metadata of a directory being modified by authority
+    fn load_directory(
+        program_id: &Pubkey,
+        authority: &AccountInfo,
+        data_account: &AccountInfo,
+        metadata_account: &AccountInfo,
+    ) -> Result<DataStoreAccountMetadata, ProgramError> {
+        let account_metadata =
+            Self::load_writable(program_id, authority, data_account, metadata_account)?;
+
+        // Ensure data_account is a directory of packed entries
+        if *account_metadata.data_type() != DataStoreTypeOption::Directory
+            || *account_metadata.directory_format() != DirectoryFormat::PackedPubkeys
+        {
+            return Err(DataStoreError::InvalidDataType.into());
+        }
+
+        Ok(account_metadata)
+    }
+
+    fn get_data_hash(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        args: GetDataHashArgs,
+    ) -> ProgramResult {
+        debug_msg!(args.debug, "GetDataHash");
+
+        let accounts_iter = &mut accounts.iter();
+        let data_account = next_account_info(accounts_iter)?;
+        let metadata_account = next_account_info(accounts_iter)?;
+
+        // Ensure length is not 0
+        if metadata_account.data_is_empty() {
+            return Err(DataStoreError::NoAccountLength.into());
+        }
+
+        // Ensure metadata_account is owned by the data program
+        if metadata_account.owner != program_id {
+            return Err(DataStoreError::InvalidAccountOwner.into());
+        }
+
+        // Load the whole metadata, as only its metadata_crc vouches for the data_hash
+        let account_metadata =
+            DataStoreAccountMetadata::load(&metadata_account.try_borrow_data()?)?;
+
+        // Ensure data_account is initialized
+        if *account_metadata.data_status() == SerializationStatusOption::Uninitialized {
+            return Err(DataStoreError::NotInitialized.into());
+        }
+
+        // Ensure the metadata_account corresponds to the data_account
+        let pda = account_metadata.metadata_pda(program_id, data_account.key)?;
+        if pda != *metadata_account.key {
+            return Err(DataStoreError::InvalidPDA.into());
+        }
+
+        set_return_data(account_metadata.data_hash());
+
+        debug_msg!(args.debug, "returned data hash");
+
+        Ok(())
+    }
+
+    fn read_data(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        args: ReadDataArgs,
+    ) -> ProgramResult {
+        debug_msg!(args.debug, "ReadData");
+
+        let accounts_iter = &mut accounts.iter();
+        let data_account = next_account_info(accounts_iter)?;
+        let metadata_account = next_account_info(accounts_iter)?;
+        let reader = next_account_info(accounts_iter).ok();
+
+        // Ensure length is not 0
+        if metadata_account.data_is_empty() {
+            return Err(DataStoreError::NoAccountLength.into());
+        }
+
+        // Ensure metadata_account is owned by the data program
+        if metadata_account.owner != program_id {
+            return Err(DataStoreError::InvalidAccountOwner.into());
+        }
+
+        let account_metadata =
+            DataStoreAccountMetadata::load(&metadata_account.try_borrow_data()?)?;
+
+        // Ensure data_account is initialized
+        if *account_metadata.data_status() == SerializationStatusOption::Uninitialized {
+            return Err(DataStoreError::NotInitialized.into());
+        }
+
+        // Ensure the metadata_account corresponds to the data_account
+        let pda = account_metadata.metadata_pda(program_id, data_account.key)?;
+        if pda != *metadata_account.key {
+            return Err(DataStoreError::InvalidPDA.into());
+        }
+
+        Self::check_read_access(&account_metadata, reader)?;
+
+        let max_read_len = MAX_RETURN_DATA - READ_DATA_HEADER_LEN;
+        let start = usize::try_from(args.offset).map_err(|_| DataStoreError::Overflow)?;
+        let end = usize::try_from(args.len)
+            .ok()
+            .and_then(|len| start.checked_add(len))
+            .ok_or(DataStoreError::Overflow)?;
+        let data_len = account_metadata.data_len().min(data_account.data_len());
+        let (start, end) = if args.clamp {
+            // Clamp the region to the used bytes and the return data, like an HTTP range
+            let start = start.min(data_len);
+            (start, end.min(data_len).min(start + max_read_len))
+        } else {
+            // Ensure the region lies within the used bytes and fits the return data
+            if end > data_len {
+                return Err(DataStoreError::InsufficientSpace.into());
+            }
+            if end - start > max_read_len {
+                return Err(DataStoreError::InvalidInstructionData.into());
+            }
+            (start, end)
+        };
+
+        // Ensure the stored bytes still match data_hash when verifying
+        if args.verify
+            && !verify_data_integrity(
+                data_account.key,
+                &data_account.data.borrow()[..data_len],
+                account_metadata.data_hash(),
+            )
+        {
+            return Err(DataStoreError::DataVerificationFailed.into());
+        }
+
+        // Return the length read followed by the bytes
+        let mut return_data = Vec::with_capacity(READ_DATA_HEADER_LEN + end - start);
+        return_data.extend_from_slice(&((end - start) as u32).to_le_bytes());
+        return_data.extend_from_slice(&data_account.data.borrow()[start..end]);
+        set_return_data(&return_data);
+
+        debug_msg!(args.debug, "returned data {}..{}", start, end);
+
+        Ok(())
+    }
+
+    /// Ensures a private data store is only read with the authority's signature
+    fn check_read_access(
+        account_metadata: &DataStoreAccountMetadata,
+        reader: Option<&AccountInfo>,
+    ) -> ProgramResult {
+        if !account_metadata.is_private() {
+            return Ok(());
+        }
+
+        // Ensure the reader signed and is the authority or its reader delegate
+        match reader {
+            Some(reader)
+                if reader.is_signer
+                    && (reader.key == account_metadata.authority()
+                        || account_metadata.reader() == Some(reader.key)) =>
+            {
+                Ok(())
+            }
+            _ => Err(DataStoreError::InvalidAuthority.into()),
+        }
+    }
+
+    fn set_content_type(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        args: SetContentTypeArgs,
+    ) -> ProgramResult {
+        debug_msg!(args.debug, "SetContentType");
+
+        let accounts_iter = &mut accounts.iter();
+        let authority = next_account_info(accounts_iter)?;
+        let data_account = next_account_info(accounts_iter)?;
+        let metadata_account = next_account_info(accounts_iter)?;
+
+        // Ensure data_account and metadata_account are owned by the data program
+        if data_account.owner != program_id || metadata_account.owner != program_id {
+            return Err(DataStoreError::InvalidAccountOwner.into());
+        }
+
+        // Ensure authority is signer
+        if !authority.is_signer {
+            return Err(DataStoreError::NotSigner.into());
+        }
+
+        // Ensure metadata_account is writable
+        if !metadata_account.is_writable {
+            return Err(DataStoreError::NotWriteable.into());
+        }
+
+        // Ensure length is not 0
+        if metadata_account.data_is_empty() {
+            return Err(DataStoreError::NoAccountLength.into());
+        }
+
+        Self::with_metadata(metadata_account, |account_metadata| {
+            // Ensure data_account is initialized and not finalized
+            match *account_metadata.data_status() {
+                SerializationStatusOption::Uninitialized => {
+                    return Err(DataStoreError::NotInitialized.into());
+                }
+                SerializationStatusOption::Finalized => {
+                    return Err(DataStoreError::AlreadyFinalized.into());
+                }
+                _ => (),
+            }
+
+            // Ensure metadata_account is being written to by valid authority
+            if account_metadata.authority() != authority.key {
+                return Err(DataStoreError::InvalidAuthority.into());
+            }
+
+            // Ensure the metadata_account corresponds to the data_account
+            let pda = account_metadata.metadata_pda(program_id, data_account.key)?;
+            if pda != *metadata_account.key {
+                return Err(DataStoreError::InvalidPDA.into());
+            }
+
+            // Ensure the content_type fits the metadata and, if strict, matches the stored data
+            if args.content_type.len() > MAX_CONTENT_TYPE_LEN {
+                return Err(DataStoreError::InvalidInstructionData.into());
+            }
+            if account_metadata.is_strict_content_type()
+                && !matches_content_type(
+                    &args.content_type,
+                    &data_account.data.borrow()[..account_metadata.data_len()],
+                )
+            {
+                return Err(DataStoreError::DataVerificationFailed.into());
+            }
+
+            debug_msg!(args.debug, "account checks passed");
+
+            account_metadata.set_content_type(args.content_type);
+
+            Ok(())
+        })?;
+
+        debug_msg!(args.debug, "content type set");
+
+        Ok(())
+    }
+
+    /// Loads the metadata of metadata_account, applies f to it and stores the result,
+    /// returning the stored metadata. The data is only borrowed while loading and while
+    /// serializing, so f may run CPIs without overlapping borrows of metadata_account.
+    fn with_metadata<F>(
+        metadata_account: &AccountInfo,
+        f: F,
+    ) -> Result<DataStoreAccountMetadata, ProgramError>
+    where
+        F: FnOnce(&mut DataStoreAccountMetadata) -> ProgramResult,
+    {
+        let mut account_metadata = {
+            let data = metadata_account.try_borrow_data()?;
+            DataStoreAccountMetadata::load(&data)?
+        };
+
+        f(&mut account_metadata)?;
+
+        account_metadata.store(&mut metadata_account.try_borrow_mut_data()?)?;
+
+        Ok(account_metadata)
+    }
+
+    fn supersede(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        args: SupersedeArgs,
+    ) -> ProgramResult {
+        debug_msg!(args.debug, "Supersede");
+
+        let accounts_iter = &mut accounts.iter();
+        let authority = next_account_info(accounts_iter)?;
+        let source_account = next_account_info(accounts_iter)?;
+        let source_metadata_account = next_account_info(accounts_iter)?;
+        let data_account = next_account_info(accounts_iter)?;
+        let metadata_account = next_account_info(accounts_iter)?;
+
+        // Ensure both data stores and their metadata accounts are owned by the data program
+        if [
+            source_account,
+            source_metadata_account,
+            data_account,
+            metadata_account,
+        ]
+        .iter()
+        .any(|account| account.owner != program_id)
+        {
+            return Err(DataStoreError::InvalidAccountOwner.into());
+        }
+
+        // Ensure authority is signer
+        if !authority.is_signer {
+            return Err(DataStoreError::NotSigner.into());
+        }
+
+        // Ensure metadata_account is writable
+        if !metadata_account.is_writable {
+            return Err(DataStoreError::NotWriteable.into());
+        }
+
+        // Ensure length is not 0
+        if source_metadata_account.data_is_empty() || metadata_account.data_is_empty() {
+            return Err(DataStoreError::NoAccountLength.into());
+        }
+
+        let source_metadata =
+            DataStoreAccountMetadata::load(&source_metadata_account.try_borrow_data()?)?;
+
+        // Ensure source_account is finalized
+        if *source_metadata.data_status() != SerializationStatusOption::Finalized {
+            return Err(DataStoreError::NotFinalized.into());
+        }
+
+        // Ensure source_account is superseded by its authority
+        if source_metadata.authority() != authority.key {
+            return Err(DataStoreError::InvalidAuthority.into());
+        }
+
+        // Ensure the source_metadata_account corresponds to the source_account
+        let pda = source_metadata.metadata_pda(program_id, source_account.key)?;
+        if pda != *source_metadata_account.key {
+            return Err(DataStoreError::InvalidPDA.into());
+        }
+
+        Self::with_metadata(metadata_account, |account_metadata| {
+            // Ensure data_account is initialized and not finalized
+            match *account_metadata.data_status() {
+                SerializationStatusOption::Uninitialized => {
+                    return Err(DataStoreError::NotInitialized.into());
+                }
+                SerializationStatusOption::Finalized => {
+                    return Err(DataStoreError::AlreadyFinalized.into());
+                }
+                _ => (),
+            }
+
+            // Ensure data_account is fresh, neither written to nor linked yet
+            if account_metadata.revision() != 0 || account_metadata.previous_version().is_some() {
+                return Err(DataStoreError::AlreadyInitialized.into());
+            }
+
+            // Ensure data_account holds the same type of data as source_account
+            if account_metadata.data_type() != source_metadata.data_type() {
+                return Err(DataStoreError::InvalidDataType.into());
+            }
+
+            // Ensure data_account is being written to by valid authority
+            if account_metadata.authority() != authority.key {
+                return Err(DataStoreError::InvalidAuthority.into());
+            }
+
+            // Ensure the metadata_account corresponds to the data_account
+            let pda = account_metadata.metadata_pda(program_id, data_account.key)?;
+            if pda != *metadata_account.key {
+                return Err(DataStoreError::InvalidPDA.into());
+            }
+
+            debug_msg!(args.debug, "account checks passed");
+
+            account_metadata.set_previous_version(Some(*source_account.key));
+
+            Ok(())
+        })?;
+
+        debug_msg!(
+            args.debug,
+            "{} supersedes {}",
+            data_account.key,
+            source_account.key
+        );
+
+        Ok(())
+    }
+
+    fn set_metadata_flags(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        args: SetMetadataFlagsArgs,
+    ) -> ProgramResult {
+        debug_msg!(args.debug, "SetMetadataFlags");
+
+        let accounts_iter = &mut accounts.iter();
+        let authority = next_account_info(accounts_iter)?;
+        let data_account = next_account_info(accounts_iter)?;
+        let metadata_account = next_account_info(accounts_iter)?;
+
+        // Ensure data_account and metadata_account are owned by the data program
+        if data_account.owner != program_id || metadata_account.owner != program_id {
+            return Err(DataStoreError::InvalidAccountOwner.into());
+        }
+
+        // Ensure authority is signer
+        if !authority.is_signer {
+            return Err(DataStoreError::NotSigner.into());
+        }
+
+        // Ensure metadata_account is writable
+        if !metadata_account.is_writable {
+            return Err(DataStoreError::NotWriteable.into());
+        }
+
+        // Ensure length is not 0
+        if metadata_account.data_is_empty() {
+            return Err(DataStoreError::NoAccountLength.into());
+        }
+
+        Self::with_metadata(metadata_account, |account_metadata| {
+            // Ensure data_account is initialized and not finalized
+            match *account_metadata.data_status() {
+                SerializationStatusOption::Uninitialized => {
+                    return Err(DataStoreError::NotInitialized.into());
+                }
+                SerializationStatusOption::Finalized => {
+                    return Err(DataStoreError::AlreadyFinalized.into());
+                }
+                _ => (),
+            }
+
+            // Ensure metadata_account is being written to by valid authority
+            if account_metadata.authority() != authority.key {
+                return Err(DataStoreError::InvalidAuthority.into());
+            }
+
+            // Ensure the metadata_account corresponds to the data_account
+            let pda = account_metadata.metadata_pda(program_id, data_account.key)?;
+            if pda != *metadata_account.key {
+                return Err(DataStoreError::InvalidPDA.into());
+            }
+
+            // Ensure only flags that may change after initialization are being changed
+            let changed = (account_metadata.flags() ^ args.values) & args.mask;
+            if changed & !MUTABLE_FLAGS != 0 {
+                return Err(DataStoreError::ImmutableFlag.into());
+            }
+
+            debug_msg!(args.debug, "account checks passed");
+
+            account_metadata.set_flag(args.mask & args.values, true);
+            account_metadata.set_flag(args.mask & !args.values, false);
+
+            // Ensure a strict content_type still matches the stored data
+            if account_metadata.has_flag(FLAG_STRICT_CONTENT_TYPE)
+                && !matches_content_type(
+                    account_metadata.content_type(),
+                    &data_account.data.borrow()[..account_metadata.data_len()],
+                )
+            {
+                return Err(DataStoreError::DataVerificationFailed.into());
+            }
+
+            Ok(())
+        })?;
+
+        debug_msg!(
+            args.debug,
+            "flags {:#x} set to {:#x}",
+            args.mask,
+            args.values
+        );
+
+        Ok(())
+    }
+
+    fn get_etag(program_id: &Pubkey, accounts: &[AccountInfo], args: GetETagArgs) -> ProgramResult {
+        debug_msg!(args.debug, "GetETag");
+
+        let accounts_iter = &mut accounts.iter();
+        let data_account = next_account_info(accounts_iter)?;
+        let metadata_account = next_account_info(accounts_iter)?;
+
+        // Ensure length is not 0
+        if metadata_account.data_is_empty() {
+            return Err(DataStoreError::NoAccountLength.into());
+        }
+
+        // Ensure metadata_account is owned by the data program
+        if metadata_account.owner != program_id {
+            return Err(DataStoreError::InvalidAccountOwner.into());
+        }
+
+        let account_metadata =
+            DataStoreAccountMetadata::load(&metadata_account.try_borrow_data()?)?;
+
+        // Ensure data_account is initialized
+        if *account_metadata.data_status() == SerializationStatusOption::Uninitialized {
+            return Err(DataStoreError::NotInitialized.into());
+        }
+
+        // Ensure the metadata_account corresponds to the data_account
+        let pda = account_metadata.metadata_pda(program_id, data_account.key)?;
+        if pda != *metadata_account.key {
+            return Err(DataStoreError::InvalidPDA.into());
+        }
+
+        set_return_data(&account_metadata.etag());
+
+        debug_msg!(args.debug, "returned etag");
+
+        Ok(())
+    }
+
+    fn get_upload_progress(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        args: GetUploadProgressArgs,
+    ) -> ProgramResult {
+        debug_msg!(args.debug, "GetUploadProgress");
+
+        let accounts_iter = &mut accounts.iter();
+        let data_account = next_account_info(accounts_iter)?;
+        let metadata_account = next_account_info(accounts_iter)?;
+
+        // Ensure length is not 0
+        if metadata_account.data_is_empty() {
+            return Err(DataStoreError::NoAccountLength.into());
+        }
+
+        // Ensure metadata_account is owned by the data program
+        if metadata_account.owner != program_id {
+            return Err(DataStoreError::InvalidAccountOwner.into());
+        }
+
+        let account_metadata =
+            DataStoreAccountMetadata::load(&metadata_account.try_borrow_data()?)?;
+
+        // Ensure data_account is initialized
+        if *account_metadata.data_status() == SerializationStatusOption::Uninitialized {
+            return Err(DataStoreError::NotInitialized.into());
+        }
+
+        // Ensure the metadata_account corresponds to the data_account
+        let pda = account_metadata.metadata_pda(program_id, data_account.key)?;
+        if pda != *metadata_account.key {
+            return Err(DataStoreError::InvalidPDA.into());
+        }
+
+        set_return_data(&borsh::to_vec(&(
+            account_metadata.upload_cursor(),
+            account_metadata.expected_total(),
+        ))?);
+
+        debug_msg!(
+            args.debug,
+            "upload at {} of {}",
+            account_metadata.upload_cursor(),
+            account_metadata.expected_total()
+        );
+
+        Ok(())
+    }
+
+    #[cfg(feature = "permissioned-init")]
+    fn set_allowlist(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        args: SetAllowlistArgs,
+    ) -> ProgramResult {
+        debug_msg!(args.debug, "SetAllowlist");
+
+        let accounts_iter = &mut accounts.iter();
+        let authority = next_account_info(accounts_iter)?;
+        let allowlist_account = next_account_info(accounts_iter)?;
+        let system_program = next_account_info(accounts_iter)?;
+        let program_data = next_account_info(accounts_iter)?;
+
+        // Ensure authority is signer
+        if !authority.is_signer {
+            return Err(DataStoreError::NotSigner.into());
+        }
+
+        // Ensure authority and allowlist_account are writable
+        if !authority.is_writable || !allowlist_account.is_writable {
+            return Err(DataStoreError::NotWriteable.into());
+        }
+
+        // Ensure the allowlist_account is the allowlist PDA
+        let (pda, bump_seed) = Pubkey::find_program_address(&[ALLOWLIST_SEED], program_id);
+        if pda != *allowlist_account.key {
+            return Err(DataStoreError::InvalidPDA.into());
+        }
+
+        // Ensure the members fit the allowlist_account
+        if args.members.len() > MAX_ALLOWLIST_MEMBERS {
+            return Err(DataStoreError::InvalidInstructionData.into());
+        }
+
+        // Create the allowlist, owned by the upgrade authority of the program so that no
+        // one can claim it first after a deploy
+        if allowlist_account.data_is_empty() {
+            Self::check_upgrade_authority(program_id, program_data, authority.key)?;
+
+            Self::create_program_account(
+                program_id,
+                authority,
+                allowlist_account,
+                system_program,
+                ALLOWLIST_SIZE,
+                &[&[ALLOWLIST_SEED, &[bump_seed]]],
+            )?;
+
+            debug_msg!(args.debug, "allowlist pda created");
+        }
+        // Else ensure the allowlist is being written to by its authority
+        else {
+            let allowlist = Allowlist::deserialize(&mut &allowlist_account.data.borrow()[..])?;
+            if allowlist.authority != *authority.key {
+                return Err(DataStoreError::InvalidAuthority.into());
+            }
+        }
+
+        let allowlist = Allowlist {
+            authority: *authority.key,
+            members: args.members,
+        };
+        allowlist.serialize(&mut &mut allowlist_account.data.borrow_mut()[..])?;
+
+        debug_msg!(
+            args.debug,
+            "allowlist set to {} members",
+            allowlist.members.len()
+        );
+
+        Ok(())
+    }
+
+    /// Ensures feepayer is a member of the allowlist PDA
+    #[cfg(feature = "permissioned-init")]
+    fn check_allowlist(
+        program_id: &Pubkey,
+        feepayer: &AccountInfo,
+        allowlist_account: Option<&AccountInfo>,
+    ) -> ProgramResult {
+        let allowlist_account = allowlist_account.ok_or(DataStoreError::InvalidAuthority)?;
+
+        // Ensure the allowlist_account is the allowlist PDA, written by the data program
+        let (pda, _) = Pubkey::find_program_address(&[ALLOWLIST_SEED], program_id);
+        if pda != *allowlist_account.key || allowlist_account.owner != program_id {
+            return Err(DataStoreError::InvalidPDA.into());
+        }
+
+        let allowlist = Allowlist::deserialize(&mut &allowlist_account.data.borrow()[..])?;
+        if !allowlist.contains(feepayer.key) {
+            return Err(DataStoreError::InvalidAuthority.into());
+        }
+
+        Ok(())
+    }
+
+    fn swap_data(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        args: SwapDataArgs,
+    ) -> ProgramResult {
+        debug_msg!(args.debug, "SwapData");
+
+        let accounts_iter = &mut accounts.iter();
+        let authority = next_account_info(accounts_iter)?;
+        let data_account = next_account_info(accounts_iter)?;
+        let metadata_account = next_account_info(accounts_iter)?;
+        let other_account = next_account_info(accounts_iter)?;
+        let other_metadata_account = next_account_info(accounts_iter)?;
+        let system_program = next_account_info(accounts_iter)?;
+
+        // Ensure two distinct data stores are swapped
+        if data_account.key == other_account.key {
+            return Err(DataStoreError::InvalidInstructionData.into());
+        }
+
+        // Ensure authority can pay for growing a dynamic data_account
+        if !authority.is_writable {
+            return Err(DataStoreError::NotWriteable.into());
+        }
+
+        let mut account_metadata =
+            Self::load_writable(program_id, authority, data_account, metadata_account)?;
+        let mut other_metadata =
+            Self::load_writable(program_id, authority, other_account, other_metadata_account)?;
+
+        // Ensure both data stores hold the same type of data
+        if account_metadata.data_type() != other_metadata.data_type() {
+            return Err(DataStoreError::InvalidDataType.into());
+        }
+
+        // Ensure neither data store forbids overwriting its contents
+        if account_metadata.is_append_only() || other_metadata.is_append_only() {
+            return Err(DataStoreError::AppendOnlyViolation.into());
+        }
+
+        let data = data_account.data.borrow()[..account_metadata.data_len()].to_vec();
+        let other_data = other_account.data.borrow()[..other_metadata.data_len()].to_vec();
+
+        // Ensure static data stores have the space for the contents they receive
+        if (!account_metadata.is_dynamic() && data_account.data_len() < other_data.len())
+            || (!other_metadata.is_dynamic() && other_account.data_len() < data.len())
+        {
+            return Err(DataStoreError::InsufficientSpace.into());
+        }
+
+        // Ensure strict data stores receive contents matching their content type
+        if (account_metadata.is_strict_content_type()
+            && !matches_content_type(account_metadata.content_type(), &other_data))
+            || (other_metadata.is_strict_content_type()
+                && !matches_content_type(other_metadata.content_type(), &data))
+        {
+            return Err(DataStoreError::DataVerificationFailed.into());
+        }
+
+        debug_msg!(args.debug, "account checks passed");
+
+        // The revision and upload progress travel with the contents they describe, so an
+        // upload resumes wherever its bytes went
+        let progress = (
+            account_metadata.revision(),
+            account_metadata.upload_cursor(),
+            account_metadata.expected_total(),
+        );
+        let other_progress = (
+            other_metadata.revision(),
+            other_metadata.upload_cursor(),
+            other_metadata.expected_total(),
+        );
+
+        for (account, metadata_account, account_metadata, contents, progress) in [
+            (
+                data_account,
+                metadata_account,
+                &mut account_metadata,
+                &other_data,
+                other_progress,
+            ),
+            (
+                other_account,
+                other_metadata_account,
+                &mut other_metadata,
+                &data,
+                progress,
+            ),
+        ] {
+            // Resize dynamic data stores to exactly the received contents
+            if account_metadata.is_dynamic() && account.data_len() != contents.len() {
+                Self::resize_data_account(authority, account, system_program, contents.len())?;
+            }
+
+            {
+                let mut account_data = account.data.borrow_mut();
+                account_data[..contents.len()].copy_from_slice(contents);
+                account_data[contents.len()..].fill(0);
+            }
+
+            let (revision, upload_cursor, expected_total) = progress;
+            account_metadata.set_space(account.data_len());
+            account_metadata.set_data_len(contents.len());
+            account_metadata.update_checksums(account.key, &account.data.borrow());
+            account_metadata.set_revision(revision);
+            account_metadata.set_upload_cursor(upload_cursor);
+            account_metadata.set_expected_total(expected_total);
+            account_metadata.store(&mut metadata_account.data.borrow_mut())?;
+
+            DataStoreEvent::Updated {
+                data_account: *account.key,
+                revision: account_metadata.revision(),
+                data_len: account_metadata.data_len() as u64,
+                data_hash: *account_metadata.data_hash(),
+            }
+            .emit();
+        }
+
+        debug_msg!(
+            args.debug,
+            "swapped {} and {}",
+            data_account.key,
+            other_account.key
+        );
+
+        Ok(())
+    }
+
+    fn get_account_state(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        args: GetAccountStateArgs,
+    ) -> ProgramResult {
+        debug_msg!(args.debug, "GetAccountState");
+
+        let accounts_iter = &mut accounts.iter();
+        let data_account = next_account_info(accounts_iter)?;
+        let metadata_account = next_account_info(accounts_iter)?;
+
+        // Ensure length is not 0
+        if metadata_account.data_is_empty() {
+            return Err(DataStoreError::NoAccountLength.into());
+        }
+
+        // Ensure metadata_account is owned by the data program
+        if metadata_account.owner != program_id {
+            return Err(DataStoreError::InvalidAccountOwner.into());
+        }
+
+        let account_metadata =
+            DataStoreAccountMetadata::load(&metadata_account.try_borrow_data()?)?;
+
+        // Ensure the metadata_account corresponds to the data_account
+        let pda = account_metadata.metadata_pda(program_id, data_account.key)?;
+        if pda != *metadata_account.key {
+            return Err(DataStoreError::InvalidPDA.into());
+        }
+
+        set_return_data(&[account_metadata.account_state()]);
+
+        debug_msg!(
+            args.debug,
+            "returned state {:#010b}",
+            account_metadata.account_state()
+        );
+
+        Ok(())
+    }
+
+    fn reclaim_rent(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        args: ReclaimRentArgs,
+    ) -> ProgramResult {
+        debug_msg!(args.debug, "ReclaimRent");
+
+        let accounts_iter = &mut accounts.iter();
+        let authority = next_account_info(accounts_iter)?;
+        let data_account = next_account_info(accounts_iter)?;
+        let metadata_account = next_account_info(accounts_iter)?;
+        let system_program = next_account_info(accounts_iter)?;
+
+        // Ensure authority can receive the refund
+        if !authority.is_writable {
+            return Err(DataStoreError::NotWriteable.into());
+        }
+
+        let mut account_metadata =
+            Self::load_writable(program_id, authority, data_account, metadata_account)?;
+
+        // Ensure data_account can be resized
+        if !account_metadata.is_dynamic() {
+            return Err(DataStoreError::NotDynamic.into());
+        }
+
+        debug_msg!(args.debug, "account checks passed");
+
+        // Shrink data_account to its used bytes, refunding the freed rent
+        let data_len = account_metadata.data_len();
+        if data_account.data_len() > data_len {
+            if account_metadata.zero_on_shrink() {
+                data_account.data.borrow_mut()[data_len..].fill(0);
+            }
+            Self::resize_data_account(authority, data_account, system_program, data_len)?;
+
+            debug_msg!(args.debug, "realloc-ed {}", data_len);
+        }
+
+        account_metadata.set_space(data_account.data_len());
+        account_metadata.store(&mut metadata_account.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    fn set_pause(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        args: SetPauseArgs,
+    ) -> ProgramResult {
+        debug_msg!(args.debug, "SetPause");
+
+        let accounts_iter = &mut accounts.iter();
+        let admin = next_account_info(accounts_iter)?;
+        let config_account = next_account_info(accounts_iter)?;
+        let system_program = next_account_info(accounts_iter)?;
+        let program_data = next_account_info(accounts_iter)?;
+
+        // Ensure admin is signer
+        if !admin.is_signer {
+            return Err(DataStoreError::NotSigner.into());
+        }
+
+        // Ensure admin and config_account are writable
+        if !admin.is_writable || !config_account.is_writable {
+            return Err(DataStoreError::NotWriteable.into());
+        }
+
+        // Ensure the config_account is the config PDA
+        let (pda, bump_seed) = Pubkey::find_program_address(&[CONFIG_SEED], program_id);
+        if pda != *config_account.key {
+            return Err(DataStoreError::InvalidPDA.into());
+        }
+
+        // Create the config, administered by the upgrade authority of the program so that
+        // no one can claim it first after a deploy
+        if config_account.data_is_empty() {
+            Self::check_upgrade_authority(program_id, program_data, admin.key)?;
+
+            Self::create_program_account(
+                program_id,
+                admin,
+                config_account,
+                system_program,
+                CONFIG_SIZE,
+                &[&[CONFIG_SEED, &[bump_seed]]],
+            )?;
+
+            debug_msg!(args.debug, "config pda created");
+        }
+        // Else ensure the config is being written to by its admin
+        else {
+            let config = ProgramConfig::deserialize(&mut &config_account.data.borrow()[..])?;
+            if config.admin != *admin.key {
+                return Err(DataStoreError::InvalidAuthority.into());
+            }
+        }
+
+        let config = ProgramConfig {
+            admin: *admin.key,
+            paused: args.paused,
+            bump_seed,
+        };
+        config.serialize(&mut &mut config_account.data.borrow_mut()[..])?;
+
+        debug_msg!(args.debug, "paused set to {}", args.paused);
+
+        Ok(())
+    }
+
+    /// Whether instruction modifies accounts and is therefore blocked while paused.
+    /// Admin instructions take the config PDA among their own accounts and check it themselves.
+    fn is_mutating(instruction: &DataStoreInstruction) -> bool {
+        !matches!(
+            instruction,
+            DataStoreInstruction::GetMetadata(_)
+                | DataStoreInstruction::GetDirectoryEntries(_)
+                | DataStoreInstruction::RegionHash(_)
+                | DataStoreInstruction::Validate(_)
+                | DataStoreInstruction::GetDataHash(_)
+                | DataStoreInstruction::ReadData(_)
+                | DataStoreInstruction::GetETag(_)
+                | DataStoreInstruction::GetUploadProgress(_)
+                | DataStoreInstruction::GetAccountState(_)
+                | DataStoreInstruction::GetSpaceInfo(_)
+                | DataStoreInstruction::SetPause(_)
+                | DataStoreInstruction::RawMetadataWrite(_)
+                | DataStoreInstruction::GetHeaders(_)
+        )
+    }
+
+    /// Ensures authority is the upgrade authority of the program, program_data being its
+    /// ProgramData account. Programs deployed immutable have no upgrade authority.
+    fn check_upgrade_authority(
+        program_id: &Pubkey,
+        program_data: &AccountInfo,
+        authority: &Pubkey,
+    ) -> ProgramResult {
+        // Ensure program_data is the ProgramData account of the program
+        let (pda, _) =
+            Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::id());
+        if pda != *program_data.key {
+            return Err(DataStoreError::InvalidPDA.into());
+        }
+        if *program_data.owner != bpf_loader_upgradeable::id() {
+            return Err(DataStoreError::InvalidAccountOwner.into());
+        }
+
+        let state: UpgradeableLoaderState = limited_deserialize(
+            &program_data.try_borrow_data()?,
+            UpgradeableLoaderState::size_of_programdata_metadata() as u64,
+        )
+        .map_err(|_| ProgramError::InvalidAccountData)?;
+        match state {
+            UpgradeableLoaderState::ProgramData {
+                upgrade_authority_address: Some(upgrade_authority),
+                ..
+            } if upgrade_authority == *authority => Ok(()),
+            _ => Err(DataStoreError::InvalidAuthority.into()),
+        }
+    }
+
+    /// Ensures the program is not paused, config_account being the config PDA.
+    /// A config PDA that was never created leaves the program unpaused.
+    fn check_not_paused(program_id: &Pubkey, config_account: &AccountInfo) -> ProgramResult {
+        // Ensure an uncreated config_account is the config PDA, which only has to be
+        // searched for until the config is created
+        if config_account.data_is_empty() {
+            let (pda, _) = Pubkey::find_program_address(&[CONFIG_SEED], program_id);
+            if pda != *config_account.key {
+                return Err(DataStoreError::InvalidPDA.into());
+            }
+            return Ok(());
+        }
+
+        // Ensure the config was written by the data program
+        if config_account.owner != program_id {
+            return Err(DataStoreError::InvalidAccountOwner.into());
+        }
+
+        let config = ProgramConfig::deserialize(&mut &config_account.data.borrow()[..])?;
+
+        // Ensure the config_account is the config PDA of the bump_seed it stores
+        let pda = Pubkey::create_program_address(&[CONFIG_SEED, &[config.bump_seed]], program_id)?;
+        if pda != *config_account.key {
+            return Err(DataStoreError::InvalidPDA.into());
+        }
+
+        if config.paused {
+            return Err(DataStoreError::ProgramPaused.into());
+        }
+
+        Ok(())
+    }
+
+    fn convert_data_type(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        args: ConvertDataTypeArgs,
+    ) -> ProgramResult {
+        debug_msg!(args.debug, "ConvertDataType");
+
+        let accounts_iter = &mut accounts.iter();
+        let authority = next_account_info(accounts_iter)?;
+        let data_account = next_account_info(accounts_iter)?;
+        let metadata_account = next_account_info(accounts_iter)?;
+
+        // Ensure data_account and metadata_account are owned by the data program
+        if data_account.owner != program_id || metadata_account.owner != program_id {
+            return Err(DataStoreError::InvalidAccountOwner.into());
+        }
+
+        // Ensure authority is signer
+        if !authority.is_signer {
+            return Err(DataStoreError::NotSigner.into());
+        }
+
+        // Ensure metadata_account is writable
+        if !metadata_account.is_writable {
+            return Err(DataStoreError::NotWriteable.into());
+        }
+
+        // Ensure length is not 0
+        if metadata_account.data_is_empty() {
+            return Err(DataStoreError::NoAccountLength.into());
+        }
+
+        Self::with_metadata(metadata_account, |account_metadata| {
+            // Ensure data_account is initialized and not finalized
+            match *account_metadata.data_status() {
+                SerializationStatusOption::Uninitialized => {
+                    return Err(DataStoreError::NotInitialized.into());
+                }
+                SerializationStatusOption::Finalized => {
+                    return Err(DataStoreError::AlreadyFinalized.into());
+                }
+                _ => (),
+            }
+
+            // Ensure data_account is not frozen
+            if account_metadata.is_frozen() {
+                return Err(DataStoreError::Frozen.into());
+            }
+
+            // Ensure metadata_account is being written to by valid authority
+            if account_metadata.authority() != authority.key {
+                return Err(DataStoreError::InvalidAuthority.into());
+            }
+
+            // Ensure the metadata_account corresponds to the data_account
+            let pda = account_metadata.metadata_pda(program_id, data_account.key)?;
+            if pda != *metadata_account.key {
+                return Err(DataStoreError::InvalidPDA.into());
+            }
+
+            // Ensure the data_type actually changes
+            if *account_metadata.data_type() == args.data_type {
+                return Err(DataStoreError::InvalidDataType.into());
+            }
+
+            // Ensure the used data is valid for the new data_type
+            let data = &data_account.data.borrow()[..account_metadata.data_len()];
+            let is_compatible = match args.data_type {
+                DataStoreTypeOption::File => true,
+                DataStoreTypeOption::Directory => {
+                    (data.is_empty()
+                        || verify_directory_data(data, account_metadata.directory_format()))
+                        && data.len() / DIRECTORY_ENTRY_SIZE <= account_metadata.max_entries()
+                }
+                DataStoreTypeOption::Symlink => data.len() == SYMLINK_TARGET_LEN,
+            };
+            if !is_compatible {
+                return Err(DataStoreError::DataVerificationFailed.into());
+            }
+
+            debug_msg!(args.debug, "account checks passed");
+
+            account_metadata.set_data_type(args.data_type);
+
+            Ok(())
+        })?;
+
+        debug_msg!(args.debug, "data type converted");
+
+        Ok(())
+    }
+
+    fn initialize_data_store_batch(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        args: InitializeDataStoreBatchArgs,
+    ) -> ProgramResult {
+        debug_msg!(args.debug, "InitializeDataStoreBatch");
+
+        let accounts_iter = &mut accounts.iter();
+        let feepayer = next_account_info(accounts_iter)?;
+        let system_program = next_account_info(accounts_iter)?;
+        #[cfg(feature = "permissioned-init")]
+        let allowlist = next_account_info(accounts_iter)?;
+        let pairs = accounts_iter.as_slice();
+
+        // Ensure the remaining accounts are (data_account, metadata_account) pairs within
+        // the batch bound, each with its bump seed
+        let batch_size = pairs.len() / 2;
+        if batch_size == 0
+            || batch_size * 2 != pairs.len()
+            || batch_size > MAX_INIT_BATCH_SIZE
+            || batch_size != args.bump_seeds.len()
+        {
+            return Err(DataStoreError::InvalidInstructionData.into());
+        }
+
+        // Initialize every pair as a regular data store. An error in any of them fails
+        // the instruction, so the runtime discards the ones already created.
+        for (pair, bump_seed) in pairs.chunks_exact(2).zip(args.bump_seeds) {
+            let group = [
+                feepayer.clone(),
+                pair[0].clone(),
+                pair[1].clone(),
+                system_program.clone(),
+                #[cfg(feature = "permissioned-init")]
+                allowlist.clone(),
+            ];
+            Self::initialize_data_store(
+                program_id,
+                &group,
+                InitializeDataStoreArgs {
+                    debug: args.debug,
+                    data_type: args.data_type.clone(),
+                    bump_seed,
+                    is_created: args.is_created,
+                    space: args.space,
+                    authority: args.authority,
+                    is_dynamic: args.is_dynamic,
+                    initial_data: Vec::new(),
+                    append_only: false,
+                    namespace: args.namespace,
+                    idempotent: false,
+                    private: false,
+                    content_type: String::new(),
+                    strict_content_type: false,
+                    expected_total: 0,
+                    min_update_interval_slots: 0,
+                    expiry_slot: None,
+                    max_revisions: None,
+                    audit: false,
+                    file_name: String::new(),
+                    zero_on_shrink: true,
+                    cache_ttl_seconds: 0,
+                    indexable: true,
+                    stream_hash: false,
+                },
+            )?;
+        }
+
+        debug_msg!(args.debug, "initialized {} data stores", batch_size);
+
+        Ok(())
+    }
+
+    fn relayed_update(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        args: UpdateDataStoreArgs,
+    ) -> ProgramResult {
+        debug_msg!(args.debug, "RelayedUpdate");
+
+        let accounts_iter = &mut accounts.iter();
+        let relayer = next_account_info(accounts_iter)?;
+        let data_account = next_account_info(accounts_iter)?;
+        let metadata_account = next_account_info(accounts_iter)?;
+        let system_program = next_account_info(accounts_iter)?;
+        let instructions_sysvar = next_account_info(accounts_iter)?;
+        let audit_account = next_account_info(accounts_iter).ok();
+
+        // Ensure relayer is signer
+        if !relayer.is_signer {
+            return Err(DataStoreError::NotSigner.into());
+        }
+
+        // Ensure length is not 0
+        if metadata_account.data_is_empty() {
+            return Err(DataStoreError::NoAccountLength.into());
+        }
+
+        // Ensure metadata_account is owned by the data program
+        if metadata_account.owner != program_id {
+            return Err(DataStoreError::InvalidAccountOwner.into());
+        }
+
+        // Ensure the preceding instruction verified a signature of the update at the
+        // current revision, its signer then being checked as the authority
+        let revision =
+            DataStoreAccountMetadata::load(&metadata_account.try_borrow_data()?)?.revision();
+        let message = relayed_update_message(program_id, data_account.key, revision, &args)?;
+        let authority = Self::find_ed25519_signer(instructions_sysvar, &message)?
+            .ok_or(DataStoreError::NotSigner)?;
+
+        let plan = Self::validate_authorized_update(
+            program_id,
+            &authority,
+            relayer,
+            data_account,
+            metadata_account,
+            &ValidateArgs::from(&args),
+        )?;
+
+        debug_msg!(args.debug, "account checks passed");
+
+        Self::apply_update(
+            program_id,
+            relayer,
+            data_account,
+            metadata_account,
+            system_program,
+            audit_account,
+            plan,
+            &args,
+        )
+    }
+
+    /// Returns the public key that signed message in the ed25519 program instruction
+    /// preceding the current one, if any. Only signatures whose offsets point into the
+    /// ed25519 instruction itself are considered.
+    fn find_ed25519_signer(
+        instructions_sysvar: &AccountInfo,
+        message: &[u8],
+    ) -> Result<Option<Pubkey>, ProgramError> {
+        let current_index = load_current_index_checked(instructions_sysvar)?;
+        let index = match current_index.checked_sub(1) {
+            Some(index) => index,
+            None => return Ok(None),
+        };
+        let instruction = load_instruction_at_checked(index as usize, instructions_sysvar)?;
+        if instruction.program_id != ed25519_program::ID {
+            return Ok(None);
+        }
+
+        // Header of u8 signature count and u8 padding, followed per signature by u16 LE
+        // signature_offset, signature_instruction_index, public_key_offset,
+        // public_key_instruction_index, message_data_offset, message_data_size and
+        // message_instruction_index, an instruction index of u16::MAX meaning this one
+        let data = &instruction.data;
+        let num_signatures = *data.first().unwrap_or(&0) as usize;
+        for i in 0..num_signatures {
+            let start = 2 + i * 14;
+            let record = match data.get(start..start + 14) {
+                Some(record) => record,
+                None => return Ok(None),
+            };
+            let field = |n: usize| u16::from_le_bytes([record[n * 2], record[n * 2 + 1]]);
+            let (public_key_offset, message_offset, message_size) =
+                (field(2) as usize, field(4) as usize, field(5) as usize);
+            if field(1) != u16::MAX || field(3) != u16::MAX || field(6) != u16::MAX {
+                continue;
+            }
+            let public_key = data.get(public_key_offset..public_key_offset + 32);
+            let signed = data.get(message_offset..message_offset + message_size);
+            if let (Some(public_key), Some(signed)) = (public_key, signed) {
+                if signed == message {
+                    return Ok(Pubkey::try_from(public_key).ok());
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn reinitialize(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        args: ReinitializeArgs,
+    ) -> ProgramResult {
+        debug_msg!(args.debug, "Reinitialize");
+
+        let accounts_iter = &mut accounts.iter();
+        let authority = next_account_info(accounts_iter)?;
+        let data_account = next_account_info(accounts_iter)?;
+        let metadata_account = next_account_info(accounts_iter)?;
+        let headers_account = next_account_info(accounts_iter)?;
+
+        // Ensure data_account and metadata_account are owned by the data program
+        if data_account.owner != program_id || metadata_account.owner != program_id {
+            return Err(DataStoreError::InvalidAccountOwner.into());
+        }
+
+        // Ensure authority is signer
+        if !authority.is_signer {
+            return Err(DataStoreError::NotSigner.into());
+        }
+
+        // Ensure data_account and metadata_account are writable
+        if !data_account.is_writable || !metadata_account.is_writable {
+            return Err(DataStoreError::NotWriteable.into());
+        }
+
+        // Ensure length is not 0
+        if metadata_account.data_is_empty() {
+            return Err(DataStoreError::NoAccountLength.into());
+        }
+
+        let account_metadata =
+            DataStoreAccountMetadata::load(&metadata_account.try_borrow_data()?)?;
+
+        // Ensure data_account is initialized, finalized ones being reinitialized as well
+        if *account_metadata.data_status() == SerializationStatusOption::Uninitialized {
+            return Err(DataStoreError::NotInitialized.into());
+        }
+
+        // Ensure data_account is not frozen
+        if account_metadata.is_frozen() {
+            return Err(DataStoreError::Frozen.into());
+        }
+
+        // Ensure data_account is being reinitialized by valid authority
+        if account_metadata.authority() != authority.key {
+            return Err(DataStoreError::InvalidAuthority.into());
+        }
+
+        // Ensure the metadata_account corresponds to the data_account
+        let pda = account_metadata.metadata_pda(program_id, data_account.key)?;
+        if pda != *metadata_account.key {
+            return Err(DataStoreError::InvalidPDA.into());
+        }
+
+        // Ensure the headers_account is the headers PDA of data_account
+        let (pda, _) = derive_headers_pda(program_id, data_account.key);
+        if pda != *headers_account.key {
+            return Err(DataStoreError::InvalidPDA.into());
+        }
+
+        // Ensure created headers are owned by the data program and writable, to be cleared
+        if !headers_account.data_is_empty() {
+            if headers_account.owner != program_id {
+                return Err(DataStoreError::InvalidAccountOwner.into());
+            }
+            if !headers_account.is_writable {
+                return Err(DataStoreError::NotWriteable.into());
+            }
+        }
+
+        // Ensure a static data_account has capacity, only dynamic ones may be empty
+        if data_account.data_len() == 0 && !args.is_dynamic {
+            return Err(DataStoreError::ZeroSpace.into());
+        }
+
+        let (content_account, parent_accounts) =
+            Self::next_recorded_accounts(accounts_iter, &account_metadata)?;
+
+        debug_msg!(args.debug, "account checks passed");
+
+        // Close the content PDA registering the contents about to be cleared
+        if let Some(content_account) = content_account {
+            if !authority.is_writable {
+                return Err(DataStoreError::NotWriteable.into());
+            }
+            Self::close_content_pda(
+                program_id,
+                authority,
+                data_account,
+                &data_account.data.borrow()[..account_metadata.data_len()],
+                content_account,
+            )?;
+
+            debug_msg!(args.debug, "content pda lamports transfered to authority");
+        }
+
+        // Remove the entry listing the data_account, which drops its name and parent
+        if let Some((parent, parent_metadata_account)) = parent_accounts {
+            Self::remove_entry(
+                program_id,
+                authority,
+                parent,
+                parent_metadata_account,
+                data_account.key,
+            )?;
+
+            debug_msg!(args.debug, "removed entry from {}", parent.key);
+        }
+
+        data_account.data.borrow_mut().fill(0);
+
+        // Clear the headers served for the previous contents, which an empty headers PDA
+        // encodes as no headers
+        if !headers_account.data_is_empty() {
+            headers_account.data.borrow_mut().fill(0);
+
+            debug_msg!(args.debug, "headers cleared");
+        }
+
+        // Reset the metadata, keeping what ties it to the accounts
+        let mut fresh_metadata = DataStoreAccountMetadata {
+            space: data_account.data_len(),
+            revision: account_metadata.revision(),
+            namespace: *account_metadata.namespace(),
+            creator: *account_metadata.creator(),
+            ..DataStoreAccountMetadata::new(
+                args.authority,
+                args.data_type,
+                account_metadata.bump_seed(),
+                args.is_dynamic,
+            )
+        };
+        fresh_metadata.update_checksums(data_account.key, &data_account.data.borrow());
+        fresh_metadata
+            .increment_revision()
+            .ok_or(DataStoreError::Overflow)?;
+        // Zero the old metadata first, so none of its trailing bytes outlive the reset
+        metadata_account.data.borrow_mut().fill(0);
+        fresh_metadata.store(&mut metadata_account.data.borrow_mut())?;
+
+        DataStoreEvent::Initialized {
+            data_account: *data_account.key,
+            authority: *fresh_metadata.authority(),
+            data_type: fresh_metadata.data_type().clone(),
+        }
+        .emit();
+
+        debug_msg!(args.debug, "data store reinitialized");
+
+        Ok(())
+    }
+
+    fn get_space_info(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        args: GetSpaceInfoArgs,
+    ) -> ProgramResult {
+        debug_msg!(args.debug, "GetSpaceInfo");
+
+        let accounts_iter = &mut accounts.iter();
+        let data_account = next_account_info(accounts_iter)?;
+        let metadata_account = next_account_info(accounts_iter)?;
+
+        // Ensure length is not 0
+        if metadata_account.data_is_empty() {
+            return Err(DataStoreError::NoAccountLength.into());
+        }
+
+        // Ensure metadata_account is owned by the data program
+        if metadata_account.owner != program_id {
+            return Err(DataStoreError::InvalidAccountOwner.into());
+        }
+
+        let account_metadata =
+            DataStoreAccountMetadata::load(&metadata_account.try_borrow_data()?)?;
+
+        // Ensure data_account is initialized
+        if *account_metadata.data_status() == SerializationStatusOption::Uninitialized {
+            return Err(DataStoreError::NotInitialized.into());
+        }
+
+        // Ensure the metadata_account corresponds to the data_account
+        let pda = account_metadata.metadata_pda(program_id, data_account.key)?;
+        if pda != *metadata_account.key {
+            return Err(DataStoreError::InvalidPDA.into());
+        }
+
+        let space_info = SpaceInfo {
+            allocated: data_account.data_len() as u64,
+            used: account_metadata.data_len() as u64,
+            is_dynamic: account_metadata.is_dynamic(),
+            rent_lamports: data_account
+                .lamports()
+                .checked_add(metadata_account.lamports())
+                .ok_or(DataStoreError::Overflow)?,
+        };
+        set_return_data(&borsh::to_vec(&space_info)?);
+
+        debug_msg!(args.debug, "returned {:?}", space_info);
+
+        Ok(())
+    }
+
+    fn set_name(program_id: &Pubkey, accounts: &[AccountInfo], args: SetNameArgs) -> ProgramResult {
+        debug_msg!(args.debug, "SetName");
+
+        let accounts_iter = &mut accounts.iter();
+        let authority = next_account_info(accounts_iter)?;
+        let data_account = next_account_info(accounts_iter)?;
+        let metadata_account = next_account_info(accounts_iter)?;
+        let parent = next_account_info(accounts_iter).ok();
+        let parent_metadata_account = next_account_info(accounts_iter).ok();
+        let system_program = next_account_info(accounts_iter).ok();
+
+        // Ensure data_account and metadata_account are owned by the data program
+        if data_account.owner != program_id || metadata_account.owner != program_id {
+            return Err(DataStoreError::InvalidAccountOwner.into());
+        }
+
+        // Ensure authority is signer
+        if !authority.is_signer {
+            return Err(DataStoreError::NotSigner.into());
+        }
+
+        // Ensure metadata_account is writable
+        if !metadata_account.is_writable {
+            return Err(DataStoreError::NotWriteable.into());
+        }
+
+        // Ensure length is not 0
+        if metadata_account.data_is_empty() {
+            return Err(DataStoreError::NoAccountLength.into());
+        }
+
+        // Ensure the name fits the metadata
+        if args.name.len() > MAX_ENTRY_NAME_LEN {
+            return Err(DataStoreError::InvalidInstructionData.into());
+        }
+
+        Self::with_metadata(metadata_account, |account_metadata| {
+            // Ensure data_account is initialized and not finalized
+            match *account_metadata.data_status() {
+                SerializationStatusOption::Uninitialized => {
+                    return Err(DataStoreError::NotInitialized.into());
+                }
+                SerializationStatusOption::Finalized => {
+                    return Err(DataStoreError::AlreadyFinalized.into());
+                }
+                _ => (),
+            }
+
+            // Ensure data_account is not frozen
+            if account_metadata.is_frozen() {
+                return Err(DataStoreError::Frozen.into());
+            }
+
+            // Ensure metadata_account is being written to by valid authority
+            if account_metadata.authority() != authority.key {
+                return Err(DataStoreError::InvalidAuthority.into());
+            }
+
+            // Ensure the metadata_account corresponds to the data_account
+            let pda = account_metadata.metadata_pda(program_id, data_account.key)?;
+            if pda != *metadata_account.key {
+                return Err(DataStoreError::InvalidPDA.into());
+            }
+
+            // Ensure the parent listing the data_account under its name is provided
+            let parent = match (parent, account_metadata.parent()) {
+                (None, Some(_)) => return Err(DataStoreError::ParentRequired.into()),
+                (parent, _) => parent,
+            };
+
+            // Rename the entry of the data_account in the parent, if provided, after
+            // ensuring no sibling already has the name
+            if let Some(parent) = parent {
+                let parent_metadata_account =
+                    parent_metadata_account.ok_or(ProgramError::NotEnoughAccountKeys)?;
+                Self::rename_entry(
+                    program_id,
+                    authority,
+                    parent,
+                    parent_metadata_account,
+                    system_program,
+                    data_account.key,
+                    &args.name,
+                )?;
+                account_metadata.set_parent(Some(*parent.key));
+
+                debug_msg!(args.debug, "renamed entry in {}", parent.key);
+            }
+
+            debug_msg!(args.debug, "account checks passed");
+
+            account_metadata.set_name(args.name);
+
+            Ok(())
+        })?;
+
+        debug_msg!(args.debug, "name set");
+
+        Ok(())
+    }
+
+    /// Renames the entry of child in the NamedEntries directory parent to name, after
+    /// ensuring child is listed in it and that no other entry of it is named name.
+    /// Dynamic parents are resized to the renamed entries, paid by authority.
+    fn rename_entry<'a>(
+        program_id: &Pubkey,
+        authority: &AccountInfo<'a>,
+        parent: &AccountInfo<'a>,
+        parent_metadata_account: &AccountInfo<'a>,
+        system_program: Option<&AccountInfo<'a>>,
+        child: &Pubkey,
+        name: &str,
+    ) -> ProgramResult {
+        // Ensure parent is not the child itself
+        if parent.key == child {
+            return Err(DataStoreError::DuplicateAccount.into());
+        }
+
+        // Ensure parent and its metadata account are owned by the data program
+        if parent.owner != program_id || parent_metadata_account.owner != program_id {
+            return Err(DataStoreError::InvalidAccountOwner.into());
+        }
+
+        // Ensure parent and its metadata account are writable
+        if !parent.is_writable || !parent_metadata_account.is_writable {
+            return Err(DataStoreError::NotWriteable.into());
+        }
+
+        // Ensure length is not 0
+        if parent_metadata_account.data_is_empty() {
+            return Err(DataStoreError::NoAccountLength.into());
+        }
+
+        // Ensure the name is valid for an entry
+        if name.is_empty() {
+            return Err(DataStoreError::InvalidInstructionData.into());
+        }
+
+        let parent_metadata = Self::with_metadata(parent_metadata_account, |parent_metadata| {
+            // Ensure parent is initialized and not finalized
+            match *parent_metadata.data_status() {
+                SerializationStatusOption::Uninitialized => {
+                    return Err(DataStoreError::NotInitialized.into());
+                }
+                SerializationStatusOption::Finalized => {
+                    return Err(DataStoreError::AlreadyFinalized.into());
+                }
+                _ => (),
+            }
+
+            // Ensure parent is not frozen
+            if parent_metadata.is_frozen() {
+                return Err(DataStoreError::Frozen.into());
+            }
+
+            // Ensure parent is a directory of named entries
+            if *parent_metadata.data_type() != DataStoreTypeOption::Directory
+                || *parent_metadata.directory_format() != DirectoryFormat::NamedEntries
+            {
+                return Err(DataStoreError::InvalidDataType.into());
+            }
+
+            // Ensure parent is being written to by valid authority
+            if parent_metadata.authority() != authority.key {
+                return Err(DataStoreError::InvalidAuthority.into());
+            }
+
+            // Ensure the parent_metadata_account corresponds to the parent
+            let pda = parent_metadata.metadata_pda(program_id, parent.key)?;
+            if pda != *parent_metadata_account.key {
+                return Err(DataStoreError::InvalidPDA.into());
+            }
+
+            let old_len = parent_metadata.data_len();
+            let renamed = {
+                let data = parent.data.borrow();
+                let entries = parse_named_entries(&data[..old_len])
+                    .ok_or(DataStoreError::DataVerificationFailed)?;
+
+                // Ensure child is an entry of parent
+                if !entries.iter().any(|(_, entry)| entry == child) {
+                    return Err(DataStoreError::EntryNotFound.into());
+                }
+
+                // Ensure no sibling has the name
+                if entries
+                    .iter()
+                    .any(|(entry_name, entry)| entry != child && *entry_name == name.as_bytes())
+                {
+                    return Err(DataStoreError::NameConflict.into());
+                }
+
+                entries
+                    .iter()
+                    .flat_map(|(entry_name, entry)| {
+                        let entry_name = if entry == child {
+                            name.as_bytes()
+                        } else {
+                            entry_name
+                        };
+                        encode_named_entry(entry_name, entry)
+                    })
+                    .collect::<Vec<u8>>()
+            };
+
+            // Ensure the parent has room for the renamed entries, growing dynamic parents
+            let new_len = renamed.len();
+            if parent.data_len() < new_len {
+                if !parent_metadata.is_dynamic() {
+                    return Err(DataStoreError::InsufficientSpace.into());
+                }
+                if new_len > MAX_DATA_SPACE {
+                    return Err(DataStoreError::SpaceTooLarge.into());
+                }
+                let system_program = system_program.ok_or(ProgramError::NotEnoughAccountKeys)?;
+                Self::resize_data_account(authority, parent, system_program, new_len)?;
+            }
+
+            // Write the renamed entries, zeroing what a shorter name left behind
+            let mut data = parent.data.borrow_mut();
+            data[..new_len].copy_from_slice(&renamed);
+            if old_len > new_len {
+                data[new_len..old_len].fill(0);
+            }
+            drop(data);
+
+            parent_metadata.set_space(parent.data_len());
+            parent_metadata.set_data_len(new_len);
+            parent_metadata.update_checksums(parent.key, &parent.data.borrow());
+            parent_metadata
+                .increment_revision()
+                .ok_or(DataStoreError::Overflow)?;
+
+            Ok(())
+        })?;
+
+        DataStoreEvent::Updated {
+            data_account: *parent.key,
+            revision: parent_metadata.revision(),
+            data_len: parent_metadata.data_len() as u64,
+            data_hash: *parent_metadata.data_hash(),
+        }
+        .emit();
+
+        Ok(())
+    }
+
+    /// Takes the content PDA and then the parent directory with its metadata account from
+    /// accounts_iter, each only when recorded in account_metadata, as the accounts to
+    /// release before the contents of a data store are dropped
+    #[allow(clippy::type_complexity)]
+    fn next_recorded_accounts<'a, 'b>(
+        accounts_iter: &mut std::slice::Iter<'b, AccountInfo<'a>>,
+        account_metadata: &DataStoreAccountMetadata,
+    ) -> Result<
+        (
+            Option<&'b AccountInfo<'a>>,
+            Option<(&'b AccountInfo<'a>, &'b AccountInfo<'a>)>,
+        ),
+        ProgramError,
+    > {
+        let content_account = if account_metadata.has_flag(FLAG_CONTENT_REGISTERED) {
+            Some(next_account_info(accounts_iter).map_err(|_| DataStoreError::ContentRegistered)?)
+        } else {
+            None
+        };
+        let parent_accounts = match account_metadata.parent() {
+            Some(parent_key) => {
+                let parent =
+                    next_account_info(accounts_iter).map_err(|_| DataStoreError::ParentRequired)?;
+                let parent_metadata_account =
+                    next_account_info(accounts_iter).map_err(|_| DataStoreError::ParentRequired)?;
+
+                // Ensure the parent is the one recorded as listing the data store
+                if parent.key != parent_key {
+                    return Err(DataStoreError::ParentRequired.into());
+                }
+                Some((parent, parent_metadata_account))
+            }
+            None => None,
+        };
+        Ok((content_account, parent_accounts))
+    }
+
+    /// Removes the entry of child from the NamedEntries directory parent, after ensuring
+    /// child is listed in it. The parent keeps its space.
+    fn remove_entry(
+        program_id: &Pubkey,
+        authority: &AccountInfo,
+        parent: &AccountInfo,
+        parent_metadata_account: &AccountInfo,
+        child: &Pubkey,
+    ) -> ProgramResult {
+        // Ensure parent is not the child itself
+        if parent.key == child {
+            return Err(DataStoreError::DuplicateAccount.into());
+        }
+
+        // Ensure parent and its metadata account are owned by the data program
+        if parent.owner != program_id || parent_metadata_account.owner != program_id {
+            return Err(DataStoreError::InvalidAccountOwner.into());
+        }
+
+        // Ensure parent and its metadata account are writable
+        if !parent.is_writable || !parent_metadata_account.is_writable {
+            return Err(DataStoreError::NotWriteable.into());
+        }
+
+        // Ensure length is not 0
+        if parent_metadata_account.data_is_empty() {
+            return Err(DataStoreError::NoAccountLength.into());
+        }
+
+        let parent_metadata = Self::with_metadata(parent_metadata_account, |parent_metadata| {
+            // Ensure parent is initialized and not finalized
+            match *parent_metadata.data_status() {
+                SerializationStatusOption::Uninitialized => {
+                    return Err(DataStoreError::NotInitialized.into());
+                }
+                SerializationStatusOption::Finalized => {
+                    return Err(DataStoreError::AlreadyFinalized.into());
+                }
+                _ => (),
+            }
+
+            // Ensure parent is not frozen
+            if parent_metadata.is_frozen() {
+                return Err(DataStoreError::Frozen.into());
+            }
+
+            // Ensure parent is a directory of named entries
+            if *parent_metadata.data_type() != DataStoreTypeOption::Directory
+                || *parent_metadata.directory_format() != DirectoryFormat::NamedEntries
+            {
+                return Err(DataStoreError::InvalidDataType.into());
+            }
+
+            // Ensure parent is being written to by valid authority
+            if parent_metadata.authority() != authority.key {
+                return Err(DataStoreError::InvalidAuthority.into());
+            }
+
+            // Ensure the parent_metadata_account corresponds to the parent
+            let pda = parent_metadata.metadata_pda(program_id, parent.key)?;
+            if pda != *parent_metadata_account.key {
+                return Err(DataStoreError::InvalidPDA.into());
+            }
+
+            let old_len = parent_metadata.data_len();
+            let remaining = {
+                let data = parent.data.borrow();
+                let entries = parse_named_entries(&data[..old_len])
+                    .ok_or(DataStoreError::DataVerificationFailed)?;
+
+                // Ensure child is an entry of parent
+                if !entries.iter().any(|(_, entry)| entry == child) {
+                    return Err(DataStoreError::EntryNotFound.into());
+                }
+
+                entries
+                    .iter()
+                    .filter(|(_, entry)| entry != child)
+                    .flat_map(|(entry_name, entry)| encode_named_entry(entry_name, entry))
+                    .collect::<Vec<u8>>()
+            };
+
+            // Write the remaining entries, zeroing the bytes of the removed one
+            let new_len = remaining.len();
+            let mut data = parent.data.borrow_mut();
+            data[..new_len].copy_from_slice(&remaining);
+            data[new_len..old_len].fill(0);
+            drop(data);
+
+            parent_metadata.set_data_len(new_len);
+            parent_metadata.update_checksums(parent.key, &parent.data.borrow());
+            parent_metadata
+                .increment_revision()
+                .ok_or(DataStoreError::Overflow)?;
+
+            Ok(())
+        })?;
+
+        DataStoreEvent::Updated {
+            data_account: *parent.key,
+            revision: parent_metadata.revision(),
+            data_len: parent_metadata.data_len() as u64,
+            data_hash: *parent_metadata.data_hash(),
+        }
+        .emit();
+
+        Ok(())
+    }
+
+    /// Appends record to the audit_account of data_account, creating it on the first
+    /// record and growing it by one record afterwards, payer funding the rent
+    fn append_audit_record<'a>(
+        program_id: &Pubkey,
+        payer: &AccountInfo<'a>,
+        data_account: &AccountInfo<'a>,
+        audit_account: &AccountInfo<'a>,
+        system_program: &AccountInfo<'a>,
+        record: &AuditRecord,
+    ) -> ProgramResult {
+        // Ensure the audit_account is the audit PDA of data_account
+        let (pda, bump_seed) =
+            Pubkey::find_program_address(&[AUDIT_SEED, data_account.key.as_ref()], program_id);
+        if pda != *audit_account.key {
+            return Err(DataStoreError::InvalidPDA.into());
+        }
+
+        // Ensure audit_account is writable
+        if !audit_account.is_writable {
+            return Err(DataStoreError::NotWriteable.into());
+        }
+
+        let start = audit_account.data_len();
+        let end = start + AUDIT_RECORD_SIZE;
+        if start == 0 {
+            Self::create_program_account(
+                program_id,
+                payer,
+                audit_account,
+                system_program,
+                AUDIT_RECORD_SIZE,
+                &[&[AUDIT_SEED, data_account.key.as_ref(), &[bump_seed]]],
+            )?;
+        } else {
+            Self::resize_data_account(payer, audit_account, system_program, end)?;
+        }
+
+        record.serialize(&mut &mut audit_account.data.borrow_mut()[start..end])?;
+
+        Ok(())
+    }
+
+    fn raw_metadata_write(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        args: RawMetadataWriteArgs,
+    ) -> ProgramResult {
+        debug_msg!(args.debug, "RawMetadataWrite");
+
+        let accounts_iter = &mut accounts.iter();
+        let admin = next_account_info(accounts_iter)?;
+        let data_account = next_account_info(accounts_iter)?;
+        let metadata_account = next_account_info(accounts_iter)?;
+        let config_account = next_account_info(accounts_iter)?;
+        let program_data = next_account_info(accounts_iter)?;
+
+        // Ensure admin is signer
+        if !admin.is_signer {
+            return Err(DataStoreError::NotSigner.into());
+        }
+
+        // Ensure the program is not paused
+        Self::check_not_paused(program_id, config_account)?;
+
+        // Ensure the write is made by the upgrade authority of the program
+        Self::check_upgrade_authority(program_id, program_data, admin.key)?;
+
+        // Ensure metadata_account is a metadata PDA of the data program, so no other
+        // program-owned account, such as an audit log, can be rewritten
+        if metadata_account.owner != program_id {
+            return Err(DataStoreError::InvalidAccountOwner.into());
+        }
+        if !metadata_account.is_writable {
+            return Err(DataStoreError::NotWriteable.into());
+        }
+        let account_metadata =
+            DataStoreAccountMetadata::load(&metadata_account.try_borrow_data()?)?;
+        let pda = account_metadata.metadata_pda(program_id, data_account.key)?;
+        if pda != *metadata_account.key {
+            return Err(DataStoreError::InvalidPDA.into());
+        }
+
+        // Ensure the write is within the metadata_account
+        let start = usize::try_from(args.offset).map_err(|_| DataStoreError::Overflow)?;
+        let end = start
+            .checked_add(args.bytes.len())
+            .ok_or(DataStoreError::Overflow)?;
+        if end > metadata_account.data_len() {
+            return Err(DataStoreError::InvalidInstructionData.into());
+        }
+
+        // Ensure the write leaves the authority, data_status and bump_seed alone, so it
+        // can never take over or unfinalize a data store
+        if start < METADATA_DATA_HASH_OFFSET && end > METADATA_AUTHORITY_OFFSET {
+            return Err(DataStoreError::InvalidInstructionData.into());
+        }
+
+        debug_msg!(args.debug, "account checks passed");
+
+        metadata_account.data.borrow_mut()[start..end].copy_from_slice(&args.bytes);
+
+        // Ensure the written metadata still deserializes as that of the data_account
+        let mut account_metadata =
+            DataStoreAccountMetadata::deserialize(&mut &metadata_account.data.borrow()[..])
+                .map_err(|_| DataStoreError::CorruptMetadata)?;
+        let pda = account_metadata.metadata_pda(program_id, data_account.key)?;
+        if pda != *metadata_account.key {
+            return Err(DataStoreError::InvalidPDA.into());
+        }
+
+        // Reseal the metadata_crc over the written bytes
+        account_metadata.store(&mut metadata_account.data.borrow_mut())?;
+
+        debug_msg!(args.debug, "wrote metadata bytes {}..{}", start, end);
+
+        Ok(())
+    }
+
+    fn copy_in_data(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        args: CopyInDataArgs,
+    ) -> ProgramResult {
+        debug_msg!(args.debug, "CopyInData");
+
+        let accounts_iter = &mut accounts.iter();
+        let authority = next_account_info(accounts_iter)?;
+        let data_account = next_account_info(accounts_iter)?;
+        let metadata_account = next_account_info(accounts_iter)?;
+        let system_program = next_account_info(accounts_iter)?;
+        let source = next_account_info(accounts_iter)?;
+        let audit_account = next_account_info(accounts_iter).ok();
+
+        // Ensure length is not 0
+        if metadata_account.data_is_empty() {
+            return Err(DataStoreError::NoAccountLength.into());
+        }
+
+        // Ensure metadata_account is owned by the data program
+        if metadata_account.owner != program_id {
+            return Err(DataStoreError::InvalidAccountOwner.into());
+        }
+
+        // Ensure the copied range is within the source data
+        let start = usize::try_from(args.source_offset).map_err(|_| DataStoreError::Overflow)?;
+        let end = usize::try_from(args.len)
+            .ok()
+            .and_then(|len| start.checked_add(len))
+            .ok_or(DataStoreError::Overflow)?;
+        if end > source.data_len() {
+            return Err(DataStoreError::InvalidInstructionData.into());
+        }
+
+        // Write the copied bytes as a regular update
+        let update_args = UpdateDataStoreArgs {
+            debug: args.debug,
+            data_hash: [0; 32],
+            data: source.data.borrow()[start..end].to_vec(),
+            offset: args.offset,
+            realloc_down: args.realloc_down,
+            data_type: DataStoreAccountMetadata::load(&metadata_account.try_borrow_data()?)?
+                .data_type()
+                .clone(),
+            expected_revision: args.expected_revision,
+            allow_gaps: false,
+            expected_hash: None,
+        };
+        let plan = Self::validate_update(
+            program_id,
+            authority,
+            data_account,
+            metadata_account,
+            &ValidateArgs::from(&update_args),
+        )?;
+
+        debug_msg!(args.debug, "account checks passed");
+
+        Self::apply_update(
+            program_id,
+            authority,
+            data_account,
+            metadata_account,
+            system_program,
+            audit_account,
+            plan,
+            &update_args,
+        )?;
+
+        debug_msg!(
+            args.debug,
+            "copied {} bytes from {}",
+            end - start,
+            source.key
+        );
+
+        Ok(())
+    }
+
+    fn split_data(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        args: SplitDataArgs,
+    ) -> ProgramResult {
+        debug_msg!(args.debug, "SplitData");
+
+        let accounts_iter = &mut accounts.iter();
+        let authority = next_account_info(accounts_iter)?;
+        let data_account = next_account_info(accounts_iter)?;
+        let metadata_account = next_account_info(accounts_iter)?;
+        let destination_account = next_account_info(accounts_iter)?;
+        let destination_metadata_account = next_account_info(accounts_iter)?;
+        let system_program = next_account_info(accounts_iter)?;
+
+        // Ensure length is not 0
+        if metadata_account.data_is_empty() {
+            return Err(DataStoreError::NoAccountLength.into());
+        }
+
+        // Ensure metadata_account is owned by the data program
+        if metadata_account.owner != program_id {
+            return Err(DataStoreError::InvalidAccountOwner.into());
+        }
+
+        // Truncate the source as a regular update writing nothing at offset
+        let update_args = UpdateDataStoreArgs {
+            debug: args.debug,
+            data_hash: [0; 32],
+            data: vec![],
+            offset: args.offset,
+            realloc_down: true,
+            data_type: DataStoreAccountMetadata::load(&metadata_account.try_borrow_data()?)?
+                .data_type()
+                .clone(),
+            expected_revision: args.expected_revision,
+            allow_gaps: false,
+            expected_hash: None,
+        };
+        let mut plan = Self::validate_update(
+            program_id,
+            authority,
+            data_account,
+            metadata_account,
+            &ValidateArgs::from(&update_args),
+        )?;
+        let account_metadata = &plan.account_metadata;
+
+        // Ensure the source does not have its content registered, which only
+        // CloseDataStore releases
+        if account_metadata.has_flag(FLAG_CONTENT_REGISTERED) {
+            return Err(DataStoreError::ContentRegistered.into());
+        }
+
+        // Ensure offset leaves a non-empty tail to move
+        let offset = plan.offset;
+        let data_len = account_metadata.data_len();
+        if offset >= data_len {
+            return Err(DataStoreError::InvalidInstructionData.into());
+        }
+
+        // The audit_pda of an audited source precedes any accounts of the destination
+        let audit_account = if account_metadata.is_audited() {
+            Some(next_account_info(accounts_iter)?)
+        } else {
+            None
+        };
+
+        debug_msg!(args.debug, "account checks passed");
+
+        // Create the destination holding the bytes from offset on, of the same data type
+        // and dynamism as the source
+        let moved = data_account.data.borrow()[offset..data_len].to_vec();
+        let moved_len = moved.len();
+        let mut destination_accounts = vec![
+            authority.clone(),
+            destination_account.clone(),
+            destination_metadata_account.clone(),
+            system_program.clone(),
+        ];
+        destination_accounts.extend(accounts_iter.cloned());
+        Self::initialize_data_store(
+            program_id,
+            &destination_accounts,
+            InitializeDataStoreArgs {
+                debug: args.debug,
+                data_type: account_metadata.data_type().clone(),
+                bump_seed: args.bump_seed,
+                is_created: false,
+                space: moved_len as u64,
+                authority: *authority.key,
+                is_dynamic: account_metadata.is_dynamic(),
+                initial_data: moved,
+                append_only: false,
+                namespace: *account_metadata.namespace(),
+                idempotent: false,
+                private: false,
+                content_type: String::new(),
+                strict_content_type: false,
+                expected_total: 0,
+                min_update_interval_slots: 0,
+                expiry_slot: None,
+                max_revisions: None,
+                audit: false,
+                file_name: String::new(),
+                zero_on_shrink: true,
+                cache_ttl_seconds: 0,
+                indexable: true,
+                stream_hash: false,
+            },
+        )?;
+
+        debug_msg!(
+            args.debug,
+            "moved {} bytes to {}",
+            moved_len,
+            destination_account.key
+        );
+
+        // Truncate the source to the bytes before offset, which no longer count as written
+        data_account.data.borrow_mut()[offset..data_len].fill(0);
+        plan.account_metadata.set_data_len(offset);
+        Self::apply_update(
+            program_id,
+            authority,
+            data_account,
+            metadata_account,
+            system_program,
+            audit_account,
+            plan,
+            &update_args,
+        )
+    }
+
+    fn concat_data(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        args: ConcatDataArgs,
+    ) -> ProgramResult {
+        debug_msg!(args.debug, "ConcatData");
+
+        let accounts_iter = &mut accounts.iter();
+        let authority = next_account_info(accounts_iter)?;
+        let data_account = next_account_info(accounts_iter)?;
+        let metadata_account = next_account_info(accounts_iter)?;
+        let source_account = next_account_info(accounts_iter)?;
+        let source_metadata_account = next_account_info(accounts_iter)?;
+        let system_program = next_account_info(accounts_iter)?;
+
+        // Ensure the source and the destination are distinct data stores
+        if source_account.key == data_account.key {
+            return Err(DataStoreError::DuplicateAccount.into());
+        }
+
+        let source_metadata = Self::load_writable(
+            program_id,
+            authority,
+            source_account,
+            source_metadata_account,
+        )?;
+
+        // Ensure a source being closed may be, as by a forced CloseDataStore
+        if args.close_source {
+            Self::check_closable(
+                program_id,
+                authority,
+                authority,
+                source_account,
+                source_metadata_account,
+                true,
+            )?;
+        }
+
+        // Ensure length is not 0
+        if metadata_account.data_is_empty() {
+            return Err(DataStoreError::NoAccountLength.into());
+        }
+
+        // Ensure metadata_account is owned by the data program
+        if metadata_account.owner != program_id {
+            return Err(DataStoreError::InvalidAccountOwner.into());
+        }
+
+        // Append the source data as a regular update of the destination
+        let destination_metadata =
+            DataStoreAccountMetadata::load(&metadata_account.try_borrow_data()?)?;
+        let update_args = UpdateDataStoreArgs {
+            debug: args.debug,
+            data_hash: [0; 32],
+            data: source_account.data.borrow()[..source_metadata.data_len()].to_vec(),
+            offset: destination_metadata.data_len() as u64,
+            realloc_down: false,
+            data_type: destination_metadata.data_type().clone(),
+            expected_revision: None,
+            allow_gaps: false,
+            expected_hash: None,
+        };
+        let plan = Self::validate_update(
+            program_id,
+            authority,
+            data_account,
+            metadata_account,
+            &ValidateArgs::from(&update_args),
+        )?;
+
+        // The audit_pda of an audited destination, then the accounts recorded by a source
+        // being closed, follow
+        let audit_account = if plan.account_metadata.is_audited() {
+            Some(next_account_info(accounts_iter)?)
+        } else {
+            None
+        };
+        let (content_account, parent_accounts) = if args.close_source {
+            Self::next_recorded_accounts(accounts_iter, &source_metadata)?
+        } else {
+            (None, None)
+        };
+
+        debug_msg!(args.debug, "account checks passed");
+
+        Self::apply_update(
+            program_id,
+            authority,
+            data_account,
+            metadata_account,
+            system_program,
+            audit_account,
+            plan,
+            &update_args,
+        )?;
+
+        debug_msg!(args.debug, "appended {} bytes", update_args.data.len());
+
+        if args.close_source {
+            // Close the content PDA registering the source along with it
+            if let Some(content_account) = content_account {
+                Self::close_content_pda(
+                    program_id,
+                    authority,
+                    source_account,
+                    &update_args.data,
+                    content_account,
+                )?;
+
+                debug_msg!(args.debug, "content pda lamports transfered to authority");
+            }
+
+            // Remove the entry listing the source from its parent directory
+            if let Some((parent, parent_metadata_account)) = parent_accounts {
+                Self::remove_entry(
+                    program_id,
+                    authority,
+                    parent,
+                    parent_metadata_account,
+                    source_account.key,
+                )?;
+
+                debug_msg!(args.debug, "removed entry from {}", parent.key);
+            }
+
+            Self::drain_account(authority, source_metadata_account)?;
+            Self::drain_account(authority, source_account)?;
+
+            DataStoreEvent::Closed {
+                data_account: *source_account.key,
+                recipient: *authority.key,
+            }
+            .emit();
+
+            debug_msg!(args.debug, "source closed");
+        }
+
+        Ok(())
+    }
+
+    fn set_cache_ttl(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        args: SetCacheTtlArgs,
+    ) -> ProgramResult {
+        debug_msg!(args.debug, "SetCacheTtl");
+
+        let accounts_iter = &mut accounts.iter();
+        let authority = next_account_info(accounts_iter)?;
+        let data_account = next_account_info(accounts_iter)?;
+        let metadata_account = next_account_info(accounts_iter)?;
+
+        // Ensure data_account and metadata_account are owned by the data program
+        if data_account.owner != program_id || metadata_account.owner != program_id {
+            return Err(DataStoreError::InvalidAccountOwner.into());
+        }
+
+        // Ensure authority is signer
+        if !authority.is_signer {
+            return Err(DataStoreError::NotSigner.into());
+        }
+
+        // Ensure metadata_account is writable
+        if !metadata_account.is_writable {
+            return Err(DataStoreError::NotWriteable.into());
+        }
+
+        // Ensure length is not 0
+        if metadata_account.data_is_empty() {
+            return Err(DataStoreError::NoAccountLength.into());
+        }
+
+        Self::with_metadata(metadata_account, |account_metadata| {
+            // Ensure data_account is initialized and not finalized
+            match *account_metadata.data_status() {
+                SerializationStatusOption::Uninitialized => {
+                    return Err(DataStoreError::NotInitialized.into());
+                }
+                SerializationStatusOption::Finalized => {
+                    return Err(DataStoreError::AlreadyFinalized.into());
+                }
+                _ => (),
+            }
+
+            // Ensure metadata_account is being written to by valid authority
+            if account_metadata.authority() != authority.key {
+                return Err(DataStoreError::InvalidAuthority.into());
+            }
+
+            // Ensure the metadata_account corresponds to the data_account
+            let pda = account_metadata.metadata_pda(program_id, data_account.key)?;
+            if pda != *metadata_account.key {
+                return Err(DataStoreError::InvalidPDA.into());
+            }
+
+            debug_msg!(args.debug, "account checks passed");
+
+            account_metadata.set_cache_ttl_seconds(args.cache_ttl_seconds);
+
+            Ok(())
+        })?;
+
+        debug_msg!(args.debug, "cache ttl set to {}", args.cache_ttl_seconds);
+
+        Ok(())
+    }
+
+    fn get_or_create_directory(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        args: GetOrCreateDirectoryArgs,
+    ) -> ProgramResult {
+        debug_msg!(args.debug, "GetOrCreateDirectory");
+
+        let accounts_iter = &mut accounts.iter();
+        let _feepayer = next_account_info(accounts_iter)?;
+        let data_account = next_account_info(accounts_iter)?;
+        let metadata_account = next_account_info(accounts_iter)?;
+
+        // Ensure data_account is the file PDA of the authority and name
+        if args.name.is_empty() || args.name.len() > MAX_FILE_NAME_LEN {
+            return Err(DataStoreError::InvalidInstructionData.into());
+        }
+        let (pda, _) = derive_file_pda(program_id, &args.authority, &args.name);
+        if pda != *data_account.key {
+            return Err(DataStoreError::InvalidPDA.into());
+        }
+
+        // Create the directory unless its metadata already exists
+        if metadata_account.data_is_empty() {
+            Self::create_directory(
+                program_id,
+                accounts,
+                InitializeDirectoryArgs {
+                    debug: args.debug,
+                    bump_seed: args.bump_seed,
+                    is_created: false,
+                    space: args.space,
+                    authority: args.authority,
+                    namespace: args.namespace,
+                    max_entries: args.max_entries,
+                    directory_format: args.directory_format,
+                },
+                args.name,
+            )?;
+
+            debug_msg!(args.debug, "directory created");
+        } else {
+            // Ensure data_account and metadata_account are owned by the data program
+            if data_account.owner != program_id || metadata_account.owner != program_id {
+                return Err(DataStoreError::InvalidAccountOwner.into());
+            }
+
+            let account_metadata =
+                DataStoreAccountMetadata::load(&metadata_account.try_borrow_data()?)?;
+
+            // Ensure the existing data_account is an initialized directory
+            if *account_metadata.data_status() == SerializationStatusOption::Uninitialized {
+                return Err(DataStoreError::NotInitialized.into());
+            }
+            if *account_metadata.data_type() != DataStoreTypeOption::Directory {
+                return Err(DataStoreError::InvalidDataType.into());
+            }
+
+            // Ensure the metadata_account corresponds to the data_account
+            let pda = account_metadata.metadata_pda(program_id, data_account.key)?;
+            if pda != *metadata_account.key {
+                return Err(DataStoreError::InvalidPDA.into());
+            }
+
+            debug_msg!(args.debug, "directory already exists");
+        }
+
+        set_return_data(data_account.key.as_ref());
+
+        Ok(())
+    }
+
+    fn recompute_hash(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        args: RecomputeHashArgs,
+    ) -> ProgramResult {
+        debug_msg!(args.debug, "RecomputeHash");
+
+        let accounts_iter = &mut accounts.iter();
+        let authority = next_account_info(accounts_iter)?;
+        let data_account = next_account_info(accounts_iter)?;
+        let metadata_account = next_account_info(accounts_iter)?;
+
+        // Ensure data_account and metadata_account are owned by the data program
+        if data_account.owner != program_id || metadata_account.owner != program_id {
+            return Err(DataStoreError::InvalidAccountOwner.into());
+        }
+
+        // Ensure authority is signer
+        if !authority.is_signer {
+            return Err(DataStoreError::NotSigner.into());
+        }
+
+        // Ensure metadata_account is writable
+        if !metadata_account.is_writable {
             return Err(DataStoreError::NotWriteable.into());
         }
 
@@ -466,56 +4765,432 @@ impl Processor {
             return Err(DataStoreError::NoAccountLength.into());
         }
 
-        let account_metadata =
-            DataStoreAccountMetadata::try_from_slice(&metadata_account.try_borrow_data()?)?;
+        let mut account_metadata =
+            DataStoreAccountMetadata::load(&metadata_account.try_borrow_data()?)?;
 
-        // Ensure data_account is initialized
-        if *account_metadata.data_status() == SerializationStatusOption::Uninitialized {
-            return Err(DataStoreError::NotInitialized.into());
+        // Ensure data_account is initialized and not finalized
+        match *account_metadata.data_status() {
+            SerializationStatusOption::Uninitialized => {
+                return Err(DataStoreError::NotInitialized.into());
+            }
+            SerializationStatusOption::Finalized => {
+                return Err(DataStoreError::AlreadyFinalized.into());
+            }
+            _ => (),
+        }
+
+        // Ensure data_account is not frozen
+        if account_metadata.is_frozen() {
+            return Err(DataStoreError::Frozen.into());
         }
 
-        // Ensure data_account is being closed by valid authority
+        // Ensure metadata_account is being written to by valid authority
         if account_metadata.authority() != authority.key {
             return Err(DataStoreError::InvalidAuthority.into());
         }
 
         // Ensure the metadata_account corresponds to the data_account
-        let pda = Pubkey::create_program_address(
-            &[
-                PDA_SEED,
-                data_account.key.as_ref(),
-                &[account_metadata.bump_seed()],
-            ],
+        let pda = account_metadata.metadata_pda(program_id, data_account.key)?;
+        if pda != *metadata_account.key {
+            return Err(DataStoreError::InvalidPDA.into());
+        }
+
+        // Ensure the contents named by the content PDA of a registered data_account stay
+        // the ones stored, rehashed or shrunk
+        if account_metadata.has_flag(FLAG_CONTENT_REGISTERED) {
+            return Err(DataStoreError::ContentRegistered.into());
+        }
+
+        // Ensure a rate-limited data_account was not updated too recently
+        Self::check_update_interval(&account_metadata)?;
+
+        // Ensure the used data fits the data_account
+        let data_len = args.data_len.unwrap_or(account_metadata.data_len() as u64);
+        if data_len > data_account.data_len() as u64 {
+            return Err(DataStoreError::InsufficientSpace.into());
+        }
+
+        // Ensure an append-only data_account keeps every byte written so far
+        if account_metadata.is_append_only() && data_len < account_metadata.data_len() as u64 {
+            return Err(DataStoreError::AppendOnlyViolation.into());
+        }
+
+        // The system_program and audit_pda of an audited data_account follow
+        let audit_accounts = if account_metadata.is_audited() {
+            Some((
+                next_account_info(accounts_iter)?,
+                next_account_info(accounts_iter)?,
+            ))
+        } else {
+            None
+        };
+
+        debug_msg!(args.debug, "account checks passed");
+
+        account_metadata.set_space(data_account.data_len());
+        account_metadata.set_data_len(data_len as usize);
+        // Keep a resumable upload from pointing past the used data
+        if account_metadata.upload_cursor() > data_len {
+            account_metadata.set_upload_cursor(data_len);
+        }
+        account_metadata.update_checksums(data_account.key, &data_account.data.borrow());
+
+        debug_msg!(args.debug, "rehashed {} bytes", account_metadata.data_len());
+
+        Self::commit_update(
             program_id,
-        )?;
+            authority,
+            data_account,
+            metadata_account,
+            audit_accounts,
+            account_metadata,
+            args.debug,
+        )
+    }
+
+    fn set_header(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        args: SetHeaderArgs,
+    ) -> ProgramResult {
+        debug_msg!(args.debug, "SetHeader");
+
+        let accounts_iter = &mut accounts.iter();
+        let authority = next_account_info(accounts_iter)?;
+        let data_account = next_account_info(accounts_iter)?;
+        let metadata_account = next_account_info(accounts_iter)?;
+        let headers_account = next_account_info(accounts_iter)?;
+        let system_program = next_account_info(accounts_iter)?;
+
+        // Ensure data_account and metadata_account are owned by the data program
+        if data_account.owner != program_id || metadata_account.owner != program_id {
+            return Err(DataStoreError::InvalidAccountOwner.into());
+        }
+
+        // Ensure authority is signer
+        if !authority.is_signer {
+            return Err(DataStoreError::NotSigner.into());
+        }
+
+        // Ensure authority and headers_account are writable
+        if !authority.is_writable || !headers_account.is_writable {
+            return Err(DataStoreError::NotWriteable.into());
+        }
+
+        // Ensure length is not 0
+        if metadata_account.data_is_empty() {
+            return Err(DataStoreError::NoAccountLength.into());
+        }
+
+        let account_metadata =
+            DataStoreAccountMetadata::load(&metadata_account.try_borrow_data()?)?;
+
+        // Ensure data_account is initialized and not finalized
+        match *account_metadata.data_status() {
+            SerializationStatusOption::Uninitialized => {
+                return Err(DataStoreError::NotInitialized.into());
+            }
+            SerializationStatusOption::Finalized => {
+                return Err(DataStoreError::AlreadyFinalized.into());
+            }
+            _ => (),
+        }
+
+        // Ensure metadata_account is being written to by valid authority
+        if account_metadata.authority() != authority.key {
+            return Err(DataStoreError::InvalidAuthority.into());
+        }
+
+        // Ensure the metadata_account corresponds to the data_account
+        let pda = account_metadata.metadata_pda(program_id, data_account.key)?;
         if pda != *metadata_account.key {
             return Err(DataStoreError::InvalidPDA.into());
         }
 
-        // Transfer metadata_account lamports back to authority and reset metadata_account
-        let curr_lamports = authority.lamports();
-        **authority.lamports.borrow_mut() = curr_lamports
-            .checked_add(metadata_account.lamports())
-            .ok_or(DataStoreError::Overflow)?;
-        **metadata_account.lamports.borrow_mut() = 0;
-        metadata_account.data.borrow_mut().fill(0);
+        // Ensure the headers_account is the headers PDA of data_account
+        let (pda, bump_seed) = derive_headers_pda(program_id, data_account.key);
+        if pda != *headers_account.key {
+            return Err(DataStoreError::InvalidPDA.into());
+        }
 
-        if args.debug {
-            msg!("{} transfered to authority for metadata pda", curr_lamports);
+        // Ensure the header can be emitted verbatim by gateways
+        if !is_valid_header(&args.key, &args.value) {
+            return Err(DataStoreError::InvalidInstructionData.into());
         }
 
-        // Transfer data_account lamports back to authority and reset data_account
-        let curr_lamports = authority.lamports();
-        **authority.lamports.borrow_mut() = curr_lamports
-            .checked_add(data_account.lamports())
-            .ok_or(DataStoreError::Overflow)?;
-        **data_account.lamports.borrow_mut() = 0;
-        data_account.data.borrow_mut().fill(0);
+        debug_msg!(args.debug, "account checks passed");
+
+        // Create the headers PDA on first use
+        let mut headers = if headers_account.data_is_empty() {
+            Self::create_program_account(
+                program_id,
+                authority,
+                headers_account,
+                system_program,
+                HEADERS_SIZE,
+                &[&[HEADERS_SEED, data_account.key.as_ref(), &[bump_seed]]],
+            )?;
+
+            debug_msg!(args.debug, "headers pda created");
+
+            Vec::new()
+        } else {
+            Vec::<HttpHeader>::deserialize(&mut &headers_account.data.borrow()[..])?
+        };
+
+        // Replace or remove the header with the same key, appending new ones
+        let position = headers
+            .iter()
+            .position(|header| header.key.eq_ignore_ascii_case(&args.key));
+        match (position, args.value.is_empty()) {
+            (Some(index), true) => {
+                headers.remove(index);
+            }
+            (Some(index), false) => headers[index].value = args.value,
+            (None, true) => return Err(DataStoreError::EntryNotFound.into()),
+            (None, false) => {
+                if headers.len() >= MAX_HEADERS {
+                    return Err(DataStoreError::HeadersFull.into());
+                }
+                headers.push(HttpHeader {
+                    key: args.key,
+                    value: args.value,
+                });
+            }
+        }
+
+        {
+            let mut headers_data = headers_account.data.borrow_mut();
+            headers_data.fill(0);
+            headers.serialize(&mut &mut headers_data[..])?;
+        }
+
+        debug_msg!(args.debug, "{} headers set", headers.len());
+
+        Ok(())
+    }
+
+    fn get_headers(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        args: GetHeadersArgs,
+    ) -> ProgramResult {
+        debug_msg!(args.debug, "GetHeaders");
+
+        let accounts_iter = &mut accounts.iter();
+        let data_account = next_account_info(accounts_iter)?;
+        let metadata_account = next_account_info(accounts_iter)?;
+        let headers_account = next_account_info(accounts_iter)?;
+        let reader = next_account_info(accounts_iter).ok();
+
+        // Ensure length is not 0
+        if metadata_account.data_is_empty() {
+            return Err(DataStoreError::NoAccountLength.into());
+        }
+
+        // Ensure metadata_account is owned by the data program
+        if metadata_account.owner != program_id {
+            return Err(DataStoreError::InvalidAccountOwner.into());
+        }
+
+        let account_metadata =
+            DataStoreAccountMetadata::load(&metadata_account.try_borrow_data()?)?;
+
+        // Ensure data_account is initialized
+        if *account_metadata.data_status() == SerializationStatusOption::Uninitialized {
+            return Err(DataStoreError::NotInitialized.into());
+        }
+
+        // Ensure the metadata_account corresponds to the data_account
+        let pda = account_metadata.metadata_pda(program_id, data_account.key)?;
+        if pda != *metadata_account.key {
+            return Err(DataStoreError::InvalidPDA.into());
+        }
+
+        // Ensure the headers_account is the headers PDA of data_account
+        let (pda, _) = derive_headers_pda(program_id, data_account.key);
+        if pda != *headers_account.key {
+            return Err(DataStoreError::InvalidPDA.into());
+        }
+
+        Self::check_read_access(&account_metadata, reader)?;
+
+        let headers = if headers_account.data_is_empty() {
+            Vec::new()
+        } else {
+            Vec::<HttpHeader>::deserialize(&mut &headers_account.data.borrow()[..])?
+        };
+        set_return_data(&borsh::to_vec(&headers)?);
+
+        debug_msg!(args.debug, "returned {} headers", headers.len());
+
+        Ok(())
+    }
+
+    fn set_reader(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        args: SetReaderArgs,
+    ) -> ProgramResult {
+        debug_msg!(args.debug, "SetReader");
+
+        let accounts_iter = &mut accounts.iter();
+        let authority = next_account_info(accounts_iter)?;
+        let data_account = next_account_info(accounts_iter)?;
+        let metadata_account = next_account_info(accounts_iter)?;
+
+        // Ensure data_account and metadata_account are owned by the data program
+        if data_account.owner != program_id || metadata_account.owner != program_id {
+            return Err(DataStoreError::InvalidAccountOwner.into());
+        }
+
+        // Ensure authority is signer
+        if !authority.is_signer {
+            return Err(DataStoreError::NotSigner.into());
+        }
+
+        // Ensure metadata_account is writable
+        if !metadata_account.is_writable {
+            return Err(DataStoreError::NotWriteable.into());
+        }
+
+        // Ensure length is not 0
+        if metadata_account.data_is_empty() {
+            return Err(DataStoreError::NoAccountLength.into());
+        }
+
+        Self::with_metadata(metadata_account, |account_metadata| {
+            // Ensure data_account is initialized, finalized ones may still grant reads
+            if *account_metadata.data_status() == SerializationStatusOption::Uninitialized {
+                return Err(DataStoreError::NotInitialized.into());
+            }
+
+            // Ensure metadata_account is being written to by valid authority
+            if account_metadata.authority() != authority.key {
+                return Err(DataStoreError::InvalidAuthority.into());
+            }
+
+            // Ensure the metadata_account corresponds to the data_account
+            let pda = account_metadata.metadata_pda(program_id, data_account.key)?;
+            if pda != *metadata_account.key {
+                return Err(DataStoreError::InvalidPDA.into());
+            }
+
+            debug_msg!(args.debug, "account checks passed");
+
+            account_metadata.set_reader(args.reader);
+
+            Ok(())
+        })?;
+
+        debug_msg!(args.debug, "reader set to {:?}", args.reader);
+
+        Ok(())
+    }
+
+    /// Creates account with space, owned by the data program and paid for by payer.
+    /// Anyone may transfer lamports to an address before it is created, which makes
+    /// create_account fail, so a funded account is instead topped up to rent exemption,
+    /// then allocated and assigned.
+    fn create_program_account<'a>(
+        program_id: &Pubkey,
+        payer: &AccountInfo<'a>,
+        account: &AccountInfo<'a>,
+        system_program: &AccountInfo<'a>,
+        space: usize,
+        signer_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        let rent_exemption_amount = Rent::get()?.minimum_balance(space);
+
+        if account.lamports() == 0 {
+            let create_account_ix = system_instruction::create_account(
+                payer.key,
+                account.key,
+                rent_exemption_amount,
+                space as u64,
+                program_id,
+            );
+            return invoke_signed(
+                &create_account_ix,
+                &[payer.clone(), account.clone(), system_program.clone()],
+                signer_seeds,
+            );
+        }
+
+        let lamports_diff = rent_exemption_amount.saturating_sub(account.lamports());
+        if lamports_diff > 0 {
+            let transfer_ix = system_instruction::transfer(payer.key, account.key, lamports_diff);
+            invoke(
+                &transfer_ix,
+                &[payer.clone(), account.clone(), system_program.clone()],
+            )?;
+        }
+        let allocate_ix = system_instruction::allocate(account.key, space as u64);
+        invoke_signed(
+            &allocate_ix,
+            &[account.clone(), system_program.clone()],
+            signer_seeds,
+        )?;
+        let assign_ix = system_instruction::assign(account.key, program_id);
+        invoke_signed(
+            &assign_ix,
+            &[account.clone(), system_program.clone()],
+            signer_seeds,
+        )
+    }
+
+    /// Reallocs the data_account to new_space, settling the rent difference with the authority.
+    /// On shrink only the rent freed by the smaller size is refunded; lamports deposited
+    /// beyond rent exemption stay with the data_account, since the authority is not
+    /// necessarily who deposited them.
+    fn resize_data_account<'a>(
+        authority: &AccountInfo<'a>,
+        data_account: &AccountInfo<'a>,
+        system_program: &AccountInfo<'a>,
+        new_space: usize,
+    ) -> ProgramResult {
+        let rent = Rent::get()?;
+        let old_len = data_account.data_len();
+        let new_minimum_balance = rent.minimum_balance(new_space);
+
+        if old_len < new_space {
+            // A data_account already holding enough lamports, such as one funded beyond
+            // the rent-exempt minimum of its empty allocation, needs no top-up
+            let lamports_diff = new_minimum_balance.saturating_sub(data_account.lamports());
+            if lamports_diff > 0 {
+                let transfer_ix =
+                    system_instruction::transfer(authority.key, data_account.key, lamports_diff);
+                invoke(
+                    &transfer_ix,
+                    &[
+                        authority.clone(),
+                        data_account.clone(),
+                        system_program.clone(),
+                    ],
+                )?;
+            }
+        } else {
+            let freed_rent = rent
+                .minimum_balance(old_len)
+                .saturating_sub(new_minimum_balance);
+            let lamports_diff = data_account
+                .lamports()
+                .saturating_sub(new_minimum_balance)
+                .min(freed_rent);
+            let authority_lamports = authority.lamports();
+            **authority.lamports.borrow_mut() = authority_lamports
+                .checked_add(lamports_diff)
+                .ok_or(DataStoreError::Overflow)?;
+            **data_account.lamports.borrow_mut() -= lamports_diff;
+        }
+
+        data_account.realloc(new_space, false)?;
 
-        if args.debug {
-            msg!("{} transfered to authority for data account", curr_lamports);
+        // Ensure the resized data_account is still rent-exempt
+        if data_account.lamports() < new_minimum_balance {
+            return Err(DataStoreError::NotRentExempt.into());
         }
 
         Ok(())
     }
-}
\ No newline at end of file
+}
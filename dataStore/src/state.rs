@@ -1,15 +1,159 @@
 use borsh::{BorshDeserialize, BorshSerialize};
 use shank::ShankAccount;
-use solana_program::pubkey::Pubkey;
+use solana_program::{
+    program::MAX_RETURN_DATA,
+    program_error::ProgramError,
+    pubkey::{Pubkey, MAX_SEED_LEN},
+};
+
+use crate::{
+    error::DataStoreError,
+    hashing::{crc32, crc32_append, digestv, HashAlgorithm, Sha256Stream},
+};
 
 pub const METADATA_SIZE: usize = 1000;
+/// Largest data account the runtime allows (10 MiB)
+pub const MAX_DATA_SPACE: usize = 10 * 1024 * 1024;
 pub const PDA_SEED: &[u8] = b"data_store";
+pub const NAMESPACE_LEN: usize = 16;
+/// Layout version of DataStoreAccountMetadata, stamped into every metadata account.
+/// Bumped whenever accounts written by older versions must be read differently, and
+/// handled by MigrateMetadata. Fields appended at the end alone need no bump, as store
+/// zeroes the tail of the account and older accounts read them as their defaults.
+pub const DATA_STORE_VERSION: u8 = 3;
+/// First version sealing metadata with metadata_crc, verified on every load. Older
+/// accounts carry no crc, and are only read by MigrateMetadata until it reseals them.
+pub const METADATA_CRC_VERSION: u8 = 2;
+/// Domain byte of the data_hash preimage, see compute_data_hash. Kept apart from
+/// DATA_STORE_VERSION so that layout changes never change the hash of stored data.
+pub const DATA_HASH_VERSION: u8 = 1;
+/// First version storing the toggles of the metadata in flags. Older accounts get their
+/// flags from the toggle fields on load.
+pub const FLAGS_VERSION: u8 = 3;
+pub const SYMLINK_TARGET_LEN: usize = 32;
+pub const DIRECTORY_ENTRY_SIZE: usize = 32;
+/// Most directory entries that fit in a single return data buffer
+pub const MAX_DIRECTORY_ENTRIES_PER_READ: usize = MAX_RETURN_DATA / DIRECTORY_ENTRY_SIZE;
+/// Longest name of an entry of a NamedEntries directory
+pub const MAX_ENTRY_NAME_LEN: usize = 64;
+/// Entry cap of directories initialized without an explicit max_entries
+pub const DEFAULT_MAX_DIRECTORY_ENTRIES: u32 = 1024;
+/// Most data store accounts closed by a single CloseDataStoreBatch, bounding its compute
+pub const MAX_CLOSE_BATCH_SIZE: usize = 8;
+/// Most data store accounts created by a single InitializeDataStoreBatch, keeping the
+/// transaction within its size and compute limits
+pub const MAX_INIT_BATCH_SIZE: usize = 8;
+/// Heuristic compute cost of an update, see estimate_update_compute_units. The per-byte
+/// costs are lower bounds, CRC-32 alone taking several units per byte, so an update
+/// estimated above UPDATE_COMPUTE_BUDGET plainly cannot complete.
+pub const UPDATE_BASE_COMPUTE_UNITS: u64 = 10_000;
+pub const UPDATE_REALLOC_COMPUTE_UNITS: u64 = 5_000;
+pub const UPDATE_COMPUTE_UNITS_PER_WRITTEN_BYTE: u64 = 1;
+pub const UPDATE_COMPUTE_UNITS_PER_HASHED_BYTE: u64 = 4;
+/// Cost of hashing in software the bytes appended to a hash stream, see Sha256Stream
+pub const UPDATE_COMPUTE_UNITS_PER_STREAMED_BYTE: u64 = 20;
+/// Default compute budget of an instruction
+pub const UPDATE_COMPUTE_BUDGET: u64 = 200_000;
+/// Domain separator leading every message signed for a RelayedUpdate
+pub const RELAYED_UPDATE_DOMAIN: &[u8] = b"dataaccount relayed update";
+/// Authority of data stores whose authority was renounced, alongside
+/// FLAG_AUTHORITY_RENOUNCED. It lies off the ed25519 curve so no one can sign for it, and
+/// unlike the all-zero key it is neither Pubkey::default() nor the System Program.
+pub const RENOUNCED_AUTHORITY: Pubkey =
+    Pubkey::new_from_array(*b"dataaccount renounced authority2");
+pub const MAX_CONTENT_TYPE_LEN: usize = 64;
+/// Length of the little-endian u32 byte count preceding the bytes returned by ReadData
+pub const READ_DATA_HEADER_LEN: usize = 4;
+pub const ALLOWLIST_SEED: &[u8] = b"allowlist";
+pub const CONFIG_SEED: &[u8] = b"config";
+/// Seed of the content PDA of `[CONTENT_SEED, content_hash]`, holding the pubkey of the
+/// first data account finalized with that content
+pub const CONTENT_SEED: &[u8] = b"content";
+pub const CONTENT_INDEX_SIZE: usize = 32;
+pub const AUDIT_SEED: &[u8] = b"audit";
+/// Seed of the file PDA of `[FILE_SEED, authority, file_name]`, a data account whose
+/// address follows from its authority and name
+pub const FILE_SEED: &[u8] = b"file";
+/// Longest file_name of a file PDA, the maximum length of a seed
+pub const MAX_FILE_NAME_LEN: usize = MAX_SEED_LEN;
+/// Seed of the headers PDA of `[HEADERS_SEED, data_account]`, holding its HttpHeaders
+pub const HEADERS_SEED: &[u8] = b"headers";
+pub const MAX_HEADERS: usize = 8;
+pub const MAX_HEADER_KEY_LEN: usize = 32;
+pub const MAX_HEADER_VALUE_LEN: usize = 64;
+/// Size of the headers PDA, holding up to MAX_HEADERS borsh-encoded HttpHeaders.
+/// Small enough for GetHeaders to return all of them at once.
+pub const HEADERS_SIZE: usize =
+    4 + MAX_HEADERS * (4 + MAX_HEADER_KEY_LEN + 4 + MAX_HEADER_VALUE_LEN);
+/// Size of a borsh-encoded AuditRecord
+pub const AUDIT_RECORD_SIZE: usize = 8 + 32 + 8 + 32;
+/// Size of the config PDA, holding a ProgramConfig
+pub const CONFIG_SIZE: usize = 32 + 1 + 1;
+pub const MAX_ALLOWLIST_MEMBERS: usize = 64;
+/// Size of the allowlist PDA, holding an Allowlist of up to MAX_ALLOWLIST_MEMBERS members
+pub const ALLOWLIST_SIZE: usize = 32 + 4 + 32 * MAX_ALLOWLIST_MEMBERS;
+/// Bits of the metadata flags, see DataStoreAccountMetadata::flags
+pub const FLAG_DYNAMIC: u32 = 1 << 0;
+pub const FLAG_APPEND_ONLY: u32 = 1 << 1;
+pub const FLAG_PRIVATE: u32 = 1 << 2;
+pub const FLAG_STRICT_CONTENT_TYPE: u32 = 1 << 3;
+pub const FLAG_AUDIT: u32 = 1 << 4;
+pub const FLAG_INDEXABLE: u32 = 1 << 5;
+/// Blocks every write to the data store until cleared, unlike finalization which is final
+pub const FLAG_FROZEN: u32 = 1 << 6;
+/// Set when finalizing with lock_authority, the data store can then never be changed,
+/// transferred or closed
+pub const FLAG_AUTHORITY_RENOUNCED: u32 = 1 << 7;
+/// Set while the content PDA of the data store registers it, which must then be closed
+/// along with the data store or its contents
+pub const FLAG_CONTENT_REGISTERED: u32 = 1 << 8;
+/// Every FLAG_* bit known to this version of the program
+pub const KNOWN_FLAGS: u32 = FLAG_DYNAMIC
+    | FLAG_APPEND_ONLY
+    | FLAG_PRIVATE
+    | FLAG_STRICT_CONTENT_TYPE
+    | FLAG_AUDIT
+    | FLAG_INDEXABLE
+    | FLAG_FROZEN
+    | FLAG_AUTHORITY_RENOUNCED
+    | FLAG_CONTENT_REGISTERED;
+/// Flags that may be changed with SetMetadataFlags after initialization
+pub const MUTABLE_FLAGS: u32 =
+    FLAG_APPEND_ONLY | FLAG_PRIVATE | FLAG_STRICT_CONTENT_TYPE | FLAG_INDEXABLE | FLAG_FROZEN;
+/// Bits of the byte returned by GetAccountState, see DataStoreAccountMetadata::account_state.
+/// The low two bits hold the SerializationStatusOption.
+pub const ACCOUNT_STATE_STATUS_MASK: u8 = 0b11;
+pub const ACCOUNT_STATE_DYNAMIC: u8 = 1 << 2;
+pub const ACCOUNT_STATE_APPEND_ONLY: u8 = 1 << 3;
+pub const ACCOUNT_STATE_PRIVATE: u8 = 1 << 4;
+pub const ACCOUNT_STATE_FROZEN: u8 = 1 << 5;
+/// Magic numbers of the content types checked by strict data stores
+pub const CONTENT_TYPE_MAGICS: [(&str, &[u8]); 4] = [
+    ("image/png", b"\x89PNG\r\n\x1a\n"),
+    ("image/jpeg", b"\xff\xd8\xff"),
+    ("application/pdf", b"%PDF-"),
+    ("application/gzip", b"\x1f\x8b"),
+];
 
 #[derive(Clone, Debug, BorshSerialize, BorshDeserialize, PartialEq)]
 #[borsh(use_discriminant = true)]
 pub enum DataStoreTypeOption {
     File = 0,
     Directory = 1,
+    Symlink = 2,
+}
+
+/// Representation of the entries of a directory data store
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize, PartialEq)]
+#[borsh(use_discriminant = true)]
+pub enum DirectoryFormat {
+    /// Child pubkeys packed back to back, managed with AddDirectoryEntry
+    PackedPubkeys = 0,
+    /// A UTF-8 JSON object written with UpdateDataStore
+    JsonManifest = 1,
+    /// Length-prefixed `name_len: u8 | name | child` records managed with
+    /// AddDirectoryEntry, names being unique and at most MAX_ENTRY_NAME_LEN bytes
+    NamedEntries = 2,
 }
 
 #[derive(Clone, Debug, BorshSerialize, BorshDeserialize, PartialEq)]
@@ -20,6 +164,65 @@ pub enum SerializationStatusOption {
     Finalized = 2,
 }
 
+/// Entry of the audit log of a data store, appended to its audit PDA of
+/// `[AUDIT_SEED, data_account]` by every update
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize, PartialEq)]
+pub struct AuditRecord {
+    pub slot: u64,
+    pub authority: Pubkey,
+    pub revision: u64,
+    pub data_hash: [u8; 32],
+}
+
+/// Response header gateways should emit when serving a data store, such as
+/// Content-Disposition. Stored verbatim in its headers PDA, see HEADERS_SEED.
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize, PartialEq)]
+pub struct HttpHeader {
+    pub key: String,
+    pub value: String,
+}
+
+/// Allocation details of a data store, returned borsh-encoded by GetSpaceInfo
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize, PartialEq)]
+pub struct SpaceInfo {
+    /// Bytes allocated to the data account
+    pub allocated: u64,
+    /// Bytes written, data_len
+    pub used: u64,
+    pub is_dynamic: bool,
+    /// Lamports held by the data account and its metadata PDA
+    pub rent_lamports: u64,
+}
+
+/// Program-wide settings, stored borsh-encoded in the PDA of `[CONFIG_SEED]`:
+/// `admin (32 bytes) || paused (1 byte) || bump_seed (1 byte)`.
+/// The canonical bump_seed of the PDA is kept so every write can check the config
+/// with create_program_address rather than searching for it.
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize)]
+pub struct ProgramConfig {
+    pub admin: Pubkey,
+    pub paused: bool,
+    pub bump_seed: u8,
+}
+
+/// Feepayers allowed to initialize data stores when the program is built with the
+/// `permissioned-init` feature, stored borsh-encoded in the PDA of `[ALLOWLIST_SEED]`:
+/// `authority (32 bytes) || member count (u32) || members (32 bytes each)`.
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize)]
+pub struct Allowlist {
+    pub authority: Pubkey,
+    pub members: Vec<Pubkey>,
+}
+
+impl Allowlist {
+    pub fn contains(&self, feepayer: &Pubkey) -> bool {
+        self.members.contains(feepayer)
+    }
+}
+
+/// Metadata stored in the data store PDA.
+/// New fields are appended so that accounts written by older versions, whose
+/// trailing bytes are zeroed, still deserialize with default values.
 #[derive(Clone, Debug, BorshSerialize, BorshDeserialize, ShankAccount)]
 pub struct DataStoreAccountMetadata {
     pub data_type: DataStoreTypeOption,
@@ -29,17 +232,141 @@ pub struct DataStoreAccountMetadata {
     pub data_hash: [u8; 32],
     pub is_dynamic: bool,
     pub space: usize,
+    pub revision: u64,
+    pub data_len: u64,
+    pub version: u8,
+    pub append_only: bool,
+    pub namespace: [u8; 16],
+    pub private: bool,
+    pub content_type: String,
+    pub strict_content_type: bool,
+    pub previous_version: Option<Pubkey>,
+    pub max_entries: u32,
+    pub upload_cursor: u64,
+    pub expected_total: u64,
+    pub crc32: u32,
+    pub creator: Pubkey,
+    pub updated_slot: u64,
+    pub min_update_interval_slots: u64,
+    pub directory_format: DirectoryFormat,
+    pub expiry_slot: Option<u64>,
+    pub max_revisions: Option<u64>,
+    pub name: String,
+    pub audit: bool,
+    pub keep_tail_on_shrink: bool,
+    pub metadata_crc: u32,
+    pub cache_ttl_seconds: u32,
+    pub unlisted: bool,
+    pub hash_stream: Option<Sha256Stream>,
+    pub reader: Option<Pubkey>,
+    /// FLAG_* toggles of the data store. is_dynamic, append_only, private,
+    /// strict_content_type, audit and unlisted mirror them for readers of older layouts.
+    pub flags: u32,
+    pub parent: Option<Pubkey>,
+}
+
+/// Layout of metadata written before DATA_STORE_VERSION was stamped into accounts
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize)]
+pub struct DataStoreAccountMetadataV0 {
+    pub data_type: DataStoreTypeOption,
+    pub authority: Pubkey,
+    pub data_status: SerializationStatusOption,
+    pub bump_seed: u8,
+    pub data_hash: [u8; 32],
+    pub is_dynamic: bool,
+    pub space: usize,
 }
 
 #[derive(Clone, Debug, BorshSerialize, BorshDeserialize)]
 pub struct InitializeDataStoreArgs {
     pub debug: bool,
     pub data_type: DataStoreTypeOption,
+    /// Canonical bump of the metadata PDA, as returned by find_program_address
     pub bump_seed: u8,
     pub is_created: bool,
     pub space: u64,
     pub authority: Pubkey,
     pub is_dynamic: bool,
+    pub initial_data: Vec<u8>,
+    pub append_only: bool,
+    /// Extra metadata PDA seed isolating tenants, all zeroes for the default namespace
+    pub namespace: [u8; NAMESPACE_LEN],
+    /// Succeed without changes if an identical data store is already initialized
+    pub idempotent: bool,
+    /// Restrict ReadData and GetMetadata to the authority
+    pub private: bool,
+    /// MIME type of the data, at most MAX_CONTENT_TYPE_LEN bytes
+    pub content_type: String,
+    /// Reject a content_type that does not match the magic number of the data
+    pub strict_content_type: bool,
+    /// Total length of a multi-transaction upload, finalizing before reaching it fails. 0 for none
+    pub expected_total: u64,
+    /// Slots that must pass between two updates, 0 for no limit
+    pub min_update_interval_slots: u64,
+    /// Slot after which anyone may close the data store, refunding the creator
+    pub expiry_slot: Option<u64>,
+    /// Number of updates after which the data store is finalized automatically, at least 1
+    pub max_revisions: Option<u64>,
+    /// Append an AuditRecord to the audit PDA on each update
+    pub audit: bool,
+    /// Create the data account as the file PDA of the authority and this name, empty for a keypair account
+    pub file_name: String,
+    /// Zero the freed tail of the data account when it shrinks, true by default
+    pub zero_on_shrink: bool,
+    /// Cache lifetime suggested to gateways, 0 for no caching
+    pub cache_ttl_seconds: u32,
+    /// Show the data store in public listings, true by default
+    pub indexable: bool,
+    /// Hash appends incrementally, see DataStoreAccountMetadata::start_hash_stream
+    pub stream_hash: bool,
+}
+
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize)]
+pub struct InitializeDirectoryArgs {
+    pub debug: bool,
+    pub bump_seed: u8,
+    pub is_created: bool,
+    pub space: u64,
+    pub authority: Pubkey,
+    pub namespace: [u8; NAMESPACE_LEN],
+    /// Most entries the directory may hold, 0 for DEFAULT_MAX_DIRECTORY_ENTRIES
+    pub max_entries: u32,
+    pub directory_format: DirectoryFormat,
+}
+
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize)]
+pub struct GetOrCreateDirectoryArgs {
+    pub debug: bool,
+    /// Name of the directory, its data account is the file PDA of authority and name
+    pub name: String,
+    pub bump_seed: u8,
+    pub space: u64,
+    pub authority: Pubkey,
+    pub namespace: [u8; NAMESPACE_LEN],
+    /// Most entries the directory may hold, 0 for DEFAULT_MAX_DIRECTORY_ENTRIES
+    pub max_entries: u32,
+    pub directory_format: DirectoryFormat,
+}
+
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize)]
+pub struct InitializeDataStoreBatchArgs {
+    pub debug: bool,
+    pub data_type: DataStoreTypeOption,
+    /// Canonical bump of each metadata PDA, in the order of the account pairs
+    pub bump_seeds: Vec<u8>,
+    pub is_created: bool,
+    pub space: u64,
+    pub authority: Pubkey,
+    pub is_dynamic: bool,
+    pub namespace: [u8; NAMESPACE_LEN],
+}
+
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize)]
+pub struct ReinitializeArgs {
+    pub debug: bool,
+    pub data_type: DataStoreTypeOption,
+    pub authority: Pubkey,
+    pub is_dynamic: bool,
 }
 
 #[derive(Clone, Debug, BorshSerialize, BorshDeserialize)]
@@ -50,21 +377,285 @@ pub struct UpdateDataStoreArgs {
     pub offset: u64,
     pub realloc_down: bool,
     pub data_type: DataStoreTypeOption,
+    pub expected_revision: Option<u64>,
+    /// Allow writing past data_len, zero-filling the gap, instead of failing
+    pub allow_gaps: bool,
+    /// Only apply the write if the stored data_hash still matches
+    pub expected_hash: Option<[u8; 32]>,
+}
+
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize)]
+pub struct CopyInDataArgs {
+    pub debug: bool,
+    /// Range of the source account data to copy
+    pub source_offset: u64,
+    pub len: u64,
+    /// Offset in the data store account to write the copied bytes at
+    pub offset: u64,
+    pub realloc_down: bool,
+    pub expected_revision: Option<u64>,
+}
+
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize)]
+pub struct SplitDataArgs {
+    pub debug: bool,
+    /// Offset of the first byte moved to the destination
+    pub offset: u64,
+    /// Canonical bump of the destination metadata PDA
+    pub bump_seed: u8,
+    pub expected_revision: Option<u64>,
+}
+
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize)]
+pub struct ConcatDataArgs {
+    pub debug: bool,
+    /// Close the source once appended, refunding its lamports to the authority
+    pub close_source: bool,
+}
+
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize)]
+pub struct GetMetadataArgs {
+    pub debug: bool,
+}
+
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize)]
+pub struct SetMetadataFlagsArgs {
+    pub debug: bool,
+    /// Flags to change, only MUTABLE_FLAGS may be set
+    pub mask: u32,
+    /// New values of the flags in mask
+    pub values: u32,
+}
+
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize)]
+pub struct SetPauseArgs {
+    pub debug: bool,
+    pub paused: bool,
+}
+
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize)]
+pub struct RawMetadataWriteArgs {
+    pub debug: bool,
+    pub offset: u64,
+    pub bytes: Vec<u8>,
+}
+
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize)]
+pub struct SetAllowlistArgs {
+    pub debug: bool,
+    pub members: Vec<Pubkey>,
+}
+
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize)]
+pub struct ReclaimRentArgs {
+    pub debug: bool,
+}
+
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize)]
+pub struct SwapDataArgs {
+    pub debug: bool,
+}
+
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize)]
+pub struct SupersedeArgs {
+    pub debug: bool,
+}
+
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize)]
+pub struct ConvertDataTypeArgs {
+    pub debug: bool,
+    pub data_type: DataStoreTypeOption,
+}
+
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize)]
+pub struct SetNameArgs {
+    pub debug: bool,
+    pub name: String,
+}
+
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize)]
+pub struct RecomputeHashArgs {
+    pub debug: bool,
+    /// New length of the used data, None to keep the current data_len
+    pub data_len: Option<u64>,
+}
+
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize)]
+pub struct SetHeaderArgs {
+    pub debug: bool,
+    pub key: String,
+    /// Value of the header, empty to remove it
+    pub value: String,
+}
+
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize)]
+pub struct GetHeadersArgs {
+    pub debug: bool,
+}
+
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize)]
+pub struct SetReaderArgs {
+    pub debug: bool,
+    /// Delegate allowed to read the private data store, None to revoke it
+    pub reader: Option<Pubkey>,
+}
+
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize)]
+pub struct SetCacheTtlArgs {
+    pub debug: bool,
+    pub cache_ttl_seconds: u32,
+}
+
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize)]
+pub struct SetContentTypeArgs {
+    pub debug: bool,
+    pub content_type: String,
+}
+
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize)]
+pub struct ReadDataArgs {
+    pub debug: bool,
+    pub offset: u64,
+    pub len: u64,
+    /// Return the bytes available up to data_len instead of failing on a range past it
+    pub clamp: bool,
+    /// Check the used bytes against data_hash before returning, at extra compute cost
+    pub verify: bool,
+}
+
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize)]
+pub struct GetDataHashArgs {
+    pub debug: bool,
+}
+
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize)]
+pub struct GetSpaceInfoArgs {
+    pub debug: bool,
+}
+
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize)]
+pub struct GetUploadProgressArgs {
+    pub debug: bool,
+}
+
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize)]
+pub struct GetAccountStateArgs {
+    pub debug: bool,
+}
+
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize)]
+pub struct GetETagArgs {
+    pub debug: bool,
+}
+
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize)]
+pub struct AddDirectoryEntryArgs {
+    pub debug: bool,
+    pub child: Pubkey,
+    /// Name of the entry in NamedEntries directories, empty for other formats
+    pub name: String,
+}
+
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize)]
+pub struct MoveEntryArgs {
+    pub debug: bool,
+    pub child: Pubkey,
+}
+
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize)]
+pub struct GetDirectoryEntriesArgs {
+    pub debug: bool,
+    pub offset: u32,
+    pub count: u32,
+}
+
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize)]
+pub struct RegionHashArgs {
+    pub debug: bool,
+    pub offset: u64,
+    pub len: u64,
+}
+
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize)]
+pub struct MigrateMetadataArgs {
+    pub debug: bool,
+}
+
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize)]
+pub struct CreateSymlinkArgs {
+    pub debug: bool,
+    pub bump_seed: u8,
+    pub authority: Pubkey,
+    pub target: Pubkey,
+    pub namespace: [u8; NAMESPACE_LEN],
+}
+
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize)]
+pub struct UpdateSymlinkArgs {
+    pub debug: bool,
+    pub target: Pubkey,
+}
+
+/// Seeds proving that a new authority is a PDA of the given program
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize)]
+pub struct NewAuthorityPda {
+    pub seeds: Vec<Vec<u8>>,
+    pub program: Pubkey,
+}
+
+/// Arguments of an update to check without applying it, data is replaced by its length
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize)]
+pub struct ValidateArgs {
+    pub debug: bool,
+    pub data_len: u64,
+    pub offset: u64,
+    pub realloc_down: bool,
+    pub data_type: DataStoreTypeOption,
+    pub expected_revision: Option<u64>,
+    /// Allow writing past data_len, zero-filling the gap, instead of failing
+    pub allow_gaps: bool,
+    /// Only apply the write if the stored data_hash still matches
+    pub expected_hash: Option<[u8; 32]>,
+}
+
+impl From<&UpdateDataStoreArgs> for ValidateArgs {
+    fn from(args: &UpdateDataStoreArgs) -> Self {
+        Self {
+            debug: args.debug,
+            data_len: args.data.len() as u64,
+            offset: args.offset,
+            realloc_down: args.realloc_down,
+            data_type: args.data_type.clone(),
+            expected_revision: args.expected_revision,
+            allow_gaps: args.allow_gaps,
+            expected_hash: args.expected_hash,
+        }
+    }
 }
 
 #[derive(Clone, Debug, BorshSerialize, BorshDeserialize)]
 pub struct UpdateDataStoreAuthorityArgs {
     pub debug: bool,
+    pub new_authority_pda: Option<NewAuthorityPda>,
 }
 
 #[derive(Clone, Debug, BorshSerialize, BorshDeserialize)]
 pub struct FinalizeDataStoreArgs {
     pub debug: bool,
+    /// Also renounce the authority, so the data store can never be changed or closed
+    pub lock_authority: bool,
 }
 
 #[derive(Clone, Debug, BorshSerialize, BorshDeserialize)]
 pub struct CloseDataStoreArgs {
     pub debug: bool,
+    pub force: bool,
+}
+
+#[derive(Clone, Debug, BorshSerialize, BorshDeserialize)]
+pub struct CloseDataStoreBatchArgs {
+    pub debug: bool,
+    pub force: bool,
 }
 
 impl DataStoreAccountMetadata {
@@ -72,6 +663,7 @@ impl DataStoreAccountMetadata {
         authority: Pubkey,
         data_type: DataStoreTypeOption,
         bump_seed: u8,
+        is_dynamic: bool,
     ) -> Self {
         Self {
             data_type,
@@ -79,9 +671,104 @@ impl DataStoreAccountMetadata {
             data_status: SerializationStatusOption::Initialized,
             bump_seed,
             data_hash: [0; 32],
-            is_dynamic: false,
+            is_dynamic,
             space: 0,
+            revision: 0,
+            data_len: 0,
+            version: DATA_STORE_VERSION,
+            append_only: false,
+            namespace: [0; NAMESPACE_LEN],
+            private: false,
+            content_type: String::new(),
+            strict_content_type: false,
+            previous_version: None,
+            max_entries: 0,
+            upload_cursor: 0,
+            expected_total: 0,
+            crc32: 0,
+            creator: Pubkey::default(),
+            updated_slot: 0,
+            min_update_interval_slots: 0,
+            directory_format: DirectoryFormat::PackedPubkeys,
+            expiry_slot: None,
+            max_revisions: None,
+            name: String::new(),
+            audit: false,
+            keep_tail_on_shrink: false,
+            metadata_crc: 0,
+            cache_ttl_seconds: 0,
+            unlisted: false,
+            hash_stream: None,
+            reader: None,
+            flags: if is_dynamic {
+                FLAG_DYNAMIC | FLAG_INDEXABLE
+            } else {
+                FLAG_INDEXABLE
+            },
+            parent: None,
+        }
+    }
+
+    /// Deserializes metadata from the start of the metadata account's data,
+    /// rejecting damaged accounts and accounts written by a newer version of the program.
+    /// Accounts older than METADATA_CRC_VERSION carry no metadata_crc to verify, so they
+    /// are rejected until MigrateMetadata rewrites them.
+    pub fn load(data: &[u8]) -> Result<Self, ProgramError> {
+        let mut metadata = Self::deserialize_checked(data)?;
+        if metadata.version < METADATA_CRC_VERSION {
+            return Err(DataStoreError::MigrationRequired.into());
+        }
+        if metadata.metadata_crc != metadata.compute_metadata_crc()? {
+            return Err(DataStoreError::CorruptMetadata.into());
+        }
+        if metadata.version < FLAGS_VERSION {
+            metadata.flags = metadata.legacy_flags();
         }
+        Ok(metadata)
+    }
+
+    /// Like load, but accepting accounts older than METADATA_CRC_VERSION without any
+    /// metadata_crc check. Only for MigrateMetadata, which reseals what it reads.
+    pub fn load_unsealed(data: &[u8]) -> Result<Self, ProgramError> {
+        let mut metadata = Self::deserialize_checked(data)?;
+        if metadata.version >= METADATA_CRC_VERSION {
+            return Self::load(data);
+        }
+        metadata.flags = metadata.legacy_flags();
+        Ok(metadata)
+    }
+
+    /// Deserializes metadata, rejecting layouts newer than DATA_STORE_VERSION and hash
+    /// streams no update could have left behind
+    fn deserialize_checked(data: &[u8]) -> Result<Self, ProgramError> {
+        let metadata =
+            Self::deserialize(&mut &data[..]).map_err(|_| DataStoreError::CorruptMetadata)?;
+        if metadata.version > DATA_STORE_VERSION {
+            return Err(DataStoreError::UnsupportedVersion.into());
+        }
+        if metadata
+            .hash_stream
+            .as_ref()
+            .is_some_and(|stream| !stream.is_valid())
+        {
+            return Err(DataStoreError::CorruptMetadata.into());
+        }
+        Ok(metadata)
+    }
+
+    /// Seals the metadata with its metadata_crc and serializes it at the start of the
+    /// metadata account's data. Every write of metadata goes through it, stamping
+    /// DATA_STORE_VERSION as it always writes the current layout.
+    pub fn store(&mut self, data: &mut [u8]) -> Result<(), ProgramError> {
+        self.version = DATA_STORE_VERSION;
+        self.sync_legacy_flags();
+        self.metadata_crc = self.compute_metadata_crc()?;
+        let mut buffer = &mut data[..];
+        self.serialize(&mut buffer)?;
+        // Zero what older, longer metadata left behind, so fields appended later read as
+        // their defaults
+        buffer.fill(0);
+        Ok(())
     }
 
     pub fn data_type(&self) -> &DataStoreTypeOption {
@@ -105,7 +792,283 @@ impl DataStoreAccountMetadata {
     }
 
     pub fn is_dynamic(&self) -> bool {
-        self.is_dynamic
+        self.has_flag(FLAG_DYNAMIC)
+    }
+
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    /// Whether written bytes may only be extended, never overwritten
+    pub fn is_append_only(&self) -> bool {
+        self.has_flag(FLAG_APPEND_ONLY)
+    }
+
+    /// Number of bytes of the data account that have been written
+    pub fn data_len(&self) -> usize {
+        self.data_len as usize
+    }
+
+    /// Number of bytes allocated to the data account
+    pub fn space(&self) -> usize {
+        self.space
+    }
+
+    /// Returns (used_len, allocated_space) of the data account
+    pub fn utilization(&self) -> (usize, usize) {
+        (self.data_len(), self.space)
+    }
+
+    /// Fraction of the allocated space that is not used, 0.0 for an empty allocation
+    pub fn fragmentation_ratio(&self) -> f64 {
+        let (used, allocated) = self.utilization();
+        if allocated == 0 {
+            return 0.0;
+        }
+        allocated.saturating_sub(used) as f64 / allocated as f64
+    }
+
+    pub fn namespace(&self) -> &[u8; NAMESPACE_LEN] {
+        &self.namespace
+    }
+
+    /// Whether ReadData and GetMetadata are restricted to the authority and reader
+    pub fn is_private(&self) -> bool {
+        self.has_flag(FLAG_PRIVATE)
+    }
+
+    /// MIME type declared for the data, empty if unset
+    pub fn content_type(&self) -> &str {
+        &self.content_type
+    }
+
+    /// Whether the content_type is checked against the magic number of the data
+    pub fn is_strict_content_type(&self) -> bool {
+        self.has_flag(FLAG_STRICT_CONTENT_TYPE)
+    }
+
+    /// Data account this one supersedes in its version chain, if any
+    pub fn previous_version(&self) -> Option<&Pubkey> {
+        self.previous_version.as_ref()
+    }
+
+    /// Boolean toggles of the metadata packed as FLAG_* bits
+    pub fn flags(&self) -> u32 {
+        self.flags
+    }
+
+    pub fn has_flag(&self, flag: u32) -> bool {
+        self.flags & flag == flag
+    }
+
+    /// Flags of accounts written before FLAGS_VERSION, packed from their toggle fields
+    fn legacy_flags(&self) -> u32 {
+        [
+            (FLAG_DYNAMIC, self.is_dynamic),
+            (FLAG_APPEND_ONLY, self.append_only),
+            (FLAG_PRIVATE, self.private),
+            (FLAG_STRICT_CONTENT_TYPE, self.strict_content_type),
+            (FLAG_AUDIT, self.audit),
+            (FLAG_INDEXABLE, !self.unlisted),
+        ]
+        .iter()
+        .filter(|(_, set)| *set)
+        .fold(0, |flags, (flag, _)| flags | flag)
+    }
+
+    /// Mirrors flags into the toggle fields, so the older layout still reads them right
+    fn sync_legacy_flags(&mut self) {
+        self.is_dynamic = self.has_flag(FLAG_DYNAMIC);
+        self.append_only = self.has_flag(FLAG_APPEND_ONLY);
+        self.private = self.has_flag(FLAG_PRIVATE);
+        self.strict_content_type = self.has_flag(FLAG_STRICT_CONTENT_TYPE);
+        self.audit = self.has_flag(FLAG_AUDIT);
+        self.unlisted = !self.has_flag(FLAG_INDEXABLE);
+    }
+
+    /// ETag of the current content for HTTP gateways, the sha256 of data_hash followed
+    /// by the little-endian revision. Changes with every update and is stable otherwise.
+    pub fn etag(&self) -> [u8; 32] {
+        digestv(
+            HashAlgorithm::Sha256,
+            &[&self.data_hash, &self.revision.to_le_bytes()],
+        )
+    }
+
+    /// Most entries the directory may hold.
+    /// Directories created before the cap existed store 0 and are only bounded by
+    /// MAX_DATA_SPACE.
+    pub fn max_entries(&self) -> usize {
+        match self.max_entries {
+            0 => MAX_DATA_SPACE / DIRECTORY_ENTRY_SIZE,
+            max_entries => max_entries as usize,
+        }
+    }
+
+    /// End offset of the last write, where a resumed upload continues
+    pub fn upload_cursor(&self) -> u64 {
+        self.upload_cursor
+    }
+
+    /// Total length the upload is expected to reach, 0 if not set
+    pub fn expected_total(&self) -> u64 {
+        self.expected_total
+    }
+
+    /// Whether the upload reached expected_total, always true if it is not set
+    pub fn is_upload_complete(&self) -> bool {
+        self.expected_total == 0 || self.upload_cursor == self.expected_total
+    }
+
+    /// CRC-32 of the data, a cheap corruption check, data_hash remains authoritative
+    pub fn crc32(&self) -> u32 {
+        self.crc32
+    }
+
+    /// Feepayer that initialized the data account, unchanged by authority transfers.
+    /// Pubkey::default() for accounts initialized before the creator was recorded.
+    pub fn creator(&self) -> &Pubkey {
+        &self.creator
+    }
+
+    /// Slot of the last UpdateDataStore, 0 if never updated
+    pub fn updated_slot(&self) -> u64 {
+        self.updated_slot
+    }
+
+    /// Slots that must pass between two updates, 0 for no limit
+    pub fn min_update_interval_slots(&self) -> u64 {
+        self.min_update_interval_slots
+    }
+
+    /// Status of the data account packed in a byte for light clients: the
+    /// SerializationStatusOption in ACCOUNT_STATE_STATUS_MASK, plus the
+    /// ACCOUNT_STATE_DYNAMIC, ACCOUNT_STATE_APPEND_ONLY, ACCOUNT_STATE_PRIVATE and
+    /// ACCOUNT_STATE_FROZEN bits. A frozen data store keeps its status alongside the bit.
+    pub fn account_state(&self) -> u8 {
+        [
+            (ACCOUNT_STATE_DYNAMIC, self.is_dynamic()),
+            (ACCOUNT_STATE_APPEND_ONLY, self.is_append_only()),
+            (ACCOUNT_STATE_PRIVATE, self.is_private()),
+            (ACCOUNT_STATE_FROZEN, self.is_frozen()),
+        ]
+        .iter()
+        .filter(|(_, set)| *set)
+        .fold(self.data_status.clone() as u8, |state, (bit, _)| {
+            state | bit
+        })
+    }
+
+    /// Representation of the entries of a directory
+    pub fn directory_format(&self) -> &DirectoryFormat {
+        &self.directory_format
+    }
+
+    /// Whether the authority was renounced with a locking finalize
+    pub fn is_authority_renounced(&self) -> bool {
+        self.has_flag(FLAG_AUTHORITY_RENOUNCED)
+    }
+
+    /// Slot after which anyone may close the data store, refunding the creator
+    pub fn expiry_slot(&self) -> Option<u64> {
+        self.expiry_slot
+    }
+
+    /// Whether the data store has an expiry_slot earlier than slot
+    pub fn is_expired(&self, slot: u64) -> bool {
+        self.expiry_slot.filter(|expiry| slot > *expiry).is_some()
+    }
+
+    /// Number of updates after which the data store is finalized automatically
+    pub fn max_revisions(&self) -> Option<u64> {
+        self.max_revisions
+    }
+
+    /// Whether the data store reached its max_revisions
+    pub fn is_revision_limit_reached(&self) -> bool {
+        self.max_revisions
+            .filter(|max_revisions| self.revision >= *max_revisions)
+            .is_some()
+    }
+
+    /// Name of the data store, at most MAX_ENTRY_NAME_LEN bytes, empty if unnamed
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Whether writes are blocked until FLAG_FROZEN is cleared with SetMetadataFlags
+    pub fn is_frozen(&self) -> bool {
+        self.has_flag(FLAG_FROZEN)
+    }
+
+    /// Whether each update appends an AuditRecord to the audit PDA
+    pub fn is_audited(&self) -> bool {
+        self.has_flag(FLAG_AUDIT)
+    }
+
+    /// Whether shrinking the data account zeroes the freed tail first, so it never
+    /// reappears if the account grows again. Stored inverted, so that metadata written
+    /// before the toggle existed zeroes by default.
+    pub fn zero_on_shrink(&self) -> bool {
+        !self.keep_tail_on_shrink
+    }
+
+    /// CRC-32 of the serialized metadata with metadata_crc zeroed, meaningless for
+    /// metadata older than METADATA_CRC_VERSION
+    pub fn metadata_crc(&self) -> u32 {
+        self.metadata_crc
+    }
+
+    /// Computes the metadata_crc of the current fields, see store
+    pub fn compute_metadata_crc(&self) -> Result<u32, ProgramError> {
+        let mut unsealed = self.clone();
+        unsealed.metadata_crc = 0;
+        Ok(crc32(&borsh::to_vec(&unsealed)?))
+    }
+
+    /// Cache lifetime gateways should advertise with `Cache-Control: max-age`, 0 for no
+    /// caching. Purely advisory, the program never reads it.
+    pub fn cache_ttl_seconds(&self) -> u32 {
+        self.cache_ttl_seconds
+    }
+
+    /// Whether public listings and indexers should show the data store. Advisory only,
+    /// the data stays readable either way.
+    pub fn is_indexable(&self) -> bool {
+        self.has_flag(FLAG_INDEXABLE)
+    }
+
+    /// Whether a write at offset appends to the written data of a data store hashed
+    /// incrementally, so only the written bytes need hashing, see start_hash_stream
+    pub fn is_streamed_append(&self, offset: usize) -> bool {
+        offset == self.data_len() && self.hash_stream.is_some()
+    }
+
+    /// Delegate allowed to read a private data store besides the authority
+    pub fn reader(&self) -> Option<&Pubkey> {
+        self.reader.as_ref()
+    }
+
+    /// NamedEntries directory listing the data store under its name, recorded by SetName.
+    /// Later renames must go through it, so the listing keeps the name in sync.
+    pub fn parent(&self) -> Option<&Pubkey> {
+        self.parent.as_ref()
+    }
+
+    /// Derives the metadata PDA of data_account from the stored namespace and bump_seed
+    pub fn metadata_pda(
+        &self,
+        program_id: &Pubkey,
+        data_account: &Pubkey,
+    ) -> Result<Pubkey, ProgramError> {
+        let bump_seed = [self.bump_seed];
+        let mut seeds = metadata_pda_seeds(data_account, &self.namespace);
+        seeds.push(&bump_seed);
+        Ok(Pubkey::create_program_address(&seeds, program_id)?)
     }
 
     pub fn set_data_type(&mut self, data_type: DataStoreTypeOption) {
@@ -119,15 +1082,491 @@ impl DataStoreAccountMetadata {
     pub fn set_data_status(&mut self, status: SerializationStatusOption) {
         self.data_status = status;
     }
+
+    pub fn set_data_hash(&mut self, data_hash: [u8; 32]) {
+        self.data_hash = data_hash;
+    }
+
+    pub fn set_data_len(&mut self, data_len: usize) {
+        self.data_len = data_len as u64;
+    }
+
+    pub fn set_space(&mut self, space: usize) {
+        self.space = space;
+    }
+
+    pub fn set_append_only(&mut self, append_only: bool) {
+        self.set_flag(FLAG_APPEND_ONLY, append_only);
+    }
+
+    pub fn set_namespace(&mut self, namespace: [u8; NAMESPACE_LEN]) {
+        self.namespace = namespace;
+    }
+
+    pub fn set_private(&mut self, private: bool) {
+        self.set_flag(FLAG_PRIVATE, private);
+    }
+
+    pub fn set_content_type(&mut self, content_type: String) {
+        self.content_type = content_type;
+    }
+
+    pub fn set_strict_content_type(&mut self, strict_content_type: bool) {
+        self.set_flag(FLAG_STRICT_CONTENT_TYPE, strict_content_type);
+    }
+
+    pub fn set_previous_version(&mut self, previous_version: Option<Pubkey>) {
+        self.previous_version = previous_version;
+    }
+
+    /// Sets every FLAG_* bit in flag to value, ignoring unknown bits
+    pub fn set_flag(&mut self, flag: u32, value: bool) {
+        if value {
+            self.flags |= flag & KNOWN_FLAGS;
+        } else {
+            self.flags &= !flag;
+        }
+    }
+
+    pub fn set_max_entries(&mut self, max_entries: u32) {
+        self.max_entries = max_entries;
+    }
+
+    pub fn set_upload_cursor(&mut self, upload_cursor: u64) {
+        self.upload_cursor = upload_cursor;
+    }
+
+    pub fn set_expected_total(&mut self, expected_total: u64) {
+        self.expected_total = expected_total;
+    }
+
+    /// Sets data_hash and crc32 from the contents of data_account, dropping any hash stream.
+    /// Only the first data_len bytes are covered, so trailing padding of the allocation
+    /// never affects the checksums and they are stable across reallocs.
+    pub fn update_checksums(&mut self, data_account: &Pubkey, data: &[u8]) {
+        let data = &data[..self.data_len().min(data.len())];
+        self.data_hash = compute_data_hash(data_account, data);
+        self.crc32 = crc32(data);
+        self.hash_stream = None;
+    }
+
+    pub fn set_creator(&mut self, creator: &Pubkey) {
+        self.creator = *creator;
+    }
+
+    pub fn set_updated_slot(&mut self, updated_slot: u64) {
+        self.updated_slot = updated_slot;
+    }
+
+    pub fn set_min_update_interval_slots(&mut self, min_update_interval_slots: u64) {
+        self.min_update_interval_slots = min_update_interval_slots;
+    }
+
+    pub fn set_directory_format(&mut self, directory_format: DirectoryFormat) {
+        self.directory_format = directory_format;
+    }
+
+    pub fn set_expiry_slot(&mut self, expiry_slot: Option<u64>) {
+        self.expiry_slot = expiry_slot;
+    }
+
+    pub fn set_max_revisions(&mut self, max_revisions: Option<u64>) {
+        self.max_revisions = max_revisions;
+    }
+
+    pub fn set_name(&mut self, name: String) {
+        self.name = name;
+    }
+
+    pub fn set_audit(&mut self, audit: bool) {
+        self.set_flag(FLAG_AUDIT, audit);
+    }
+
+    pub fn set_zero_on_shrink(&mut self, zero_on_shrink: bool) {
+        self.keep_tail_on_shrink = !zero_on_shrink;
+    }
+
+    pub fn set_cache_ttl_seconds(&mut self, cache_ttl_seconds: u32) {
+        self.cache_ttl_seconds = cache_ttl_seconds;
+    }
+
+    pub fn set_indexable(&mut self, indexable: bool) {
+        self.set_flag(FLAG_INDEXABLE, indexable);
+    }
+
+    /// Starts hashing the data store incrementally from the contents of data_account,
+    /// so later appends only fold the appended bytes into data_hash, see append_checksums.
+    /// This only holds for strictly contiguous appends: any other write rehashes the
+    /// whole data with update_checksums and drops the stream for good.
+    pub fn start_hash_stream(&mut self, data_account: &Pubkey, data: &[u8]) {
+        let data = &data[..self.data_len().min(data.len())];
+        let mut stream = Sha256Stream::new();
+        stream.update(data_account.as_ref());
+        stream.update(&[DATA_HASH_VERSION]);
+        stream.update(data);
+        self.data_hash = stream.finalize();
+        self.crc32 = crc32(data);
+        self.hash_stream = Some(stream);
+    }
+
+    /// Sets data_hash and crc32 after bytes were appended to the first appended_from bytes
+    /// of data_account. With a hash stream covering those, only the appended bytes are
+    /// hashed, otherwise this falls back to update_checksums.
+    pub fn append_checksums(&mut self, data_account: &Pubkey, data: &[u8], appended_from: usize) {
+        let data = &data[..self.data_len().min(data.len())];
+        let streamed_len = (data_account.as_ref().len() + 1 + appended_from) as u64;
+        match self.hash_stream.as_mut() {
+            Some(stream) if stream.len() == streamed_len && appended_from <= data.len() => {
+                let appended = &data[appended_from..];
+                stream.update(appended);
+                self.data_hash = stream.finalize();
+                self.crc32 = crc32_append(self.crc32, appended);
+            }
+            _ => self.update_checksums(data_account, data),
+        }
+    }
+
+    /// Drops the hash stream once the data can no longer be appended to
+    pub fn end_hash_stream(&mut self) {
+        self.hash_stream = None;
+    }
+
+    pub fn set_reader(&mut self, reader: Option<Pubkey>) {
+        self.reader = reader;
+    }
+
+    pub fn set_parent(&mut self, parent: Option<Pubkey>) {
+        self.parent = parent;
+    }
+
+    pub fn set_revision(&mut self, revision: u64) {
+        self.revision = revision;
+    }
+
+    pub fn increment_revision(&mut self) -> Option<u64> {
+        self.revision = self.revision.checked_add(1)?;
+        Some(self.revision)
+    }
+}
+
+impl From<DataStoreAccountMetadataV0> for DataStoreAccountMetadata {
+    fn from(metadata: DataStoreAccountMetadataV0) -> Self {
+        Self {
+            data_type: metadata.data_type,
+            authority: metadata.authority,
+            data_status: metadata.data_status,
+            bump_seed: metadata.bump_seed,
+            data_hash: metadata.data_hash,
+            is_dynamic: metadata.is_dynamic,
+            space: metadata.space,
+            revision: 0,
+            data_len: 0,
+            version: DATA_STORE_VERSION,
+            append_only: false,
+            namespace: [0; NAMESPACE_LEN],
+            private: false,
+            content_type: String::new(),
+            strict_content_type: false,
+            previous_version: None,
+            max_entries: 0,
+            upload_cursor: 0,
+            expected_total: 0,
+            crc32: 0,
+            creator: Pubkey::default(),
+            updated_slot: 0,
+            min_update_interval_slots: 0,
+            directory_format: DirectoryFormat::PackedPubkeys,
+            expiry_slot: None,
+            max_revisions: None,
+            name: String::new(),
+            audit: false,
+            keep_tail_on_shrink: false,
+            metadata_crc: 0,
+            cache_ttl_seconds: 0,
+            unlisted: false,
+            hash_stream: None,
+            reader: None,
+            flags: if metadata.is_dynamic {
+                FLAG_DYNAMIC | FLAG_INDEXABLE
+            } else {
+                FLAG_INDEXABLE
+            },
+            parent: None,
+        }
+    }
+}
+
+/// Byte offsets of the fixed-layout leading fields of a serialized
+/// DataStoreAccountMetadata, read in place by the read_* functions.
+/// Those skip the metadata_crc check of load, so the program itself always loads.
+pub const METADATA_AUTHORITY_OFFSET: usize = 1;
+pub const METADATA_STATUS_OFFSET: usize = 33;
+pub const METADATA_BUMP_SEED_OFFSET: usize = 34;
+pub const METADATA_DATA_HASH_OFFSET: usize = 35;
+pub const METADATA_VERSION_OFFSET: usize = 92;
+pub const METADATA_NAMESPACE_OFFSET: usize = 94;
+
+/// Reads the authority from metadata account data without deserializing it
+pub fn read_authority(data: &[u8]) -> Result<&Pubkey, ProgramError> {
+    data.get(METADATA_AUTHORITY_OFFSET..METADATA_AUTHORITY_OFFSET + 32)
+        .and_then(|bytes| bytemuck::try_from_bytes(bytes).ok())
+        .ok_or_else(|| DataStoreError::CorruptMetadata.into())
+}
+
+/// Reads the data_status from metadata account data without deserializing it
+pub fn read_data_status(data: &[u8]) -> Result<SerializationStatusOption, ProgramError> {
+    match data.get(METADATA_STATUS_OFFSET) {
+        Some(0) => Ok(SerializationStatusOption::Uninitialized),
+        Some(1) => Ok(SerializationStatusOption::Initialized),
+        Some(2) => Ok(SerializationStatusOption::Finalized),
+        _ => Err(DataStoreError::CorruptMetadata.into()),
+    }
+}
+
+/// Reads the bump_seed from metadata account data without deserializing it
+pub fn read_bump_seed(data: &[u8]) -> Result<u8, ProgramError> {
+    data.get(METADATA_BUMP_SEED_OFFSET)
+        .copied()
+        .ok_or_else(|| DataStoreError::CorruptMetadata.into())
+}
+
+/// Reads the data_hash from metadata account data without deserializing it
+pub fn read_data_hash(data: &[u8]) -> Result<&[u8; 32], ProgramError> {
+    data.get(METADATA_DATA_HASH_OFFSET..METADATA_DATA_HASH_OFFSET + 32)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| DataStoreError::CorruptMetadata.into())
+}
+
+/// Reads the namespace from metadata account data without deserializing it,
+/// rejecting accounts written by a newer version of the program like load does
+pub fn read_namespace(data: &[u8]) -> Result<&[u8; NAMESPACE_LEN], ProgramError> {
+    let version = data
+        .get(METADATA_VERSION_OFFSET)
+        .ok_or(DataStoreError::CorruptMetadata)?;
+    if *version > DATA_STORE_VERSION {
+        return Err(DataStoreError::UnsupportedVersion.into());
+    }
+    data.get(METADATA_NAMESPACE_OFFSET..METADATA_NAMESPACE_OFFSET + NAMESPACE_LEN)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| DataStoreError::CorruptMetadata.into())
+}
+
+/// Derives the metadata PDA of data_account from metadata account data without
+/// deserializing it, like DataStoreAccountMetadata::metadata_pda
+pub fn read_metadata_pda(
+    program_id: &Pubkey,
+    data_account: &Pubkey,
+    data: &[u8],
+) -> Result<Pubkey, ProgramError> {
+    let bump_seed = [read_bump_seed(data)?];
+    let mut seeds = metadata_pda_seeds(data_account, read_namespace(data)?);
+    seeds.push(&bump_seed);
+    Ok(Pubkey::create_program_address(&seeds, program_id)?)
+}
+
+/// Seeds of the metadata PDA of data_account, without the bump.
+/// The namespace is only a seed when set, so the default namespace derives the
+/// same PDA as before namespaces existed.
+pub fn metadata_pda_seeds<'a>(
+    data_account: &'a Pubkey,
+    namespace: &'a [u8; NAMESPACE_LEN],
+) -> Vec<&'a [u8]> {
+    let mut seeds = vec![PDA_SEED];
+    if *namespace != [0; NAMESPACE_LEN] {
+        seeds.push(namespace);
+    }
+    seeds.push(data_account.as_ref());
+    seeds
+}
+
+/// Derives the metadata PDA and canonical bump of data_account, as the program does.
+/// The namespace is zero-padded to NAMESPACE_LEN bytes, matching the namespace passed
+/// at initialization, and bytes past NAMESPACE_LEN are ignored.
+pub fn derive_metadata_pda(
+    program_id: &Pubkey,
+    data_account: &Pubkey,
+    namespace: Option<&[u8]>,
+) -> (Pubkey, u8) {
+    let mut padded_namespace = [0; NAMESPACE_LEN];
+    if let Some(namespace) = namespace {
+        let len = namespace.len().min(NAMESPACE_LEN);
+        padded_namespace[..len].copy_from_slice(&namespace[..len]);
+    }
+    Pubkey::find_program_address(
+        &metadata_pda_seeds(data_account, &padded_namespace),
+        program_id,
+    )
+}
+
+/// Computes the hash stored as data_hash for the data account's contents.
+/// The hash is domain-separated per account, the preimage being
+/// `data_account (32 bytes) || DATA_HASH_VERSION (1 byte) || data`,
+/// so identical contents in two accounts never share a hash.
+/// data is the used data_len bytes of the account, excluding trailing padding.
+pub fn compute_data_hash(data_account: &Pubkey, data: &[u8]) -> [u8; 32] {
+    digestv(
+        HashAlgorithm::Sha256,
+        &[data_account.as_ref(), &[DATA_HASH_VERSION], data],
+    )
+}
+
+/// Estimates the compute units of an update writing written_len bytes, leaving
+/// hashed_len bytes of data to hash and checksum, and reallocating the data account if
+/// realloc is set
+pub fn estimate_update_compute_units(written_len: usize, hashed_len: usize, realloc: bool) -> u64 {
+    let realloc_units = if realloc {
+        UPDATE_REALLOC_COMPUTE_UNITS
+    } else {
+        0
+    };
+    UPDATE_BASE_COMPUTE_UNITS
+        .saturating_add(realloc_units)
+        .saturating_add((written_len as u64).saturating_mul(UPDATE_COMPUTE_UNITS_PER_WRITTEN_BYTE))
+        .saturating_add((hashed_len as u64).saturating_mul(UPDATE_COMPUTE_UNITS_PER_HASHED_BYTE))
+}
+
+/// Builds the message an authority signs off-chain to authorize a RelayedUpdate of
+/// data_account: `RELAYED_UPDATE_DOMAIN || program_id || data_account || revision (u64 LE)
+/// || sha256(borsh(args))`. Binding the current revision makes a signed message usable
+/// for a single update, and the domain and program_id keep it from being replayed as
+/// any other signed message or against another deployment of the program.
+pub fn relayed_update_message(
+    program_id: &Pubkey,
+    data_account: &Pubkey,
+    revision: u64,
+    args: &UpdateDataStoreArgs,
+) -> Result<Vec<u8>, ProgramError> {
+    let args_hash = digestv(HashAlgorithm::Sha256, &[&borsh::to_vec(args)?]);
+    let mut message = Vec::with_capacity(RELAYED_UPDATE_DOMAIN.len() + 32 + 32 + 8 + 32);
+    message.extend_from_slice(RELAYED_UPDATE_DOMAIN);
+    message.extend_from_slice(program_id.as_ref());
+    message.extend_from_slice(data_account.as_ref());
+    message.extend_from_slice(&revision.to_le_bytes());
+    message.extend_from_slice(&args_hash);
+    Ok(message)
+}
+
+/// Derives the file PDA and canonical bump of the data account named file_name by
+/// authority, which InitializeDataStore creates when given a file_name
+pub fn derive_file_pda(program_id: &Pubkey, authority: &Pubkey, file_name: &str) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[FILE_SEED, authority.as_ref(), file_name.as_bytes()],
+        program_id,
+    )
+}
+
+/// Checks that key is an HTTP header name and value a header value, both within their
+/// length caps. Line breaks are rejected so gateways can emit headers verbatim.
+pub fn is_valid_header(key: &str, value: &str) -> bool {
+    let is_token = |c: u8| c.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&c);
+    !key.is_empty()
+        && key.len() <= MAX_HEADER_KEY_LEN
+        && key.bytes().all(is_token)
+        && value.len() <= MAX_HEADER_VALUE_LEN
+        && value
+            .bytes()
+            .all(|c| c == b'\t' || (b' '..=b'~').contains(&c))
+}
+
+/// Derives the headers PDA and canonical bump of data_account
+pub fn derive_headers_pda(program_id: &Pubkey, data_account: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[HEADERS_SEED, data_account.as_ref()], program_id)
+}
+
+/// Hashes data for the content PDA. Unlike data_hash this is a plain SHA-256 of the
+/// used data, so identical contents in any two accounts map to the same content PDA.
+pub fn compute_content_hash(data: &[u8]) -> [u8; 32] {
+    digestv(HashAlgorithm::Sha256, &[data])
+}
+
+/// Derives the content PDA and canonical bump of content_hash, see compute_content_hash.
+/// Clients derive it to check whether the content was already uploaded.
+pub fn derive_content_pda(program_id: &Pubkey, content_hash: &[u8; 32]) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[CONTENT_SEED, content_hash], program_id)
+}
+
+/// Verifies that data read from a data account matches its stored data_hash
+pub fn verify_data_integrity(data_account: &Pubkey, data: &[u8], data_hash: &[u8; 32]) -> bool {
+    compute_data_hash(data_account, data) == *data_hash
 }
 
 /// Verifies that the data conforms to the data_type
 pub fn verify_data(data: &[u8], data_type: DataStoreTypeOption) -> SerializationStatusOption {
-    if data.is_empty() || data_type == DataStoreTypeOption::File {
-        return SerializationStatusOption::Uninitialized;
-    }
     match data_type {
+        _ if data.is_empty() => SerializationStatusOption::Uninitialized,
+        DataStoreTypeOption::File => SerializationStatusOption::Uninitialized,
         DataStoreTypeOption::Directory => SerializationStatusOption::Initialized,
-        _ => SerializationStatusOption::Finalized,
+        DataStoreTypeOption::Symlink if data.len() == SYMLINK_TARGET_LEN => {
+            SerializationStatusOption::Initialized
+        }
+        DataStoreTypeOption::Symlink => SerializationStatusOption::Uninitialized,
+    }
+}
+
+/// Reads the target of a symlink from the data account's contents
+pub fn read_symlink_target(data: &[u8]) -> Option<Pubkey> {
+    let target: [u8; SYMLINK_TARGET_LEN] = data.try_into().ok()?;
+    Some(Pubkey::new_from_array(target))
+}
+
+/// Returns the index of child among the packed directory entries
+pub fn find_directory_entry(entries: &[u8], child: &Pubkey) -> Option<usize> {
+    entries
+        .chunks_exact(DIRECTORY_ENTRY_SIZE)
+        .position(|entry| entry == child.as_ref())
+}
+
+/// Checks data against the magic number of content_type.
+/// Content types without a known magic number, and empty data, always match.
+pub fn matches_content_type(content_type: &str, data: &[u8]) -> bool {
+    if data.is_empty() {
+        return true;
+    }
+    CONTENT_TYPE_MAGICS
+        .iter()
+        .filter(|(known_type, _)| *known_type == content_type)
+        .all(|(_, magic)| data.starts_with(magic))
+}
+
+/// Verifies the used bytes of a directory against its format: packed pubkeys must be
+/// whole entries, a JSON manifest must be UTF-8 enclosed in `{` and `}`
+pub fn verify_directory_data(data: &[u8], directory_format: &DirectoryFormat) -> bool {
+    match directory_format {
+        DirectoryFormat::PackedPubkeys => {
+            data.len() / DIRECTORY_ENTRY_SIZE * DIRECTORY_ENTRY_SIZE == data.len()
+        }
+        DirectoryFormat::JsonManifest => {
+            data.first() == Some(&b'{')
+                && data.last() == Some(&b'}')
+                && core::str::from_utf8(data).is_ok()
+        }
+        DirectoryFormat::NamedEntries => parse_named_entries(data).is_some(),
     }
-}
\ No newline at end of file
+}
+
+/// Splits the records of a NamedEntries directory into (name, child) pairs, None if a
+/// record is truncated or its name exceeds MAX_ENTRY_NAME_LEN
+pub fn parse_named_entries(data: &[u8]) -> Option<Vec<(&[u8], Pubkey)>> {
+    let mut entries = Vec::new();
+    let mut rest = data;
+    while let Some((name_len, tail)) = rest.split_first() {
+        let name_len = *name_len as usize;
+        if name_len > MAX_ENTRY_NAME_LEN || tail.len() < name_len + DIRECTORY_ENTRY_SIZE {
+            return None;
+        }
+        let (name, tail) = tail.split_at(name_len);
+        let (child, tail) = tail.split_at(DIRECTORY_ENTRY_SIZE);
+        entries.push((name, Pubkey::new_from_array(child.try_into().ok()?)));
+        rest = tail;
+    }
+    Some(entries)
+}
+
+/// Encodes a record of a NamedEntries directory
+pub fn encode_named_entry(name: &[u8], child: &Pubkey) -> Vec<u8> {
+    let mut record = Vec::with_capacity(1 + name.len() + DIRECTORY_ENTRY_SIZE);
+    record.push(name.len() as u8);
+    record.extend_from_slice(name);
+    record.extend_from_slice(child.as_ref());
+    record
+}
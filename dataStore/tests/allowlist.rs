@@ -0,0 +1,60 @@
+#![cfg(feature = "permissioned-init")]
+
+mod common;
+
+use common::{assert_error, init_args, initialize_ix, metadata, metadata_pda, process, start};
+use dataaccount::error::DataStoreError;
+use solana_program_test::tokio;
+use solana_sdk::{
+    account::AccountSharedData,
+    signature::{Keypair, Signer},
+    system_program,
+};
+
+#[tokio::test]
+async fn only_allowlisted_feepayers_initialize() {
+    let mut context = start().await;
+
+    // The context payer is the only allowlisted feepayer
+    let payer = context.payer.pubkey();
+    let data_account = Keypair::new();
+    let args = init_args(&data_account.pubkey(), &payer, 64);
+    process(
+        &mut context,
+        &[initialize_ix(&payer, &data_account.pubkey(), args)],
+        &[&data_account],
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        *metadata(&mut context, &data_account.pubkey())
+            .await
+            .creator(),
+        payer
+    );
+
+    let stranger = Keypair::new();
+    context.set_account(
+        &stranger.pubkey(),
+        &AccountSharedData::new(1_000_000_000, 0, &system_program::id()),
+    );
+    let data_account = Keypair::new();
+    let args = init_args(&data_account.pubkey(), &stranger.pubkey(), 64);
+    let result = process(
+        &mut context,
+        &[initialize_ix(
+            &stranger.pubkey(),
+            &data_account.pubkey(),
+            args,
+        )],
+        &[&stranger, &data_account],
+    )
+    .await;
+    assert_error(result, DataStoreError::InvalidAuthority);
+    assert!(context
+        .banks_client
+        .get_account(metadata_pda(&data_account.pubkey()).0)
+        .await
+        .unwrap()
+        .is_none());
+}
@@ -0,0 +1,322 @@
+mod common;
+
+use common::{
+    assert_error, caller_pda, caller_program_id, close_ix, config_pda, cpi_ix, initialize,
+    instruction, metadata, metadata_pda, process, start, update_args, update_ix, CALLER_SEED,
+};
+use dataaccount::{
+    error::DataStoreError,
+    instruction::DataStoreInstruction,
+    state::{
+        FinalizeDataStoreArgs, NewAuthorityPda, SerializationStatusOption,
+        UpdateDataStoreAuthorityArgs, RENOUNCED_AUTHORITY,
+    },
+};
+use solana_program_test::tokio;
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+};
+
+fn update_authority_ix(
+    authority: &Pubkey,
+    data_account: &Pubkey,
+    new_authority: &Pubkey,
+) -> Instruction {
+    instruction(
+        vec![
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new_readonly(*data_account, false),
+            AccountMeta::new(metadata_pda(data_account).0, false),
+            AccountMeta::new_readonly(*new_authority, true),
+            AccountMeta::new_readonly(config_pda(), false),
+        ],
+        DataStoreInstruction::UpdateDataStoreAuthority(UpdateDataStoreAuthorityArgs {
+            debug: false,
+            new_authority_pda: None,
+        }),
+    )
+}
+
+fn finalize_ix(authority: &Pubkey, data_account: &Pubkey, lock_authority: bool) -> Instruction {
+    instruction(
+        vec![
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new_readonly(*data_account, false),
+            AccountMeta::new(metadata_pda(data_account).0, false),
+            AccountMeta::new_readonly(config_pda(), false),
+        ],
+        DataStoreInstruction::FinalizeDataStore(FinalizeDataStoreArgs {
+            debug: false,
+            lock_authority,
+        }),
+    )
+}
+
+#[tokio::test]
+async fn same_authority_is_rejected() {
+    let mut context = start().await;
+    let data_account = initialize(&mut context, |args| args).await.pubkey();
+    let payer = context.payer.pubkey();
+
+    let result = process(
+        &mut context,
+        &[update_authority_ix(&payer, &data_account, &payer)],
+        &[],
+    )
+    .await;
+    assert_error(result, DataStoreError::AuthorityUnchanged);
+    assert_eq!(
+        *metadata(&mut context, &data_account).await.authority(),
+        payer
+    );
+
+    let new_authority = Keypair::new();
+    process(
+        &mut context,
+        &[update_authority_ix(
+            &payer,
+            &data_account,
+            &new_authority.pubkey(),
+        )],
+        &[&new_authority],
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        *metadata(&mut context, &data_account).await.authority(),
+        new_authority.pubkey()
+    );
+}
+
+#[tokio::test]
+async fn authority_changes_without_touching_the_data_account() {
+    let mut context = start().await;
+    let data_account = initialize(&mut context, |args| args).await.pubkey();
+    let payer = context.payer.pubkey();
+    let before = context
+        .banks_client
+        .get_account(data_account)
+        .await
+        .unwrap()
+        .unwrap();
+
+    // The data account is passed read-only and does not sign
+    let new_authority = Keypair::new();
+    let transfer_ix = update_authority_ix(&payer, &data_account, &new_authority.pubkey());
+    assert!(!transfer_ix.accounts[1].is_signer && !transfer_ix.accounts[1].is_writable);
+    process(&mut context, &[transfer_ix], &[&new_authority])
+        .await
+        .unwrap();
+
+    let after = context
+        .banks_client
+        .get_account(data_account)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(after, before);
+    assert_eq!(
+        *metadata(&mut context, &data_account).await.authority(),
+        new_authority.pubkey()
+    );
+
+    // Only the new authority may write from now on
+    let result = process(
+        &mut context,
+        &[update_ix(&payer, &data_account, update_args(b"old", 0))],
+        &[],
+    )
+    .await;
+    assert_error(result, DataStoreError::InvalidAuthority);
+    process(
+        &mut context,
+        &[update_ix(
+            &new_authority.pubkey(),
+            &data_account,
+            update_args(b"new", 0),
+        )],
+        &[&new_authority],
+    )
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn authority_transferred_to_pda_updates_through_cpi() {
+    let mut context = start().await;
+    let data_account = initialize(&mut context, |args| args).await.pubkey();
+    let payer = context.payer.pubkey();
+    let (pda, bump_seed) = Pubkey::find_program_address(&[CALLER_SEED], &caller_program_id());
+    assert_eq!(pda, caller_pda());
+
+    // The PDA can not sign, its seeds prove it instead
+    let mut transfer_ix = update_authority_ix(&payer, &data_account, &pda);
+    transfer_ix.accounts[3].is_signer = false;
+    transfer_ix.data = borsh::to_vec(&DataStoreInstruction::UpdateDataStoreAuthority(
+        UpdateDataStoreAuthorityArgs {
+            debug: false,
+            new_authority_pda: Some(NewAuthorityPda {
+                seeds: vec![CALLER_SEED.to_vec(), vec![bump_seed]],
+                program: caller_program_id(),
+            }),
+        },
+    ))
+    .unwrap();
+    process(&mut context, &[transfer_ix], &[]).await.unwrap();
+    assert_eq!(
+        *metadata(&mut context, &data_account).await.authority(),
+        pda
+    );
+
+    // Only the program deriving the PDA writes from now on, signing for it
+    let result = process(
+        &mut context,
+        &[update_ix(&payer, &data_account, update_args(b"payer", 0))],
+        &[],
+    )
+    .await;
+    assert_error(result, DataStoreError::InvalidAuthority);
+    process(
+        &mut context,
+        &[cpi_ix(update_ix(
+            &pda,
+            &data_account,
+            update_args(b"program", 0),
+        ))],
+        &[],
+    )
+    .await
+    .unwrap();
+    let account = context
+        .banks_client
+        .get_account(data_account)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(&account.data[..7], b"program");
+}
+
+#[tokio::test]
+async fn creator_survives_authority_transfer() {
+    let mut context = start().await;
+    let data_account = initialize(&mut context, |args| args).await.pubkey();
+    let payer = context.payer.pubkey();
+    assert_eq!(
+        *metadata(&mut context, &data_account).await.creator(),
+        payer
+    );
+
+    let new_authority = Keypair::new();
+    process(
+        &mut context,
+        &[update_authority_ix(
+            &payer,
+            &data_account,
+            &new_authority.pubkey(),
+        )],
+        &[&new_authority],
+    )
+    .await
+    .unwrap();
+
+    let account_metadata = metadata(&mut context, &data_account).await;
+    assert_eq!(*account_metadata.authority(), new_authority.pubkey());
+    assert_eq!(*account_metadata.creator(), payer);
+}
+
+#[tokio::test]
+async fn unlocked_finalize_keeps_the_authority() {
+    let mut context = start().await;
+    let data_account = initialize(&mut context, |args| args).await.pubkey();
+    let payer = context.payer.pubkey();
+    process(
+        &mut context,
+        &[finalize_ix(&payer, &data_account, false)],
+        &[],
+    )
+    .await
+    .unwrap();
+    let account_metadata = metadata(&mut context, &data_account).await;
+    assert_eq!(
+        *account_metadata.data_status(),
+        SerializationStatusOption::Finalized
+    );
+    assert!(!account_metadata.is_authority_renounced());
+
+    // The authority can still be transferred, and the new one close the data store
+    let new_authority = Keypair::new();
+    process(
+        &mut context,
+        &[update_authority_ix(
+            &payer,
+            &data_account,
+            &new_authority.pubkey(),
+        )],
+        &[&new_authority],
+    )
+    .await
+    .unwrap();
+    process(
+        &mut context,
+        &[close_ix(&new_authority.pubkey(), &data_account, false)],
+        &[&new_authority],
+    )
+    .await
+    .unwrap();
+    assert!(context
+        .banks_client
+        .get_account(metadata_pda(&data_account).0)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn locked_finalize_renounces_the_authority() {
+    let mut context = start().await;
+    let data_account = initialize(&mut context, |args| args).await.pubkey();
+    let payer = context.payer.pubkey();
+    process(
+        &mut context,
+        &[finalize_ix(&payer, &data_account, true)],
+        &[],
+    )
+    .await
+    .unwrap();
+    let account_metadata = metadata(&mut context, &data_account).await;
+    assert_eq!(
+        *account_metadata.data_status(),
+        SerializationStatusOption::Finalized
+    );
+    assert!(account_metadata.is_authority_renounced());
+    assert_eq!(*account_metadata.authority(), RENOUNCED_AUTHORITY);
+
+    // Nobody can transfer, close or write to the data store anymore
+    let new_authority = Keypair::new();
+    let result = process(
+        &mut context,
+        &[update_authority_ix(
+            &payer,
+            &data_account,
+            &new_authority.pubkey(),
+        )],
+        &[&new_authority],
+    )
+    .await;
+    assert_error(result, DataStoreError::InvalidAuthority);
+    let result = process(&mut context, &[close_ix(&payer, &data_account, false)], &[]).await;
+    assert_error(result, DataStoreError::InvalidAuthority);
+    let result = process(
+        &mut context,
+        &[update_ix(&payer, &data_account, update_args(b"hello", 0))],
+        &[],
+    )
+    .await;
+    assert_error(result, DataStoreError::AlreadyFinalized);
+    assert_eq!(
+        metadata(&mut context, &data_account).await.revision(),
+        account_metadata.revision()
+    );
+}
@@ -0,0 +1,68 @@
+#![cfg(feature = "client")]
+
+mod common;
+
+use common::{initialize, metadata, metadata_pda, start, update_args, update_ix};
+use dataaccount::{
+    client::{estimate_rent, max_chunk_size, PACKET_DATA_SIZE},
+    state::{InitializeDataStoreArgs, UpdateDataStoreArgs},
+};
+use solana_program_test::tokio;
+use solana_sdk::{signature::Signer, transaction::Transaction};
+
+#[tokio::test]
+async fn estimated_rent_matches_balances() {
+    let mut context = start().await;
+    let space = 1000;
+    let data_account = initialize(&mut context, |args| InitializeDataStoreArgs {
+        space,
+        ..args
+    })
+    .await
+    .pubkey();
+
+    let mut balance = 0;
+    for account in [data_account, metadata_pda(&data_account).0] {
+        balance += context.banks_client.get_balance(account).await.unwrap();
+    }
+    assert_eq!(balance, estimate_rent(space as usize));
+}
+
+#[tokio::test]
+async fn max_chunk_fills_a_transaction() {
+    let mut context = start().await;
+    let data_account = initialize(&mut context, |args| InitializeDataStoreArgs {
+        space: 0,
+        is_dynamic: true,
+        ..args
+    })
+    .await
+    .pubkey();
+    let payer = context.payer.pubkey();
+    let write = |len: usize| {
+        let args = UpdateDataStoreArgs {
+            expected_revision: Some(0),
+            ..update_args(&vec![1; len], 0)
+        };
+        Transaction::new_with_payer(&[update_ix(&payer, &data_account, args)], Some(&payer))
+    };
+    // Signature count, the authority signature, and the message
+    let size = |transaction: &Transaction| 1 + 64 + transaction.message.serialize().len();
+
+    let chunk_size = max_chunk_size(true, 0);
+    assert_eq!(size(&write(chunk_size)), PACKET_DATA_SIZE);
+    assert_eq!(size(&write(chunk_size + 1)), PACKET_DATA_SIZE + 1);
+
+    let mut transaction = write(chunk_size);
+    let blockhash = context.get_new_latest_blockhash().await.unwrap();
+    transaction.sign(&[&context.payer], blockhash);
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+    assert_eq!(
+        metadata(&mut context, &data_account).await.data_len(),
+        chunk_size
+    );
+}
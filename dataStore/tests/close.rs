@@ -0,0 +1,302 @@
+mod common;
+
+use common::{
+    assert_error, close_ix, config_pda, finalize_ix, initialize, instruction, metadata_pda,
+    process, start, update_args, update_ix,
+};
+use dataaccount::{
+    error::DataStoreError,
+    instruction::DataStoreInstruction,
+    state::{CloseDataStoreBatchArgs, InitializeDataStoreArgs},
+};
+use solana_program_test::{tokio, BanksClientError, ProgramTestContext};
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_instruction,
+    transaction::Transaction,
+};
+
+/// CloseDataStore refunding recipient instead of the authority
+fn close_to_ix(authority: &Pubkey, data_account: &Pubkey, recipient: &Pubkey) -> Instruction {
+    let mut close_ix = close_ix(authority, data_account, true);
+    close_ix
+        .accounts
+        .insert(3, AccountMeta::new(*recipient, false));
+    close_ix
+}
+
+fn close_batch_ix(
+    authority: &Pubkey,
+    recipient: &Pubkey,
+    data_accounts: &[Pubkey],
+    force: bool,
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(*authority, true),
+        AccountMeta::new(*recipient, false),
+    ];
+    for data_account in data_accounts {
+        accounts.push(AccountMeta::new(*data_account, false));
+        accounts.push(AccountMeta::new(metadata_pda(data_account).0, false));
+    }
+    accounts.push(AccountMeta::new_readonly(config_pda(), false));
+    instruction(
+        accounts,
+        DataStoreInstruction::CloseDataStoreBatch(CloseDataStoreBatchArgs {
+            debug: false,
+            force,
+        }),
+    )
+}
+
+/// Processes instructions in a transaction whose fee signer pays, instead of the
+/// context payer
+async fn process_paid_by(
+    context: &mut ProgramTestContext,
+    instructions: &[Instruction],
+    signer: &Keypair,
+) -> Result<(), BanksClientError> {
+    let blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let transaction = Transaction::new_signed_with_payer(
+        instructions,
+        Some(&signer.pubkey()),
+        &[signer],
+        blockhash,
+    );
+    context.banks_client.process_transaction(transaction).await
+}
+
+async fn balance(context: &mut ProgramTestContext, account: &Pubkey) -> u64 {
+    context.banks_client.get_balance(*account).await.unwrap()
+}
+
+async fn exists(context: &mut ProgramTestContext, account: &Pubkey) -> bool {
+    context
+        .banks_client
+        .get_account(*account)
+        .await
+        .unwrap()
+        .is_some()
+}
+
+#[tokio::test]
+async fn unfinalized_close_requires_force() {
+    let mut context = start().await;
+    let data_account = initialize(&mut context, |args| args).await.pubkey();
+    let pda = metadata_pda(&data_account).0;
+    let payer = context.payer.pubkey();
+
+    let result = process(&mut context, &[close_ix(&payer, &data_account, false)], &[]).await;
+    assert_error(result, DataStoreError::NotFinalized);
+    assert!(exists(&mut context, &data_account).await);
+    assert!(exists(&mut context, &pda).await);
+
+    process(&mut context, &[close_ix(&payer, &data_account, true)], &[])
+        .await
+        .unwrap();
+    assert!(!exists(&mut context, &data_account).await);
+    assert!(!exists(&mut context, &pda).await);
+}
+
+#[tokio::test]
+async fn finalized_close_needs_no_force() {
+    let mut context = start().await;
+    let data_account = initialize(&mut context, |args| args).await.pubkey();
+    let payer = context.payer.pubkey();
+
+    process(
+        &mut context,
+        &[
+            finalize_ix(&payer, &data_account),
+            close_ix(&payer, &data_account, false),
+        ],
+        &[],
+    )
+    .await
+    .unwrap();
+    assert!(!exists(&mut context, &data_account).await);
+    assert!(!exists(&mut context, &metadata_pda(&data_account).0).await);
+}
+
+#[tokio::test]
+async fn close_refunds_recipient() {
+    let mut context = start().await;
+    let data_account = initialize(&mut context, |args| args).await.pubkey();
+    let pda = metadata_pda(&data_account).0;
+    let payer = context.payer.pubkey();
+    let recipient = Pubkey::new_unique();
+
+    let refund = balance(&mut context, &data_account).await + balance(&mut context, &pda).await;
+    let payer_balance = balance(&mut context, &payer).await;
+
+    process(
+        &mut context,
+        &[close_to_ix(&payer, &data_account, &recipient)],
+        &[],
+    )
+    .await
+    .unwrap();
+    assert!(!exists(&mut context, &data_account).await);
+    assert!(!exists(&mut context, &pda).await);
+    assert_eq!(balance(&mut context, &recipient).await, refund);
+    // The authority only paid the transaction fee
+    assert!(balance(&mut context, &payer).await < payer_balance);
+}
+
+#[tokio::test]
+async fn batch_closes_all_or_nothing() {
+    let mut context = start().await;
+    let payer = context.payer.pubkey();
+    let recipient = Pubkey::new_unique();
+    let mut data_accounts = Vec::new();
+    for _ in 0..3 {
+        data_accounts.push(initialize(&mut context, |args| args).await.pubkey());
+    }
+    let mut refund = 0;
+    for data_account in &data_accounts {
+        refund += balance(&mut context, data_account).await;
+        refund += balance(&mut context, &metadata_pda(data_account).0).await;
+    }
+
+    // The last data store is not finalized, so none is closed without force
+    process(
+        &mut context,
+        &[
+            finalize_ix(&payer, &data_accounts[0]),
+            finalize_ix(&payer, &data_accounts[1]),
+        ],
+        &[],
+    )
+    .await
+    .unwrap();
+    let result = process(
+        &mut context,
+        &[close_batch_ix(&payer, &recipient, &data_accounts, false)],
+        &[],
+    )
+    .await;
+    assert_error(result, DataStoreError::NotFinalized);
+    for data_account in &data_accounts {
+        assert!(exists(&mut context, data_account).await);
+        assert!(exists(&mut context, &metadata_pda(data_account).0).await);
+    }
+
+    process(
+        &mut context,
+        &[close_batch_ix(&payer, &recipient, &data_accounts, true)],
+        &[],
+    )
+    .await
+    .unwrap();
+    for data_account in &data_accounts {
+        assert!(!exists(&mut context, data_account).await);
+        assert!(!exists(&mut context, &metadata_pda(data_account).0).await);
+    }
+    assert_eq!(balance(&mut context, &recipient).await, refund);
+}
+
+#[tokio::test]
+async fn expired_data_store_is_closed_by_anyone_for_its_creator() {
+    let mut context = start().await;
+    let payer = context.payer.pubkey();
+    let stranger = Keypair::new();
+    let expiry_slot = context.banks_client.get_root_slot().await.unwrap() + 10;
+    let expiring = |args| InitializeDataStoreArgs {
+        expiry_slot: Some(expiry_slot),
+        ..args
+    };
+    let closed_early = initialize(&mut context, expiring).await.pubkey();
+    let closed_late = initialize(&mut context, expiring).await.pubkey();
+
+    // Before expiry only the authority closes
+    let result = process(
+        &mut context,
+        &[close_to_ix(&stranger.pubkey(), &closed_late, &payer)],
+        &[&stranger],
+    )
+    .await;
+    assert_error(result, DataStoreError::InvalidAuthority);
+    process(&mut context, &[close_ix(&payer, &closed_early, true)], &[])
+        .await
+        .unwrap();
+    assert!(!exists(&mut context, &closed_early).await);
+
+    context.warp_to_slot(expiry_slot + 1).unwrap();
+
+    // After expiry anyone closes, refunding the creator only. The stranger pays its own
+    // fees, so the creator receives exactly the refund.
+    let funding = 1_000_000_000;
+    process(
+        &mut context,
+        &[system_instruction::transfer(
+            &payer,
+            &stranger.pubkey(),
+            funding,
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+    let result = process_paid_by(
+        &mut context,
+        &[close_to_ix(
+            &stranger.pubkey(),
+            &closed_late,
+            &stranger.pubkey(),
+        )],
+        &stranger,
+    )
+    .await;
+    assert_error(result, DataStoreError::InvalidAuthority);
+    let pda = metadata_pda(&closed_late).0;
+    let refund = balance(&mut context, &closed_late).await + balance(&mut context, &pda).await;
+    let payer_balance = balance(&mut context, &payer).await;
+    process_paid_by(
+        &mut context,
+        &[close_to_ix(&stranger.pubkey(), &closed_late, &payer)],
+        &stranger,
+    )
+    .await
+    .unwrap();
+    assert!(!exists(&mut context, &closed_late).await);
+    assert!(!exists(&mut context, &pda).await);
+    assert_eq!(balance(&mut context, &payer).await, payer_balance + refund);
+    assert!(balance(&mut context, &stranger.pubkey()).await < funding);
+}
+
+#[tokio::test]
+async fn same_account_passed_twice_is_duplicate_account() {
+    let mut context = start().await;
+    let data_account = initialize(&mut context, |args| args).await.pubkey();
+    let pda = metadata_pda(&data_account).0;
+    let payer = context.payer.pubkey();
+    let refund = balance(&mut context, &data_account).await + balance(&mut context, &pda).await;
+
+    // The data account standing in for its metadata account, in close, update and finalize
+    let mut aliased_ixs = vec![
+        close_ix(&payer, &data_account, true),
+        update_ix(&payer, &data_account, update_args(b"hello", 0)),
+        finalize_ix(&payer, &data_account),
+    ];
+    for aliased_ix in &mut aliased_ixs {
+        aliased_ix.accounts[2] = AccountMeta::new(data_account, false);
+    }
+    // And either of them standing in for the recipient
+    aliased_ixs.push(close_to_ix(&payer, &data_account, &data_account));
+    aliased_ixs.push(close_to_ix(&payer, &data_account, &pda));
+    for aliased_ix in aliased_ixs {
+        let result = process(&mut context, &[aliased_ix], &[]).await;
+        assert_error(result, DataStoreError::DuplicateAccount);
+    }
+
+    assert_eq!(
+        balance(&mut context, &data_account).await + balance(&mut context, &pda).await,
+        refund
+    );
+    process(&mut context, &[close_ix(&payer, &data_account, true)], &[])
+        .await
+        .unwrap();
+    assert!(!exists(&mut context, &data_account).await);
+}
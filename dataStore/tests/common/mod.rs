@@ -0,0 +1,443 @@
+#![allow(dead_code)]
+
+use dataaccount::{
+    error::DataStoreError,
+    instruction::DataStoreInstruction,
+    processor::Processor,
+    state::{
+        compute_content_hash, derive_content_pda, metadata_pda_seeds, CloseDataStoreArgs,
+        DataStoreAccountMetadata, DataStoreTypeOption, FinalizeDataStoreArgs,
+        InitializeDataStoreArgs, ReclaimRentArgs, SetMetadataFlagsArgs, UpdateDataStoreArgs,
+        ALLOWLIST_SEED, CONFIG_SEED, NAMESPACE_LEN,
+    },
+};
+use solana_program_test::{processor, BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    instruction::{AccountMeta, Instruction, InstructionError},
+    program::{get_return_data, invoke_signed, set_return_data},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_program,
+    transaction::{Transaction, TransactionError},
+};
+
+pub fn program_id() -> Pubkey {
+    Pubkey::new_from_array([7; 32])
+}
+
+/// Program forwarding its instruction to the data program, see forward
+pub fn caller_program_id() -> Pubkey {
+    Pubkey::new_from_array([8; 32])
+}
+
+/// PDA of the caller program, signing the instructions it forwards
+pub fn caller_pda() -> Pubkey {
+    Pubkey::find_program_address(&[CALLER_SEED], &caller_program_id()).0
+}
+
+pub const CALLER_SEED: &[u8] = b"caller";
+
+/// Processor of the caller program. Invokes the program of the first account with the
+/// remaining accounts and the instruction data as is, signing for the caller PDA, then
+/// reads the return data of the callee and returns it as its own.
+fn forward(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let (callee, callee_accounts) = accounts
+        .split_first()
+        .ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let (pda, bump_seed) = Pubkey::find_program_address(&[CALLER_SEED], program_id);
+    let instruction = Instruction {
+        program_id: *callee.key,
+        accounts: callee_accounts
+            .iter()
+            .map(|account| AccountMeta {
+                pubkey: *account.key,
+                is_signer: account.is_signer || *account.key == pda,
+                is_writable: account.is_writable,
+            })
+            .collect(),
+        data: data.to_vec(),
+    };
+    invoke_signed(&instruction, accounts, &[&[CALLER_SEED, &[bump_seed]]])?;
+
+    // Native program tests report the empty return data of the last program invoked,
+    // such as the system program, where the runtime reports none
+    if let Some((returning_program, return_data)) =
+        get_return_data().filter(|(_, return_data)| !return_data.is_empty())
+    {
+        if returning_program != *callee.key {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        set_return_data(&return_data);
+    }
+    Ok(())
+}
+
+/// Wraps instruction of the data program into one of the caller program forwarding it
+pub fn cpi_ix(instruction: Instruction) -> Instruction {
+    let mut accounts = vec![AccountMeta::new_readonly(instruction.program_id, false)];
+    accounts.extend(instruction.accounts.into_iter().map(|mut account| {
+        // The caller PDA can not sign the transaction, the caller program signs for it
+        if account.pubkey == caller_pda() {
+            account.is_signer = false;
+        }
+        account
+    }));
+    Instruction {
+        program_id: caller_program_id(),
+        accounts,
+        data: instruction.data,
+    }
+}
+
+pub async fn start() -> ProgramTestContext {
+    let mut program_test = ProgramTest::new(
+        "dataaccount",
+        program_id(),
+        processor!(Processor::process_instruction),
+    );
+    program_test.add_program("caller", caller_program_id(), processor!(forward));
+    let context = program_test.start_with_context().await;
+
+    #[cfg(feature = "permissioned-init")]
+    let context = allowlist_payer(context).await;
+
+    context
+}
+
+/// Lists the context payer in the allowlist PDA, as programs built with
+/// permissioned-init only let allowlisted feepayers initialize. The payer funds the
+/// PDA, keeping the bank capitalization, which warp_to_slot verifies, unchanged.
+#[cfg(feature = "permissioned-init")]
+async fn allowlist_payer(mut context: ProgramTestContext) -> ProgramTestContext {
+    use dataaccount::state::{Allowlist, ALLOWLIST_SIZE};
+    use solana_sdk::{account::Account, rent::Rent};
+
+    let mut data = borsh::to_vec(&Allowlist {
+        authority: context.payer.pubkey(),
+        members: vec![context.payer.pubkey()],
+    })
+    .unwrap();
+    data.resize(ALLOWLIST_SIZE, 0);
+    let lamports = Rent::default().minimum_balance(ALLOWLIST_SIZE);
+    let account = Account {
+        lamports,
+        data,
+        owner: program_id(),
+        executable: false,
+        rent_epoch: 0,
+    };
+    context.set_account(&allowlist_pda(), &account.into());
+
+    let payer = context.payer.pubkey();
+    let mut payer_account = context
+        .banks_client
+        .get_account(payer)
+        .await
+        .unwrap()
+        .unwrap();
+    payer_account.lamports -= lamports;
+    context.set_account(&payer, &payer_account.into());
+    context
+}
+
+pub fn allowlist_pda() -> Pubkey {
+    Pubkey::find_program_address(&[ALLOWLIST_SEED], &program_id()).0
+}
+
+pub fn config_pda() -> Pubkey {
+    Pubkey::find_program_address(&[CONFIG_SEED], &program_id()).0
+}
+
+pub fn metadata_pda(data_account: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &metadata_pda_seeds(data_account, &[0; NAMESPACE_LEN]),
+        &program_id(),
+    )
+}
+
+/// Arguments of a static File data store of space bytes, owned by authority
+pub fn init_args(data_account: &Pubkey, authority: &Pubkey, space: u64) -> InitializeDataStoreArgs {
+    InitializeDataStoreArgs {
+        debug: false,
+        data_type: DataStoreTypeOption::File,
+        bump_seed: metadata_pda(data_account).1,
+        is_created: false,
+        space,
+        authority: *authority,
+        is_dynamic: false,
+        initial_data: vec![],
+        append_only: false,
+        namespace: [0; NAMESPACE_LEN],
+        idempotent: false,
+        private: false,
+        content_type: String::new(),
+        strict_content_type: false,
+        expected_total: 0,
+        min_update_interval_slots: 0,
+        expiry_slot: None,
+        max_revisions: None,
+        audit: false,
+        file_name: String::new(),
+        zero_on_shrink: true,
+        cache_ttl_seconds: 0,
+        indexable: true,
+        stream_hash: false,
+    }
+}
+
+/// Arguments writing data at offset of a File data store
+pub fn update_args(data: &[u8], offset: u64) -> UpdateDataStoreArgs {
+    UpdateDataStoreArgs {
+        debug: false,
+        data_hash: [0; 32],
+        data: data.to_vec(),
+        offset,
+        realloc_down: false,
+        data_type: DataStoreTypeOption::File,
+        expected_revision: None,
+        allow_gaps: false,
+        expected_hash: None,
+    }
+}
+
+pub fn instruction(accounts: Vec<AccountMeta>, instruction: DataStoreInstruction) -> Instruction {
+    Instruction {
+        program_id: program_id(),
+        accounts,
+        data: borsh::to_vec(&instruction).unwrap(),
+    }
+}
+
+/// Accounts of the instructions creating a keypair data store, such as InitializeDataStore
+/// and CreateSymlink, up to the config PDA
+pub fn create_accounts(feepayer: &Pubkey, data_account: &Pubkey) -> Vec<AccountMeta> {
+    let mut accounts = vec![
+        AccountMeta::new(*feepayer, true),
+        AccountMeta::new(*data_account, true),
+        AccountMeta::new(metadata_pda(data_account).0, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+    if cfg!(feature = "permissioned-init") {
+        accounts.push(AccountMeta::new_readonly(allowlist_pda(), false));
+    }
+    accounts.push(AccountMeta::new_readonly(config_pda(), false));
+    accounts
+}
+
+pub fn initialize_ix(
+    feepayer: &Pubkey,
+    data_account: &Pubkey,
+    args: InitializeDataStoreArgs,
+) -> Instruction {
+    instruction(
+        create_accounts(feepayer, data_account),
+        DataStoreInstruction::InitializeDataStore(args),
+    )
+}
+
+pub fn update_ix(
+    authority: &Pubkey,
+    data_account: &Pubkey,
+    args: UpdateDataStoreArgs,
+) -> Instruction {
+    instruction(
+        vec![
+            AccountMeta::new(*authority, true),
+            AccountMeta::new(*data_account, false),
+            AccountMeta::new(metadata_pda(data_account).0, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(config_pda(), false),
+        ],
+        DataStoreInstruction::UpdateDataStore(args),
+    )
+}
+
+pub fn reclaim_rent_ix(authority: &Pubkey, data_account: &Pubkey) -> Instruction {
+    let mut reclaim_ix = update_ix(authority, data_account, update_args(&[], 0));
+    reclaim_ix.data = borsh::to_vec(&DataStoreInstruction::ReclaimRent(ReclaimRentArgs {
+        debug: false,
+    }))
+    .unwrap();
+    reclaim_ix
+}
+
+pub fn finalize_ix(authority: &Pubkey, data_account: &Pubkey) -> Instruction {
+    instruction(
+        vec![
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new_readonly(*data_account, false),
+            AccountMeta::new(metadata_pda(data_account).0, false),
+            AccountMeta::new_readonly(config_pda(), false),
+        ],
+        DataStoreInstruction::FinalizeDataStore(FinalizeDataStoreArgs {
+            debug: false,
+            lock_authority: false,
+        }),
+    )
+}
+
+/// Content PDA of data
+pub fn content_pda(data: &[u8]) -> Pubkey {
+    derive_content_pda(&program_id(), &compute_content_hash(data)).0
+}
+
+/// FinalizeDataStore also registering data, the contents of data_account, in its
+/// content PDA paid for by authority
+pub fn finalize_register_ix(authority: &Pubkey, data_account: &Pubkey, data: &[u8]) -> Instruction {
+    let mut finalize_ix = finalize_ix(authority, data_account);
+    finalize_ix.accounts[0].is_writable = true;
+    finalize_ix.accounts.splice(
+        3..3,
+        [
+            AccountMeta::new(content_pda(data), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    );
+    finalize_ix
+}
+
+pub fn close_ix(authority: &Pubkey, data_account: &Pubkey, force: bool) -> Instruction {
+    instruction(
+        vec![
+            AccountMeta::new(*authority, true),
+            AccountMeta::new(*data_account, false),
+            AccountMeta::new(metadata_pda(data_account).0, false),
+            AccountMeta::new_readonly(config_pda(), false),
+        ],
+        DataStoreInstruction::CloseDataStore(CloseDataStoreArgs {
+            debug: false,
+            force,
+        }),
+    )
+}
+
+pub fn set_flags_ix(
+    authority: &Pubkey,
+    data_account: &Pubkey,
+    mask: u32,
+    values: u32,
+) -> Instruction {
+    instruction(
+        vec![
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new_readonly(*data_account, false),
+            AccountMeta::new(metadata_pda(data_account).0, false),
+            AccountMeta::new_readonly(config_pda(), false),
+        ],
+        DataStoreInstruction::SetMetadataFlags(SetMetadataFlagsArgs {
+            debug: false,
+            mask,
+            values,
+        }),
+    )
+}
+
+/// Sends instructions in a transaction paid for by the context payer, with a fresh
+/// blockhash so identical transactions are not deduplicated
+pub async fn process(
+    context: &mut ProgramTestContext,
+    instructions: &[Instruction],
+    signers: &[&Keypair],
+) -> Result<(), BanksClientError> {
+    let blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let mut all_signers = vec![&context.payer];
+    all_signers.extend_from_slice(signers);
+    let transaction = Transaction::new_signed_with_payer(
+        instructions,
+        Some(&context.payer.pubkey()),
+        &all_signers,
+        blockhash,
+    );
+    context.banks_client.process_transaction(transaction).await
+}
+
+/// Initializes a keypair data store of the context payer from args, returning its data account
+pub async fn initialize(
+    context: &mut ProgramTestContext,
+    args: impl FnOnce(InitializeDataStoreArgs) -> InitializeDataStoreArgs,
+) -> Keypair {
+    let data_account = Keypair::new();
+    let payer = context.payer.pubkey();
+    let args = args(init_args(&data_account.pubkey(), &payer, 64));
+    process(
+        context,
+        &[initialize_ix(&payer, &data_account.pubkey(), args)],
+        &[&data_account],
+    )
+    .await
+    .unwrap();
+    data_account
+}
+
+/// Like process, returning the return data of the transaction and the compute units
+/// it consumed
+pub async fn process_with_metadata(
+    context: &mut ProgramTestContext,
+    instructions: &[Instruction],
+    signers: &[&Keypair],
+) -> Result<(Option<Vec<u8>>, u64), BanksClientError> {
+    let blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let mut all_signers = vec![&context.payer];
+    all_signers.extend_from_slice(signers);
+    let transaction = Transaction::new_signed_with_payer(
+        instructions,
+        Some(&context.payer.pubkey()),
+        &all_signers,
+        blockhash,
+    );
+    let outcome = context
+        .banks_client
+        .process_transaction_with_metadata(transaction)
+        .await?;
+    outcome.result.map_err(BanksClientError::TransactionError)?;
+    let metadata = outcome.metadata.unwrap();
+    Ok((
+        metadata.return_data.map(|return_data| return_data.data),
+        metadata.compute_units_consumed,
+    ))
+}
+
+/// Runs a read-only instruction, returning the data it returned
+pub async fn query(
+    context: &mut ProgramTestContext,
+    instruction: Instruction,
+) -> Result<Vec<u8>, BanksClientError> {
+    let (return_data, _) = process_with_metadata(context, &[instruction], &[]).await?;
+    Ok(return_data.unwrap_or_default())
+}
+
+pub async fn update(
+    context: &mut ProgramTestContext,
+    data_account: &Pubkey,
+    args: UpdateDataStoreArgs,
+) -> Result<(), BanksClientError> {
+    let payer = context.payer.pubkey();
+    process(context, &[update_ix(&payer, data_account, args)], &[]).await
+}
+
+pub async fn metadata(
+    context: &mut ProgramTestContext,
+    data_account: &Pubkey,
+) -> DataStoreAccountMetadata {
+    let account = context
+        .banks_client
+        .get_account(metadata_pda(data_account).0)
+        .await
+        .unwrap()
+        .unwrap();
+    DataStoreAccountMetadata::load(&account.data).unwrap()
+}
+
+/// Asserts the first instruction of a transaction failed with error
+pub fn assert_error(result: Result<(), BanksClientError>, error: DataStoreError) {
+    match result {
+        Err(BanksClientError::TransactionError(TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(code),
+        ))) => assert_eq!(code, error.clone() as u32, "expected {:?}", error),
+        other => panic!("expected {:?}, got {:?}", error, other),
+    }
+}
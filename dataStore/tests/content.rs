@@ -0,0 +1,157 @@
+mod common;
+
+use common::{
+    assert_error, config_pda, content_pda, finalize_register_ix, init_args, initialize,
+    initialize_ix, instruction, metadata, metadata_pda, process, program_id, start,
+};
+use dataaccount::{
+    error::DataStoreError,
+    instruction::DataStoreInstruction,
+    state::{InitializeDataStoreArgs, SetContentTypeArgs, FLAG_CONTENT_REGISTERED},
+};
+use solana_program::hash::hashv;
+use solana_program_test::tokio;
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+};
+
+const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+const JPEG: &[u8] = b"\xff\xd8\xff\xe0\0\x10JFIF";
+
+fn set_content_type_ix(
+    authority: &Pubkey,
+    data_account: &Pubkey,
+    content_type: &str,
+) -> Instruction {
+    instruction(
+        vec![
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new_readonly(*data_account, false),
+            AccountMeta::new(metadata_pda(data_account).0, false),
+            AccountMeta::new_readonly(config_pda(), false),
+        ],
+        DataStoreInstruction::SetContentType(SetContentTypeArgs {
+            debug: false,
+            content_type: content_type.to_string(),
+        }),
+    )
+}
+
+/// Arguments of a strict data store of content_type holding initial_data
+fn strict_args(
+    content_type: &str,
+    initial_data: &[u8],
+) -> impl FnOnce(InitializeDataStoreArgs) -> InitializeDataStoreArgs {
+    let content_type = content_type.to_string();
+    let initial_data = initial_data.to_vec();
+    move |args| InitializeDataStoreArgs {
+        content_type,
+        strict_content_type: true,
+        initial_data,
+        ..args
+    }
+}
+
+#[tokio::test]
+async fn strict_content_type_matches_magic_number() {
+    let mut context = start().await;
+    let payer = context.payer.pubkey();
+
+    let png = initialize(&mut context, strict_args("image/png", PNG))
+        .await
+        .pubkey();
+    assert_eq!(
+        metadata(&mut context, &png).await.content_type(),
+        "image/png"
+    );
+
+    // A JPEG labeled as PNG is rejected at initialization
+    let data_account = Keypair::new();
+    let args = strict_args("image/png", JPEG)(init_args(&data_account.pubkey(), &payer, 64));
+    let result = process(
+        &mut context,
+        &[initialize_ix(&payer, &data_account.pubkey(), args)],
+        &[&data_account],
+    )
+    .await;
+    assert_error(result, DataStoreError::DataVerificationFailed);
+
+    // And when relabeling a strict data store
+    let jpeg = initialize(&mut context, strict_args("image/jpeg", JPEG))
+        .await
+        .pubkey();
+    let result = process(
+        &mut context,
+        &[set_content_type_ix(&payer, &jpeg, "image/png")],
+        &[],
+    )
+    .await;
+    assert_error(result, DataStoreError::DataVerificationFailed);
+    assert_eq!(
+        metadata(&mut context, &jpeg).await.content_type(),
+        "image/jpeg"
+    );
+
+    // Unknown types carry no magic number to check
+    process(
+        &mut context,
+        &[set_content_type_ix(&payer, &png, "text/plain")],
+        &[],
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        metadata(&mut context, &png).await.content_type(),
+        "text/plain"
+    );
+}
+
+#[tokio::test]
+async fn content_lookup_pda_maps_to_the_first_registrant() {
+    let mut context = start().await;
+    let payer = context.payer.pubkey();
+    let content = b"same content";
+    let with_content = |args| InitializeDataStoreArgs {
+        initial_data: content.to_vec(),
+        ..args
+    };
+    let first = initialize(&mut context, with_content).await.pubkey();
+    let second = initialize(&mut context, with_content).await.pubkey();
+
+    // Clients derive the lookup PDA from the SHA-256 of the content alone
+    let (lookup, _) =
+        Pubkey::find_program_address(&[b"content", &hashv(&[content]).to_bytes()], &program_id());
+    assert_eq!(lookup, content_pda(content));
+    assert!(context
+        .banks_client
+        .get_account(lookup)
+        .await
+        .unwrap()
+        .is_none());
+
+    for data_account in [first, second] {
+        process(
+            &mut context,
+            &[finalize_register_ix(&payer, &data_account, content)],
+            &[],
+        )
+        .await
+        .unwrap();
+    }
+    let account = context
+        .banks_client
+        .get_account(lookup)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(account.owner, program_id());
+    assert_eq!(account.data, first.to_bytes());
+    assert!(metadata(&mut context, &first)
+        .await
+        .has_flag(FLAG_CONTENT_REGISTERED));
+    assert!(!metadata(&mut context, &second)
+        .await
+        .has_flag(FLAG_CONTENT_REGISTERED));
+}
@@ -0,0 +1,732 @@
+mod common;
+
+use common::{
+    assert_error, config_pda, create_accounts, finalize_ix, initialize, instruction, metadata,
+    metadata_pda, process, process_with_metadata, program_id, query, set_flags_ix, start, update,
+    update_args,
+};
+use dataaccount::{
+    error::DataStoreError,
+    instruction::DataStoreInstruction,
+    state::{
+        derive_file_pda, derive_headers_pda, encode_named_entry, parse_named_entries,
+        verify_directory_data, AddDirectoryEntryArgs, ConcatDataArgs, DataStoreTypeOption,
+        DirectoryFormat, GetDirectoryEntriesArgs, GetOrCreateDirectoryArgs,
+        InitializeDataStoreArgs, InitializeDirectoryArgs, MoveEntryArgs, ReinitializeArgs,
+        SetNameArgs, UpdateDataStoreArgs, DIRECTORY_ENTRY_SIZE, FLAG_APPEND_ONLY, FLAG_FROZEN,
+        MAX_DIRECTORY_ENTRIES_PER_READ, MAX_ENTRY_NAME_LEN, NAMESPACE_LEN,
+    },
+};
+use solana_program_test::{tokio, BanksClientError, ProgramTestContext};
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_program,
+};
+
+/// Creates a directory of the context payer with space bytes, returning its data account
+async fn create_directory(
+    context: &mut ProgramTestContext,
+    space: u64,
+    max_entries: u32,
+    directory_format: DirectoryFormat,
+) -> Pubkey {
+    let data_account = Keypair::new();
+    let payer = context.payer.pubkey();
+    let create_ix = instruction(
+        create_accounts(&payer, &data_account.pubkey()),
+        DataStoreInstruction::InitializeDirectory(InitializeDirectoryArgs {
+            debug: false,
+            bump_seed: metadata_pda(&data_account.pubkey()).1,
+            is_created: false,
+            space,
+            authority: payer,
+            namespace: [0; NAMESPACE_LEN],
+            max_entries,
+            directory_format,
+        }),
+    );
+    process(context, &[create_ix], &[&data_account])
+        .await
+        .unwrap();
+    data_account.pubkey()
+}
+
+fn add_entry_ix(authority: &Pubkey, directory: &Pubkey, child: Pubkey, name: &str) -> Instruction {
+    instruction(
+        vec![
+            AccountMeta::new(*authority, true),
+            AccountMeta::new(*directory, false),
+            AccountMeta::new(metadata_pda(directory).0, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(config_pda(), false),
+        ],
+        DataStoreInstruction::AddDirectoryEntry(AddDirectoryEntryArgs {
+            debug: false,
+            child,
+            name: name.to_string(),
+        }),
+    )
+}
+
+async fn add_entry(
+    context: &mut ProgramTestContext,
+    directory: &Pubkey,
+    child: Pubkey,
+    name: &str,
+) -> Result<(), BanksClientError> {
+    let payer = context.payer.pubkey();
+    process(
+        context,
+        &[add_entry_ix(&payer, directory, child, name)],
+        &[],
+    )
+    .await
+}
+
+/// Adds unnamed children to directory, several per transaction
+async fn add_entries(context: &mut ProgramTestContext, directory: &Pubkey, children: &[Pubkey]) {
+    let payer = context.payer.pubkey();
+    for chunk in children.chunks(8) {
+        let add_ixs: Vec<Instruction> = chunk
+            .iter()
+            .map(|child| add_entry_ix(&payer, directory, *child, ""))
+            .collect();
+        process(context, &add_ixs, &[]).await.unwrap();
+    }
+}
+
+async fn get_entries(
+    context: &mut ProgramTestContext,
+    directory: &Pubkey,
+    offset: u32,
+    count: u32,
+) -> Vec<u8> {
+    let get_ix = instruction(
+        vec![
+            AccountMeta::new_readonly(*directory, false),
+            AccountMeta::new_readonly(metadata_pda(directory).0, false),
+        ],
+        DataStoreInstruction::GetDirectoryEntries(GetDirectoryEntriesArgs {
+            debug: false,
+            offset,
+            count,
+        }),
+    );
+    query(context, get_ix).await.unwrap()
+}
+
+fn move_entry_ix(
+    authority: &Pubkey,
+    source: &Pubkey,
+    destination: &Pubkey,
+    child: Pubkey,
+) -> Instruction {
+    instruction(
+        vec![
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new(*source, false),
+            AccountMeta::new(metadata_pda(source).0, false),
+            AccountMeta::new(*authority, true),
+            AccountMeta::new(*destination, false),
+            AccountMeta::new(metadata_pda(destination).0, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(config_pda(), false),
+        ],
+        DataStoreInstruction::MoveEntry(MoveEntryArgs {
+            debug: false,
+            child,
+        }),
+    )
+}
+
+async fn data(context: &mut ProgramTestContext, account: &Pubkey) -> Vec<u8> {
+    let account = context
+        .banks_client
+        .get_account(*account)
+        .await
+        .unwrap()
+        .unwrap();
+    account.data
+}
+
+#[tokio::test]
+async fn dynamic_directory_grows_past_initial_space() {
+    let mut context = start().await;
+    let directory = create_directory(
+        &mut context,
+        10 * DIRECTORY_ENTRY_SIZE as u64,
+        64,
+        DirectoryFormat::PackedPubkeys,
+    )
+    .await;
+
+    let children: Vec<Pubkey> = (0..40).map(|_| Pubkey::new_unique()).collect();
+    for child in &children {
+        add_entry(&mut context, &directory, *child, "")
+            .await
+            .unwrap();
+    }
+
+    let account_metadata = metadata(&mut context, &directory).await;
+    assert_eq!(account_metadata.data_len(), 40 * DIRECTORY_ENTRY_SIZE);
+    assert_eq!(account_metadata.space(), 40 * DIRECTORY_ENTRY_SIZE);
+    let entries = data(&mut context, &directory).await;
+    assert_eq!(entries.len(), 40 * DIRECTORY_ENTRY_SIZE);
+    for (entry, child) in entries.chunks_exact(DIRECTORY_ENTRY_SIZE).zip(&children) {
+        assert_eq!(entry, child.as_ref());
+    }
+}
+
+#[tokio::test]
+async fn entries_are_listed_in_pages() {
+    let mut context = start().await;
+    let directory = create_directory(&mut context, 0, 64, DirectoryFormat::PackedPubkeys).await;
+    let children: Vec<Pubkey> = (0..50).map(|_| Pubkey::new_unique()).collect();
+    add_entries(&mut context, &directory, &children).await;
+
+    // A page is capped by the return data size, whatever count asks for
+    let first_page = get_entries(&mut context, &directory, 0, 50).await;
+    assert_eq!(
+        first_page.len(),
+        MAX_DIRECTORY_ENTRIES_PER_READ * DIRECTORY_ENTRY_SIZE
+    );
+    let next = (first_page.len() / DIRECTORY_ENTRY_SIZE) as u32;
+    let second_page = get_entries(&mut context, &directory, next, 50).await;
+    assert_eq!(
+        second_page.len(),
+        (50 - next as usize) * DIRECTORY_ENTRY_SIZE
+    );
+
+    let listed: Vec<Pubkey> = [first_page, second_page]
+        .concat()
+        .chunks_exact(DIRECTORY_ENTRY_SIZE)
+        .map(|entry| Pubkey::try_from(entry).unwrap())
+        .collect();
+    assert_eq!(listed, children);
+
+    // Past the end the page is empty
+    assert!(get_entries(&mut context, &directory, 50, 10)
+        .await
+        .is_empty());
+}
+
+#[tokio::test]
+async fn append_only_source_keeps_its_entries() {
+    let mut context = start().await;
+    let source = create_directory(&mut context, 0, 8, DirectoryFormat::PackedPubkeys).await;
+    let destination = create_directory(&mut context, 0, 8, DirectoryFormat::PackedPubkeys).await;
+    let children = [Pubkey::new_unique(), Pubkey::new_unique()];
+    add_entries(&mut context, &source, &children).await;
+
+    let payer = context.payer.pubkey();
+    process(
+        &mut context,
+        &[set_flags_ix(
+            &payer,
+            &source,
+            FLAG_APPEND_ONLY,
+            FLAG_APPEND_ONLY,
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    let result = process(
+        &mut context,
+        &[move_entry_ix(&payer, &source, &destination, children[0])],
+        &[],
+    )
+    .await;
+    assert_error(result, DataStoreError::AppendOnlyViolation);
+    assert_eq!(
+        data(&mut context, &source).await,
+        [children[0].to_bytes(), children[1].to_bytes()].concat()
+    );
+    assert_eq!(metadata(&mut context, &destination).await.data_len(), 0);
+}
+
+#[tokio::test]
+async fn entry_moves_between_directories() {
+    let mut context = start().await;
+    let source = create_directory(&mut context, 0, 8, DirectoryFormat::PackedPubkeys).await;
+    let destination = create_directory(&mut context, 0, 8, DirectoryFormat::PackedPubkeys).await;
+    let children = [
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    ];
+    add_entries(&mut context, &source, &children[..2]).await;
+    add_entries(&mut context, &destination, &children[2..]).await;
+
+    let payer = context.payer.pubkey();
+    process(
+        &mut context,
+        &[move_entry_ix(&payer, &source, &destination, children[0])],
+        &[],
+    )
+    .await
+    .unwrap();
+    // The last source entry takes the place of the moved one, the freed one is zeroed
+    assert_eq!(
+        data(&mut context, &source).await,
+        [children[1].to_bytes(), [0; 32]].concat()
+    );
+    assert_eq!(metadata(&mut context, &source).await.data_len(), 32);
+    assert_eq!(
+        data(&mut context, &destination).await,
+        [children[2].to_bytes(), children[0].to_bytes()].concat()
+    );
+    assert_eq!(metadata(&mut context, &destination).await.data_len(), 64);
+
+    // The child is no longer in the source, and only once in the destination
+    let result = process(
+        &mut context,
+        &[move_entry_ix(&payer, &source, &destination, children[0])],
+        &[],
+    )
+    .await;
+    assert_error(result, DataStoreError::EntryNotFound);
+    let result = process(
+        &mut context,
+        &[move_entry_ix(&payer, &destination, &source, children[1])],
+        &[],
+    )
+    .await;
+    assert_error(result, DataStoreError::EntryNotFound);
+}
+
+#[tokio::test]
+async fn full_directory_rejects_entries() {
+    let mut context = start().await;
+    let directory = create_directory(&mut context, 0, 2, DirectoryFormat::PackedPubkeys).await;
+    let children = [Pubkey::new_unique(), Pubkey::new_unique()];
+    add_entries(&mut context, &directory, &children).await;
+
+    let result = add_entry(&mut context, &directory, Pubkey::new_unique(), "").await;
+    assert_error(result, DataStoreError::DirectoryFull);
+    assert_eq!(
+        data(&mut context, &directory).await,
+        [children[0].to_bytes(), children[1].to_bytes()].concat()
+    );
+
+    // Nor can one be moved in
+    let source = create_directory(&mut context, 0, 2, DirectoryFormat::PackedPubkeys).await;
+    let child = Pubkey::new_unique();
+    add_entries(&mut context, &source, &[child]).await;
+    let payer = context.payer.pubkey();
+    let result = process(
+        &mut context,
+        &[move_entry_ix(&payer, &source, &directory, child)],
+        &[],
+    )
+    .await;
+    assert_error(result, DataStoreError::DirectoryFull);
+    assert_eq!(metadata(&mut context, &source).await.data_len(), 32);
+}
+
+#[tokio::test]
+async fn frozen_directory_rejects_entries() {
+    let mut context = start().await;
+    let directory = create_directory(&mut context, 64, 2, DirectoryFormat::PackedPubkeys).await;
+    let payer = context.payer.pubkey();
+    process(
+        &mut context,
+        &[set_flags_ix(&payer, &directory, FLAG_FROZEN, FLAG_FROZEN)],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    let result = add_entry(&mut context, &directory, Pubkey::new_unique(), "").await;
+    assert_error(result, DataStoreError::Frozen);
+    assert_eq!(metadata(&mut context, &directory).await.data_len(), 0);
+}
+
+#[test]
+fn each_directory_format_verifies_its_data() {
+    use DirectoryFormat::*;
+
+    let child = Pubkey::new_unique();
+    let mut named = encode_named_entry(b"index.html", &child);
+    named.extend(encode_named_entry(b"", &child));
+    let cases: [(DirectoryFormat, &[u8], bool); 12] = [
+        (PackedPubkeys, &[], true),
+        (PackedPubkeys, &[1; DIRECTORY_ENTRY_SIZE * 2], true),
+        (PackedPubkeys, &[1; DIRECTORY_ENTRY_SIZE + 1], false),
+        (PackedPubkeys, &[1; DIRECTORY_ENTRY_SIZE - 1], false),
+        (
+            JsonManifest,
+            br#"{"index.html":"11111111111111111111111111111111"}"#,
+            true,
+        ),
+        (JsonManifest, b"{}", true),
+        (JsonManifest, b"", false),
+        (JsonManifest, br#"{"truncated":"#, false),
+        (JsonManifest, b"{\xff}", false),
+        (NamedEntries, &named, true),
+        (NamedEntries, &named[..named.len() - 1], false),
+        (NamedEntries, &[0xff; DIRECTORY_ENTRY_SIZE + 1], false),
+    ];
+    for (directory_format, data, is_valid) in cases {
+        assert_eq!(
+            verify_directory_data(data, &directory_format),
+            is_valid,
+            "{:?} {:?}",
+            directory_format,
+            data
+        );
+    }
+}
+
+#[tokio::test]
+async fn json_manifest_is_verified_on_finalize() {
+    let mut context = start().await;
+    let directory = create_directory(&mut context, 64, 2, DirectoryFormat::JsonManifest).await;
+    let payer = context.payer.pubkey();
+    let manifest_args = |data: &[u8], offset| UpdateDataStoreArgs {
+        data_type: DataStoreTypeOption::Directory,
+        ..update_args(data, offset)
+    };
+
+    // A manifest may be written in chunks, it is only verified once sealed
+    update(&mut context, &directory, manifest_args(br#"{"a":"#, 0))
+        .await
+        .unwrap();
+    let result = process(&mut context, &[finalize_ix(&payer, &directory)], &[]).await;
+    assert_error(result, DataStoreError::DataVerificationFailed);
+
+    update(&mut context, &directory, manifest_args(b"1}", 5))
+        .await
+        .unwrap();
+    process(&mut context, &[finalize_ix(&payer, &directory)], &[])
+        .await
+        .unwrap();
+    assert_eq!(&data(&mut context, &directory).await[..7], br#"{"a":1}"#);
+}
+
+#[tokio::test]
+async fn named_entries_are_listed_with_their_names() {
+    let mut context = start().await;
+    let directory = create_directory(&mut context, 256, 8, DirectoryFormat::NamedEntries).await;
+    let children = [
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    ];
+    let names = [
+        "readme.md".to_string(),
+        "src".to_string(),
+        "a".repeat(MAX_ENTRY_NAME_LEN),
+    ];
+    for (name, child) in names.iter().zip(children) {
+        add_entry(&mut context, &directory, child, name)
+            .await
+            .unwrap();
+    }
+
+    // Names are unique, non-empty and bounded
+    let result = add_entry(&mut context, &directory, Pubkey::new_unique(), "src").await;
+    assert_error(result, DataStoreError::EntryAlreadyExists);
+    for name in [String::new(), "a".repeat(MAX_ENTRY_NAME_LEN + 1)] {
+        let result = add_entry(&mut context, &directory, Pubkey::new_unique(), &name).await;
+        assert_error(result, DataStoreError::InvalidInstructionData);
+    }
+
+    let entries = get_entries(&mut context, &directory, 0, 8).await;
+    let entries = parse_named_entries(&entries).unwrap();
+    assert_eq!(entries.len(), 3);
+    for ((name, child), (expected_name, expected_child)) in
+        entries.iter().zip(names.iter().zip(children))
+    {
+        assert_eq!(*name, expected_name.as_bytes());
+        assert_eq!(*child, expected_child);
+    }
+
+    // Pages start at an entry, not a byte offset
+    let page = get_entries(&mut context, &directory, 1, 1).await;
+    assert_eq!(page, encode_named_entry(b"src", &children[1]));
+}
+
+#[tokio::test]
+async fn reinitialize_removes_the_entry_from_the_parent() {
+    let mut context = start().await;
+    let payer = context.payer.pubkey();
+    let parent = create_directory(&mut context, 256, 8, DirectoryFormat::NamedEntries).await;
+    let child = initialize(&mut context, |args| args).await.pubkey();
+    let sibling = Pubkey::new_unique();
+    add_entry(&mut context, &parent, child, "child")
+        .await
+        .unwrap();
+    add_entry(&mut context, &parent, sibling, "sibling")
+        .await
+        .unwrap();
+    let parent_accounts = [
+        AccountMeta::new(parent, false),
+        AccountMeta::new(metadata_pda(&parent).0, false),
+    ];
+    let data_store_ix = |extra_accounts: &[AccountMeta], data_store_instruction| {
+        let mut accounts = vec![
+            AccountMeta::new_readonly(payer, true),
+            AccountMeta::new(child, false),
+            AccountMeta::new(metadata_pda(&child).0, false),
+        ];
+        accounts.extend_from_slice(extra_accounts);
+        accounts.push(AccountMeta::new_readonly(config_pda(), false));
+        instruction(accounts, data_store_instruction)
+    };
+
+    // Renaming through the parent records it
+    let set_name = DataStoreInstruction::SetName(SetNameArgs {
+        debug: false,
+        name: "renamed".to_string(),
+    });
+    process(
+        &mut context,
+        &[data_store_ix(&parent_accounts, set_name)],
+        &[],
+    )
+    .await
+    .unwrap();
+    assert_eq!(metadata(&mut context, &child).await.parent(), Some(&parent));
+
+    let headers = AccountMeta::new_readonly(derive_headers_pda(&program_id(), &child).0, false);
+    let reinitialize = DataStoreInstruction::Reinitialize(ReinitializeArgs {
+        debug: false,
+        data_type: DataStoreTypeOption::File,
+        authority: payer,
+        is_dynamic: false,
+    });
+    let result = process(
+        &mut context,
+        &[data_store_ix(
+            std::slice::from_ref(&headers),
+            reinitialize.clone(),
+        )],
+        &[],
+    )
+    .await;
+    assert_error(result, DataStoreError::ParentRequired);
+
+    process(
+        &mut context,
+        &[data_store_ix(
+            &[
+                headers,
+                parent_accounts[0].clone(),
+                parent_accounts[1].clone(),
+            ],
+            reinitialize,
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+    let account_metadata = metadata(&mut context, &child).await;
+    assert_eq!(account_metadata.parent(), None);
+    assert_eq!(account_metadata.name(), "");
+    let entries = get_entries(&mut context, &parent, 0, 8).await;
+    assert_eq!(entries, encode_named_entry(b"sibling", &sibling));
+    assert_eq!(
+        metadata(&mut context, &parent).await.data_len(),
+        entries.len()
+    );
+}
+
+/// SetName of data_account, renaming its entry in parent if any
+fn set_name_ix(
+    authority: &Pubkey,
+    data_account: &Pubkey,
+    parent: Option<&Pubkey>,
+    name: &str,
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*authority, true),
+        AccountMeta::new_readonly(*data_account, false),
+        AccountMeta::new(metadata_pda(data_account).0, false),
+    ];
+    if let Some(parent) = parent {
+        accounts.push(AccountMeta::new(*parent, false));
+        accounts.push(AccountMeta::new(metadata_pda(parent).0, false));
+    }
+    accounts.push(AccountMeta::new_readonly(config_pda(), false));
+    instruction(
+        accounts,
+        DataStoreInstruction::SetName(SetNameArgs {
+            debug: false,
+            name: name.to_string(),
+        }),
+    )
+}
+
+#[tokio::test]
+async fn set_name_renames_without_colliding_with_siblings() {
+    let mut context = start().await;
+    let payer = context.payer.pubkey();
+    let file = initialize(&mut context, |args| InitializeDataStoreArgs {
+        initial_data: b"hello".to_vec(),
+        ..args
+    })
+    .await
+    .pubkey();
+    let data_hash = *metadata(&mut context, &file).await.data_hash();
+
+    // A plain rename only touches the name
+    process(
+        &mut context,
+        &[set_name_ix(&payer, &file, None, "a.txt")],
+        &[],
+    )
+    .await
+    .unwrap();
+    let account_metadata = metadata(&mut context, &file).await;
+    assert_eq!(account_metadata.name(), "a.txt");
+    assert_eq!(*account_metadata.data_hash(), data_hash);
+    assert_eq!(account_metadata.parent(), None);
+
+    // Within a directory, the entry is renamed unless a sibling has the name
+    let parent = create_directory(&mut context, 256, 8, DirectoryFormat::NamedEntries).await;
+    let sibling = Pubkey::new_unique();
+    add_entry(&mut context, &parent, file, "a.txt")
+        .await
+        .unwrap();
+    add_entry(&mut context, &parent, sibling, "b.txt")
+        .await
+        .unwrap();
+    let result = process(
+        &mut context,
+        &[set_name_ix(&payer, &file, Some(&parent), "b.txt")],
+        &[],
+    )
+    .await;
+    assert_error(result, DataStoreError::NameConflict);
+    assert_eq!(metadata(&mut context, &file).await.name(), "a.txt");
+
+    process(
+        &mut context,
+        &[set_name_ix(&payer, &file, Some(&parent), "c.txt")],
+        &[],
+    )
+    .await
+    .unwrap();
+    assert_eq!(metadata(&mut context, &file).await.name(), "c.txt");
+    let entries = get_entries(&mut context, &parent, 0, 8).await;
+    assert_eq!(
+        entries,
+        [
+            encode_named_entry(b"c.txt", &file),
+            encode_named_entry(b"b.txt", &sibling)
+        ]
+        .concat()
+    );
+}
+
+#[tokio::test]
+async fn closing_a_concatenated_source_removes_its_entry() {
+    let mut context = start().await;
+    let payer = context.payer.pubkey();
+    let parent = create_directory(&mut context, 256, 8, DirectoryFormat::NamedEntries).await;
+    let destination = initialize(&mut context, |args| args).await.pubkey();
+    let source = initialize(&mut context, |args| args).await.pubkey();
+    update(&mut context, &source, update_args(b"chunk", 0))
+        .await
+        .unwrap();
+    add_entry(&mut context, &parent, source, "chunk")
+        .await
+        .unwrap();
+    process(
+        &mut context,
+        &[set_name_ix(&payer, &source, Some(&parent), "chunk")],
+        &[],
+    )
+    .await
+    .unwrap();
+    let concat_ix = |parent_accounts: &[AccountMeta]| {
+        let mut accounts = vec![
+            AccountMeta::new(payer, true),
+            AccountMeta::new(destination, false),
+            AccountMeta::new(metadata_pda(&destination).0, false),
+            AccountMeta::new(source, false),
+            AccountMeta::new(metadata_pda(&source).0, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ];
+        accounts.extend_from_slice(parent_accounts);
+        accounts.push(AccountMeta::new_readonly(config_pda(), false));
+        instruction(
+            accounts,
+            DataStoreInstruction::ConcatData(ConcatDataArgs {
+                debug: false,
+                close_source: true,
+            }),
+        )
+    };
+
+    // Closing the source needs the parent listing it
+    let result = process(&mut context, &[concat_ix(&[])], &[]).await;
+    assert_error(result, DataStoreError::ParentRequired);
+
+    process(
+        &mut context,
+        &[concat_ix(&[
+            AccountMeta::new(parent, false),
+            AccountMeta::new(metadata_pda(&parent).0, false),
+        ])],
+        &[],
+    )
+    .await
+    .unwrap();
+    assert_eq!(data(&mut context, &destination).await[..5], *b"chunk");
+    assert!(context
+        .banks_client
+        .get_account(source)
+        .await
+        .unwrap()
+        .is_none());
+    assert!(get_entries(&mut context, &parent, 0, 8).await.is_empty());
+    assert_eq!(metadata(&mut context, &parent).await.data_len(), 0);
+}
+
+#[tokio::test]
+async fn get_or_create_directory_returns_the_existing_one() {
+    let mut context = start().await;
+    let payer = context.payer.pubkey();
+    let (directory, _) = derive_file_pda(&program_id(), &payer, "mnt");
+    let get_or_create_ix = |space| {
+        let mut accounts = create_accounts(&payer, &directory);
+        accounts[1].is_signer = false;
+        instruction(
+            accounts,
+            DataStoreInstruction::GetOrCreateDirectory(GetOrCreateDirectoryArgs {
+                debug: false,
+                name: "mnt".to_string(),
+                bump_seed: metadata_pda(&directory).1,
+                space,
+                authority: payer,
+                namespace: [0; NAMESPACE_LEN],
+                max_entries: 0,
+                directory_format: DirectoryFormat::NamedEntries,
+            }),
+        )
+    };
+
+    let (return_data, _) = process_with_metadata(&mut context, &[get_or_create_ix(128)], &[])
+        .await
+        .unwrap();
+    assert_eq!(return_data.unwrap(), directory.to_bytes());
+    let created = metadata(&mut context, &directory).await;
+    assert_eq!(*created.data_type(), DataStoreTypeOption::Directory);
+
+    // The second call, asking for another size, finds the directory unchanged
+    let (return_data, _) = process_with_metadata(&mut context, &[get_or_create_ix(256)], &[])
+        .await
+        .unwrap();
+    assert_eq!(return_data.unwrap(), directory.to_bytes());
+    let found = metadata(&mut context, &directory).await;
+    assert_eq!(
+        borsh::to_vec(&found).unwrap(),
+        borsh::to_vec(&created).unwrap()
+    );
+    assert_eq!(data(&mut context, &directory).await.len(), 128);
+}
@@ -0,0 +1,65 @@
+use dataaccount::error::{error_code, error_from_code, DataStoreError};
+
+/// Every error in code order, codes are part of the wire format and must never change
+const ERRORS: &[DataStoreError] = &[
+    DataStoreError::NotImplemented,
+    DataStoreError::NotWriteable,
+    DataStoreError::NoAccountLength,
+    DataStoreError::NonZeroData,
+    DataStoreError::NotSigner,
+    DataStoreError::InvalidSystemProgram,
+    DataStoreError::InvalidAuthority,
+    DataStoreError::InvalidPDA,
+    DataStoreError::AlreadyInitialized,
+    DataStoreError::NotInitialized,
+    DataStoreError::AlreadyFinalized,
+    DataStoreError::Overflow,
+    DataStoreError::InsufficientSpace,
+    DataStoreError::InvalidDataType,
+    DataStoreError::DataVerificationFailed,
+    DataStoreError::InvalidInstructionData,
+    DataStoreError::RevisionMismatch,
+    DataStoreError::SpaceTooLarge,
+    DataStoreError::NotFinalized,
+    DataStoreError::InvalidAccountOwner,
+    DataStoreError::UnsupportedVersion,
+    DataStoreError::AppendOnlyViolation,
+    DataStoreError::InvalidDirectorySize,
+    DataStoreError::ZeroSpace,
+    DataStoreError::EntryNotFound,
+    DataStoreError::EntryAlreadyExists,
+    DataStoreError::NotRentExempt,
+    DataStoreError::ImmutableFlag,
+    DataStoreError::DirectoryFull,
+    DataStoreError::UploadIncomplete,
+    DataStoreError::AuthorityUnchanged,
+    DataStoreError::UpdateTooSoon,
+    DataStoreError::NotDynamic,
+    DataStoreError::SizeMismatch,
+    DataStoreError::CorruptMetadata,
+    DataStoreError::NonContiguousWrite,
+    DataStoreError::ProgramPaused,
+    DataStoreError::OperationTooLarge,
+    DataStoreError::NameConflict,
+    DataStoreError::DuplicateAccount,
+    DataStoreError::HeadersFull,
+    DataStoreError::Frozen,
+    DataStoreError::ContentRegistered,
+    DataStoreError::ParentRequired,
+    DataStoreError::MigrationRequired,
+];
+
+#[test]
+fn error_codes_round_trip() {
+    for (code, error) in ERRORS.iter().enumerate() {
+        assert_eq!(error_code(error), code as u32, "{:?}", error);
+        assert_eq!(error_from_code(code as u32).as_ref(), Some(error));
+        assert_eq!(error_from_code(error_code(error)).as_ref(), Some(error));
+    }
+}
+
+#[test]
+fn unknown_code_has_no_error() {
+    assert_eq!(error_from_code(ERRORS.len() as u32), None);
+    assert_eq!(error_from_code(u32::MAX), None);
+}
@@ -0,0 +1,209 @@
+mod common;
+
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, RwLock};
+
+use borsh::BorshDeserialize;
+use common::{initialize, metadata, process, start, update, update_args, update_ix};
+use dataaccount::{
+    events::DataStoreEvent,
+    instruction::DataStoreInstruction,
+    state::{compute_data_hash, InitializeDataStoreArgs, SerializationStatusOption},
+};
+use solana_program_test::tokio;
+use solana_sdk::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    instruction::Instruction,
+    program_stubs::{set_syscall_stubs, SyscallStubs},
+    pubkey::Pubkey,
+    signature::Signer,
+};
+
+/// Data logged with sol_log_data by the program
+static LOGGED_DATA: Mutex<Vec<Vec<u8>>> = Mutex::new(Vec::new());
+
+/// Syscall stubs of the program test, shared with the stubs wrapping them
+type Stubs = Arc<RwLock<Option<Box<dyn SyscallStubs>>>>;
+
+/// Syscall stubs of the program test, also recording log data.
+/// The program test stubs only print it, so it never reaches the transaction logs
+/// of a native program.
+struct LogDataStubs(Stubs);
+
+impl LogDataStubs {
+    fn forward<R>(&self, call: impl FnOnce(&dyn SyscallStubs) -> R) -> R {
+        call(self.0.read().unwrap().as_deref().unwrap())
+    }
+}
+
+impl SyscallStubs for LogDataStubs {
+    fn sol_log(&self, message: &str) {
+        self.forward(|stubs| stubs.sol_log(message))
+    }
+    fn sol_log_compute_units(&self) {
+        self.forward(|stubs| stubs.sol_log_compute_units())
+    }
+    fn sol_remaining_compute_units(&self) -> u64 {
+        self.forward(|stubs| stubs.sol_remaining_compute_units())
+    }
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        self.forward(|stubs| stubs.sol_invoke_signed(instruction, account_infos, signers_seeds))
+    }
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.forward(|stubs| stubs.sol_get_clock_sysvar(var_addr))
+    }
+    fn sol_get_epoch_schedule_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.forward(|stubs| stubs.sol_get_epoch_schedule_sysvar(var_addr))
+    }
+    fn sol_get_fees_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.forward(|stubs| stubs.sol_get_fees_sysvar(var_addr))
+    }
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.forward(|stubs| stubs.sol_get_rent_sysvar(var_addr))
+    }
+    fn sol_get_epoch_rewards_sysvar(&self, var_addr: *mut u8) -> u64 {
+        self.forward(|stubs| stubs.sol_get_epoch_rewards_sysvar(var_addr))
+    }
+    fn sol_get_last_restart_slot(&self, var_addr: *mut u8) -> u64 {
+        self.forward(|stubs| stubs.sol_get_last_restart_slot(var_addr))
+    }
+    fn sol_get_return_data(&self) -> Option<(Pubkey, Vec<u8>)> {
+        self.forward(|stubs| stubs.sol_get_return_data())
+    }
+    fn sol_set_return_data(&self, data: &[u8]) {
+        self.forward(|stubs| stubs.sol_set_return_data(data))
+    }
+    fn sol_log_data(&self, fields: &[&[u8]]) {
+        LOGGED_DATA
+            .lock()
+            .unwrap()
+            .extend(fields.iter().map(|field| field.to_vec()));
+    }
+    fn sol_get_processed_sibling_instruction(&self, index: usize) -> Option<Instruction> {
+        self.forward(|stubs| stubs.sol_get_processed_sibling_instruction(index))
+    }
+    fn sol_get_stack_height(&self) -> u64 {
+        self.forward(|stubs| stubs.sol_get_stack_height())
+    }
+}
+
+/// Held while recording, as the syscall stubs are shared by every test of this file
+static RECORDING: Mutex<()> = Mutex::new(());
+
+/// Records the log data of the program until dropped, which puts the program test
+/// stubs back in place, even when an assertion failed. Started after the program
+/// test, which installs its stubs when first started.
+struct RecordLogData {
+    stubs: Stubs,
+    _recording: MutexGuard<'static, ()>,
+}
+
+impl RecordLogData {
+    fn start() -> Self {
+        let recording = RECORDING.lock().unwrap_or_else(PoisonError::into_inner);
+        LOGGED_DATA.lock().unwrap().clear();
+        let stubs: Stubs = Arc::new(RwLock::new(None));
+        let original = set_syscall_stubs(Box::new(LogDataStubs(stubs.clone())));
+        *stubs.write().unwrap() = Some(original);
+        Self {
+            stubs,
+            _recording: recording,
+        }
+    }
+
+    /// Events emitted since the last call
+    fn events(&self) -> Vec<DataStoreEvent> {
+        LOGGED_DATA
+            .lock()
+            .unwrap()
+            .drain(..)
+            .map(|data| DataStoreEvent::try_from_slice(&data).unwrap())
+            .collect()
+    }
+}
+
+impl Drop for RecordLogData {
+    fn drop(&mut self) {
+        if let Some(original) = self.stubs.write().unwrap().take() {
+            set_syscall_stubs(original);
+        }
+    }
+}
+
+#[tokio::test]
+async fn update_logs_updated_event() {
+    let mut context = start().await;
+    let recording = RecordLogData::start();
+    let data_account = initialize(&mut context, |args| args).await.pubkey();
+    recording.events();
+
+    update(&mut context, &data_account, update_args(b"hello", 0))
+        .await
+        .unwrap();
+
+    let account_metadata = metadata(&mut context, &data_account).await;
+    assert_eq!(
+        recording.events(),
+        vec![DataStoreEvent::Updated {
+            data_account,
+            revision: account_metadata.revision(),
+            data_len: 5,
+            data_hash: *account_metadata.data_hash(),
+        }]
+    );
+}
+
+#[tokio::test]
+async fn write_and_finalize_seals_once() {
+    let mut context = start().await;
+    let recording = RecordLogData::start();
+    let payer = context.payer.pubkey();
+
+    // The last revision the limit allows already seals the data store
+    for max_revisions in [None, Some(1)] {
+        let data_account = initialize(&mut context, |args| InitializeDataStoreArgs {
+            max_revisions,
+            ..args
+        })
+        .await
+        .pubkey();
+        recording.events();
+
+        let mut write_ix = update_ix(&payer, &data_account, update_args(b"sealed", 0));
+        write_ix.data = borsh::to_vec(&DataStoreInstruction::WriteAndFinalize(update_args(
+            b"sealed", 0,
+        )))
+        .unwrap();
+        process(&mut context, &[write_ix], &[]).await.unwrap();
+
+        let account_metadata = metadata(&mut context, &data_account).await;
+        assert_eq!(
+            *account_metadata.data_status(),
+            SerializationStatusOption::Finalized
+        );
+        let data_hash = compute_data_hash(&data_account, b"sealed");
+        assert_eq!(*account_metadata.data_hash(), data_hash);
+        assert_eq!(
+            recording.events(),
+            vec![
+                DataStoreEvent::Updated {
+                    data_account,
+                    revision: account_metadata.revision(),
+                    data_len: 6,
+                    data_hash,
+                },
+                DataStoreEvent::Finalized {
+                    data_account,
+                    data_hash,
+                },
+            ],
+            "max_revisions {:?}",
+            max_revisions
+        );
+    }
+}
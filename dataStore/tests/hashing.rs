@@ -0,0 +1,154 @@
+use dataaccount::{
+    hashing::{crc32, digest, syscall, HashAlgorithm, Sha256Stream},
+    state::{compute_data_hash, DataStoreAccountMetadata, DataStoreTypeOption, DATA_HASH_VERSION},
+};
+use solana_program::{hash::hashv, pubkey::Pubkey};
+
+/// Lengths around the 64-byte block and the 55-byte single block padding limit
+const LENGTHS: [usize; 7] = [0, 55, 56, 63, 64, 65, 1000];
+
+fn data(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i * 31 + 7) as u8).collect()
+}
+
+#[test]
+fn stream_matches_hashv_for_every_split() {
+    for len in LENGTHS {
+        let data = data(len);
+        let expected = hashv(&[&data]).to_bytes();
+        for split in 0..=len {
+            let mut stream = Sha256Stream::new();
+            stream.update(&data[..split]);
+            // Finalizing leaves the stream open for more updates
+            assert_eq!(stream.finalize(), hashv(&[&data[..split]]).to_bytes());
+            stream.update(&data[split..]);
+            assert_eq!(stream.len(), len as u64);
+            assert!(stream.is_valid());
+            assert_eq!(stream.finalize(), expected, "len {} split {}", len, split);
+        }
+    }
+}
+
+#[test]
+fn stream_matches_compute_data_hash_for_every_split() {
+    let data_account = Pubkey::new_from_array([3; 32]);
+    for len in LENGTHS {
+        let data = data(len);
+        let expected = compute_data_hash(&data_account, &data);
+        for split in 0..=len {
+            let mut stream = Sha256Stream::new();
+            stream.update(data_account.as_ref());
+            stream.update(&[DATA_HASH_VERSION]);
+            stream.update(&data[..split]);
+            stream.update(&data[split..]);
+            assert_eq!(stream.finalize(), expected, "len {} split {}", len, split);
+        }
+    }
+}
+
+#[test]
+fn append_checksums_match_full_recompute() {
+    let data_account = Pubkey::new_from_array([3; 32]);
+    let data = data(1000);
+    let mut metadata = DataStoreAccountMetadata::new(
+        Pubkey::new_from_array([1; 32]),
+        DataStoreTypeOption::File,
+        255,
+        true,
+    );
+    metadata.set_data_len(0);
+    metadata.start_hash_stream(&data_account, &data);
+
+    let mut len = 0;
+    for chunk in [1, 54, 9, 0, 64, 63, 65, 300, 444] {
+        let appended_from = len;
+        len += chunk;
+        metadata.set_data_len(len);
+        metadata.append_checksums(&data_account, &data, appended_from);
+        assert!(metadata.hash_stream.is_some());
+
+        let mut recomputed = metadata.clone();
+        recomputed.update_checksums(&data_account, &data);
+        assert_eq!(metadata.data_hash(), recomputed.data_hash(), "len {}", len);
+        assert_eq!(metadata.crc32(), recomputed.crc32());
+        assert_eq!(
+            *metadata.data_hash(),
+            compute_data_hash(&data_account, &data[..len])
+        );
+        assert_eq!(metadata.crc32(), crc32(&data[..len]));
+    }
+    assert_eq!(len, data.len());
+}
+
+/// Published digests of "" and "abc": FIPS 180-2 for SHA-256, the reference
+/// implementation for BLAKE3
+const VECTORS: [(HashAlgorithm, &[u8], &str); 4] = [
+    (
+        HashAlgorithm::Sha256,
+        b"",
+        "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+    ),
+    (
+        HashAlgorithm::Sha256,
+        b"abc",
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+    ),
+    (
+        HashAlgorithm::Blake3,
+        b"",
+        "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262",
+    ),
+    (
+        HashAlgorithm::Blake3,
+        b"abc",
+        "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85",
+    ),
+];
+
+fn hex(bytes: &[u8; 32]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[test]
+fn syscall_backend_matches_fixed_vectors() {
+    for (algo, input, expected) in VECTORS {
+        assert_eq!(hex(&syscall::digestv(algo, &[input])), expected);
+        assert_eq!(hex(&digest(algo, input)), expected);
+        // Concatenated vals hash as one input
+        let (head, tail) = input.split_at(input.len() / 2);
+        assert_eq!(hex(&syscall::digestv(algo, &[head, tail])), expected);
+    }
+}
+
+#[cfg(feature = "client")]
+#[test]
+fn host_backend_matches_syscall_backend() {
+    use dataaccount::hashing::host;
+
+    for (algo, input, expected) in VECTORS {
+        assert_eq!(hex(&host::digestv(algo, &[input])), expected);
+        let (head, tail) = input.split_at(input.len() / 2);
+        assert_eq!(
+            host::digestv(algo, &[head, tail]),
+            syscall::digestv(algo, &[head, tail])
+        );
+    }
+    let data = data(1000);
+    for algo in [HashAlgorithm::Sha256, HashAlgorithm::Blake3] {
+        assert_eq!(
+            host::digestv(algo, &[&data[..300], &data[300..]]),
+            syscall::digestv(algo, &[&data])
+        );
+    }
+}
+
+#[test]
+fn crc32_matches_reference_vectors() {
+    // The CRC-32 check value, and the checksum zlib gives the pangram
+    assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+    assert_eq!(
+        crc32(b"The quick brown fox jumps over the lazy dog"),
+        0x414f_a339
+    );
+    assert_eq!(crc32(b""), 0);
+}
@@ -0,0 +1,131 @@
+mod common;
+
+use borsh::BorshDeserialize;
+use common::{
+    assert_error, config_pda, initialize, instruction, metadata_pda, process, program_id, query,
+    start,
+};
+use dataaccount::{
+    error::DataStoreError,
+    instruction::DataStoreInstruction,
+    state::{derive_headers_pda, GetHeadersArgs, HttpHeader, SetHeaderArgs, MAX_HEADERS},
+};
+use solana_program_test::{tokio, BanksClientError, ProgramTestContext};
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    signature::Signer,
+    system_program,
+};
+
+fn set_header_ix(authority: &Pubkey, data_account: &Pubkey, key: &str, value: &str) -> Instruction {
+    instruction(
+        vec![
+            AccountMeta::new(*authority, true),
+            AccountMeta::new_readonly(*data_account, false),
+            AccountMeta::new_readonly(metadata_pda(data_account).0, false),
+            AccountMeta::new(derive_headers_pda(&program_id(), data_account).0, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(config_pda(), false),
+        ],
+        DataStoreInstruction::SetHeader(SetHeaderArgs {
+            debug: false,
+            key: key.to_string(),
+            value: value.to_string(),
+        }),
+    )
+}
+
+async fn set_header(
+    context: &mut ProgramTestContext,
+    data_account: &Pubkey,
+    key: &str,
+    value: &str,
+) -> Result<(), BanksClientError> {
+    let payer = context.payer.pubkey();
+    process(
+        context,
+        &[set_header_ix(&payer, data_account, key, value)],
+        &[],
+    )
+    .await
+}
+
+async fn get_headers(context: &mut ProgramTestContext, data_account: &Pubkey) -> Vec<HttpHeader> {
+    let get_ix = instruction(
+        vec![
+            AccountMeta::new_readonly(*data_account, false),
+            AccountMeta::new_readonly(metadata_pda(data_account).0, false),
+            AccountMeta::new_readonly(derive_headers_pda(&program_id(), data_account).0, false),
+        ],
+        DataStoreInstruction::GetHeaders(GetHeadersArgs { debug: false }),
+    );
+    let return_data = query(context, get_ix).await.unwrap();
+    Vec::<HttpHeader>::try_from_slice(&return_data).unwrap()
+}
+
+fn header(key: &str, value: &str) -> HttpHeader {
+    HttpHeader {
+        key: key.to_string(),
+        value: value.to_string(),
+    }
+}
+
+#[tokio::test]
+async fn content_disposition_is_set_and_read_back() {
+    let mut context = start().await;
+    let data_account = initialize(&mut context, |args| args).await.pubkey();
+    assert!(get_headers(&mut context, &data_account).await.is_empty());
+
+    let disposition = r#"attachment; filename="report.pdf""#;
+    set_header(
+        &mut context,
+        &data_account,
+        "Content-Disposition",
+        disposition,
+    )
+    .await
+    .unwrap();
+    set_header(&mut context, &data_account, "Content-Encoding", "gzip")
+        .await
+        .unwrap();
+    assert_eq!(
+        get_headers(&mut context, &data_account).await,
+        [
+            header("Content-Disposition", disposition),
+            header("Content-Encoding", "gzip"),
+        ]
+    );
+
+    // Keys match case-insensitively, an empty value removing the header
+    set_header(&mut context, &data_account, "content-disposition", "inline")
+        .await
+        .unwrap();
+    set_header(&mut context, &data_account, "CONTENT-ENCODING", "")
+        .await
+        .unwrap();
+    assert_eq!(
+        get_headers(&mut context, &data_account).await,
+        [header("Content-Disposition", "inline")]
+    );
+
+    // Headers gateways could not emit verbatim, or past the cap, are refused
+    let result = set_header(&mut context, &data_account, "X-Split", "a\r\nb").await;
+    assert_error(result, DataStoreError::InvalidInstructionData);
+    for index in 1..MAX_HEADERS {
+        set_header(
+            &mut context,
+            &data_account,
+            &format!("X-Header-{}", index),
+            "1",
+        )
+        .await
+        .unwrap();
+    }
+    let result = set_header(&mut context, &data_account, "X-Overflow", "1").await;
+    assert_error(result, DataStoreError::HeadersFull);
+    assert_eq!(
+        get_headers(&mut context, &data_account).await.len(),
+        MAX_HEADERS
+    );
+}
@@ -0,0 +1,639 @@
+mod common;
+
+use common::{
+    allowlist_pda, assert_error, config_pda, init_args, initialize, initialize_ix, instruction,
+    metadata, metadata_pda, process, program_id, start, update_args, update_ix,
+};
+use dataaccount::{
+    error::DataStoreError,
+    instruction::DataStoreInstruction,
+    state::{
+        derive_file_pda, derive_metadata_pda, DataStoreAccountMetadata, DataStoreTypeOption,
+        InitializeDataStoreArgs, InitializeDataStoreBatchArgs, SerializationStatusOption,
+        MAX_DATA_SPACE, METADATA_SIZE, NAMESPACE_LEN,
+    },
+};
+use solana_program_test::{tokio, ProgramTestContext};
+use solana_sdk::{
+    account::AccountSharedData,
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_program,
+};
+
+/// Points the metadata PDA of instruction, at index 2, to the one of namespace
+fn in_namespace(
+    mut instruction: Instruction,
+    data_account: &Pubkey,
+    namespace: &[u8],
+) -> Instruction {
+    instruction.accounts[2].pubkey =
+        derive_metadata_pda(&program_id(), data_account, Some(namespace)).0;
+    instruction
+}
+
+async fn namespaced_metadata(
+    context: &mut ProgramTestContext,
+    data_account: &Pubkey,
+    namespace: &[u8],
+) -> Option<DataStoreAccountMetadata> {
+    let pda = derive_metadata_pda(&program_id(), data_account, Some(namespace)).0;
+    context
+        .banks_client
+        .get_account(pda)
+        .await
+        .unwrap()
+        .map(|account| DataStoreAccountMetadata::load(&account.data).unwrap())
+}
+
+#[tokio::test]
+async fn space_above_maximum_is_rejected() {
+    let mut context = start().await;
+    let data_account = Keypair::new();
+    let payer = context.payer.pubkey();
+
+    let args = init_args(&data_account.pubkey(), &payer, MAX_DATA_SPACE as u64 + 1);
+    let result = process(
+        &mut context,
+        &[initialize_ix(&payer, &data_account.pubkey(), args)],
+        &[&data_account],
+    )
+    .await;
+    assert_error(result, DataStoreError::SpaceTooLarge);
+
+    let pda = metadata_pda(&data_account.pubkey()).0;
+    assert!(context
+        .banks_client
+        .get_account(pda)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn space_wrapping_a_32_bit_usize_is_rejected() {
+    let mut context = start().await;
+    let payer = context.payer.pubkey();
+
+    // Each would wrap to a small allocation if cast to a 32-bit usize unchecked
+    for space in [u32::MAX as u64 + 1, u32::MAX as u64 + 64, u64::MAX] {
+        let data_account = Keypair::new();
+        let args = InitializeDataStoreArgs {
+            is_dynamic: true,
+            ..init_args(&data_account.pubkey(), &payer, space)
+        };
+        let result = process(
+            &mut context,
+            &[initialize_ix(&payer, &data_account.pubkey(), args)],
+            &[&data_account],
+        )
+        .await;
+        assert_error(result, DataStoreError::SpaceTooLarge);
+        assert!(context
+            .banks_client
+            .get_account(data_account.pubkey())
+            .await
+            .unwrap()
+            .is_none());
+    }
+}
+
+#[tokio::test]
+async fn wrong_bump_seed_is_rejected() {
+    let mut context = start().await;
+    let data_account = Keypair::new();
+    let payer = context.payer.pubkey();
+
+    let mut args = init_args(&data_account.pubkey(), &payer, 64);
+    args.bump_seed = args.bump_seed.wrapping_sub(1);
+    let result = process(
+        &mut context,
+        &[initialize_ix(&payer, &data_account.pubkey(), args)],
+        &[&data_account],
+    )
+    .await;
+    assert_error(result, DataStoreError::InvalidPDA);
+
+    let pda = metadata_pda(&data_account.pubkey()).0;
+    assert!(context
+        .banks_client
+        .get_account(pda)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn namespaces_do_not_collide() {
+    let mut context = start().await;
+    let data_account = Keypair::new();
+    let payer = context.payer.pubkey();
+    let tenant_a = *b"tenant-a\0\0\0\0\0\0\0\0";
+    let tenant_b = *b"tenant-b\0\0\0\0\0\0\0\0";
+
+    let pdas = [&[0; 16], &tenant_a, &tenant_b].map(|namespace| {
+        derive_metadata_pda(&program_id(), &data_account.pubkey(), Some(namespace))
+    });
+    assert_eq!(pdas[0], metadata_pda(&data_account.pubkey()));
+    assert!(pdas[0].0 != pdas[1].0 && pdas[0].0 != pdas[2].0 && pdas[1].0 != pdas[2].0);
+
+    // Tenant A initializes the data account in its namespace
+    let init = |namespace: [u8; 16]| {
+        let args = InitializeDataStoreArgs {
+            namespace,
+            bump_seed: derive_metadata_pda(&program_id(), &data_account.pubkey(), Some(&namespace))
+                .1,
+            ..init_args(&data_account.pubkey(), &payer, 64)
+        };
+        in_namespace(
+            initialize_ix(&payer, &data_account.pubkey(), args),
+            &data_account.pubkey(),
+            &namespace,
+        )
+    };
+    process(&mut context, &[init(tenant_a)], &[&data_account])
+        .await
+        .unwrap();
+    let tenant_a_metadata = namespaced_metadata(&mut context, &data_account.pubkey(), &tenant_a)
+        .await
+        .unwrap();
+    assert_eq!(*tenant_a_metadata.namespace(), tenant_a);
+
+    // Tenant B can not take over the same data account in its own namespace
+    assert!(process(&mut context, &[init(tenant_b)], &[&data_account])
+        .await
+        .is_err());
+    assert!(
+        namespaced_metadata(&mut context, &data_account.pubkey(), &tenant_b)
+            .await
+            .is_none()
+    );
+
+    // Writes only go through the PDA of the namespace the data store lives in
+    for namespace in [[0; 16], tenant_b] {
+        let write_ix = in_namespace(
+            update_ix(&payer, &data_account.pubkey(), update_args(b"other", 0)),
+            &data_account.pubkey(),
+            &namespace,
+        );
+        assert!(process(&mut context, &[write_ix], &[]).await.is_err());
+    }
+    let write_ix = in_namespace(
+        update_ix(&payer, &data_account.pubkey(), update_args(b"tenant a", 0)),
+        &data_account.pubkey(),
+        &tenant_a,
+    );
+    process(&mut context, &[write_ix], &[]).await.unwrap();
+    let tenant_a_metadata = namespaced_metadata(&mut context, &data_account.pubkey(), &tenant_a)
+        .await
+        .unwrap();
+    assert_eq!(tenant_a_metadata.data_len(), 8);
+    assert_eq!(tenant_a_metadata.revision(), 1);
+}
+
+#[tokio::test]
+async fn zero_space_is_only_allowed_for_dynamic_files() {
+    let mut context = start().await;
+    let payer = context.payer.pubkey();
+
+    let data_account = Keypair::new();
+    let args = init_args(&data_account.pubkey(), &payer, 0);
+    let result = process(
+        &mut context,
+        &[initialize_ix(&payer, &data_account.pubkey(), args)],
+        &[&data_account],
+    )
+    .await;
+    assert_error(result, DataStoreError::ZeroSpace);
+
+    // A dynamic file starts empty, rent-exempt, and grows on its first write
+    let data_account = Keypair::new();
+    let args = InitializeDataStoreArgs {
+        is_dynamic: true,
+        ..init_args(&data_account.pubkey(), &payer, 0)
+    };
+    process(
+        &mut context,
+        &[initialize_ix(&payer, &data_account.pubkey(), args)],
+        &[&data_account],
+    )
+    .await
+    .unwrap();
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let account = context
+        .banks_client
+        .get_account(data_account.pubkey())
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(account.data.is_empty());
+    assert!(account.lamports >= rent.minimum_balance(0));
+
+    process(
+        &mut context,
+        &[update_ix(
+            &payer,
+            &data_account.pubkey(),
+            update_args(b"grown", 0),
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+    let account = context
+        .banks_client
+        .get_account(data_account.pubkey())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(account.data, b"grown");
+}
+
+#[tokio::test]
+async fn idempotent_initialize_reuses_identical_data_store() {
+    let mut context = start().await;
+    let data_account = Keypair::new();
+    let payer = context.payer.pubkey();
+    let args = InitializeDataStoreArgs {
+        idempotent: true,
+        ..init_args(&data_account.pubkey(), &payer, 64)
+    };
+    let init = |args: InitializeDataStoreArgs| initialize_ix(&payer, &data_account.pubkey(), args);
+
+    // Fresh: the data store is created
+    process(&mut context, &[init(args.clone())], &[&data_account])
+        .await
+        .unwrap();
+    process(
+        &mut context,
+        &[update_ix(
+            &payer,
+            &data_account.pubkey(),
+            update_args(b"kept", 0),
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+    let pda = metadata_pda(&data_account.pubkey()).0;
+    let before = context.banks_client.get_account(pda).await.unwrap();
+
+    // Identical: nothing is re-created
+    process(&mut context, &[init(args.clone())], &[&data_account])
+        .await
+        .unwrap();
+    assert_eq!(context.banks_client.get_account(pda).await.unwrap(), before);
+
+    // Conflicting or strict: the existing data store is kept and reported
+    let conflicting = [
+        InitializeDataStoreArgs {
+            authority: Pubkey::new_unique(),
+            ..args.clone()
+        },
+        InitializeDataStoreArgs {
+            data_type: DataStoreTypeOption::Directory,
+            ..args.clone()
+        },
+        InitializeDataStoreArgs {
+            idempotent: false,
+            ..args
+        },
+    ];
+    for args in conflicting {
+        let result = process(&mut context, &[init(args)], &[&data_account]).await;
+        assert_error(result, DataStoreError::AlreadyInitialized);
+    }
+    assert_eq!(context.banks_client.get_account(pda).await.unwrap(), before);
+}
+
+#[tokio::test]
+async fn underfunded_metadata_pda_is_topped_up() {
+    let mut context = start().await;
+    let data_account = Keypair::new();
+    let payer = context.payer.pubkey();
+
+    // Someone sent the metadata PDA a lamport before it was created
+    let pda = metadata_pda(&data_account.pubkey()).0;
+    context.set_account(&pda, &AccountSharedData::new(1, 0, &system_program::id()));
+
+    let args = init_args(&data_account.pubkey(), &payer, 64);
+    process(
+        &mut context,
+        &[initialize_ix(&payer, &data_account.pubkey(), args)],
+        &[&data_account],
+    )
+    .await
+    .unwrap();
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let account = context
+        .banks_client
+        .get_account(pda)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(account.owner, program_id());
+    assert_eq!(account.data.len(), METADATA_SIZE);
+    assert_eq!(account.lamports, rent.minimum_balance(METADATA_SIZE));
+}
+
+#[tokio::test]
+async fn off_chain_derivation_matches_program() {
+    let mut context = start().await;
+    let payer = context.payer.pubkey();
+
+    // Short namespaces are zero-padded, bytes past NAMESPACE_LEN are ignored
+    for namespace in [
+        None,
+        Some(&b"app"[..]),
+        Some(&b"a-namespace-longer-than-16"[..]),
+    ] {
+        let data_account = Keypair::new();
+        let (pda, bump_seed) =
+            derive_metadata_pda(&program_id(), &data_account.pubkey(), namespace);
+        let mut padded_namespace = [0; NAMESPACE_LEN];
+        if let Some(namespace) = namespace {
+            let len = namespace.len().min(NAMESPACE_LEN);
+            padded_namespace[..len].copy_from_slice(&namespace[..len]);
+        }
+
+        let args = InitializeDataStoreArgs {
+            namespace: padded_namespace,
+            bump_seed,
+            ..init_args(&data_account.pubkey(), &payer, 64)
+        };
+        let mut init_ix = initialize_ix(&payer, &data_account.pubkey(), args);
+        init_ix.accounts[2].pubkey = pda;
+        process(&mut context, &[init_ix], &[&data_account])
+            .await
+            .unwrap();
+
+        let account = context
+            .banks_client
+            .get_account(pda)
+            .await
+            .unwrap()
+            .unwrap();
+        let account_metadata = DataStoreAccountMetadata::load(&account.data).unwrap();
+        assert_eq!(
+            account_metadata
+                .metadata_pda(&program_id(), &data_account.pubkey())
+                .unwrap(),
+            pda
+        );
+        assert_eq!(account_metadata.bump_seed(), bump_seed);
+    }
+}
+
+#[tokio::test]
+async fn adopted_account_must_have_the_requested_space() {
+    let mut context = start().await;
+    let payer = context.payer.pubkey();
+    let rent = context.banks_client.get_rent().await.unwrap();
+    // A funded system account of 100 bytes, created outside the program
+    let funded_account = |context: &mut ProgramTestContext| {
+        let data_account = Keypair::new();
+        context.set_account(
+            &data_account.pubkey(),
+            &AccountSharedData::new(rent.minimum_balance(100), 100, &system_program::id()),
+        );
+        data_account
+    };
+    let adopt_ix = |data_account: &Keypair, space, is_dynamic| {
+        let args = InitializeDataStoreArgs {
+            is_created: true,
+            is_dynamic,
+            ..init_args(&data_account.pubkey(), &payer, space)
+        };
+        initialize_ix(&payer, &data_account.pubkey(), args)
+    };
+
+    let data_account = funded_account(&mut context);
+    let result = process(
+        &mut context,
+        &[adopt_ix(&data_account, 64, false)],
+        &[&data_account],
+    )
+    .await;
+    assert_error(result, DataStoreError::SizeMismatch);
+    let account = context
+        .banks_client
+        .get_account(data_account.pubkey())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(account.owner, system_program::id());
+
+    process(
+        &mut context,
+        &[adopt_ix(&data_account, 100, false)],
+        &[&data_account],
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        metadata(&mut context, &data_account.pubkey()).await.space(),
+        100
+    );
+
+    // Dynamic data stores are resized to the requested space instead, the feepayer
+    // topping up the rent
+    let data_account = funded_account(&mut context);
+    process(
+        &mut context,
+        &[adopt_ix(&data_account, 200, true)],
+        &[&data_account],
+    )
+    .await
+    .unwrap();
+    let account = context
+        .banks_client
+        .get_account(data_account.pubkey())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(account.owner, program_id());
+    assert_eq!(account.data.len(), 200);
+    assert_eq!(account.lamports, rent.minimum_balance(200));
+}
+
+/// InitializeDataStoreBatch of data_accounts, with their metadata bump seeds
+fn initialize_batch_ix(
+    feepayer: &Pubkey,
+    data_accounts: &[Pubkey],
+    bump_seeds: Vec<u8>,
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(*feepayer, true),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+    if cfg!(feature = "permissioned-init") {
+        accounts.push(AccountMeta::new_readonly(allowlist_pda(), false));
+    }
+    for data_account in data_accounts {
+        accounts.push(AccountMeta::new(*data_account, true));
+        accounts.push(AccountMeta::new(metadata_pda(data_account).0, false));
+    }
+    accounts.push(AccountMeta::new_readonly(config_pda(), false));
+    instruction(
+        accounts,
+        DataStoreInstruction::InitializeDataStoreBatch(InitializeDataStoreBatchArgs {
+            debug: false,
+            data_type: DataStoreTypeOption::File,
+            bump_seeds,
+            is_created: false,
+            space: 64,
+            authority: *feepayer,
+            is_dynamic: false,
+            namespace: [0; NAMESPACE_LEN],
+        }),
+    )
+}
+
+#[tokio::test]
+async fn batch_initializes_all_or_nothing() {
+    let mut context = start().await;
+    let payer = context.payer.pubkey();
+    let keypairs: Vec<_> = (0..3).map(|_| Keypair::new()).collect();
+    let signers: Vec<_> = keypairs.iter().collect();
+    let data_accounts: Vec<_> = keypairs.iter().map(Keypair::pubkey).collect();
+    let mut bump_seeds: Vec<_> = data_accounts
+        .iter()
+        .map(|data_account| metadata_pda(data_account).1)
+        .collect();
+
+    // A wrong bump seed in the last group leaves no data store behind
+    bump_seeds[2] = bump_seeds[2].wrapping_sub(1);
+    let result = process(
+        &mut context,
+        &[initialize_batch_ix(
+            &payer,
+            &data_accounts,
+            bump_seeds.clone(),
+        )],
+        &signers,
+    )
+    .await;
+    assert_error(result, DataStoreError::InvalidPDA);
+    for data_account in &data_accounts {
+        assert!(context
+            .banks_client
+            .get_account(metadata_pda(data_account).0)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    bump_seeds[2] = metadata_pda(&data_accounts[2]).1;
+    process(
+        &mut context,
+        &[initialize_batch_ix(&payer, &data_accounts, bump_seeds)],
+        &signers,
+    )
+    .await
+    .unwrap();
+    for data_account in &data_accounts {
+        let account_metadata = metadata(&mut context, data_account).await;
+        assert_eq!(
+            *account_metadata.data_status(),
+            SerializationStatusOption::Initialized
+        );
+        assert_eq!(account_metadata.authority(), &payer);
+    }
+}
+
+#[tokio::test]
+async fn metadata_pda_is_created_without_the_data_account() {
+    let mut context = start().await;
+    let payer = context.payer.pubkey();
+    let rent = context.banks_client.get_rent().await.unwrap();
+
+    // A keypair data account, and a file PDA that never signs the transaction
+    let keypair_account = initialize(&mut context, |args| args).await.pubkey();
+    let (file_account, _) = derive_file_pda(&program_id(), &payer, "notes.txt");
+    let args = InitializeDataStoreArgs {
+        file_name: "notes.txt".to_string(),
+        ..init_args(&file_account, &payer, 64)
+    };
+    let mut file_ix = initialize_ix(&payer, &file_account, args);
+    file_ix.accounts[1].is_signer = false;
+    process(&mut context, &[file_ix], &[]).await.unwrap();
+
+    for data_account in [keypair_account, file_account] {
+        let account = context
+            .banks_client
+            .get_account(metadata_pda(&data_account).0)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(account.owner, program_id());
+        assert_eq!(account.data.len(), METADATA_SIZE);
+        assert_eq!(account.lamports, rent.minimum_balance(METADATA_SIZE));
+        assert_eq!(metadata(&mut context, &data_account).await.space(), 64);
+    }
+}
+
+#[tokio::test]
+async fn named_file_address_is_rederived_from_its_authority_and_name() {
+    let mut context = start().await;
+    let payer = context.payer.pubkey();
+    let authority = Pubkey::new_unique();
+    let create_file_ix = |file_account: &Pubkey, file_name: &str| {
+        let args = InitializeDataStoreArgs {
+            file_name: file_name.to_string(),
+            initial_data: b"hello".to_vec(),
+            ..init_args(file_account, &authority, 64)
+        };
+        let mut create_ix = initialize_ix(&payer, file_account, args);
+        create_ix.accounts[1].is_signer = false;
+        create_ix
+    };
+
+    // The file PDA of another authority is refused
+    let (foreign_account, _) = derive_file_pda(&program_id(), &payer, "hello.txt");
+    let result = process(
+        &mut context,
+        &[create_file_ix(&foreign_account, "hello.txt")],
+        &[],
+    )
+    .await;
+    assert_error(result, DataStoreError::InvalidPDA);
+
+    let (file_account, _) = derive_file_pda(&program_id(), &authority, "hello.txt");
+    process(
+        &mut context,
+        &[create_file_ix(&file_account, "hello.txt")],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    // Anyone finds the file again from the authority and name alone
+    let (rederived, _) =
+        Pubkey::find_program_address(&[b"file", authority.as_ref(), b"hello.txt"], &program_id());
+    assert_eq!(rederived, file_account);
+    let account = context
+        .banks_client
+        .get_account(rederived)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(account.owner, program_id());
+    assert_eq!(&account.data[..5], b"hello");
+    let account_metadata = metadata(&mut context, &rederived).await;
+    assert_eq!(account_metadata.name(), "hello.txt");
+    assert_eq!(account_metadata.authority(), &authority);
+    assert_ne!(
+        derive_file_pda(&program_id(), &authority, "other.txt").0,
+        file_account
+    );
+
+    // The name is taken once created
+    let result = process(
+        &mut context,
+        &[create_file_ix(&file_account, "hello.txt")],
+        &[],
+    )
+    .await;
+    assert_error(result, DataStoreError::AlreadyInitialized);
+}
@@ -0,0 +1,57 @@
+mod common;
+
+use common::{initialize, start, update_args, update_ix};
+use dataaccount::state::UpdateDataStoreArgs;
+use solana_program_test::{tokio, ProgramTestContext};
+use solana_sdk::{pubkey::Pubkey, signature::Signer, transaction::Transaction};
+
+/// Writes data with the debug flag, returning the transaction logs and the compute
+/// units it consumed
+async fn logged_update(
+    context: &mut ProgramTestContext,
+    data_account: &Pubkey,
+    data: &[u8],
+    debug: bool,
+) -> (Vec<String>, u64) {
+    let payer = context.payer.pubkey();
+    let args = UpdateDataStoreArgs {
+        debug,
+        ..update_args(data, 0)
+    };
+    let blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let transaction = Transaction::new_signed_with_payer(
+        &[update_ix(&payer, data_account, args)],
+        Some(&payer),
+        &[&context.payer],
+        blockhash,
+    );
+    let outcome = context
+        .banks_client
+        .process_transaction_with_metadata(transaction)
+        .await
+        .unwrap();
+    outcome.result.unwrap();
+    let metadata = outcome.metadata.unwrap();
+    (metadata.log_messages, metadata.compute_units_consumed)
+}
+
+/// Whether the program logged its debug messages
+fn debug_logged(logs: &[String]) -> bool {
+    logs.iter().any(|log| log == "Program log: UpdateDataStore")
+}
+
+/// Debug logs only cost compute units when the verbose-logs feature is on: on chain
+/// every msg! is a sol_log syscall of at least 100 compute units plus the formatting
+/// of its arguments, three of them on a plain update. Native program tests charge
+/// builtins a flat cost, so the logs are checked rather than the compute units.
+#[tokio::test]
+async fn debug_logs_follow_the_verbose_logs_feature() {
+    let mut context = start().await;
+    let data_account = initialize(&mut context, |args| args).await.pubkey();
+
+    let (logs, _) = logged_update(&mut context, &data_account, b"quiet", false).await;
+    assert!(!debug_logged(&logs));
+
+    let (logs, _) = logged_update(&mut context, &data_account, b"debug", true).await;
+    assert_eq!(debug_logged(&logs), cfg!(feature = "verbose-logs"));
+}
@@ -0,0 +1,807 @@
+mod common;
+
+use borsh::BorshDeserialize;
+use common::{
+    assert_error, close_ix, config_pda, content_pda, cpi_ix, finalize_ix, finalize_register_ix,
+    initialize, instruction, metadata, metadata_pda, process, program_id, query, set_flags_ix,
+    start, update, update_args, update_ix,
+};
+use dataaccount::{
+    error::DataStoreError,
+    instruction::DataStoreInstruction,
+    state::{
+        compute_data_hash, derive_headers_pda, ConvertDataTypeArgs, DataStoreAccountMetadata,
+        DataStoreAccountMetadataV0, DataStoreTypeOption, GetAccountStateArgs, GetMetadataArgs,
+        InitializeDataStoreArgs, MigrateMetadataArgs, RecomputeHashArgs, ReinitializeArgs,
+        SerializationStatusOption, SetCacheTtlArgs, SetNameArgs, UpdateDataStoreAuthorityArgs,
+        ACCOUNT_STATE_FROZEN, ACCOUNT_STATE_STATUS_MASK, DATA_STORE_VERSION, FLAG_APPEND_ONLY,
+        FLAG_CONTENT_REGISTERED, FLAG_DYNAMIC, FLAG_FROZEN, FLAG_INDEXABLE, FLAG_PRIVATE,
+        METADATA_AUTHORITY_OFFSET, METADATA_SIZE, METADATA_VERSION_OFFSET,
+    },
+};
+use solana_program_test::tokio;
+use solana_sdk::{
+    account::AccountSharedData,
+    instruction::{AccountMeta, Instruction},
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+};
+
+fn get_metadata_ix(data_account: &Pubkey) -> Instruction {
+    instruction(
+        vec![
+            AccountMeta::new_readonly(*data_account, false),
+            AccountMeta::new_readonly(metadata_pda(data_account).0, false),
+        ],
+        DataStoreInstruction::GetMetadata(GetMetadataArgs { debug: false }),
+    )
+}
+
+fn get_account_state_ix(data_account: &Pubkey) -> Instruction {
+    instruction(
+        vec![
+            AccountMeta::new_readonly(*data_account, false),
+            AccountMeta::new_readonly(metadata_pda(data_account).0, false),
+        ],
+        DataStoreInstruction::GetAccountState(GetAccountStateArgs { debug: false }),
+    )
+}
+
+/// Instruction on data_account taking the authority, the data store and the config PDA
+fn data_store_ix(
+    authority: &Pubkey,
+    data_account: &Pubkey,
+    extra_accounts: &[AccountMeta],
+    data_store_instruction: DataStoreInstruction,
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*authority, true),
+        AccountMeta::new(*data_account, false),
+        AccountMeta::new(metadata_pda(data_account).0, false),
+    ];
+    accounts.extend_from_slice(extra_accounts);
+    accounts.push(AccountMeta::new_readonly(config_pda(), false));
+    instruction(accounts, data_store_instruction)
+}
+
+#[tokio::test]
+async fn flipped_byte_is_corrupt_metadata() {
+    let mut context = start().await;
+    let data_account = initialize(&mut context, |args| args).await.pubkey();
+    let pda = metadata_pda(&data_account).0;
+
+    let mut account = context
+        .banks_client
+        .get_account(pda)
+        .await
+        .unwrap()
+        .unwrap();
+    account.data[METADATA_AUTHORITY_OFFSET] ^= 1;
+    assert_eq!(
+        DataStoreAccountMetadata::load(&account.data).unwrap_err(),
+        ProgramError::from(DataStoreError::CorruptMetadata)
+    );
+    context.set_account(&pda, &AccountSharedData::from(account));
+
+    let result = update(&mut context, &data_account, update_args(b"hello", 0)).await;
+    assert_error(result, DataStoreError::CorruptMetadata);
+}
+
+#[tokio::test]
+async fn garbage_metadata_is_corrupt_metadata() {
+    let mut context = start().await;
+    let data_account = initialize(&mut context, |args| args).await.pubkey();
+    let payer = context.payer.pubkey();
+    let pda = metadata_pda(&data_account).0;
+
+    let mut account = context
+        .banks_client
+        .get_account(pda)
+        .await
+        .unwrap()
+        .unwrap();
+    for (index, byte) in account.data.iter_mut().enumerate() {
+        *byte = (index * 167 + 13) as u8;
+    }
+    context.set_account(&pda, &AccountSharedData::from(account));
+
+    let new_authority = Keypair::new();
+    let update_authority =
+        DataStoreInstruction::UpdateDataStoreAuthority(UpdateDataStoreAuthorityArgs {
+            debug: false,
+            new_authority_pda: None,
+        });
+    let update_authority_ix = data_store_ix(
+        &payer,
+        &data_account,
+        &[AccountMeta::new_readonly(new_authority.pubkey(), true)],
+        update_authority,
+    );
+    let writes: [(Instruction, &[&Keypair]); 4] = [
+        (
+            update_ix(&payer, &data_account, update_args(b"hello", 0)),
+            &[],
+        ),
+        (update_authority_ix, &[&new_authority]),
+        (finalize_ix(&payer, &data_account), &[]),
+        (close_ix(&payer, &data_account, false), &[]),
+    ];
+    for (instruction, signers) in writes {
+        let result = process(&mut context, &[instruction], signers).await;
+        assert_error(result, DataStoreError::CorruptMetadata);
+    }
+}
+
+#[tokio::test]
+async fn get_metadata_returns_metadata_to_calling_program() {
+    let mut context = start().await;
+    let data_account = initialize(&mut context, |args| args).await.pubkey();
+    update(&mut context, &data_account, update_args(b"hello", 0))
+        .await
+        .unwrap();
+
+    // The caller program reads the return data of the data program after the CPI
+    let return_data = query(&mut context, cpi_ix(get_metadata_ix(&data_account)))
+        .await
+        .unwrap();
+    let returned = DataStoreAccountMetadata::try_from_slice(&return_data).unwrap();
+    let stored = metadata(&mut context, &data_account).await;
+    assert_eq!(
+        borsh::to_vec(&returned).unwrap(),
+        borsh::to_vec(&stored).unwrap()
+    );
+    assert_eq!(returned.authority(), &context.payer.pubkey());
+    assert_eq!(returned.data_len(), 5);
+}
+
+#[tokio::test]
+async fn version_0_metadata_is_migrated() {
+    let mut context = start().await;
+    let data_account = initialize(&mut context, |args| args).await.pubkey();
+    let (pda, bump_seed) = metadata_pda(&data_account);
+    let payer = context.payer.pubkey();
+
+    // Rewrite the data store as the first release of the program left it: the version 0
+    // layout, and contents its hash was never maintained for
+    let mut account = context
+        .banks_client
+        .get_account(pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let mut data = borsh::to_vec(&DataStoreAccountMetadataV0 {
+        data_type: DataStoreTypeOption::File,
+        authority: payer,
+        data_status: SerializationStatusOption::Initialized,
+        bump_seed,
+        data_hash: [0; 32],
+        is_dynamic: false,
+        space: 64,
+    })
+    .unwrap();
+    data.resize(METADATA_SIZE, 0);
+    account.data = data;
+    context.set_account(&pda, &AccountSharedData::from(account));
+
+    let mut account = context
+        .banks_client
+        .get_account(data_account)
+        .await
+        .unwrap()
+        .unwrap();
+    account.data[..5].copy_from_slice(b"hello");
+    let contents = account.data.clone();
+    context.set_account(&data_account, &AccountSharedData::from(account));
+
+    let migrate_ix = instruction(
+        vec![
+            AccountMeta::new_readonly(payer, true),
+            AccountMeta::new_readonly(data_account, false),
+            AccountMeta::new(pda, false),
+            AccountMeta::new_readonly(config_pda(), false),
+        ],
+        DataStoreInstruction::MigrateMetadata(MigrateMetadataArgs { debug: false }),
+    );
+    process(&mut context, &[migrate_ix], &[]).await.unwrap();
+
+    let account_metadata = metadata(&mut context, &data_account).await;
+    assert_eq!(account_metadata.version(), DATA_STORE_VERSION);
+    assert_eq!(account_metadata.authority(), &payer);
+    assert_eq!(account_metadata.bump_seed(), bump_seed);
+    assert_eq!(account_metadata.space(), 64);
+    assert_eq!(account_metadata.data_len(), 64);
+    assert_eq!(
+        account_metadata.data_hash(),
+        &compute_data_hash(&data_account, &contents)
+    );
+
+    // The migrated data store takes updates again
+    update(&mut context, &data_account, update_args(b"world", 0))
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn pre_crc_metadata_is_only_read_by_migrate_metadata() {
+    let mut context = start().await;
+    let data_account = initialize(&mut context, |args| args).await.pubkey();
+    let pda = metadata_pda(&data_account).0;
+    let payer = context.payer.pubkey();
+
+    // Stamp the metadata with version 1, which sealed no metadata_crc
+    let mut account = context
+        .banks_client
+        .get_account(pda)
+        .await
+        .unwrap()
+        .unwrap();
+    account.data[METADATA_VERSION_OFFSET] = 1;
+    context.set_account(&pda, &AccountSharedData::from(account));
+
+    let result = query(&mut context, get_metadata_ix(&data_account)).await;
+    assert_error(result.map(drop), DataStoreError::MigrationRequired);
+    let result = update(&mut context, &data_account, update_args(b"hello", 0)).await;
+    assert_error(result, DataStoreError::MigrationRequired);
+
+    let migrate_ix = instruction(
+        vec![
+            AccountMeta::new_readonly(payer, true),
+            AccountMeta::new_readonly(data_account, false),
+            AccountMeta::new(pda, false),
+            AccountMeta::new_readonly(config_pda(), false),
+        ],
+        DataStoreInstruction::MigrateMetadata(MigrateMetadataArgs { debug: false }),
+    );
+    process(&mut context, &[migrate_ix], &[]).await.unwrap();
+    assert_eq!(
+        metadata(&mut context, &data_account).await.version(),
+        DATA_STORE_VERSION
+    );
+    update(&mut context, &data_account, update_args(b"hello", 0))
+        .await
+        .unwrap();
+}
+
+#[test]
+fn utilization_of_empty_and_full_allocations() {
+    let mut metadata =
+        DataStoreAccountMetadata::new(Pubkey::new_unique(), DataStoreTypeOption::File, 255, false);
+    assert_eq!(metadata.utilization(), (0, 0));
+    assert_eq!(metadata.fragmentation_ratio(), 0.0);
+
+    metadata.set_space(64);
+    assert_eq!(metadata.utilization(), (0, 64));
+    assert_eq!(metadata.fragmentation_ratio(), 1.0);
+
+    metadata.set_data_len(16);
+    assert_eq!(metadata.utilization(), (16, 64));
+    assert_eq!(metadata.fragmentation_ratio(), 0.75);
+
+    metadata.set_data_len(64);
+    assert_eq!(metadata.utilization(), (64, 64));
+    assert_eq!(metadata.fragmentation_ratio(), 0.0);
+}
+
+#[tokio::test]
+async fn utilization_follows_writes() {
+    let mut context = start().await;
+    let data_account = initialize(&mut context, |args| args).await.pubkey();
+    assert_eq!(
+        metadata(&mut context, &data_account).await.utilization(),
+        (0, 64)
+    );
+
+    update(&mut context, &data_account, update_args(&[1; 64], 0))
+        .await
+        .unwrap();
+    let account_metadata = metadata(&mut context, &data_account).await;
+    assert_eq!(account_metadata.utilization(), (64, 64));
+    assert_eq!(account_metadata.fragmentation_ratio(), 0.0);
+}
+
+#[tokio::test]
+async fn metadata_is_rewritten_around_nested_cpis() {
+    let mut context = start().await;
+    let data_account = initialize(&mut context, |args| args).await.pubkey();
+    update(&mut context, &data_account, update_args(b"content", 0))
+        .await
+        .unwrap();
+    let payer = context.payer.pubkey();
+
+    // Finalizing stores the metadata, creates the content PDA through the system program,
+    // then stores the metadata again, all within a call from another program
+    process(
+        &mut context,
+        &[cpi_ix(finalize_register_ix(
+            &payer,
+            &data_account,
+            b"content",
+        ))],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    let account_metadata = metadata(&mut context, &data_account).await;
+    assert_eq!(
+        *account_metadata.data_status(),
+        SerializationStatusOption::Finalized
+    );
+    assert!(account_metadata.has_flag(FLAG_CONTENT_REGISTERED));
+    let content_account = context
+        .banks_client
+        .get_account(content_pda(b"content"))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(content_account.data, data_account.to_bytes());
+}
+
+#[tokio::test]
+async fn several_flags_toggle_at_once() {
+    let mut context = start().await;
+    let data_account = initialize(&mut context, |args| args).await.pubkey();
+    let payer = context.payer.pubkey();
+    let flags = metadata(&mut context, &data_account).await.flags();
+    assert_eq!(flags & (FLAG_APPEND_ONLY | FLAG_PRIVATE), 0);
+    assert_ne!(flags & FLAG_INDEXABLE, 0);
+
+    let mask = FLAG_APPEND_ONLY | FLAG_PRIVATE | FLAG_INDEXABLE;
+    process(
+        &mut context,
+        &[set_flags_ix(
+            &payer,
+            &data_account,
+            mask,
+            FLAG_APPEND_ONLY | FLAG_PRIVATE,
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+    let toggled = metadata(&mut context, &data_account).await;
+    assert_eq!(toggled.flags(), flags ^ mask);
+    assert!(toggled.is_append_only() && toggled.is_private());
+
+    // is_dynamic is fixed at initialization, though restating it is harmless
+    let result = process(
+        &mut context,
+        &[set_flags_ix(
+            &payer,
+            &data_account,
+            FLAG_DYNAMIC | FLAG_PRIVATE,
+            FLAG_DYNAMIC,
+        )],
+        &[],
+    )
+    .await;
+    assert_error(result, DataStoreError::ImmutableFlag);
+    assert_eq!(
+        metadata(&mut context, &data_account).await.flags(),
+        flags ^ mask
+    );
+    process(
+        &mut context,
+        &[set_flags_ix(
+            &payer,
+            &data_account,
+            FLAG_DYNAMIC | FLAG_PRIVATE,
+            0,
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        metadata(&mut context, &data_account).await.flags(),
+        flags ^ mask ^ FLAG_PRIVATE
+    );
+}
+
+#[tokio::test]
+async fn frozen_data_store_rejects_every_write() {
+    let mut context = start().await;
+    let data_account = initialize(&mut context, |args| args).await.pubkey();
+    let payer = context.payer.pubkey();
+    update(&mut context, &data_account, update_args(b"hello", 0))
+        .await
+        .unwrap();
+    process(
+        &mut context,
+        &[set_flags_ix(
+            &payer,
+            &data_account,
+            FLAG_FROZEN,
+            FLAG_FROZEN,
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+    let frozen = context
+        .banks_client
+        .get_account(metadata_pda(&data_account).0)
+        .await
+        .unwrap();
+
+    let headers =
+        AccountMeta::new_readonly(derive_headers_pda(&program_id(), &data_account).0, false);
+    let writes = [
+        DataStoreInstruction::ConvertDataType(ConvertDataTypeArgs {
+            debug: false,
+            data_type: DataStoreTypeOption::Symlink,
+        }),
+        DataStoreInstruction::SetName(SetNameArgs {
+            debug: false,
+            name: "renamed".to_string(),
+        }),
+        DataStoreInstruction::RecomputeHash(RecomputeHashArgs {
+            debug: false,
+            data_len: Some(64),
+        }),
+    ];
+    for write in writes {
+        let result = process(
+            &mut context,
+            &[data_store_ix(&payer, &data_account, &[], write)],
+            &[],
+        )
+        .await;
+        assert_error(result, DataStoreError::Frozen);
+    }
+    let reinitialize = DataStoreInstruction::Reinitialize(ReinitializeArgs {
+        debug: false,
+        data_type: DataStoreTypeOption::File,
+        authority: payer,
+        is_dynamic: false,
+    });
+    let result = process(
+        &mut context,
+        &[data_store_ix(
+            &payer,
+            &data_account,
+            &[headers],
+            reinitialize,
+        )],
+        &[],
+    )
+    .await;
+    assert_error(result, DataStoreError::Frozen);
+    let result = update(&mut context, &data_account, update_args(b"world", 0)).await;
+    assert_error(result, DataStoreError::Frozen);
+    assert_eq!(
+        context
+            .banks_client
+            .get_account(metadata_pda(&data_account).0)
+            .await
+            .unwrap(),
+        frozen
+    );
+
+    // Thawing lets writes through again
+    process(
+        &mut context,
+        &[set_flags_ix(&payer, &data_account, FLAG_FROZEN, 0)],
+        &[],
+    )
+    .await
+    .unwrap();
+    update(&mut context, &data_account, update_args(b"world", 0))
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn account_state_reports_finalized_and_frozen() {
+    let mut context = start().await;
+    let data_account = initialize(&mut context, |args| args).await.pubkey();
+    let payer = context.payer.pubkey();
+    let state = |return_data: Vec<u8>| {
+        assert_eq!(return_data.len(), 1);
+        return_data[0]
+    };
+
+    let initialized = state(
+        query(&mut context, get_account_state_ix(&data_account))
+            .await
+            .unwrap(),
+    );
+    assert_eq!(
+        initialized & ACCOUNT_STATE_STATUS_MASK,
+        SerializationStatusOption::Initialized as u8
+    );
+    assert_eq!(initialized & ACCOUNT_STATE_FROZEN, 0);
+
+    // Frozen keeps the status alongside the bit
+    process(
+        &mut context,
+        &[set_flags_ix(
+            &payer,
+            &data_account,
+            FLAG_FROZEN,
+            FLAG_FROZEN,
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+    let frozen = state(
+        query(&mut context, get_account_state_ix(&data_account))
+            .await
+            .unwrap(),
+    );
+    assert_eq!(frozen, initialized | ACCOUNT_STATE_FROZEN);
+    process(
+        &mut context,
+        &[set_flags_ix(&payer, &data_account, FLAG_FROZEN, 0)],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    process(&mut context, &[finalize_ix(&payer, &data_account)], &[])
+        .await
+        .unwrap();
+    let finalized = state(
+        query(&mut context, get_account_state_ix(&data_account))
+            .await
+            .unwrap(),
+    );
+    assert_eq!(
+        finalized & ACCOUNT_STATE_STATUS_MASK,
+        SerializationStatusOption::Finalized as u8
+    );
+    assert_eq!(finalized & ACCOUNT_STATE_FROZEN, 0);
+    assert_eq!(
+        finalized,
+        metadata(&mut context, &data_account).await.account_state()
+    );
+}
+
+#[tokio::test]
+async fn data_type_is_converted_only_to_a_compatible_type() {
+    let mut context = start().await;
+    let data_account = initialize(&mut context, |args| args).await.pubkey();
+    let payer = context.payer.pubkey();
+    let stranger = Keypair::new();
+    let convert = |authority: &Pubkey, data_type| {
+        data_store_ix(
+            authority,
+            &data_account,
+            &[],
+            DataStoreInstruction::ConvertDataType(ConvertDataTypeArgs {
+                debug: false,
+                data_type,
+            }),
+        )
+    };
+    update(&mut context, &data_account, update_args(b"hello", 0))
+        .await
+        .unwrap();
+
+    // Five bytes are neither a directory nor a symlink target
+    for data_type in [DataStoreTypeOption::Directory, DataStoreTypeOption::Symlink] {
+        let result = process(&mut context, &[convert(&payer, data_type)], &[]).await;
+        assert_error(result, DataStoreError::DataVerificationFailed);
+    }
+    let result = process(
+        &mut context,
+        &[convert(&payer, DataStoreTypeOption::File)],
+        &[],
+    )
+    .await;
+    assert_error(result, DataStoreError::InvalidDataType);
+
+    // A single pubkey is both
+    let target = Pubkey::new_unique();
+    update(&mut context, &data_account, update_args(target.as_ref(), 0))
+        .await
+        .unwrap();
+    let result = process(
+        &mut context,
+        &[convert(&stranger.pubkey(), DataStoreTypeOption::Symlink)],
+        &[&stranger],
+    )
+    .await;
+    assert_error(result, DataStoreError::InvalidAuthority);
+    for data_type in [DataStoreTypeOption::Symlink, DataStoreTypeOption::Directory] {
+        process(&mut context, &[convert(&payer, data_type.clone())], &[])
+            .await
+            .unwrap();
+        assert_eq!(
+            *metadata(&mut context, &data_account).await.data_type(),
+            data_type
+        );
+    }
+
+    process(&mut context, &[finalize_ix(&payer, &data_account)], &[])
+        .await
+        .unwrap();
+    let result = process(
+        &mut context,
+        &[convert(&payer, DataStoreTypeOption::File)],
+        &[],
+    )
+    .await;
+    assert_error(result, DataStoreError::AlreadyFinalized);
+}
+
+#[tokio::test]
+async fn finalized_file_is_reinitialized_empty() {
+    let mut context = start().await;
+    let payer = context.payer.pubkey();
+    let new_authority = Pubkey::new_unique();
+    let data_account = initialize(&mut context, |args| InitializeDataStoreArgs {
+        initial_data: b"hello".to_vec(),
+        ..args
+    })
+    .await
+    .pubkey();
+    process(&mut context, &[finalize_ix(&payer, &data_account)], &[])
+        .await
+        .unwrap();
+    let finalized = metadata(&mut context, &data_account).await;
+    let lamports = context
+        .banks_client
+        .get_balance(data_account)
+        .await
+        .unwrap();
+
+    let headers =
+        AccountMeta::new_readonly(derive_headers_pda(&program_id(), &data_account).0, false);
+    let reinitialize = DataStoreInstruction::Reinitialize(ReinitializeArgs {
+        debug: false,
+        data_type: DataStoreTypeOption::Directory,
+        authority: new_authority,
+        is_dynamic: false,
+    });
+    process(
+        &mut context,
+        &[data_store_ix(
+            &payer,
+            &data_account,
+            &[headers],
+            reinitialize,
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    // The same account and rent, holding a fresh empty data store
+    let account = context
+        .banks_client
+        .get_account(data_account)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(account.lamports, lamports);
+    assert_eq!(account.data, vec![0; 64]);
+    let account_metadata = metadata(&mut context, &data_account).await;
+    assert_eq!(
+        *account_metadata.data_status(),
+        SerializationStatusOption::Initialized
+    );
+    assert_eq!(
+        *account_metadata.data_type(),
+        DataStoreTypeOption::Directory
+    );
+    assert_eq!(account_metadata.authority(), &new_authority);
+    assert_eq!(account_metadata.data_len(), 0);
+    assert_eq!(account_metadata.space(), 64);
+    assert_eq!(
+        account_metadata.data_hash(),
+        &compute_data_hash(&data_account, b"")
+    );
+    // The revision keeps counting, so the old ETag is not reused
+    assert_eq!(account_metadata.revision(), finalized.revision() + 1);
+    assert_ne!(account_metadata.etag(), finalized.etag());
+}
+
+#[tokio::test]
+async fn cache_ttl_is_set_and_read_back() {
+    let mut context = start().await;
+    let payer = context.payer.pubkey();
+    let data_account = initialize(&mut context, |args| InitializeDataStoreArgs {
+        cache_ttl_seconds: 3600,
+        ..args
+    })
+    .await
+    .pubkey();
+    let set_cache_ttl = |cache_ttl_seconds| {
+        DataStoreInstruction::SetCacheTtl(SetCacheTtlArgs {
+            debug: false,
+            cache_ttl_seconds,
+        })
+    };
+    let returned_cache_ttl = |return_data: Vec<u8>| {
+        DataStoreAccountMetadata::try_from_slice(&return_data)
+            .unwrap()
+            .cache_ttl_seconds()
+    };
+    let return_data = query(&mut context, get_metadata_ix(&data_account))
+        .await
+        .unwrap();
+    assert_eq!(returned_cache_ttl(return_data), 3600);
+
+    // Only the authority sets it, zero turning caching off
+    let stranger = Keypair::new();
+    let result = process(
+        &mut context,
+        &[data_store_ix(
+            &stranger.pubkey(),
+            &data_account,
+            &[],
+            set_cache_ttl(60),
+        )],
+        &[&stranger],
+    )
+    .await;
+    assert_error(result, DataStoreError::InvalidAuthority);
+    for cache_ttl_seconds in [60, 0] {
+        process(
+            &mut context,
+            &[data_store_ix(
+                &payer,
+                &data_account,
+                &[],
+                set_cache_ttl(cache_ttl_seconds),
+            )],
+            &[],
+        )
+        .await
+        .unwrap();
+        let return_data = query(&mut context, get_metadata_ix(&data_account))
+            .await
+            .unwrap();
+        assert_eq!(returned_cache_ttl(return_data), cache_ttl_seconds);
+    }
+}
+
+#[tokio::test]
+async fn indexable_flag_is_toggled_by_the_authority() {
+    let mut context = start().await;
+    let payer = context.payer.pubkey();
+    let listed = initialize(&mut context, |args| args).await.pubkey();
+    let unlisted = initialize(&mut context, |args| InitializeDataStoreArgs {
+        indexable: false,
+        ..args
+    })
+    .await
+    .pubkey();
+    let returned_indexable = |return_data: Vec<u8>| {
+        DataStoreAccountMetadata::try_from_slice(&return_data)
+            .unwrap()
+            .is_indexable()
+    };
+
+    // Listed by default, unless opted out at initialization
+    for (data_account, indexable) in [(listed, true), (unlisted, false)] {
+        let return_data = query(&mut context, get_metadata_ix(&data_account))
+            .await
+            .unwrap();
+        assert_eq!(returned_indexable(return_data), indexable);
+    }
+
+    let stranger = Keypair::new();
+    let result = process(
+        &mut context,
+        &[set_flags_ix(&stranger.pubkey(), &listed, FLAG_INDEXABLE, 0)],
+        &[&stranger],
+    )
+    .await;
+    assert_error(result, DataStoreError::InvalidAuthority);
+    for indexable in [false, true, false] {
+        let values = if indexable { FLAG_INDEXABLE } else { 0 };
+        process(
+            &mut context,
+            &[set_flags_ix(&payer, &listed, FLAG_INDEXABLE, values)],
+            &[],
+        )
+        .await
+        .unwrap();
+        let return_data = query(&mut context, get_metadata_ix(&listed)).await.unwrap();
+        assert_eq!(returned_indexable(return_data), indexable);
+    }
+}
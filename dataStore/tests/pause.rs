@@ -0,0 +1,288 @@
+mod common;
+
+use common::{
+    assert_error, config_pda, init_args, initialize, initialize_ix, instruction, metadata,
+    metadata_pda, process, program_id, query, start, update, update_args, update_ix,
+};
+use dataaccount::{
+    error::DataStoreError,
+    instruction::DataStoreInstruction,
+    state::{
+        GetMetadataArgs, ProgramConfig, RawMetadataWriteArgs, SetPauseArgs, CONFIG_SEED,
+        METADATA_AUTHORITY_OFFSET, METADATA_DATA_HASH_OFFSET, METADATA_SIZE,
+    },
+};
+use solana_program_test::{tokio, ProgramTestContext};
+use solana_sdk::{
+    account::AccountSharedData,
+    bpf_loader_upgradeable::{self, UpgradeableLoaderState},
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_program,
+};
+
+fn program_data_pda() -> Pubkey {
+    Pubkey::find_program_address(&[program_id().as_ref()], &bpf_loader_upgradeable::id()).0
+}
+
+/// Stores a ProgramData account naming upgrade_authority, as a deploy with the
+/// upgradeable loader would
+fn set_upgrade_authority(context: &mut ProgramTestContext, upgrade_authority: &Pubkey) {
+    let state = UpgradeableLoaderState::ProgramData {
+        slot: 0,
+        upgrade_authority_address: Some(*upgrade_authority),
+    };
+    let account = AccountSharedData::new_data_with_space(
+        1_000_000_000,
+        &state,
+        UpgradeableLoaderState::size_of_programdata_metadata(),
+        &bpf_loader_upgradeable::id(),
+    )
+    .unwrap();
+    context.set_account(&program_data_pda(), &account);
+}
+
+fn set_pause_ix(admin: &Pubkey, paused: bool) -> Instruction {
+    instruction(
+        vec![
+            AccountMeta::new(*admin, true),
+            AccountMeta::new(config_pda(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(program_data_pda(), false),
+        ],
+        DataStoreInstruction::SetPause(SetPauseArgs {
+            debug: false,
+            paused,
+        }),
+    )
+}
+
+fn get_metadata_ix(data_account: &Pubkey) -> Instruction {
+    instruction(
+        vec![
+            AccountMeta::new_readonly(*data_account, false),
+            AccountMeta::new_readonly(metadata_pda(data_account).0, false),
+        ],
+        DataStoreInstruction::GetMetadata(GetMetadataArgs { debug: false }),
+    )
+}
+
+/// Funds a fresh keypair to pay for its own transactions
+fn funded_keypair(context: &mut ProgramTestContext) -> Keypair {
+    let keypair = Keypair::new();
+    context.set_account(
+        &keypair.pubkey(),
+        &AccountSharedData::new(1_000_000_000, 0, &system_program::id()),
+    );
+    keypair
+}
+
+#[tokio::test]
+async fn only_the_admin_toggles_the_pause() {
+    let mut context = start().await;
+    let payer = context.payer.pubkey();
+    set_upgrade_authority(&mut context, &payer);
+
+    // The config can only be created by the upgrade authority
+    let intruder = funded_keypair(&mut context);
+    let result = process(
+        &mut context,
+        &[set_pause_ix(&intruder.pubkey(), true)],
+        &[&intruder],
+    )
+    .await;
+    assert_error(result, DataStoreError::InvalidAuthority);
+
+    process(&mut context, &[set_pause_ix(&payer, false)], &[])
+        .await
+        .unwrap();
+    let config = context
+        .banks_client
+        .get_account(config_pda())
+        .await
+        .unwrap()
+        .unwrap();
+    let config: ProgramConfig = borsh::from_slice(&config.data).unwrap();
+    assert_eq!(config.admin, payer);
+    assert!(!config.paused);
+    assert_eq!(
+        config.bump_seed,
+        Pubkey::find_program_address(&[CONFIG_SEED], &program_id()).1
+    );
+
+    // And then only toggled by its admin
+    let result = process(
+        &mut context,
+        &[set_pause_ix(&intruder.pubkey(), true)],
+        &[&intruder],
+    )
+    .await;
+    assert_error(result, DataStoreError::InvalidAuthority);
+    process(&mut context, &[set_pause_ix(&payer, true)], &[])
+        .await
+        .unwrap();
+    process(&mut context, &[set_pause_ix(&payer, false)], &[])
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn pause_blocks_writes_but_not_reads() {
+    let mut context = start().await;
+    let payer = context.payer.pubkey();
+    set_upgrade_authority(&mut context, &payer);
+    let data_account = initialize(&mut context, |args| args).await.pubkey();
+
+    process(&mut context, &[set_pause_ix(&payer, true)], &[])
+        .await
+        .unwrap();
+    let result = update(&mut context, &data_account, update_args(b"hello", 0)).await;
+    assert_error(result, DataStoreError::ProgramPaused);
+    let new_account = Keypair::new();
+    let args = init_args(&new_account.pubkey(), &payer, 64);
+    let result = process(
+        &mut context,
+        &[initialize_ix(&payer, &new_account.pubkey(), args.clone())],
+        &[&new_account],
+    )
+    .await;
+    assert_error(result, DataStoreError::ProgramPaused);
+    query(&mut context, get_metadata_ix(&data_account))
+        .await
+        .unwrap();
+
+    process(&mut context, &[set_pause_ix(&payer, false)], &[])
+        .await
+        .unwrap();
+    update(&mut context, &data_account, update_args(b"hello", 0))
+        .await
+        .unwrap();
+    process(
+        &mut context,
+        &[initialize_ix(&payer, &new_account.pubkey(), args)],
+        &[&new_account],
+    )
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn writes_require_the_config_pda() {
+    let mut context = start().await;
+    let payer = context.payer.pubkey();
+    let data_account = initialize(&mut context, |args| args).await.pubkey();
+    let with_config = |config: Pubkey| {
+        let mut update_ix = update_ix(&payer, &data_account, update_args(b"hello", 0));
+        update_ix.accounts.last_mut().unwrap().pubkey = config;
+        update_ix
+    };
+
+    // Before the config is created, another empty account does not stand in for it
+    let result = process(&mut context, &[with_config(Pubkey::new_unique())], &[]).await;
+    assert_error(result, DataStoreError::InvalidPDA);
+
+    // Nor does it once the config is created, nor another account of the program
+    set_upgrade_authority(&mut context, &payer);
+    process(&mut context, &[set_pause_ix(&payer, true)], &[])
+        .await
+        .unwrap();
+    let result = process(&mut context, &[with_config(Pubkey::new_unique())], &[]).await;
+    assert_error(result, DataStoreError::InvalidPDA);
+    let result = process(
+        &mut context,
+        &[with_config(metadata_pda(&data_account).0)],
+        &[],
+    )
+    .await;
+    assert!(result.is_err());
+    let result = process(&mut context, &[with_config(config_pda())], &[]).await;
+    assert_error(result, DataStoreError::ProgramPaused);
+}
+
+fn raw_metadata_write_ix(
+    admin: &Pubkey,
+    data_account: &Pubkey,
+    offset: u64,
+    bytes: &[u8],
+) -> Instruction {
+    instruction(
+        vec![
+            AccountMeta::new_readonly(*admin, true),
+            AccountMeta::new_readonly(*data_account, false),
+            AccountMeta::new(metadata_pda(data_account).0, false),
+            AccountMeta::new_readonly(config_pda(), false),
+            AccountMeta::new_readonly(program_data_pda(), false),
+        ],
+        DataStoreInstruction::RawMetadataWrite(RawMetadataWriteArgs {
+            debug: false,
+            offset,
+            bytes: bytes.to_vec(),
+        }),
+    )
+}
+
+#[tokio::test]
+async fn raw_metadata_writes_are_admin_only_and_bounded() {
+    let mut context = start().await;
+    let payer = context.payer.pubkey();
+    set_upgrade_authority(&mut context, &payer);
+    let data_account = initialize(&mut context, |args| args).await.pubkey();
+    let backfilled = [7; 32];
+    let hash_offset = METADATA_DATA_HASH_OFFSET as u64;
+
+    let intruder = funded_keypair(&mut context);
+    let result = process(
+        &mut context,
+        &[raw_metadata_write_ix(
+            &intruder.pubkey(),
+            &data_account,
+            hash_offset,
+            &backfilled,
+        )],
+        &[&intruder],
+    )
+    .await;
+    assert_error(result, DataStoreError::InvalidAuthority);
+
+    // Past the end of the metadata account, or over the authority
+    let out_of_bounds = [
+        (METADATA_SIZE as u64 - 1, [0; 2].as_slice()),
+        (METADATA_SIZE as u64, &[0]),
+        (METADATA_AUTHORITY_OFFSET as u64, &[0; 32]),
+    ];
+    for (offset, bytes) in out_of_bounds {
+        let result = process(
+            &mut context,
+            &[raw_metadata_write_ix(&payer, &data_account, offset, bytes)],
+            &[],
+        )
+        .await;
+        assert_error(result, DataStoreError::InvalidInstructionData);
+    }
+    let result = process(
+        &mut context,
+        &[raw_metadata_write_ix(&payer, &data_account, u64::MAX, &[0])],
+        &[],
+    )
+    .await;
+    assert_error(result, DataStoreError::Overflow);
+
+    // The admin's write lands, the metadata_crc resealed over it
+    process(
+        &mut context,
+        &[raw_metadata_write_ix(
+            &payer,
+            &data_account,
+            hash_offset,
+            &backfilled,
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        *metadata(&mut context, &data_account).await.data_hash(),
+        backfilled
+    );
+}
@@ -0,0 +1,411 @@
+mod common;
+
+use borsh::BorshDeserialize;
+use common::{
+    assert_error, config_pda, cpi_ix, initialize, instruction, metadata, metadata_pda, process,
+    process_with_metadata, query, start, update, update_args,
+};
+use dataaccount::{
+    error::DataStoreError,
+    instruction::DataStoreInstruction,
+    state::{
+        compute_data_hash, GetDataHashArgs, GetETagArgs, GetSpaceInfoArgs, InitializeDataStoreArgs,
+        ReadDataArgs, RegionHashArgs, SetReaderArgs, SpaceInfo, METADATA_DATA_HASH_OFFSET,
+        READ_DATA_HEADER_LEN,
+    },
+};
+use solana_program::hash::hashv;
+use solana_program_test::{tokio, BanksClientError, ProgramTestContext};
+use solana_sdk::{
+    account::AccountSharedData,
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+};
+
+/// Bytes written per transaction, well within the transaction size limit
+const CHUNK: usize = 512;
+
+fn data(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i * 31 + 7) as u8).collect()
+}
+
+/// Initializes a File data store of space bytes holding data
+async fn create_file(context: &mut ProgramTestContext, space: u64, data: &[u8]) -> Pubkey {
+    let data_account = initialize(context, |args| InitializeDataStoreArgs { space, ..args })
+        .await
+        .pubkey();
+    for (index, chunk) in data.chunks(CHUNK).enumerate() {
+        update(
+            context,
+            &data_account,
+            update_args(chunk, (index * CHUNK) as u64),
+        )
+        .await
+        .unwrap();
+    }
+    data_account
+}
+
+fn region_hash_ix(data_account: &Pubkey, offset: u64, len: u64) -> Instruction {
+    instruction(
+        vec![
+            AccountMeta::new_readonly(*data_account, false),
+            AccountMeta::new_readonly(metadata_pda(data_account).0, false),
+        ],
+        DataStoreInstruction::RegionHash(RegionHashArgs {
+            debug: false,
+            offset,
+            len,
+        }),
+    )
+}
+
+fn get_data_hash_ix(data_account: &Pubkey) -> Instruction {
+    instruction(
+        vec![
+            AccountMeta::new_readonly(*data_account, false),
+            AccountMeta::new_readonly(metadata_pda(data_account).0, false),
+        ],
+        DataStoreInstruction::GetDataHash(GetDataHashArgs { debug: false }),
+    )
+}
+
+fn get_etag_ix(data_account: &Pubkey) -> Instruction {
+    instruction(
+        vec![
+            AccountMeta::new_readonly(*data_account, false),
+            AccountMeta::new_readonly(metadata_pda(data_account).0, false),
+        ],
+        DataStoreInstruction::GetETag(GetETagArgs { debug: false }),
+    )
+}
+
+/// ReadData of len bytes at offset, signed by reader if any
+fn read_data_ix(
+    data_account: &Pubkey,
+    reader: Option<&Pubkey>,
+    offset: u64,
+    len: u64,
+    clamp: bool,
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*data_account, false),
+        AccountMeta::new_readonly(metadata_pda(data_account).0, false),
+    ];
+    accounts.extend(reader.map(|reader| AccountMeta::new_readonly(*reader, true)));
+    instruction(
+        accounts,
+        DataStoreInstruction::ReadData(ReadDataArgs {
+            debug: false,
+            offset,
+            len,
+            clamp,
+            verify: false,
+        }),
+    )
+}
+
+fn verified_read_data_ix(data_account: &Pubkey, offset: u64, len: u64) -> Instruction {
+    instruction(
+        vec![
+            AccountMeta::new_readonly(*data_account, false),
+            AccountMeta::new_readonly(metadata_pda(data_account).0, false),
+        ],
+        DataStoreInstruction::ReadData(ReadDataArgs {
+            debug: false,
+            offset,
+            len,
+            clamp: false,
+            verify: true,
+        }),
+    )
+}
+
+/// Bytes returned by ReadData, after their length
+fn read_bytes(return_data: &[u8]) -> &[u8] {
+    let (len, bytes) = return_data.split_at(READ_DATA_HEADER_LEN);
+    assert_eq!(
+        u32::from_le_bytes(len.try_into().unwrap()) as usize,
+        bytes.len()
+    );
+    bytes
+}
+
+fn set_reader_ix(authority: &Pubkey, data_account: &Pubkey, reader: Option<Pubkey>) -> Instruction {
+    instruction(
+        vec![
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new_readonly(*data_account, false),
+            AccountMeta::new(metadata_pda(data_account).0, false),
+            AccountMeta::new_readonly(config_pda(), false),
+        ],
+        DataStoreInstruction::SetReader(SetReaderArgs {
+            debug: false,
+            reader,
+        }),
+    )
+}
+
+/// Like query, also signed by signer
+async fn query_signed(
+    context: &mut ProgramTestContext,
+    instruction: Instruction,
+    signer: &Keypair,
+) -> Result<Vec<u8>, BanksClientError> {
+    let (return_data, _) = process_with_metadata(context, &[instruction], &[signer]).await?;
+    Ok(return_data.unwrap_or_default())
+}
+
+#[tokio::test]
+async fn region_hash_matches_off_chain_hash() {
+    let mut context = start().await;
+    let data = data(4096);
+    let data_account = create_file(&mut context, 4096, &data).await;
+
+    let region_hash = query(&mut context, region_hash_ix(&data_account, 1000, 256))
+        .await
+        .unwrap();
+    assert_eq!(region_hash, hashv(&[&data[1000..1256]]).to_bytes());
+
+    // The last byte of the account is the last one that can be hashed
+    let region_hash = query(&mut context, region_hash_ix(&data_account, 3840, 256))
+        .await
+        .unwrap();
+    assert_eq!(region_hash, hashv(&[&data[3840..]]).to_bytes());
+    let result = query(&mut context, region_hash_ix(&data_account, 3841, 256)).await;
+    assert_error(result.map(drop), DataStoreError::InsufficientSpace);
+}
+
+#[tokio::test]
+async fn data_hash_is_returned_to_calling_program() {
+    let mut context = start().await;
+    let data_account = create_file(&mut context, 64, b"hello").await;
+
+    let data_hash = query(&mut context, cpi_ix(get_data_hash_ix(&data_account)))
+        .await
+        .unwrap();
+    assert_eq!(data_hash, compute_data_hash(&data_account, b"hello"));
+
+    update(&mut context, &data_account, update_args(b" world", 5))
+        .await
+        .unwrap();
+    let data_hash = query(&mut context, cpi_ix(get_data_hash_ix(&data_account)))
+        .await
+        .unwrap();
+    assert_eq!(data_hash, compute_data_hash(&data_account, b"hello world"));
+    assert_eq!(
+        data_hash,
+        metadata(&mut context, &data_account).await.data_hash()
+    );
+}
+
+#[tokio::test]
+async fn private_data_is_only_read_by_authority_and_reader() {
+    let mut context = start().await;
+    let data_account = initialize(&mut context, |args| InitializeDataStoreArgs {
+        private: true,
+        ..args
+    })
+    .await
+    .pubkey();
+    update(&mut context, &data_account, update_args(b"secret", 0))
+        .await
+        .unwrap();
+    let payer = context.payer.pubkey();
+    let reader = Keypair::new();
+
+    // Unsigned or by anyone else
+    let read = |reader: Option<&Pubkey>| read_data_ix(&data_account, reader, 0, 6, false);
+    let result = query(&mut context, read(None)).await;
+    assert_error(result.map(drop), DataStoreError::InvalidAuthority);
+    let result = query_signed(&mut context, read(Some(&reader.pubkey())), &reader).await;
+    assert_error(result.map(drop), DataStoreError::InvalidAuthority);
+
+    // By the authority
+    let data = query(&mut context, read(Some(&payer))).await.unwrap();
+    assert_eq!(read_bytes(&data), b"secret");
+
+    // By the reader delegate, until revoked
+    process(
+        &mut context,
+        &[set_reader_ix(&payer, &data_account, Some(reader.pubkey()))],
+        &[],
+    )
+    .await
+    .unwrap();
+    let data = query_signed(&mut context, read(Some(&reader.pubkey())), &reader).await;
+    assert_eq!(read_bytes(&data.unwrap()), b"secret");
+    process(
+        &mut context,
+        &[set_reader_ix(&payer, &data_account, None)],
+        &[],
+    )
+    .await
+    .unwrap();
+    let result = query_signed(&mut context, read(Some(&reader.pubkey())), &reader).await;
+    assert_error(result.map(drop), DataStoreError::InvalidAuthority);
+}
+
+#[tokio::test]
+async fn etag_changes_with_updates_only() {
+    let mut context = start().await;
+    let data_account = create_file(&mut context, 64, b"hello").await;
+
+    let etag = query(&mut context, get_etag_ix(&data_account))
+        .await
+        .unwrap();
+    assert_eq!(etag, metadata(&mut context, &data_account).await.etag());
+    for _ in 0..2 {
+        assert_eq!(
+            query(&mut context, get_etag_ix(&data_account))
+                .await
+                .unwrap(),
+            etag
+        );
+    }
+
+    // Rewriting the same bytes is still a new revision
+    update(&mut context, &data_account, update_args(b"hello", 0))
+        .await
+        .unwrap();
+    let updated_etag = query(&mut context, get_etag_ix(&data_account))
+        .await
+        .unwrap();
+    assert_ne!(updated_etag, etag);
+    assert_eq!(
+        updated_etag,
+        metadata(&mut context, &data_account).await.etag()
+    );
+}
+
+#[tokio::test]
+async fn reads_are_bounded_by_used_bytes() {
+    let mut context = start().await;
+    // 11 used bytes of a 64-byte account
+    let data_account = create_file(&mut context, 64, b"hello world").await;
+    let read = |offset, len, clamp| read_data_ix(&data_account, None, offset, len, clamp);
+
+    // In range
+    let data = query(&mut context, read(6, 5, false)).await.unwrap();
+    assert_eq!(read_bytes(&data), b"world");
+
+    // Clamped to the used bytes
+    let data = query(&mut context, read(6, 50, true)).await.unwrap();
+    assert_eq!(read_bytes(&data), b"world");
+    let data = query(&mut context, read(20, 10, true)).await.unwrap();
+    assert_eq!(read_bytes(&data), b"");
+
+    // Strict reads past the used bytes fail, though within the allocated space
+    let result = query(&mut context, read(6, 6, false)).await;
+    assert_error(result.map(drop), DataStoreError::InsufficientSpace);
+    let result = query(&mut context, read(0, 64, false)).await;
+    assert_error(result.map(drop), DataStoreError::InsufficientSpace);
+}
+
+#[tokio::test]
+async fn verified_read_of_tampered_data_fails() {
+    let mut context = start().await;
+    let data_account = create_file(&mut context, 64, b"hello world").await;
+
+    let data = query(&mut context, verified_read_data_ix(&data_account, 0, 5))
+        .await
+        .unwrap();
+    assert_eq!(read_bytes(&data), b"hello");
+
+    // Corrupt a byte outside the region read, bypassing the program
+    let mut account = context
+        .banks_client
+        .get_account(data_account)
+        .await
+        .unwrap()
+        .unwrap();
+    account.data[8] ^= 0xff;
+    context.set_account(&data_account, &AccountSharedData::from(account));
+
+    // Unverified reads serve the bytes as they are, verified reads refuse them
+    let data = query(&mut context, read_data_ix(&data_account, None, 0, 5, false))
+        .await
+        .unwrap();
+    assert_eq!(read_bytes(&data), b"hello");
+    let result = query(&mut context, verified_read_data_ix(&data_account, 0, 5)).await;
+    assert_error(result.map(drop), DataStoreError::DataVerificationFailed);
+}
+
+#[tokio::test]
+async fn data_hash_of_corrupt_metadata_is_refused() {
+    let mut context = start().await;
+    let data_account = create_file(&mut context, 64, b"hello").await;
+    let pda = metadata_pda(&data_account).0;
+
+    // Flip a byte of the stored data_hash, bypassing the program
+    let mut account = context
+        .banks_client
+        .get_account(pda)
+        .await
+        .unwrap()
+        .unwrap();
+    account.data[METADATA_DATA_HASH_OFFSET] ^= 0xff;
+    context.set_account(&pda, &AccountSharedData::from(account));
+
+    let result = query(&mut context, get_data_hash_ix(&data_account)).await;
+    assert_error(result.map(drop), DataStoreError::CorruptMetadata);
+}
+
+/// Compute units of GetDataHash against GetETag, which loads the same metadata.
+/// Builtins are metered at a flat cost under program-test, so this only guards
+/// against GetDataHash growing extra work; measure on a validator for real costs.
+#[tokio::test]
+async fn data_hash_compute_units() {
+    let mut context = start().await;
+    let data_account = create_file(&mut context, 4096, &data(4096)).await;
+
+    let (_, data_hash_units) =
+        process_with_metadata(&mut context, &[get_data_hash_ix(&data_account)], &[])
+            .await
+            .unwrap();
+    let (_, etag_units) = process_with_metadata(&mut context, &[get_etag_ix(&data_account)], &[])
+        .await
+        .unwrap();
+    println!("GetDataHash: {data_hash_units} CUs, GetETag: {etag_units} CUs");
+    assert!(data_hash_units <= etag_units);
+}
+
+#[tokio::test]
+async fn space_info_matches_a_partially_written_account() {
+    let mut context = start().await;
+    let data_account = create_file(&mut context, 4096, &data(1000)).await;
+    let pda = metadata_pda(&data_account).0;
+
+    let space_info_ix = instruction(
+        vec![
+            AccountMeta::new_readonly(data_account, false),
+            AccountMeta::new_readonly(pda, false),
+        ],
+        DataStoreInstruction::GetSpaceInfo(GetSpaceInfoArgs { debug: false }),
+    );
+    let return_data = query(&mut context, space_info_ix).await.unwrap();
+    let space_info = SpaceInfo::try_from_slice(&return_data).unwrap();
+
+    let account = context
+        .banks_client
+        .get_account(data_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let metadata_lamports = context.banks_client.get_balance(pda).await.unwrap();
+    assert_eq!(
+        space_info,
+        SpaceInfo {
+            allocated: account.data.len() as u64,
+            used: 1000,
+            is_dynamic: false,
+            rent_lamports: account.lamports + metadata_lamports,
+        }
+    );
+    assert_eq!(space_info.allocated, 4096);
+    assert_eq!(
+        space_info.used,
+        metadata(&mut context, &data_account).await.data_len() as u64
+    );
+}
@@ -0,0 +1,158 @@
+mod common;
+
+use common::{
+    config_pda, initialize, instruction, metadata, metadata_pda, process, program_id, start,
+    update_args,
+};
+use dataaccount::{
+    error::DataStoreError,
+    instruction::DataStoreInstruction,
+    state::{relayed_update_message, InitializeDataStoreArgs, UpdateDataStoreArgs},
+};
+use solana_program_test::{tokio, BanksClientError, ProgramTestContext};
+use solana_sdk::{
+    ed25519_program,
+    instruction::{AccountMeta, Instruction, InstructionError},
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_program, sysvar,
+    transaction::TransactionError,
+};
+
+/// Ed25519 program instruction verifying the signature of message by signer, with the
+/// public key, signature and message in its own data
+fn ed25519_ix(signer: &Keypair, message: &[u8]) -> Instruction {
+    // Header of signature count and padding, then a single record of offsets
+    const DATA_START: u16 = 2 + 14;
+    let public_key_offset = DATA_START;
+    let signature_offset = public_key_offset + 32;
+    let message_offset = signature_offset + 64;
+    let mut data = vec![1, 0];
+    for field in [
+        signature_offset,
+        u16::MAX,
+        public_key_offset,
+        u16::MAX,
+        message_offset,
+        message.len() as u16,
+        u16::MAX,
+    ] {
+        data.extend_from_slice(&field.to_le_bytes());
+    }
+    data.extend_from_slice(signer.pubkey().as_ref());
+    data.extend_from_slice(signer.sign_message(message).as_ref());
+    data.extend_from_slice(message);
+    Instruction {
+        program_id: ed25519_program::id(),
+        accounts: vec![],
+        data,
+    }
+}
+
+fn relayed_update_ix(
+    relayer: &Pubkey,
+    data_account: &Pubkey,
+    args: UpdateDataStoreArgs,
+) -> Instruction {
+    instruction(
+        vec![
+            AccountMeta::new(*relayer, true),
+            AccountMeta::new(*data_account, false),
+            AccountMeta::new(metadata_pda(data_account).0, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(sysvar::instructions::id(), false),
+            AccountMeta::new_readonly(config_pda(), false),
+        ],
+        DataStoreInstruction::RelayedUpdate(args),
+    )
+}
+
+/// Relays args to data_account with a signature of message by authority
+async fn relay(
+    context: &mut ProgramTestContext,
+    authority: &Keypair,
+    data_account: &Pubkey,
+    message: &[u8],
+    args: UpdateDataStoreArgs,
+) -> Result<(), BanksClientError> {
+    let relayer = context.payer.pubkey();
+    process(
+        context,
+        &[
+            ed25519_ix(authority, message),
+            relayed_update_ix(&relayer, data_account, args),
+        ],
+        &[],
+    )
+    .await
+}
+
+/// Asserts the relayed update, second in its transaction, failed with error
+fn assert_relay_error(result: Result<(), BanksClientError>, error: DataStoreError) {
+    match result {
+        Err(BanksClientError::TransactionError(TransactionError::InstructionError(
+            1,
+            InstructionError::Custom(code),
+        ))) => assert_eq!(code, error.clone() as u32, "expected {:?}", error),
+        other => panic!("expected {:?}, got {:?}", error, other),
+    }
+}
+
+#[tokio::test]
+async fn relayed_update_is_bound_to_its_program_and_revision() {
+    let mut context = start().await;
+    let authority = Keypair::new();
+    let data_account = initialize(&mut context, |args| InitializeDataStoreArgs {
+        authority: authority.pubkey(),
+        ..args
+    })
+    .await
+    .pubkey();
+    let revision = metadata(&mut context, &data_account).await.revision();
+    let args = update_args(b"relayed", 0);
+
+    // Messages signed for another program, or without the domain, are refused
+    let message =
+        relayed_update_message(&Pubkey::new_unique(), &data_account, revision, &args).unwrap();
+    let result = relay(
+        &mut context,
+        &authority,
+        &data_account,
+        &message,
+        args.clone(),
+    )
+    .await;
+    assert_relay_error(result, DataStoreError::NotSigner);
+    let message = relayed_update_message(&program_id(), &data_account, revision, &args).unwrap();
+    let undomained = &message[message.len() - 32 - 8 - 32..];
+    let result = relay(
+        &mut context,
+        &authority,
+        &data_account,
+        undomained,
+        args.clone(),
+    )
+    .await;
+    assert_relay_error(result, DataStoreError::NotSigner);
+
+    relay(
+        &mut context,
+        &authority,
+        &data_account,
+        &message,
+        args.clone(),
+    )
+    .await
+    .unwrap();
+    let account_metadata = metadata(&mut context, &data_account).await;
+    assert_eq!(account_metadata.revision(), revision + 1);
+    assert_eq!(account_metadata.data_len(), 7);
+
+    // Replaying the signed message at the now stale revision is refused
+    let result = relay(&mut context, &authority, &data_account, &message, args).await;
+    assert_relay_error(result, DataStoreError::NotSigner);
+    assert_eq!(
+        metadata(&mut context, &data_account).await.revision(),
+        revision + 1
+    );
+}
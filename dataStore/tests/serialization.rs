@@ -0,0 +1,439 @@
+use borsh::{BorshDeserialize, BorshSerialize};
+use dataaccount::{
+    error::DataStoreError,
+    hashing::{HashAlgorithm, Sha256Stream},
+    instruction::DataStoreInstruction,
+    state::{
+        read_authority, read_bump_seed, read_data_hash, read_data_status, read_metadata_pda,
+        read_namespace, DataStoreAccountMetadata, DataStoreTypeOption, DirectoryFormat,
+        InitializeDataStoreArgs, SerializationStatusOption, UpdateDataStoreArgs,
+        METADATA_AUTHORITY_OFFSET, METADATA_DATA_HASH_OFFSET, METADATA_SIZE,
+    },
+};
+use proptest::{collection::vec, option, prelude::*};
+use solana_program::pubkey::Pubkey;
+
+fn pubkey() -> impl Strategy<Value = Pubkey> {
+    any::<[u8; 32]>().prop_map(Pubkey::new_from_array)
+}
+
+fn data_type() -> impl Strategy<Value = DataStoreTypeOption> {
+    prop_oneof![
+        Just(DataStoreTypeOption::File),
+        Just(DataStoreTypeOption::Directory),
+        Just(DataStoreTypeOption::Symlink),
+    ]
+}
+
+fn data_status() -> impl Strategy<Value = SerializationStatusOption> {
+    prop_oneof![
+        Just(SerializationStatusOption::Uninitialized),
+        Just(SerializationStatusOption::Initialized),
+        Just(SerializationStatusOption::Finalized),
+    ]
+}
+
+fn directory_format() -> impl Strategy<Value = DirectoryFormat> {
+    prop_oneof![
+        Just(DirectoryFormat::PackedPubkeys),
+        Just(DirectoryFormat::JsonManifest),
+        Just(DirectoryFormat::NamedEntries),
+    ]
+}
+
+fn hash_stream() -> impl Strategy<Value = Sha256Stream> {
+    vec(any::<u8>(), 0..200).prop_map(|data| {
+        let mut stream = Sha256Stream::new();
+        stream.update(&data);
+        stream
+    })
+}
+
+fn metadata() -> impl Strategy<Value = DataStoreAccountMetadata> {
+    (
+        (
+            data_type(),
+            pubkey(),
+            data_status(),
+            any::<u8>(),
+            any::<[u8; 32]>(),
+            any::<bool>(),
+            any::<u32>(),
+            any::<u64>(),
+            any::<u64>(),
+            any::<u8>(),
+            any::<bool>(),
+            any::<[u8; 16]>(),
+        ),
+        (
+            any::<bool>(),
+            "[a-z/+.-]{0,64}",
+            any::<bool>(),
+            option::of(pubkey()),
+            any::<u32>(),
+            any::<u64>(),
+            any::<u64>(),
+            any::<u32>(),
+            pubkey(),
+            any::<u64>(),
+            any::<u64>(),
+            directory_format(),
+        ),
+        (
+            option::of(any::<u64>()),
+            option::of(any::<u64>()),
+            "[a-z0-9._-]{0,32}",
+            any::<bool>(),
+            any::<bool>(),
+            any::<u32>(),
+            any::<u32>(),
+            any::<bool>(),
+            option::of(hash_stream()),
+            option::of(pubkey()),
+            any::<u32>(),
+            option::of(pubkey()),
+        ),
+    )
+        .prop_map(
+            |(
+                (
+                    data_type,
+                    authority,
+                    data_status,
+                    bump_seed,
+                    data_hash,
+                    is_dynamic,
+                    space,
+                    revision,
+                    data_len,
+                    version,
+                    append_only,
+                    namespace,
+                ),
+                (
+                    private,
+                    content_type,
+                    strict_content_type,
+                    previous_version,
+                    max_entries,
+                    upload_cursor,
+                    expected_total,
+                    crc32,
+                    creator,
+                    updated_slot,
+                    min_update_interval_slots,
+                    directory_format,
+                ),
+                (
+                    expiry_slot,
+                    max_revisions,
+                    name,
+                    audit,
+                    keep_tail_on_shrink,
+                    metadata_crc,
+                    cache_ttl_seconds,
+                    unlisted,
+                    hash_stream,
+                    reader,
+                    flags,
+                    parent,
+                ),
+            )| DataStoreAccountMetadata {
+                data_type,
+                authority,
+                data_status,
+                bump_seed,
+                data_hash,
+                is_dynamic,
+                space: space as usize,
+                revision,
+                data_len,
+                version,
+                append_only,
+                namespace,
+                private,
+                content_type,
+                strict_content_type,
+                previous_version,
+                max_entries,
+                upload_cursor,
+                expected_total,
+                crc32,
+                creator,
+                updated_slot,
+                min_update_interval_slots,
+                directory_format,
+                expiry_slot,
+                max_revisions,
+                name,
+                audit,
+                keep_tail_on_shrink,
+                metadata_crc,
+                cache_ttl_seconds,
+                unlisted,
+                hash_stream,
+                reader,
+                flags,
+                parent,
+            },
+        )
+}
+
+fn initialize_args() -> impl Strategy<Value = InitializeDataStoreArgs> {
+    (
+        (
+            any::<bool>(),
+            data_type(),
+            any::<u8>(),
+            any::<bool>(),
+            any::<u64>(),
+            pubkey(),
+            any::<bool>(),
+            vec(any::<u8>(), 0..300),
+            any::<bool>(),
+            any::<[u8; 16]>(),
+            any::<bool>(),
+            any::<bool>(),
+        ),
+        (
+            "[a-z/+.-]{0,64}",
+            any::<bool>(),
+            any::<u64>(),
+            any::<u64>(),
+            option::of(any::<u64>()),
+            option::of(any::<u64>()),
+            any::<bool>(),
+            "[a-z0-9._-]{0,32}",
+            any::<bool>(),
+            any::<u32>(),
+            any::<bool>(),
+            any::<bool>(),
+        ),
+    )
+        .prop_map(
+            |(
+                (
+                    debug,
+                    data_type,
+                    bump_seed,
+                    is_created,
+                    space,
+                    authority,
+                    is_dynamic,
+                    initial_data,
+                    append_only,
+                    namespace,
+                    idempotent,
+                    private,
+                ),
+                (
+                    content_type,
+                    strict_content_type,
+                    expected_total,
+                    min_update_interval_slots,
+                    expiry_slot,
+                    max_revisions,
+                    audit,
+                    file_name,
+                    zero_on_shrink,
+                    cache_ttl_seconds,
+                    indexable,
+                    stream_hash,
+                ),
+            )| InitializeDataStoreArgs {
+                debug,
+                data_type,
+                bump_seed,
+                is_created,
+                space,
+                authority,
+                is_dynamic,
+                initial_data,
+                append_only,
+                namespace,
+                idempotent,
+                private,
+                content_type,
+                strict_content_type,
+                expected_total,
+                min_update_interval_slots,
+                expiry_slot,
+                max_revisions,
+                audit,
+                file_name,
+                zero_on_shrink,
+                cache_ttl_seconds,
+                indexable,
+                stream_hash,
+            },
+        )
+}
+
+fn update_args() -> impl Strategy<Value = UpdateDataStoreArgs> {
+    (
+        any::<bool>(),
+        any::<[u8; 32]>(),
+        vec(any::<u8>(), 0..300),
+        any::<u64>(),
+        any::<bool>(),
+        data_type(),
+        option::of(any::<u64>()),
+        any::<bool>(),
+        option::of(any::<[u8; 32]>()),
+    )
+        .prop_map(
+            |(
+                debug,
+                data_hash,
+                data,
+                offset,
+                realloc_down,
+                data_type,
+                expected_revision,
+                allow_gaps,
+                expected_hash,
+            )| UpdateDataStoreArgs {
+                debug,
+                data_hash,
+                data,
+                offset,
+                realloc_down,
+                data_type,
+                expected_revision,
+                allow_gaps,
+                expected_hash,
+            },
+        )
+}
+
+/// Asserts value deserializes from its encoding, consuming all of it, and encodes
+/// back to the very same bytes
+fn assert_round_trip<T: BorshSerialize + BorshDeserialize>(value: &T) -> Vec<u8> {
+    let bytes = borsh::to_vec(value).unwrap();
+    let decoded = T::try_from_slice(&bytes).unwrap();
+    assert_eq!(borsh::to_vec(&decoded).unwrap(), bytes);
+    bytes
+}
+
+proptest! {
+    #[test]
+    fn metadata_round_trips(metadata in metadata()) {
+        assert_round_trip(&metadata);
+    }
+
+    #[test]
+    fn stored_metadata_round_trips(mut metadata in metadata()) {
+        let mut data = vec![0xff; METADATA_SIZE];
+        metadata.store(&mut data).unwrap();
+        let loaded = DataStoreAccountMetadata::load(&data).unwrap();
+        prop_assert_eq!(borsh::to_vec(&loaded).unwrap(), borsh::to_vec(&metadata).unwrap());
+
+        let mut restored = vec![0; METADATA_SIZE];
+        loaded.clone().store(&mut restored).unwrap();
+        prop_assert_eq!(restored, data);
+    }
+
+    #[test]
+    fn overfull_hash_stream_is_corrupt_metadata(mut metadata in metadata(), extra in 0..64usize) {
+        // Patch in a buffer holding a whole block or more, which update never leaves
+        let buffer_len = 64 + extra;
+        let mut stream = borsh::to_vec(&Sha256Stream::new()).unwrap();
+        stream[32..40].copy_from_slice(&(buffer_len as u64).to_le_bytes());
+        stream.splice(40.., (buffer_len as u32).to_le_bytes());
+        stream.resize(44 + buffer_len, 0);
+        let stream = Sha256Stream::try_from_slice(&stream).unwrap();
+        prop_assert!(!stream.is_valid());
+
+        metadata.hash_stream = Some(stream);
+        let mut data = vec![0; METADATA_SIZE];
+        metadata.store(&mut data).unwrap();
+        prop_assert_eq!(
+            DataStoreAccountMetadata::load(&data).unwrap_err(),
+            DataStoreError::CorruptMetadata.into()
+        );
+    }
+
+    #[test]
+    fn in_place_readers_agree_with_load(mut metadata in metadata(), data_account in pubkey()) {
+        let mut data = vec![0; METADATA_SIZE];
+        metadata.store(&mut data).unwrap();
+        let loaded = DataStoreAccountMetadata::load(&data).unwrap();
+
+        prop_assert_eq!(read_authority(&data).unwrap(), loaded.authority());
+        prop_assert_eq!(&read_data_status(&data).unwrap(), loaded.data_status());
+        prop_assert_eq!(read_bump_seed(&data).unwrap(), loaded.bump_seed());
+        prop_assert_eq!(read_data_hash(&data).unwrap(), loaded.data_hash());
+        prop_assert_eq!(read_namespace(&data).unwrap(), loaded.namespace());
+        let program_id = Pubkey::new_unique();
+        prop_assert_eq!(
+            read_metadata_pda(&program_id, &data_account, &data),
+            loaded.metadata_pda(&program_id, &data_account)
+        );
+    }
+
+    #[test]
+    fn initialize_args_round_trip(args in initialize_args()) {
+        assert_round_trip(&args);
+        let bytes = assert_round_trip(&DataStoreInstruction::InitializeDataStore(args.clone()));
+        prop_assert_eq!(&bytes[1..], &borsh::to_vec(&args).unwrap()[..]);
+    }
+
+    #[test]
+    fn update_args_round_trip(args in update_args()) {
+        assert_round_trip(&args);
+        let bytes = assert_round_trip(&DataStoreInstruction::UpdateDataStore(args.clone()));
+        prop_assert_eq!(&bytes[1..], &borsh::to_vec(&args).unwrap()[..]);
+    }
+}
+
+/// Asserts value is encoded as its discriminant byte
+fn assert_discriminant<T: BorshSerialize + BorshDeserialize>(value: T, discriminant: u8) {
+    assert_eq!(borsh::to_vec(&value).unwrap(), [discriminant]);
+    assert_eq!(
+        borsh::to_vec(&T::try_from_slice(&[discriminant]).unwrap()).unwrap(),
+        [discriminant]
+    );
+}
+
+#[test]
+fn enum_discriminants() {
+    assert_discriminant(DataStoreTypeOption::File, 0);
+    assert_discriminant(DataStoreTypeOption::Directory, 1);
+    assert_discriminant(DataStoreTypeOption::Symlink, 2);
+    assert!(DataStoreTypeOption::try_from_slice(&[3]).is_err());
+
+    assert_discriminant(DirectoryFormat::PackedPubkeys, 0);
+    assert_discriminant(DirectoryFormat::JsonManifest, 1);
+    assert_discriminant(DirectoryFormat::NamedEntries, 2);
+    assert!(DirectoryFormat::try_from_slice(&[3]).is_err());
+
+    assert_discriminant(SerializationStatusOption::Uninitialized, 0);
+    assert_discriminant(SerializationStatusOption::Initialized, 1);
+    assert_discriminant(SerializationStatusOption::Finalized, 2);
+    assert!(SerializationStatusOption::try_from_slice(&[3]).is_err());
+
+    assert_discriminant(HashAlgorithm::Sha256, 0);
+    assert_discriminant(HashAlgorithm::Blake3, 1);
+    assert!(HashAlgorithm::try_from_slice(&[2]).is_err());
+}
+
+#[test]
+fn metadata_field_offsets() {
+    let authority = Pubkey::new_from_array([1; 32]);
+    let mut metadata =
+        DataStoreAccountMetadata::new(authority, DataStoreTypeOption::Directory, 254, true);
+    metadata.data_hash = [2; 32];
+    let bytes = borsh::to_vec(&metadata).unwrap();
+
+    assert_eq!(bytes[0], 1);
+    assert_eq!(
+        bytes[METADATA_AUTHORITY_OFFSET..METADATA_AUTHORITY_OFFSET + 32],
+        [1; 32]
+    );
+    assert_eq!(bytes[METADATA_AUTHORITY_OFFSET + 32], 1);
+    assert_eq!(bytes[METADATA_AUTHORITY_OFFSET + 33], 254);
+    assert_eq!(
+        bytes[METADATA_DATA_HASH_OFFSET..METADATA_DATA_HASH_OFFSET + 32],
+        [2; 32]
+    );
+}
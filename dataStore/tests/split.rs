@@ -0,0 +1,195 @@
+mod common;
+
+use common::{
+    allowlist_pda, assert_error, config_pda, initialize, instruction, metadata, metadata_pda,
+    process, start, update, update_args,
+};
+use dataaccount::{
+    error::DataStoreError,
+    instruction::DataStoreInstruction,
+    state::{compute_data_hash, ConcatDataArgs, InitializeDataStoreArgs, SplitDataArgs},
+};
+use solana_program_test::{tokio, BanksClientError, ProgramTestContext};
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_program,
+};
+
+fn data(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i * 31 + 7) as u8).collect()
+}
+
+fn split_data_ix(
+    authority: &Pubkey,
+    data_account: &Pubkey,
+    destination: &Pubkey,
+    offset: u64,
+    expected_revision: Option<u64>,
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(*authority, true),
+        AccountMeta::new(*data_account, false),
+        AccountMeta::new(metadata_pda(data_account).0, false),
+        AccountMeta::new(*destination, true),
+        AccountMeta::new(metadata_pda(destination).0, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+    if cfg!(feature = "permissioned-init") {
+        accounts.push(AccountMeta::new_readonly(allowlist_pda(), false));
+    }
+    accounts.push(AccountMeta::new_readonly(config_pda(), false));
+    instruction(
+        accounts,
+        DataStoreInstruction::SplitData(SplitDataArgs {
+            debug: false,
+            offset,
+            bump_seed: metadata_pda(destination).1,
+            expected_revision,
+        }),
+    )
+}
+
+async fn split(
+    context: &mut ProgramTestContext,
+    data_account: &Pubkey,
+    destination: &Keypair,
+    offset: u64,
+    expected_revision: Option<u64>,
+) -> Result<(), BanksClientError> {
+    let payer = context.payer.pubkey();
+    let split_ix = split_data_ix(
+        &payer,
+        data_account,
+        &destination.pubkey(),
+        offset,
+        expected_revision,
+    );
+    process(context, &[split_ix], &[destination]).await
+}
+
+/// Initializes an empty dynamic File data store and writes data to it
+async fn dynamic_file(context: &mut ProgramTestContext, data: &[u8]) -> Pubkey {
+    let data_account = initialize(context, |args| InitializeDataStoreArgs {
+        is_dynamic: true,
+        space: 0,
+        ..args
+    })
+    .await
+    .pubkey();
+    update(context, &data_account, update_args(data, 0))
+        .await
+        .unwrap();
+    data_account
+}
+
+async fn contents(context: &mut ProgramTestContext, data_account: &Pubkey) -> Vec<u8> {
+    context
+        .banks_client
+        .get_account(*data_account)
+        .await
+        .unwrap()
+        .unwrap()
+        .data
+}
+
+#[tokio::test]
+async fn kilobyte_file_is_split_at_offset_400() {
+    let mut context = start().await;
+    let payer = context.payer.pubkey();
+    let file = data(1024);
+    let source = dynamic_file(&mut context, &file[..512]).await;
+    update(&mut context, &source, update_args(&file[512..], 512))
+        .await
+        .unwrap();
+    let revision = metadata(&mut context, &source).await.revision();
+    let destination = Keypair::new();
+
+    // Stale revisions and splits leaving nothing to move are refused
+    let result = split(&mut context, &source, &destination, 400, Some(revision - 1)).await;
+    assert_error(result, DataStoreError::RevisionMismatch);
+    let result = split(&mut context, &source, &destination, 1024, Some(revision)).await;
+    assert_error(result, DataStoreError::InvalidInstructionData);
+
+    split(&mut context, &source, &destination, 400, Some(revision))
+        .await
+        .unwrap();
+
+    // The source keeps the head, shrunk to it, as its next revision
+    assert_eq!(contents(&mut context, &source).await, file[..400]);
+    let source_metadata = metadata(&mut context, &source).await;
+    assert_eq!(source_metadata.data_len(), 400);
+    assert_eq!(source_metadata.space(), 400);
+    assert_eq!(source_metadata.revision(), revision + 1);
+    assert_eq!(
+        *source_metadata.data_hash(),
+        compute_data_hash(&source, &file[..400])
+    );
+
+    // The destination holds the tail, for the same authority
+    let destination = destination.pubkey();
+    assert_eq!(contents(&mut context, &destination).await, file[400..]);
+    let destination_metadata = metadata(&mut context, &destination).await;
+    assert_eq!(destination_metadata.data_len(), 624);
+    assert_eq!(destination_metadata.authority(), &payer);
+    assert_eq!(
+        *destination_metadata.data_hash(),
+        compute_data_hash(&destination, &file[400..])
+    );
+}
+
+fn concat_data_ix(
+    authority: &Pubkey,
+    data_account: &Pubkey,
+    source: &Pubkey,
+    close_source: bool,
+) -> Instruction {
+    instruction(
+        vec![
+            AccountMeta::new(*authority, true),
+            AccountMeta::new(*data_account, false),
+            AccountMeta::new(metadata_pda(data_account).0, false),
+            AccountMeta::new(*source, false),
+            AccountMeta::new(metadata_pda(source).0, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(config_pda(), false),
+        ],
+        DataStoreInstruction::ConcatData(ConcatDataArgs {
+            debug: false,
+            close_source,
+        }),
+    )
+}
+
+#[tokio::test]
+async fn dynamic_files_are_concatenated() {
+    let mut context = start().await;
+    let payer = context.payer.pubkey();
+    let file = data(700);
+    let head = dynamic_file(&mut context, &file[..300]).await;
+    let middle = dynamic_file(&mut context, &file[300..500]).await;
+    let tail = dynamic_file(&mut context, &file[500..]).await;
+
+    // Keeping the source, then closing it
+    for (source, close_source) in [(middle, false), (tail, true)] {
+        process(
+            &mut context,
+            &[concat_data_ix(&payer, &head, &source, close_source)],
+            &[],
+        )
+        .await
+        .unwrap();
+    }
+
+    assert_eq!(contents(&mut context, &head).await, file);
+    let head_metadata = metadata(&mut context, &head).await;
+    assert_eq!(head_metadata.data_len(), 700);
+    assert_eq!(head_metadata.space(), 700);
+    assert_eq!(*head_metadata.data_hash(), compute_data_hash(&head, &file));
+    assert_eq!(contents(&mut context, &middle).await, file[300..500]);
+    for account in [tail, metadata_pda(&tail).0] {
+        let account = context.banks_client.get_account(account).await.unwrap();
+        assert!(account.is_none());
+    }
+}
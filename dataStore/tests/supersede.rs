@@ -0,0 +1,87 @@
+mod common;
+
+use common::{
+    assert_error, config_pda, finalize_ix, initialize, instruction, metadata, metadata_pda,
+    process, start, update, update_args,
+};
+use dataaccount::{error::DataStoreError, instruction::DataStoreInstruction, state::SupersedeArgs};
+use solana_program_test::{tokio, ProgramTestContext};
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    signature::Signer,
+};
+
+fn supersede_ix(authority: &Pubkey, source: &Pubkey, data_account: &Pubkey) -> Instruction {
+    instruction(
+        vec![
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new_readonly(*source, false),
+            AccountMeta::new_readonly(metadata_pda(source).0, false),
+            AccountMeta::new_readonly(*data_account, false),
+            AccountMeta::new(metadata_pda(data_account).0, false),
+            AccountMeta::new_readonly(config_pda(), false),
+        ],
+        DataStoreInstruction::Supersede(SupersedeArgs { debug: false }),
+    )
+}
+
+/// Writes data to data_account and finalizes it
+async fn publish(context: &mut ProgramTestContext, data_account: &Pubkey, data: &[u8]) {
+    update(context, data_account, update_args(data, 0))
+        .await
+        .unwrap();
+    let payer = context.payer.pubkey();
+    process(context, &[finalize_ix(&payer, data_account)], &[])
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn versions_form_a_chain() {
+    let mut context = start().await;
+    let payer = context.payer.pubkey();
+    let v1 = initialize(&mut context, |args| args).await.pubkey();
+    publish(&mut context, &v1, b"v1").await;
+    let v1_metadata = context
+        .banks_client
+        .get_account(metadata_pda(&v1).0)
+        .await
+        .unwrap();
+
+    let v2 = initialize(&mut context, |args| args).await.pubkey();
+    process(&mut context, &[supersede_ix(&payer, &v1, &v2)], &[])
+        .await
+        .unwrap();
+    // The superseding data store keeps accepting updates until published
+    publish(&mut context, &v2, b"v1 and v2").await;
+
+    let v3 = initialize(&mut context, |args| args).await.pubkey();
+    process(&mut context, &[supersede_ix(&payer, &v2, &v3)], &[])
+        .await
+        .unwrap();
+
+    // Walk the chain back from the latest version
+    let mut chain = vec![v3];
+    while let Some(previous_version) = metadata(&mut context, chain.last().unwrap())
+        .await
+        .previous_version()
+    {
+        chain.push(*previous_version);
+    }
+    assert_eq!(chain, [v3, v2, v1]);
+
+    // The superseded source is left untouched
+    assert_eq!(
+        context
+            .banks_client
+            .get_account(metadata_pda(&v1).0)
+            .await
+            .unwrap(),
+        v1_metadata
+    );
+
+    // A data store links to a single previous version
+    let result = process(&mut context, &[supersede_ix(&payer, &v1, &v3)], &[]).await;
+    assert_error(result, DataStoreError::AlreadyInitialized);
+}
@@ -0,0 +1,120 @@
+mod common;
+
+use common::{
+    assert_error, config_pda, finalize_ix, initialize, instruction, metadata, metadata_pda,
+    process, start, update, update_args,
+};
+use dataaccount::{
+    error::DataStoreError,
+    instruction::DataStoreInstruction,
+    state::{compute_data_hash, InitializeDataStoreArgs, SwapDataArgs},
+};
+use solana_program_test::{tokio, ProgramTestContext};
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    signature::Signer,
+    system_program,
+};
+
+fn data(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i * 31 + 7) as u8).collect()
+}
+
+fn swap_data_ix(authority: &Pubkey, data_account: &Pubkey, other_account: &Pubkey) -> Instruction {
+    instruction(
+        vec![
+            AccountMeta::new(*authority, true),
+            AccountMeta::new(*data_account, false),
+            AccountMeta::new(metadata_pda(data_account).0, false),
+            AccountMeta::new(*other_account, false),
+            AccountMeta::new(metadata_pda(other_account).0, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(config_pda(), false),
+        ],
+        DataStoreInstruction::SwapData(SwapDataArgs { debug: false }),
+    )
+}
+
+/// Initializes an empty dynamic File data store and writes data to it
+async fn dynamic_file(context: &mut ProgramTestContext, data: &[u8]) -> Pubkey {
+    let data_account = initialize(context, |args| InitializeDataStoreArgs {
+        is_dynamic: true,
+        space: 0,
+        ..args
+    })
+    .await
+    .pubkey();
+    update(context, &data_account, update_args(data, 0))
+        .await
+        .unwrap();
+    data_account
+}
+
+async fn contents(context: &mut ProgramTestContext, data_account: &Pubkey) -> Vec<u8> {
+    context
+        .banks_client
+        .get_account(*data_account)
+        .await
+        .unwrap()
+        .unwrap()
+        .data
+}
+
+#[tokio::test]
+async fn dynamic_files_swap_contents() {
+    let mut context = start().await;
+    let payer = context.payer.pubkey();
+    let (small, large) = (data(100), data(200).into_iter().rev().collect::<Vec<_>>());
+    let staged = dynamic_file(&mut context, &small).await;
+    let production = dynamic_file(&mut context, &large).await;
+    // The staged file was edited twice
+    update(&mut context, &staged, update_args(&small[..10], 0))
+        .await
+        .unwrap();
+    let staged_metadata = metadata(&mut context, &staged).await;
+    let production_metadata = metadata(&mut context, &production).await;
+
+    process(
+        &mut context,
+        &[swap_data_ix(&payer, &staged, &production)],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    // Each dynamic account is resized to the contents it received
+    assert_eq!(contents(&mut context, &staged).await, large);
+    assert_eq!(contents(&mut context, &production).await, small);
+    let (swapped_staged, swapped_production) = (
+        metadata(&mut context, &staged).await,
+        metadata(&mut context, &production).await,
+    );
+    assert_eq!(swapped_staged.data_len(), 200);
+    assert_eq!(swapped_production.data_len(), 100);
+    assert_eq!(swapped_production.revision(), staged_metadata.revision());
+    assert_eq!(swapped_staged.revision(), production_metadata.revision());
+
+    // Hashes are bound to the account holding the contents
+    assert_ne!(swapped_production.data_hash(), staged_metadata.data_hash());
+    assert_eq!(
+        *swapped_production.data_hash(),
+        compute_data_hash(&production, &small)
+    );
+    assert_eq!(
+        *swapped_staged.data_hash(),
+        compute_data_hash(&staged, &large)
+    );
+
+    // Finalized data stores can not be swapped
+    process(&mut context, &[finalize_ix(&payer, &production)], &[])
+        .await
+        .unwrap();
+    let result = process(
+        &mut context,
+        &[swap_data_ix(&payer, &staged, &production)],
+        &[],
+    )
+    .await;
+    assert_error(result, DataStoreError::AlreadyFinalized);
+}
@@ -0,0 +1,164 @@
+mod common;
+
+use common::{
+    assert_error, config_pda, create_accounts, instruction, metadata, metadata_pda, process,
+    set_flags_ix, start,
+};
+use dataaccount::{
+    error::DataStoreError,
+    instruction::DataStoreInstruction,
+    state::{
+        verify_data, CreateSymlinkArgs, DataStoreTypeOption, SerializationStatusOption,
+        UpdateSymlinkArgs, FLAG_FROZEN, NAMESPACE_LEN, SYMLINK_TARGET_LEN,
+    },
+};
+use solana_program_test::{tokio, ProgramTestContext};
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+};
+
+fn update_symlink_ix(authority: &Pubkey, data_account: &Pubkey, target: Pubkey) -> Instruction {
+    instruction(
+        vec![
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new(*data_account, false),
+            AccountMeta::new(metadata_pda(data_account).0, false),
+            AccountMeta::new_readonly(config_pda(), false),
+        ],
+        DataStoreInstruction::UpdateSymlink(UpdateSymlinkArgs {
+            debug: false,
+            target,
+        }),
+    )
+}
+
+async fn create_symlink(context: &mut ProgramTestContext, target: Pubkey) -> Pubkey {
+    let data_account = Keypair::new();
+    let payer = context.payer.pubkey();
+    let create_ix = instruction(
+        create_accounts(&payer, &data_account.pubkey()),
+        DataStoreInstruction::CreateSymlink(CreateSymlinkArgs {
+            debug: false,
+            bump_seed: metadata_pda(&data_account.pubkey()).1,
+            authority: payer,
+            target,
+            namespace: [0; NAMESPACE_LEN],
+        }),
+    );
+    process(context, &[create_ix], &[&data_account])
+        .await
+        .unwrap();
+    data_account.pubkey()
+}
+
+async fn target(context: &mut ProgramTestContext, data_account: &Pubkey) -> Vec<u8> {
+    let account = context
+        .banks_client
+        .get_account(*data_account)
+        .await
+        .unwrap()
+        .unwrap();
+    account.data
+}
+
+#[test]
+fn verify_data_accepts_only_whole_symlink_targets() {
+    use DataStoreTypeOption::*;
+    use SerializationStatusOption::*;
+
+    assert_eq!(verify_data(&[], Symlink), Uninitialized);
+    assert_eq!(verify_data(&[1; SYMLINK_TARGET_LEN], Symlink), Initialized);
+    assert_eq!(
+        verify_data(&[1; SYMLINK_TARGET_LEN - 1], Symlink),
+        Uninitialized
+    );
+    assert_eq!(
+        verify_data(&[1; SYMLINK_TARGET_LEN + 1], Symlink),
+        Uninitialized
+    );
+    assert_eq!(verify_data(&[1; 3], Directory), Initialized);
+    assert_eq!(verify_data(&[1; SYMLINK_TARGET_LEN], File), Uninitialized);
+}
+
+#[tokio::test]
+async fn symlink_is_created_and_retargeted() {
+    let mut context = start().await;
+    let first = Pubkey::new_unique();
+    let data_account = create_symlink(&mut context, first).await;
+
+    let account_metadata = metadata(&mut context, &data_account).await;
+    assert_eq!(*account_metadata.data_type(), DataStoreTypeOption::Symlink);
+    assert_eq!(account_metadata.data_len(), SYMLINK_TARGET_LEN);
+    assert_eq!(target(&mut context, &data_account).await, first.as_ref());
+
+    let second = Pubkey::new_unique();
+    let payer = context.payer.pubkey();
+    process(
+        &mut context,
+        &[update_symlink_ix(&payer, &data_account, second)],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    let updated_metadata = metadata(&mut context, &data_account).await;
+    assert_eq!(updated_metadata.revision(), account_metadata.revision() + 1);
+    assert_ne!(updated_metadata.data_hash(), account_metadata.data_hash());
+    assert_eq!(target(&mut context, &data_account).await, second.as_ref());
+}
+
+#[tokio::test]
+async fn symlink_update_requires_authority() {
+    let mut context = start().await;
+    let first = Pubkey::new_unique();
+    let data_account = create_symlink(&mut context, first).await;
+
+    let intruder = Keypair::new();
+    let result = process(
+        &mut context,
+        &[update_symlink_ix(
+            &intruder.pubkey(),
+            &data_account,
+            Pubkey::new_unique(),
+        )],
+        &[&intruder],
+    )
+    .await;
+    assert_error(result, DataStoreError::InvalidAuthority);
+    assert_eq!(target(&mut context, &data_account).await, first.as_ref());
+}
+
+#[tokio::test]
+async fn frozen_symlink_is_not_retargeted() {
+    let mut context = start().await;
+    let first = Pubkey::new_unique();
+    let data_account = create_symlink(&mut context, first).await;
+    let payer = context.payer.pubkey();
+    process(
+        &mut context,
+        &[set_flags_ix(
+            &payer,
+            &data_account,
+            FLAG_FROZEN,
+            FLAG_FROZEN,
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    let result = process(
+        &mut context,
+        &[update_symlink_ix(
+            &payer,
+            &data_account,
+            Pubkey::new_unique(),
+        )],
+        &[],
+    )
+    .await;
+    assert_error(result, DataStoreError::Frozen);
+    assert_eq!(target(&mut context, &data_account).await, first.as_ref());
+}
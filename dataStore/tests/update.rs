@@ -0,0 +1,1255 @@
+mod common;
+
+use borsh::BorshDeserialize;
+use common::{
+    assert_error, close_ix, config_pda, finalize_ix, init_args, initialize, initialize_ix,
+    instruction, metadata, metadata_pda, process, program_id, reclaim_rent_ix, start, update,
+    update_args, update_ix,
+};
+use dataaccount::{
+    error::DataStoreError,
+    hashing::crc32,
+    instruction::DataStoreInstruction,
+    state::{
+        compute_data_hash, AuditRecord, CopyInDataArgs, DataStoreAccountMetadata,
+        DataStoreTypeOption, InitializeDataStoreArgs, RecomputeHashArgs, SerializationStatusOption,
+        UpdateDataStoreArgs, ValidateArgs, AUDIT_RECORD_SIZE, AUDIT_SEED, FLAG_CONTENT_REGISTERED,
+        MAX_DATA_SPACE,
+    },
+};
+use solana_program_test::{tokio, BanksClientError, ProgramTestContext};
+use solana_sdk::{
+    account::AccountSharedData,
+    instruction::{AccountMeta, Instruction, InstructionError},
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_program,
+    transaction::TransactionError,
+};
+
+fn validate_ix(
+    authority: &Pubkey,
+    data_account: &Pubkey,
+    args: &UpdateDataStoreArgs,
+) -> Instruction {
+    instruction(
+        vec![
+            AccountMeta::new(*authority, true),
+            AccountMeta::new(*data_account, false),
+            AccountMeta::new(metadata_pda(data_account).0, false),
+        ],
+        DataStoreInstruction::Validate(ValidateArgs::from(args)),
+    )
+}
+
+async fn account_data(context: &mut ProgramTestContext, data_account: &Pubkey) -> Vec<u8> {
+    context
+        .banks_client
+        .get_account(*data_account)
+        .await
+        .unwrap()
+        .unwrap()
+        .data
+}
+
+/// Error of a transaction result, if any
+fn error(result: Result<(), BanksClientError>) -> Option<TransactionError> {
+    match result {
+        Ok(()) => None,
+        Err(BanksClientError::TransactionError(error)) => Some(error),
+        Err(other) => panic!("unexpected {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn stale_revision_is_rejected() {
+    let mut context = start().await;
+    let data_account = initialize(&mut context, |args| args).await.pubkey();
+    let revision = metadata(&mut context, &data_account).await.revision();
+
+    // Both writers saw the same revision, the first one to write wins
+    let first = update_args(b"first", 0);
+    update(
+        &mut context,
+        &data_account,
+        UpdateDataStoreArgs {
+            expected_revision: Some(revision),
+            ..first
+        },
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        metadata(&mut context, &data_account).await.revision(),
+        revision + 1
+    );
+
+    let second = update_args(b"second", 0);
+    let result = update(
+        &mut context,
+        &data_account,
+        UpdateDataStoreArgs {
+            expected_revision: Some(revision),
+            ..second
+        },
+    )
+    .await;
+    assert_error(result, DataStoreError::RevisionMismatch);
+
+    let account_metadata = metadata(&mut context, &data_account).await;
+    assert_eq!(account_metadata.revision(), revision + 1);
+    assert_eq!(account_metadata.data_len(), b"first".len());
+}
+
+#[tokio::test]
+async fn stale_expected_hash_is_rejected() {
+    let mut context = start().await;
+    let data_account = initialize(&mut context, |args| args).await.pubkey();
+    update(&mut context, &data_account, update_args(b"draft", 0))
+        .await
+        .unwrap();
+    let seen_hash = *metadata(&mut context, &data_account).await.data_hash();
+    let editor_write = |data: &[u8]| UpdateDataStoreArgs {
+        expected_hash: Some(seen_hash),
+        ..update_args(data, 0)
+    };
+
+    // Both editors read the draft, only the first write applies
+    update(&mut context, &data_account, editor_write(b"first"))
+        .await
+        .unwrap();
+    let result = update(&mut context, &data_account, editor_write(b"other")).await;
+    assert_error(result, DataStoreError::RevisionMismatch);
+
+    let account_metadata = metadata(&mut context, &data_account).await;
+    assert_eq!(
+        *account_metadata.data_hash(),
+        compute_data_hash(&data_account, b"first")
+    );
+    assert_eq!(
+        account_data(&mut context, &data_account).await[..5],
+        *b"first"
+    );
+
+    // Rereading the stored hash lets the second editor write
+    let current_hash = *account_metadata.data_hash();
+    update(
+        &mut context,
+        &data_account,
+        UpdateDataStoreArgs {
+            expected_hash: Some(current_hash),
+            ..update_args(b"other", 0)
+        },
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        account_data(&mut context, &data_account).await[..5],
+        *b"other"
+    );
+}
+
+#[tokio::test]
+async fn data_type_change_is_rejected() {
+    let mut context = start().await;
+    let data_account = initialize(&mut context, |args| args).await.pubkey();
+
+    let result = update(
+        &mut context,
+        &data_account,
+        UpdateDataStoreArgs {
+            data_type: DataStoreTypeOption::Directory,
+            ..update_args(&[0; 32], 0)
+        },
+    )
+    .await;
+    assert_error(result, DataStoreError::InvalidDataType);
+
+    let account_metadata = metadata(&mut context, &data_account).await;
+    assert_eq!(*account_metadata.data_type(), DataStoreTypeOption::File);
+    assert_eq!(account_metadata.data_len(), 0);
+}
+
+#[tokio::test]
+async fn growing_above_maximum_space_is_rejected() {
+    let mut context = start().await;
+    let data_account = initialize(&mut context, |args| InitializeDataStoreArgs {
+        is_dynamic: true,
+        ..args
+    })
+    .await
+    .pubkey();
+
+    let result = update(
+        &mut context,
+        &data_account,
+        UpdateDataStoreArgs {
+            allow_gaps: true,
+            ..update_args(&[1], MAX_DATA_SPACE as u64)
+        },
+    )
+    .await;
+    assert_error(result, DataStoreError::SpaceTooLarge);
+}
+
+#[tokio::test]
+async fn offset_overflow_is_rejected() {
+    let mut context = start().await;
+    let data_account = initialize(&mut context, |args| InitializeDataStoreArgs {
+        is_dynamic: true,
+        ..args
+    })
+    .await
+    .pubkey();
+
+    let result = update(
+        &mut context,
+        &data_account,
+        UpdateDataStoreArgs {
+            allow_gaps: true,
+            ..update_args(&[1], u64::MAX)
+        },
+    )
+    .await;
+    assert_error(result, DataStoreError::Overflow);
+}
+
+#[tokio::test]
+async fn append_only_rejects_overwrites() {
+    let mut context = start().await;
+    let data_account = initialize(&mut context, |args| InitializeDataStoreArgs {
+        append_only: true,
+        ..args
+    })
+    .await
+    .pubkey();
+
+    update(&mut context, &data_account, update_args(b"hello", 0))
+        .await
+        .unwrap();
+    update(&mut context, &data_account, update_args(b" world", 5))
+        .await
+        .unwrap();
+
+    let result = update(&mut context, &data_account, update_args(b"J", 0)).await;
+    assert_error(result, DataStoreError::AppendOnlyViolation);
+    let result = update(&mut context, &data_account, update_args(b"W", 6)).await;
+    assert_error(result, DataStoreError::AppendOnlyViolation);
+
+    assert_eq!(metadata(&mut context, &data_account).await.data_len(), 11);
+    let account = context
+        .banks_client
+        .get_account(data_account)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(&account.data[..11], b"hello world");
+}
+
+#[tokio::test]
+async fn system_owned_data_account_is_rejected() {
+    let mut context = start().await;
+    let data_account = initialize(&mut context, |args| args).await.pubkey();
+
+    // Hand the data account back to the system program, its metadata still valid
+    let mut account = context
+        .banks_client
+        .get_account(data_account)
+        .await
+        .unwrap()
+        .unwrap();
+    account.owner = system_program::id();
+    context.set_account(&data_account, &AccountSharedData::from(account));
+
+    let result = update(&mut context, &data_account, update_args(b"hello", 0)).await;
+    assert_error(result, DataStoreError::InvalidAccountOwner);
+    assert_eq!(metadata(&mut context, &data_account).await.data_len(), 0);
+}
+
+#[tokio::test]
+async fn foreign_owned_data_account_is_not_finalized() {
+    let mut context = start().await;
+    let payer = context.payer.pubkey();
+    let data_account = initialize(&mut context, |args| InitializeDataStoreArgs {
+        initial_data: b"hello".to_vec(),
+        ..args
+    })
+    .await
+    .pubkey();
+
+    // Hand the data account, bytes unchanged, to another program
+    let mut account = context
+        .banks_client
+        .get_account(data_account)
+        .await
+        .unwrap()
+        .unwrap();
+    account.owner = Pubkey::new_unique();
+    context.set_account(&data_account, &AccountSharedData::from(account));
+
+    let result = process(&mut context, &[finalize_ix(&payer, &data_account)], &[]).await;
+    assert_error(result, DataStoreError::InvalidAccountOwner);
+    assert_eq!(
+        *metadata(&mut context, &data_account).await.data_status(),
+        SerializationStatusOption::Initialized
+    );
+}
+
+#[tokio::test]
+async fn foreign_owned_metadata_account_is_rejected() {
+    let mut context = start().await;
+    let payer = context.payer.pubkey();
+    let data_account = initialize(&mut context, |args| InitializeDataStoreArgs {
+        initial_data: b"hello".to_vec(),
+        ..args
+    })
+    .await
+    .pubkey();
+
+    // Hand the metadata account, still valid metadata at the PDA, to another program
+    let pda = metadata_pda(&data_account).0;
+    let mut account = context
+        .banks_client
+        .get_account(pda)
+        .await
+        .unwrap()
+        .unwrap();
+    account.owner = Pubkey::new_unique();
+    context.set_account(&pda, &AccountSharedData::from(account));
+
+    let result = update(&mut context, &data_account, update_args(b"world", 0)).await;
+    assert_error(result, DataStoreError::InvalidAccountOwner);
+    for handler_ix in [
+        finalize_ix(&payer, &data_account),
+        close_ix(&payer, &data_account, true),
+    ] {
+        let result = process(&mut context, &[handler_ix], &[]).await;
+        assert_error(result, DataStoreError::InvalidAccountOwner);
+    }
+    let account_metadata = metadata(&mut context, &data_account).await;
+    assert_eq!(
+        *account_metadata.data_status(),
+        SerializationStatusOption::Initialized
+    );
+    assert_eq!(
+        account_data(&mut context, &data_account).await[..5],
+        *b"hello"
+    );
+}
+
+#[tokio::test]
+async fn identical_contents_hash_per_account() {
+    let mut context = start().await;
+    let first = initialize(&mut context, |args| args).await.pubkey();
+    let second = initialize(&mut context, |args| args).await.pubkey();
+
+    for data_account in [&first, &second] {
+        update(&mut context, data_account, update_args(b"same bytes", 0))
+            .await
+            .unwrap();
+    }
+
+    let first_hash = *metadata(&mut context, &first).await.data_hash();
+    let second_hash = *metadata(&mut context, &second).await.data_hash();
+    assert_ne!(first_hash, second_hash);
+    assert_eq!(first_hash, compute_data_hash(&first, b"same bytes"));
+    assert_eq!(second_hash, compute_data_hash(&second, b"same bytes"));
+}
+
+#[tokio::test]
+async fn shrink_refund_leaves_surplus_with_data_account() {
+    let mut context = start().await;
+    let data_account = initialize(&mut context, |args| InitializeDataStoreArgs {
+        is_dynamic: true,
+        ..args
+    })
+    .await
+    .pubkey();
+    update(&mut context, &data_account, update_args(&[1; 1000], 0))
+        .await
+        .unwrap();
+
+    // Someone other than the authority sends the data account extra lamports
+    const SURPLUS: u64 = 1_000_000;
+    let mut account = context
+        .banks_client
+        .get_account(data_account)
+        .await
+        .unwrap()
+        .unwrap();
+    account.lamports += SURPLUS;
+    context.set_account(&data_account, &AccountSharedData::from(account));
+
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let payer = context.payer.pubkey();
+    let payer_balance = context.banks_client.get_balance(payer).await.unwrap();
+    update(
+        &mut context,
+        &data_account,
+        UpdateDataStoreArgs {
+            realloc_down: true,
+            ..update_args(&[2; 10], 0)
+        },
+    )
+    .await
+    .unwrap();
+
+    let account = context
+        .banks_client
+        .get_account(data_account)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(account.data.len(), 10);
+    assert_eq!(account.lamports, rent.minimum_balance(10) + SURPLUS);
+    // The authority only got the rent freed by the smaller size, less the fee
+    let freed_rent = rent.minimum_balance(1000) - rent.minimum_balance(10);
+    assert_eq!(
+        context.banks_client.get_balance(payer).await.unwrap(),
+        payer_balance + freed_rent - 5000
+    );
+}
+
+#[tokio::test]
+async fn validate_fails_like_update() {
+    let mut context = start().await;
+    let data_account = initialize(&mut context, |args| InitializeDataStoreArgs {
+        append_only: true,
+        ..args
+    })
+    .await
+    .pubkey();
+    update(&mut context, &data_account, update_args(b"hello", 0))
+        .await
+        .unwrap();
+    let payer = context.payer.pubkey();
+    let revision = metadata(&mut context, &data_account).await.revision();
+
+    let cases = [
+        (
+            UpdateDataStoreArgs {
+                data_type: DataStoreTypeOption::Directory,
+                ..update_args(&[0; 32], 5)
+            },
+            Some(DataStoreError::InvalidDataType),
+        ),
+        (
+            update_args(b"J", 0),
+            Some(DataStoreError::AppendOnlyViolation),
+        ),
+        (
+            update_args(&[1; 60], 5),
+            Some(DataStoreError::InsufficientSpace),
+        ),
+        (
+            update_args(b"gap", 6),
+            Some(DataStoreError::NonContiguousWrite),
+        ),
+        (update_args(b"!", u64::MAX), Some(DataStoreError::Overflow)),
+        (
+            UpdateDataStoreArgs {
+                expected_revision: Some(revision + 1),
+                ..update_args(b" world", 5)
+            },
+            Some(DataStoreError::RevisionMismatch),
+        ),
+        (
+            UpdateDataStoreArgs {
+                expected_hash: Some([0; 32]),
+                ..update_args(b" world", 5)
+            },
+            Some(DataStoreError::RevisionMismatch),
+        ),
+        // The successful write comes last, as it moves the revision on
+        (update_args(b" world", 5), None),
+    ];
+    for (args, expected) in cases {
+        let expected = expected.map(|error| {
+            TransactionError::InstructionError(0, InstructionError::Custom(error as u32))
+        });
+        let validate_ix = validate_ix(&payer, &data_account, &args);
+        let validated = process(&mut context, &[validate_ix], &[]).await;
+        assert_eq!(error(validated), expected);
+        // Validate leaves the data store alone
+        assert_eq!(
+            metadata(&mut context, &data_account).await.revision(),
+            revision
+        );
+        assert_eq!(
+            error(update(&mut context, &data_account, args).await),
+            expected
+        );
+    }
+    assert_eq!(metadata(&mut context, &data_account).await.data_len(), 11);
+}
+
+#[tokio::test]
+async fn first_write_grows_empty_dynamic_account() {
+    let mut context = start().await;
+    let data_account = initialize(&mut context, |args| InitializeDataStoreArgs {
+        space: 0,
+        is_dynamic: true,
+        ..args
+    })
+    .await
+    .pubkey();
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let payer = context.payer.pubkey();
+    let payer_balance = context.banks_client.get_balance(payer).await.unwrap();
+    let empty_balance = context
+        .banks_client
+        .get_balance(data_account)
+        .await
+        .unwrap();
+    assert_eq!(empty_balance, rent.minimum_balance(0));
+
+    update(&mut context, &data_account, update_args(&[7; 100], 0))
+        .await
+        .unwrap();
+
+    let account = context
+        .banks_client
+        .get_account(data_account)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(account.data, [7; 100]);
+    assert_eq!(account.lamports, rent.minimum_balance(100));
+    // The authority paid the rent of the grown account and the fee
+    assert_eq!(
+        context.banks_client.get_balance(payer).await.unwrap(),
+        payer_balance - (rent.minimum_balance(100) - empty_balance) - 5000
+    );
+    let account_metadata = metadata(&mut context, &data_account).await;
+    assert_eq!(account_metadata.utilization(), (100, 100));
+    assert_eq!(account_metadata.revision(), 1);
+}
+
+#[tokio::test]
+async fn underfunded_authority_can_not_grow() {
+    let mut context = start().await;
+    let authority = Keypair::new();
+    let data_account = initialize(&mut context, |args| InitializeDataStoreArgs {
+        space: 0,
+        is_dynamic: true,
+        authority: authority.pubkey(),
+        ..args
+    })
+    .await
+    .pubkey();
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let top_up = rent.minimum_balance(100) - rent.minimum_balance(0);
+
+    // One lamport short of the rent of the grown account
+    context.set_account(
+        &authority.pubkey(),
+        &AccountSharedData::new(top_up - 1, 0, &system_program::id()),
+    );
+    let write_ix = update_ix(
+        &authority.pubkey(),
+        &data_account,
+        update_args(&[7; 100], 0),
+    );
+    let result = process(&mut context, std::slice::from_ref(&write_ix), &[&authority]).await;
+    assert!(result.is_err());
+    let account = context
+        .banks_client
+        .get_account(data_account)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(account.data.is_empty());
+    assert_eq!(account.lamports, rent.minimum_balance(0));
+    assert_eq!(metadata(&mut context, &data_account).await.data_len(), 0);
+
+    context.set_account(
+        &authority.pubkey(),
+        &AccountSharedData::new(top_up, 0, &system_program::id()),
+    );
+    process(&mut context, &[write_ix], &[&authority])
+        .await
+        .unwrap();
+    let account = context
+        .banks_client
+        .get_account(data_account)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(account.lamports, rent.minimum_balance(100));
+    assert_eq!(
+        context
+            .banks_client
+            .get_balance(authority.pubkey())
+            .await
+            .unwrap(),
+        0
+    );
+}
+
+#[tokio::test]
+async fn crc32_follows_contents() {
+    let mut context = start().await;
+    let data_account = initialize(&mut context, |args| args).await.pubkey();
+    assert_eq!(metadata(&mut context, &data_account).await.crc32(), 0);
+
+    update(&mut context, &data_account, update_args(b"123456789", 0))
+        .await
+        .unwrap();
+    assert_eq!(
+        metadata(&mut context, &data_account).await.crc32(),
+        0xcbf4_3926
+    );
+
+    // A single changed byte changes the checksum
+    update(&mut context, &data_account, update_args(b"0", 0))
+        .await
+        .unwrap();
+    let crc = metadata(&mut context, &data_account).await.crc32();
+    assert_ne!(crc, 0xcbf4_3926);
+    assert_eq!(crc, crc32(b"023456789"));
+}
+
+#[tokio::test]
+async fn hash_ignores_allocated_padding() {
+    let mut context = start().await;
+    let payload = b"same content, different allocations";
+    let data_account = initialize(&mut context, |args| InitializeDataStoreArgs {
+        space: 4096,
+        is_dynamic: true,
+        ..args
+    })
+    .await
+    .pubkey();
+    update(&mut context, &data_account, update_args(payload, 0))
+        .await
+        .unwrap();
+    let padded_hash = *metadata(&mut context, &data_account).await.data_hash();
+    assert_eq!(padded_hash, compute_data_hash(&data_account, payload));
+
+    // Shrinking the allocation from 4KB to the used bytes keeps the hash
+    let payer = context.payer.pubkey();
+    process(&mut context, &[reclaim_rent_ix(&payer, &data_account)], &[])
+        .await
+        .unwrap();
+    let account_metadata = metadata(&mut context, &data_account).await;
+    assert_eq!(account_metadata.space(), payload.len());
+    assert_eq!(*account_metadata.data_hash(), padded_hash);
+
+    // As does a 1KB allocation of the same content, under its own domain separation
+    let data_account = initialize(&mut context, |args| InitializeDataStoreArgs {
+        space: 1024,
+        ..args
+    })
+    .await
+    .pubkey();
+    update(&mut context, &data_account, update_args(payload, 0))
+        .await
+        .unwrap();
+    assert_eq!(
+        *metadata(&mut context, &data_account).await.data_hash(),
+        compute_data_hash(&data_account, payload)
+    );
+}
+
+#[tokio::test]
+async fn second_update_in_the_same_slot_is_rejected() {
+    let mut context = start().await;
+    let payer = context.payer.pubkey();
+    let data_account = initialize(&mut context, |args| InitializeDataStoreArgs {
+        min_update_interval_slots: 1,
+        ..args
+    })
+    .await
+    .pubkey();
+
+    // Both updates of one transaction land in the same slot
+    let result = process(
+        &mut context,
+        &[
+            update_ix(&payer, &data_account, update_args(b"first", 0)),
+            update_ix(&payer, &data_account, update_args(b"second", 0)),
+        ],
+        &[],
+    )
+    .await;
+    assert_eq!(
+        error(result),
+        Some(TransactionError::InstructionError(
+            1,
+            InstructionError::Custom(DataStoreError::UpdateTooSoon as u32)
+        ))
+    );
+    assert_eq!(metadata(&mut context, &data_account).await.revision(), 0);
+
+    update(&mut context, &data_account, update_args(b"first", 0))
+        .await
+        .unwrap();
+    let updated_slot = metadata(&mut context, &data_account).await.updated_slot();
+    context.warp_to_slot(updated_slot + 1).unwrap();
+    update(&mut context, &data_account, update_args(b"second", 0))
+        .await
+        .unwrap();
+    assert_eq!(metadata(&mut context, &data_account).await.revision(), 2);
+}
+
+#[tokio::test]
+async fn reclaim_rent_refunds_truncated_bytes() {
+    let mut context = start().await;
+    let payer = context.payer.pubkey();
+    let data_account = initialize(&mut context, |args| InitializeDataStoreArgs {
+        space: 0,
+        is_dynamic: true,
+        ..args
+    })
+    .await
+    .pubkey();
+    for offset in [0, 512] {
+        update(&mut context, &data_account, update_args(&[7; 512], offset))
+            .await
+            .unwrap();
+    }
+
+    // Truncate to 100 bytes, leaving the 1KB allocation and its rent in place
+    let truncate_ix = instruction(
+        vec![
+            AccountMeta::new_readonly(payer, true),
+            AccountMeta::new_readonly(data_account, false),
+            AccountMeta::new(metadata_pda(&data_account).0, false),
+            AccountMeta::new_readonly(config_pda(), false),
+        ],
+        DataStoreInstruction::RecomputeHash(RecomputeHashArgs {
+            debug: false,
+            data_len: Some(100),
+        }),
+    );
+    process(&mut context, &[truncate_ix], &[]).await.unwrap();
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let account = context
+        .banks_client
+        .get_account(data_account)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(account.data.len(), 1024);
+    assert_eq!(account.lamports, rent.minimum_balance(1024));
+
+    let payer_balance = context.banks_client.get_balance(payer).await.unwrap();
+    process(&mut context, &[reclaim_rent_ix(&payer, &data_account)], &[])
+        .await
+        .unwrap();
+    let account = context
+        .banks_client
+        .get_account(data_account)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(account.data, [7; 100]);
+    assert_eq!(account.lamports, rent.minimum_balance(100));
+    // The authority got the rent of the freed 924 bytes back, less the fee
+    assert_eq!(
+        context.banks_client.get_balance(payer).await.unwrap(),
+        payer_balance + rent.minimum_balance(1024) - rent.minimum_balance(100) - 5000
+    );
+    assert_eq!(
+        metadata(&mut context, &data_account).await.utilization(),
+        (100, 100)
+    );
+
+    // Static data stores keep their allocation
+    let data_account = initialize(&mut context, |args| args).await.pubkey();
+    let result = process(&mut context, &[reclaim_rent_ix(&payer, &data_account)], &[]).await;
+    assert_error(result, DataStoreError::NotDynamic);
+}
+
+#[tokio::test]
+async fn writes_past_the_used_bytes_need_allow_gaps() {
+    let mut context = start().await;
+    let data_account = initialize(&mut context, |args| args).await.pubkey();
+
+    // Appending right after the used bytes is contiguous
+    update(&mut context, &data_account, update_args(b"head", 0))
+        .await
+        .unwrap();
+    update(&mut context, &data_account, update_args(b"-tail", 4))
+        .await
+        .unwrap();
+    assert_eq!(
+        &account_data(&mut context, &data_account).await[..9],
+        b"head-tail"
+    );
+
+    // Leaving a gap is rejected by default
+    let result = update(&mut context, &data_account, update_args(b"far", 20)).await;
+    assert_error(result, DataStoreError::NonContiguousWrite);
+    assert_eq!(metadata(&mut context, &data_account).await.data_len(), 9);
+
+    // And zero-filled when allowed
+    let args = UpdateDataStoreArgs {
+        allow_gaps: true,
+        ..update_args(b"far", 20)
+    };
+    update(&mut context, &data_account, args).await.unwrap();
+    let data = account_data(&mut context, &data_account).await;
+    assert_eq!(&data[..9], b"head-tail");
+    assert_eq!(data[9..20], [0; 11]);
+    assert_eq!(&data[20..23], b"far");
+    assert_eq!(metadata(&mut context, &data_account).await.data_len(), 23);
+}
+
+#[tokio::test]
+async fn oversized_write_is_operation_too_large() {
+    let mut context = start().await;
+    let payer = context.payer.pubkey();
+    // Accounts this large can only be adopted, not created through a CPI
+    let space = 60_000;
+    let data_account = Keypair::new();
+    let rent = context.banks_client.get_rent().await.unwrap();
+    context.set_account(
+        &data_account.pubkey(),
+        &AccountSharedData::new(rent.minimum_balance(space), space, &system_program::id()),
+    );
+    let args = InitializeDataStoreArgs {
+        is_created: true,
+        ..init_args(&data_account.pubkey(), &payer, space as u64)
+    };
+    process(
+        &mut context,
+        &[initialize_ix(&payer, &data_account.pubkey(), args)],
+        &[&data_account],
+    )
+    .await
+    .unwrap();
+    let data_account = data_account.pubkey();
+
+    // Writing 1KB at 50KB leaves 50KB of data to hash, plainly over the budget
+    let data = [7; 1024];
+    let result = update(
+        &mut context,
+        &data_account,
+        UpdateDataStoreArgs {
+            allow_gaps: true,
+            ..update_args(&data, 50_000)
+        },
+    )
+    .await;
+    assert_error(result, DataStoreError::OperationTooLarge);
+    assert_eq!(metadata(&mut context, &data_account).await.data_len(), 0);
+
+    // The same write at the start only hashes what it writes
+    update(&mut context, &data_account, update_args(&data, 0))
+        .await
+        .unwrap();
+    assert_eq!(metadata(&mut context, &data_account).await.data_len(), 1024);
+}
+
+#[tokio::test]
+async fn exhausted_revisions_finalize_the_data_store() {
+    let mut context = start().await;
+    let data_account = initialize(&mut context, |args| InitializeDataStoreArgs {
+        max_revisions: Some(3),
+        ..args
+    })
+    .await
+    .pubkey();
+
+    for (revision, edit) in [b"one", b"two", b"six"].iter().enumerate() {
+        let account_metadata = metadata(&mut context, &data_account).await;
+        assert_eq!(account_metadata.revision(), revision as u64);
+        assert_eq!(
+            *account_metadata.data_status(),
+            SerializationStatusOption::Initialized
+        );
+        update(&mut context, &data_account, update_args(*edit, 0))
+            .await
+            .unwrap();
+    }
+
+    // The third edit was the last one
+    let account_metadata = metadata(&mut context, &data_account).await;
+    assert_eq!(account_metadata.revision(), 3);
+    assert_eq!(
+        *account_metadata.data_status(),
+        SerializationStatusOption::Finalized
+    );
+    let result = update(&mut context, &data_account, update_args(b"ten", 0)).await;
+    assert_error(result, DataStoreError::AlreadyFinalized);
+    assert_eq!(
+        account_data(&mut context, &data_account).await[..3],
+        *b"six"
+    );
+}
+
+#[tokio::test]
+async fn writes_end_exactly_at_the_account_size() {
+    let mut context = start().await;
+    let fixed = initialize(&mut context, |args| args).await.pubkey();
+    let dynamic = initialize(&mut context, |args| InitializeDataStoreArgs {
+        is_dynamic: true,
+        ..args
+    })
+    .await
+    .pubkey();
+    let gap_write = |data: &[u8], offset| UpdateDataStoreArgs {
+        allow_gaps: true,
+        ..update_args(data, offset)
+    };
+
+    // The last 8 bytes of a fixed 64-byte account, but not one byte further
+    update(&mut context, &fixed, gap_write(&[1; 8], 56))
+        .await
+        .unwrap();
+    assert_eq!(account_data(&mut context, &fixed).await[56..], [1; 8]);
+    let result = update(&mut context, &fixed, gap_write(&[2; 8], 57)).await;
+    assert_error(result, DataStoreError::InsufficientSpace);
+
+    // A dynamic account is filled to its size without a realloc, then grown to exactly
+    // the end of the next write
+    update(&mut context, &dynamic, gap_write(&[3; 8], 56))
+        .await
+        .unwrap();
+    assert_eq!(account_data(&mut context, &dynamic).await.len(), 64);
+    update(&mut context, &dynamic, update_args(&[4; 9], 64))
+        .await
+        .unwrap();
+    let data = account_data(&mut context, &dynamic).await;
+    assert_eq!(data.len(), 73);
+    assert_eq!(data[56..], [[3; 8].as_slice(), &[4; 9]].concat());
+    assert_eq!(metadata(&mut context, &dynamic).await.space(), 73);
+}
+
+#[tokio::test]
+async fn audited_edits_append_one_record_each() {
+    let mut context = start().await;
+    let payer = context.payer.pubkey();
+    let data_account = initialize(&mut context, |args| InitializeDataStoreArgs {
+        audit: true,
+        ..args
+    })
+    .await
+    .pubkey();
+    let (audit_pda, _) =
+        Pubkey::find_program_address(&[AUDIT_SEED, data_account.as_ref()], &program_id());
+    let audited_update_ix = |args| {
+        let mut update_ix = update_ix(&payer, &data_account, args);
+        update_ix
+            .accounts
+            .insert(4, AccountMeta::new(audit_pda, false));
+        update_ix
+    };
+
+    // Audited data stores are not updated without their audit PDA
+    let result = update(&mut context, &data_account, update_args(b"one", 0)).await;
+    assert_eq!(
+        error(result),
+        Some(TransactionError::InstructionError(
+            0,
+            InstructionError::NotEnoughAccountKeys
+        ))
+    );
+
+    let mut expected = Vec::new();
+    for (revision, edit) in [b"one", b"two", b"six"].iter().enumerate() {
+        process(
+            &mut context,
+            &[audited_update_ix(update_args(*edit, 0))],
+            &[],
+        )
+        .await
+        .unwrap();
+        let account_metadata = metadata(&mut context, &data_account).await;
+        expected.push(AuditRecord {
+            slot: account_metadata.updated_slot(),
+            authority: payer,
+            revision: revision as u64 + 1,
+            data_hash: compute_data_hash(&data_account, *edit),
+        });
+    }
+
+    let audit_data = account_data(&mut context, &audit_pda).await;
+    assert_eq!(audit_data.len(), 3 * AUDIT_RECORD_SIZE);
+    let records: Vec<AuditRecord> = audit_data
+        .chunks(AUDIT_RECORD_SIZE)
+        .map(|record| AuditRecord::try_from_slice(record).unwrap())
+        .collect();
+    assert_eq!(records, expected);
+}
+
+#[tokio::test]
+async fn regrown_region_reads_as_zeros() {
+    let mut context = start().await;
+    let payer = context.payer.pubkey();
+    let data_account = initialize(&mut context, |args| InitializeDataStoreArgs {
+        is_dynamic: true,
+        ..args
+    })
+    .await
+    .pubkey();
+    let shrink_args = UpdateDataStoreArgs {
+        realloc_down: true,
+        ..update_args(&[2; 10], 0)
+    };
+    let regrow_args = UpdateDataStoreArgs {
+        allow_gaps: true,
+        ..update_args(&[3], 99)
+    };
+    let expected = [[2; 10].as_slice(), &[0; 89], &[3]].concat();
+
+    // Shrinking and regrowing in separate transactions
+    update(&mut context, &data_account, update_args(&[1; 100], 0))
+        .await
+        .unwrap();
+    update(&mut context, &data_account, shrink_args.clone())
+        .await
+        .unwrap();
+    assert_eq!(account_data(&mut context, &data_account).await.len(), 10);
+    update(&mut context, &data_account, regrow_args.clone())
+        .await
+        .unwrap();
+    assert_eq!(account_data(&mut context, &data_account).await, expected);
+
+    // And within a single one
+    update(&mut context, &data_account, update_args(&[1; 100], 0))
+        .await
+        .unwrap();
+    process(
+        &mut context,
+        &[
+            update_ix(&payer, &data_account, shrink_args),
+            update_ix(&payer, &data_account, regrow_args),
+        ],
+        &[],
+    )
+    .await
+    .unwrap();
+    assert_eq!(account_data(&mut context, &data_account).await, expected);
+    assert_eq!(
+        *metadata(&mut context, &data_account).await.data_hash(),
+        compute_data_hash(&data_account, &expected)
+    );
+}
+
+fn copy_in_ix(
+    authority: &Pubkey,
+    data_account: &Pubkey,
+    source: &Pubkey,
+    source_offset: u64,
+    len: u64,
+    offset: u64,
+) -> Instruction {
+    instruction(
+        vec![
+            AccountMeta::new(*authority, true),
+            AccountMeta::new(*data_account, false),
+            AccountMeta::new(metadata_pda(data_account).0, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(*source, false),
+            AccountMeta::new_readonly(config_pda(), false),
+        ],
+        DataStoreInstruction::CopyInData(CopyInDataArgs {
+            debug: false,
+            source_offset,
+            len,
+            offset,
+            realloc_down: false,
+            expected_revision: None,
+        }),
+    )
+}
+
+#[tokio::test]
+async fn copies_in_bytes_from_a_scratch_account() {
+    let mut context = start().await;
+    let payer = context.payer.pubkey();
+    let data_account = initialize(&mut context, |args| args).await.pubkey();
+
+    // A scratch account of another program, standing in for its output
+    let source = Pubkey::new_unique();
+    let scratch: Vec<u8> = (0..100).collect();
+    let mut account = AccountSharedData::new(1_000_000_000, scratch.len(), &Pubkey::new_unique());
+    account.set_data_from_slice(&scratch);
+    context.set_account(&source, &account);
+
+    process(
+        &mut context,
+        &[copy_in_ix(&payer, &data_account, &source, 10, 20, 0)],
+        &[],
+    )
+    .await
+    .unwrap();
+    let data = account_data(&mut context, &data_account).await;
+    assert_eq!(data[..20], scratch[10..30]);
+    let account_metadata = metadata(&mut context, &data_account).await;
+    assert_eq!(account_metadata.data_len(), 20);
+    assert_eq!(
+        *account_metadata.data_hash(),
+        compute_data_hash(&data_account, &scratch[10..30])
+    );
+
+    // Ranges past the end of the source, or of the fixed data store, are refused
+    let result = process(
+        &mut context,
+        &[copy_in_ix(&payer, &data_account, &source, 90, 20, 20)],
+        &[],
+    )
+    .await;
+    assert_error(result, DataStoreError::InvalidInstructionData);
+    let result = process(
+        &mut context,
+        &[copy_in_ix(&payer, &data_account, &source, 0, 50, 20)],
+        &[],
+    )
+    .await;
+    assert_error(result, DataStoreError::InsufficientSpace);
+
+    // As are copies into a finalized data store
+    process(&mut context, &[finalize_ix(&payer, &data_account)], &[])
+        .await
+        .unwrap();
+    let result = process(
+        &mut context,
+        &[copy_in_ix(&payer, &data_account, &source, 30, 10, 20)],
+        &[],
+    )
+    .await;
+    assert_error(result, DataStoreError::AlreadyFinalized);
+    assert_eq!(account_data(&mut context, &data_account).await, data);
+}
+
+fn recompute_hash_ix(
+    authority: &Pubkey,
+    data_account: &Pubkey,
+    audit_accounts: &[AccountMeta],
+    data_len: Option<u64>,
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(*authority, true),
+        AccountMeta::new_readonly(*data_account, false),
+        AccountMeta::new(metadata_pda(data_account).0, false),
+    ];
+    accounts.extend_from_slice(audit_accounts);
+    accounts.push(AccountMeta::new_readonly(config_pda(), false));
+    instruction(
+        accounts,
+        DataStoreInstruction::RecomputeHash(RecomputeHashArgs {
+            debug: false,
+            data_len,
+        }),
+    )
+}
+
+/// Overwrites the start of the data account with data, as a composing program would
+async fn write_out_of_band(context: &mut ProgramTestContext, data_account: &Pubkey, data: &[u8]) {
+    let mut account = context
+        .banks_client
+        .get_account(*data_account)
+        .await
+        .unwrap()
+        .unwrap();
+    account.data[..data.len()].copy_from_slice(data);
+    context.set_account(data_account, &AccountSharedData::from(account));
+}
+
+#[tokio::test]
+async fn out_of_band_write_is_rehashed_as_an_update() {
+    let mut context = start().await;
+    let payer = context.payer.pubkey();
+    let data_account = initialize(&mut context, |args| InitializeDataStoreArgs {
+        audit: true,
+        ..args
+    })
+    .await
+    .pubkey();
+    let (audit_pda, _) =
+        Pubkey::find_program_address(&[AUDIT_SEED, data_account.as_ref()], &program_id());
+    let audit_accounts = [
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new(audit_pda, false),
+    ];
+    let mut write_ix = update_ix(&payer, &data_account, update_args(b"hello", 0));
+    write_ix.accounts.insert(4, audit_accounts[1].clone());
+    process(&mut context, &[write_ix], &[]).await.unwrap();
+    write_out_of_band(&mut context, &data_account, b"HELLO world").await;
+
+    // Like an update, the rehash of an audited data store is logged
+    let result = process(
+        &mut context,
+        &[recompute_hash_ix(&payer, &data_account, &[], Some(11))],
+        &[],
+    )
+    .await;
+    assert_eq!(
+        error(result),
+        Some(TransactionError::InstructionError(
+            0,
+            InstructionError::NotEnoughAccountKeys
+        ))
+    );
+    process(
+        &mut context,
+        &[recompute_hash_ix(
+            &payer,
+            &data_account,
+            &audit_accounts,
+            Some(11),
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+    let account_metadata = metadata(&mut context, &data_account).await;
+    let data_hash = compute_data_hash(&data_account, b"HELLO world");
+    assert_eq!(account_metadata.data_len(), 11);
+    assert_eq!(*account_metadata.data_hash(), data_hash);
+    assert_eq!(account_metadata.crc32(), crc32(b"HELLO world"));
+    assert_eq!(account_metadata.revision(), 2);
+    let audit_data = account_data(&mut context, &audit_pda).await;
+    let record = AuditRecord::try_from_slice(&audit_data[AUDIT_RECORD_SIZE..]).unwrap();
+    assert_eq!(record.revision, 2);
+    assert_eq!(record.data_hash, data_hash);
+
+    // Rate limits apply to rehashes too
+    let rate_limited = initialize(&mut context, |args| InitializeDataStoreArgs {
+        min_update_interval_slots: 1_000,
+        ..args
+    })
+    .await
+    .pubkey();
+    update(&mut context, &rate_limited, update_args(b"hello", 0))
+        .await
+        .unwrap();
+    write_out_of_band(&mut context, &rate_limited, b"HELLO").await;
+    let result = process(
+        &mut context,
+        &[recompute_hash_ix(&payer, &rate_limited, &[], None)],
+        &[],
+    )
+    .await;
+    assert_error(result, DataStoreError::UpdateTooSoon);
+
+    // A registered data store is not shrunk away from the contents its content PDA names.
+    // Only finalized data stores register, so the flag is set directly.
+    let registered = initialize(&mut context, |args| InitializeDataStoreArgs {
+        initial_data: b"hello".to_vec(),
+        ..args
+    })
+    .await
+    .pubkey();
+    let pda = metadata_pda(&registered).0;
+    let mut account = context
+        .banks_client
+        .get_account(pda)
+        .await
+        .unwrap()
+        .unwrap();
+    let mut account_metadata = DataStoreAccountMetadata::load(&account.data).unwrap();
+    account_metadata.set_flag(FLAG_CONTENT_REGISTERED, true);
+    account_metadata.store(&mut account.data).unwrap();
+    context.set_account(&pda, &AccountSharedData::from(account));
+    let result = process(
+        &mut context,
+        &[recompute_hash_ix(&payer, &registered, &[], Some(2))],
+        &[],
+    )
+    .await;
+    assert_error(result, DataStoreError::ContentRegistered);
+}
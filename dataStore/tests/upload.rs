@@ -0,0 +1,98 @@
+mod common;
+
+use borsh::BorshDeserialize;
+use common::{
+    assert_error, finalize_ix, initialize, instruction, metadata, metadata_pda, process, query,
+    start, update, update_args,
+};
+use dataaccount::{
+    error::DataStoreError,
+    instruction::DataStoreInstruction,
+    state::{GetUploadProgressArgs, InitializeDataStoreArgs, SerializationStatusOption},
+};
+use solana_program_test::{tokio, ProgramTestContext};
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    signature::Signer,
+};
+
+const TOTAL: u64 = 1000;
+const CHUNK: usize = 200;
+
+fn get_upload_progress_ix(data_account: &Pubkey) -> Instruction {
+    instruction(
+        vec![
+            AccountMeta::new_readonly(*data_account, false),
+            AccountMeta::new_readonly(metadata_pda(data_account).0, false),
+        ],
+        DataStoreInstruction::GetUploadProgress(GetUploadProgressArgs { debug: false }),
+    )
+}
+
+/// (upload_cursor, expected_total) of data_account
+async fn progress(context: &mut ProgramTestContext, data_account: &Pubkey) -> (u64, u64) {
+    let return_data = query(context, get_upload_progress_ix(data_account))
+        .await
+        .unwrap();
+    <(u64, u64)>::try_from_slice(&return_data).unwrap()
+}
+
+/// Uploads up to count chunks of data, from where the upload of data_account stands
+async fn upload(
+    context: &mut ProgramTestContext,
+    data_account: &Pubkey,
+    data: &[u8],
+    count: usize,
+) {
+    let (cursor, _) = progress(context, data_account).await;
+    let remaining = &data[cursor as usize..];
+    for (index, chunk) in remaining.chunks(CHUNK).take(count).enumerate() {
+        let offset = cursor + (index * CHUNK) as u64;
+        update(context, data_account, update_args(chunk, offset))
+            .await
+            .unwrap();
+    }
+}
+
+#[tokio::test]
+async fn upload_resumes_from_cursor() {
+    let mut context = start().await;
+    let data_account = initialize(&mut context, |args| InitializeDataStoreArgs {
+        space: TOTAL,
+        expected_total: TOTAL,
+        ..args
+    })
+    .await
+    .pubkey();
+    let data: Vec<u8> = (0..TOTAL).map(|i| (i * 7) as u8).collect();
+    assert_eq!(progress(&mut context, &data_account).await, (0, TOTAL));
+
+    // The client stops after two chunks
+    upload(&mut context, &data_account, &data, 2).await;
+    assert_eq!(progress(&mut context, &data_account).await, (400, TOTAL));
+
+    // Finalizing before the upload is complete is rejected
+    let payer = context.payer.pubkey();
+    let result = process(&mut context, &[finalize_ix(&payer, &data_account)], &[]).await;
+    assert_error(result, DataStoreError::UploadIncomplete);
+
+    // A restarted client picks up from the cursor
+    upload(&mut context, &data_account, &data, usize::MAX).await;
+    assert_eq!(progress(&mut context, &data_account).await, (TOTAL, TOTAL));
+    process(&mut context, &[finalize_ix(&payer, &data_account)], &[])
+        .await
+        .unwrap();
+
+    assert_eq!(
+        *metadata(&mut context, &data_account).await.data_status(),
+        SerializationStatusOption::Finalized
+    );
+    let account = context
+        .banks_client
+        .get_account(data_account)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(account.data, data);
+}